@@ -0,0 +1,443 @@
+//! Solver benchmarks, `cargo bench` (`--features parallel` adds the parallel sweeps).
+//!
+//! Baseline (release build, one x86_64 core), to compare solver changes against:
+//!
+//! | benchmark                              | time     |
+//! |----------------------------------------|----------|
+//! | grid_sweep_10k/serial                  | 1.82 ms  |
+//! | mixed_sweep_5k                         | 1.03 ms  |
+//! | mosfet_test_solve_state                | 270 µs   |
+//! | rc_test_1000_ticks                     | 26.3 ms  |
+//! | rc_test_1000_ticks_predicted/linear    | 12.4 ms  |
+//! | rc_test_1000_ticks_predicted/quadratic | 612 µs   |
+//! | rc_ladder_20_ticks/unmasked            | 475 ms   |
+//! | rc_ladder_20_ticks/masked              | 478 ms   |
+//! | switch_bank_solve_state/full           | 2.05 ms  |
+//! | switch_bank_solve_state/skip_inert     | 808 µs   |
+//! | bus_solve_state/plain                  | 36.1 ms  |
+//! | bus_solve_state/degree_compensated     | 25.8 ms  |
+//! | grid_solve_state/4                     | 2.14 ms  |
+//! | grid_solve_state/6                     | 17.3 ms  |
+//! | grid_solve_state/8                     | 47.7 ms  |
+//! | grid_solve_state/10                    | 166 ms   |
+//! | half_bridge_pwm_period                 | 67.4 ms  |
+//! | rc_ladder_solve/dense_500              | 60.0 ms  |
+//! | rc_ladder_solve/banded_2000            | 56.9 µs  |
+//! | rc_ladder_solve/tridiagonal_2000       | 35.8 µs  |
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use esc_sim_test::linalg::{banded::BandedMat, Mat};
+use esc_sim_test::sim::{
+    components::{
+        ComponentParameter, LinearComponentValue, MOSFETComponentValue, MOSFETDopingType,
+    },
+    cosim::{CoSim, Control},
+    make_half_bridge, make_resistor_grid, mosfet_test_circuit, rc_test_circuit,
+    solver::{Predictor, VoltageAveraging},
+    CircuitState, ComponentValueEnum,
+};
+
+/// One relaxation sweep over a ~10k resistor grid.
+fn grid_sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grid_sweep_10k");
+    let mut circuit = make_resistor_grid(71, 1.0);
+    #[cfg(feature = "parallel")]
+    circuit.set_parallel(false);
+    group.bench_function("serial", |b| b.iter(|| circuit.relaxation_sweep(0.5)));
+    #[cfg(feature = "parallel")]
+    {
+        let mut circuit = make_resistor_grid(71, 1.0);
+        group.bench_function("parallel", |b| b.iter(|| circuit.relaxation_sweep(0.5)));
+    }
+    group.finish();
+}
+
+/// RC ladder with a MOSFET hanging off every tenth section, ~5k components of mixed kinds.
+fn make_mixed_ladder(n_sections: usize) -> CircuitState {
+    let mut circuit = CircuitState::new_empty();
+    let gnd = circuit.create_net();
+    let gate = circuit.create_net();
+    let mut prev = circuit.create_net();
+    circuit.create_component(
+        ComponentValueEnum::Linear(LinearComponentValue::Source(5.0)),
+        &[gnd, prev],
+    );
+    circuit.create_component(
+        ComponentValueEnum::Linear(LinearComponentValue::Source(10.0)),
+        &[gnd, gate],
+    );
+    for i in 0..n_sections {
+        let next = circuit.create_net();
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Resistive(10.0)),
+            &[prev, next],
+        );
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Capacitive(1e-6)),
+            &[next, gnd],
+        );
+        if i % 10 == 0 {
+            circuit.create_component(
+                ComponentValueEnum::MOSFET(MOSFETComponentValue {
+                    beta: 0.02,
+                    ty: MOSFETDopingType::NChannel,
+                    body_diode_ideality_facotor: 1.0,
+                    body_diode_saturation_current: 1e-9,
+                    threshold_voltage: 1.0,
+                    saturation_knee: 8.0,
+                    multiplicity: 1.0,
+                    avalanche: None,
+                }),
+                &[gnd, gate, next],
+            );
+        }
+        prev = next;
+    }
+    circuit
+}
+
+fn mixed_sweep(c: &mut Criterion) {
+    let mut circuit = make_mixed_ladder(2400);
+    #[cfg(feature = "parallel")]
+    circuit.set_parallel(false);
+    c.bench_function("mixed_sweep_5k", |b| {
+        b.iter(|| circuit.relaxation_sweep(0.5))
+    });
+}
+
+/// `solve_state` of `make_mosfet_test` from its unsolved state, to convergence: a P-channel switch
+/// and its load, the MOSFET solved iteratively against the resistor.
+fn mosfet_test_solve(c: &mut Criterion) {
+    let (circuit, _) = mosfet_test_circuit();
+    c.bench_function("mosfet_test_solve_state", |b| {
+        b.iter_batched_ref(
+            || circuit.clone(),
+            |circuit| circuit.solve_state(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// 1000 ticks of 10 us of `make_rc_test`, from its solved initial state.
+fn rc_test_ticks(c: &mut Criterion) {
+    let (mut circuit, _) = rc_test_circuit();
+    circuit.solve_state();
+    c.bench_function("rc_test_1000_ticks", |b| {
+        b.iter_batched_ref(
+            || circuit.clone(),
+            |circuit| {
+                for _ in 0..1000 {
+                    circuit.tick(0.000_01);
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// `rc_test_1000_ticks` with `SolverConfig::predictor` warm-starting each tick.
+fn rc_test_ticks_predicted(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rc_test_1000_ticks_predicted");
+    for (name, predictor) in [
+        ("linear", Predictor::Linear),
+        ("quadratic", Predictor::Quadratic),
+    ] {
+        let (mut circuit, _) = rc_test_circuit();
+        let mut config = *circuit.solver_config();
+        config.predictor = predictor;
+        circuit.set_solver_config(config);
+        circuit.solve_state();
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || circuit.clone(),
+                |circuit| {
+                    for _ in 0..1000 {
+                        circuit.tick(0.000_01);
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// 20 ticks of 1 us of a 200 section RC ladder right after the source at one end steps from 0 V
+/// to 1 V, with and without `SolverConfig::settle_after`. The far end of the ladder barely moves
+/// within a tick, so most of its components settle.
+fn rc_ladder_ticks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rc_ladder_20_ticks");
+    group.sample_size(20);
+    for (name, settle_after) in [("unmasked", None), ("masked", Some(5))] {
+        let mut circuit = CircuitState::new_empty();
+        let gnd = circuit.create_net();
+        let mut prev = circuit.create_net();
+        let source = circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Source(0.0)),
+            &[gnd, prev],
+        );
+        for _ in 0..200 {
+            let next = circuit.create_net();
+            circuit.create_component(
+                ComponentValueEnum::Linear(LinearComponentValue::Resistive(10.0)),
+                &[prev, next],
+            );
+            circuit.create_component(
+                ComponentValueEnum::Linear(LinearComponentValue::Capacitive(1e-6)),
+                &[next, gnd],
+            );
+            prev = next;
+        }
+        #[cfg(feature = "parallel")]
+        circuit.set_parallel(false);
+        let mut config = *circuit.solver_config();
+        config.settle_after = settle_after;
+        circuit.set_solver_config(config);
+        circuit.solve_state();
+        if let Some(v) = circuit
+            .component_mut(source)
+            .parameter_mut(ComponentParameter::Value)
+        {
+            *v = 1.0;
+        }
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || circuit.clone(),
+                |circuit| {
+                    for _ in 0..20 {
+                        circuit.tick(1e-6);
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// `solve_state` from all nets at 0 V of 20 loads of 10 ohm on a 1 V source behind 1 ohm, each
+/// with 10 switches to ground of which one is closed, iterating over every component and with
+/// `SolverConfig::skip_inert` leaving out the 180 open switches.
+fn switch_bank_solve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("switch_bank_solve_state");
+    group.sample_size(20);
+    for (name, skip_inert) in [("full", false), ("skip_inert", true)] {
+        let mut circuit = CircuitState::new_empty();
+        let gnd = circuit.create_net();
+        let supply = circuit.create_net();
+        let bank = circuit.create_net();
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Source(1.0)),
+            &[gnd, supply],
+        );
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Resistive(1.0)),
+            &[supply, bank],
+        );
+        for _ in 0..20 {
+            let load = circuit.create_net();
+            circuit.create_component(
+                ComponentValueEnum::Linear(LinearComponentValue::Resistive(10.0)),
+                &[bank, load],
+            );
+            for k in 0..10 {
+                circuit.create_component(
+                    ComponentValueEnum::Linear(LinearComponentValue::Switch {
+                        closed: k == 0,
+                        off_resistance: None,
+                    }),
+                    &[load, gnd],
+                );
+            }
+        }
+        #[cfg(feature = "parallel")]
+        circuit.set_parallel(false);
+        let mut config = *circuit.solver_config();
+        config.skip_inert = skip_inert;
+        circuit.set_solver_config(config);
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || circuit.clone(),
+                |circuit| circuit.solve_state(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// `solve_state` from all nets at 0 V of a bus fed from 10 V through 1 ohm and loaded by 49
+/// MOSFETs in parallel (a net of 50 terminals), with `SolverConfig::voltage_averaging` plain and
+/// degree-compensated (exponent 0.25), which takes 666 sweeps to the 1348 of the plain average.
+/// Both are checked to settle the bus at the same voltage first.
+fn bus_solve(c: &mut Criterion) {
+    let schemes = [
+        ("plain", VoltageAveraging::Plain),
+        (
+            "degree_compensated",
+            VoltageAveraging::DegreeCompensated { exponent: 0.25 },
+        ),
+    ];
+    let circuits = schemes.map(|(name, voltage_averaging)| {
+        let mut circuit = CircuitState::new_empty();
+        let gnd = circuit.create_net();
+        let supply = circuit.create_net();
+        let gate = circuit.create_net();
+        let bus = circuit.create_net();
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Source(10.0)),
+            &[gnd, supply],
+        );
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Source(10.0)),
+            &[gnd, gate],
+        );
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Resistive(1.0)),
+            &[supply, bus],
+        );
+        for _ in 0..49 {
+            circuit.create_component(
+                ComponentValueEnum::MOSFET(MOSFETComponentValue {
+                    beta: 0.1 / 49.0,
+                    ty: MOSFETDopingType::NChannel,
+                    body_diode_ideality_facotor: 1.0,
+                    body_diode_saturation_current: 1e-12,
+                    threshold_voltage: 2.0,
+                    saturation_knee: 8.0,
+                    multiplicity: 1.0,
+                    avalanche: None,
+                }),
+                &[gnd, gate, bus],
+            );
+        }
+        #[cfg(feature = "parallel")]
+        circuit.set_parallel(false);
+        let mut config = *circuit.solver_config();
+        config.seed = false;
+        config.voltage_averaging = voltage_averaging;
+        circuit.set_solver_config(config);
+
+        let mut solved = circuit.clone();
+        assert!(solved.solve_state(), "{name} doesn't converge");
+        let v_bus: f64 = solved.net(bus).voltage() - solved.net(gnd).voltage();
+        (name, circuit, v_bus)
+    });
+    let [(_, _, plain), (_, _, compensated)] = &circuits;
+    assert!(
+        (plain - compensated).abs() < 1e-9,
+        "the bus settles at {plain} V plain but {compensated} V compensated"
+    );
+
+    let mut group = c.benchmark_group("bus_solve_state");
+    group.sample_size(20);
+    for (name, circuit, _) in circuits {
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || circuit.clone(),
+                |circuit| circuit.solve_state(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// `solve_state` of `make_resistor_grid` from all nets at 0 V. The sweeps needed grow quickly with
+/// the grid, so from 12 x 12 on it stops at `RelaxationStrategy::max_outer` without converging.
+fn grid_solve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grid_solve_state");
+    group.sample_size(20);
+    for side in [4, 6, 8, 10] {
+        let circuit = make_resistor_grid(side, 1.0);
+        group.bench_with_input(BenchmarkId::from_parameter(side), &circuit, |b, circuit| {
+            b.iter_batched_ref(
+                || circuit.clone(),
+                |circuit| circuit.solve_state(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// One 50 us PWM period (100 ticks) of `make_half_bridge` at 50 % duty, from a few periods in, so
+/// both MOSFETs switch hard twice per iteration.
+fn half_bridge_period(c: &mut Criterion) {
+    const PERIOD: f64 = 50e-6;
+    let (circuit, names) = make_half_bridge();
+    let mut sim = CoSim::new(circuit, PERIOD / 100.0);
+    for (gate_drive, inverted) in [("Vg_high", false), ("Vg_low", true)] {
+        let control = Control::Pwm {
+            component: names.component(gate_drive).unwrap(),
+            period: PERIOD,
+            low: 0.0,
+            high: 10.0,
+            inverted,
+        };
+        sim.add_control(control, 0.5);
+    }
+    sim.step_until(4.0 * PERIOD).unwrap();
+    c.bench_function("half_bridge_pwm_period", |b| {
+        b.iter_batched_ref(
+            || sim.clone(),
+            |sim| sim.step_until(sim.time() + PERIOD).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Nodal matrix of an `n`-node RC ladder at one backward-Euler step (10 ohm sections, 1 uF to
+/// ground per node at dt = 100 ns, driven through 1 ohm at the first node), and a right-hand side.
+fn rc_ladder_system(n: usize) -> (Mat<f64>, Mat<f64>) {
+    let (g, c_dt) = (0.1, 10.0);
+    let mut a = Mat::zeros(n, n);
+    let mut b = Mat::zeros(n, 1);
+    for i in 0..n {
+        a[[i, i]] += c_dt;
+        b[[i, 0]] = (i as f64 * 0.01).sin();
+        if i + 1 < n {
+            a[[i, i]] += g;
+            a[[i + 1, i + 1]] += g;
+            a[[i, i + 1]] -= g;
+            a[[i + 1, i]] -= g;
+        }
+    }
+    a[[0, 0]] += 1.0;
+    b[[0, 0]] += 5.0;
+    (a, b)
+}
+
+/// Solves of `rc_ladder_system`: the dense LU at 500 nodes (at 2000 it takes about 33 s), the
+/// banded LU and the Thomas algorithm at 2000.
+fn banded_solve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rc_ladder_solve");
+    group.sample_size(10);
+    let (a, b) = rc_ladder_system(500);
+    group.bench_function("dense_500", |bench| bench.iter(|| a.solve(&b).unwrap()));
+    let (a, b) = rc_ladder_system(2000);
+    let banded = BandedMat::from_mat(&a, 0.0).unwrap();
+    group.bench_function("banded_2000", |bench| {
+        bench.iter(|| banded.solve(&b).unwrap())
+    });
+    group.bench_function("tridiagonal_2000", |bench| {
+        bench.iter(|| banded.solve_tridiagonal(&b).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    grid_sweep,
+    mixed_sweep,
+    mosfet_test_solve,
+    rc_test_ticks,
+    rc_test_ticks_predicted,
+    rc_ladder_ticks,
+    switch_bank_solve,
+    bus_solve,
+    grid_solve,
+    half_bridge_period,
+    banded_solve
+);
+criterion_main!(benches);
@@ -0,0 +1,237 @@
+//! Python module `esc_sim`: circuits built by name as with `CircuitBuilder`, fixed-step runs
+//! returning numpy arrays, and parameter changes between runs.
+
+use std::sync::atomic::AtomicBool;
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{
+        ComponentParameter, LinearComponentValue, MOSFETComponentValue, MOSFETDopingType,
+    },
+    error::SimError,
+    probe::{Probe, Recording},
+    run::{run_with_progress, RunConfig},
+    ComponentId, ComponentMut,
+};
+use numpy::IntoPyArray;
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyKeyError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+create_exception!(
+    esc_sim,
+    SimulationError,
+    PyException,
+    "An error of the simulator, with its `SimError` description as the message."
+);
+
+fn sim_err(err: SimError) -> PyErr {
+    SimulationError::new_err(err.to_string())
+}
+
+/// A circuit whose nets and components are referred to by name; the first net named is ground.
+///
+/// Components can be added at any time, also between runs, which continue from the present
+/// state.
+#[pyclass(module = "esc_sim")]
+struct Circuit {
+    builder: CircuitBuilder,
+}
+
+#[pymethods]
+impl Circuit {
+    #[new]
+    fn new() -> Self {
+        Self {
+            builder: CircuitBuilder::new(),
+        }
+    }
+
+    fn resistor(&mut self, name: &str, a: &str, b: &str, ohms: f64) -> PyResult<()> {
+        self.builder.resistor(name, a, b, ohms).map_err(sim_err)?;
+        Ok(())
+    }
+    fn capacitor(&mut self, name: &str, a: &str, b: &str, farads: f64) -> PyResult<()> {
+        self.builder
+            .capacitor(name, a, b, farads)
+            .map_err(sim_err)?;
+        Ok(())
+    }
+    fn inductor(&mut self, name: &str, a: &str, b: &str, henries: f64) -> PyResult<()> {
+        self.builder
+            .inductor(name, a, b, henries)
+            .map_err(sim_err)?;
+        Ok(())
+    }
+    /// A source holding `V(b) - V(a) = volts`.
+    fn source(&mut self, name: &str, a: &str, b: &str, volts: f64) -> PyResult<()> {
+        self.builder.source(name, a, b, volts).map_err(sim_err)?;
+        Ok(())
+    }
+    #[pyo3(signature = (name, a, b, closed = false))]
+    fn switch(&mut self, name: &str, a: &str, b: &str, closed: bool) -> PyResult<()> {
+        self.builder.switch(name, a, b, closed).map_err(sim_err)?;
+        Ok(())
+    }
+    #[pyo3(signature = (
+        name, source, gate, drain, beta, threshold_voltage,
+        p_channel = false, saturation_current = 1e-14, ideality_factor = 1.0,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn mosfet(
+        &mut self,
+        name: &str,
+        source: &str,
+        gate: &str,
+        drain: &str,
+        beta: f64,
+        threshold_voltage: f64,
+        p_channel: bool,
+        saturation_current: f64,
+        ideality_factor: f64,
+    ) -> PyResult<()> {
+        let value = MOSFETComponentValue {
+            ty: if p_channel {
+                MOSFETDopingType::PChannel
+            } else {
+                MOSFETDopingType::NChannel
+            },
+            beta,
+            threshold_voltage,
+            body_diode_saturation_current: saturation_current,
+            body_diode_ideality_facotor: ideality_factor,
+            saturation_knee: 8.0,
+            multiplicity: 1.0,
+        };
+        self.builder
+            .mosfet(name, value, source, gate, drain)
+            .map_err(sim_err)?;
+        Ok(())
+    }
+
+    fn set_capacitor_voltage(&mut self, name: &str, volts: f64) -> PyResult<()> {
+        let component = self.component(name)?;
+        self.builder
+            .circuit_mut()
+            .set_initial_capacitor_voltage(component, volts)
+            .map_err(sim_err)
+    }
+    fn set_inductor_current(&mut self, name: &str, amps: f64) -> PyResult<()> {
+        let component = self.component(name)?;
+        self.builder
+            .circuit_mut()
+            .set_initial_inductor_current(component, amps)
+            .map_err(sim_err)
+    }
+    /// Set `"value"` (resistance, capacitance, inductance or source voltage), `"beta"` or
+    /// `"threshold_voltage"` of a component.
+    #[pyo3(signature = (name, value, parameter = "value"))]
+    fn set_parameter(&mut self, name: &str, value: f64, parameter: &str) -> PyResult<()> {
+        let component = self.component(name)?;
+        let parameter = match parameter {
+            "value" => ComponentParameter::Value,
+            "beta" => ComponentParameter::Beta,
+            "threshold_voltage" => ComponentParameter::ThresholdVoltage,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown parameter {parameter:?}"
+                )))
+            }
+        };
+        let slot = self
+            .builder
+            .circuit_mut()
+            .component_mut(component)
+            .parameter_mut(parameter)
+            .ok_or_else(|| PyValueError::new_err(format!("{name} has no {parameter:?}")))?;
+        *slot = value;
+        Ok(())
+    }
+    fn set_switch(&mut self, name: &str, closed: bool) -> PyResult<()> {
+        let component = self.component(name)?;
+        if let ComponentMut::Linear(v) = self.builder.circuit_mut().component_mut(component) {
+            if let LinearComponentValue::Switch { closed: c, .. } = &mut v.value {
+                *c = closed;
+                return Ok(());
+            }
+        }
+        Err(sim_err(SimError::WrongComponentKind {
+            component,
+            expected: "a switch",
+        }))
+    }
+
+    /// Tick `steps` times by `dt` (solving the initial state first), returning a dict of numpy
+    /// arrays: `"time"` (`steps + 1` samples from 0) and one entry per probe, keyed by its spec.
+    /// Probes are `net:<net>` (voltage to ground) or `comp:<component>.<current|voltage|power>`.
+    ///
+    /// The GIL is released while the circuit runs.
+    fn run<'py>(
+        &mut self,
+        py: Python<'py>,
+        dt: f64,
+        steps: usize,
+        probes: Vec<String>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let probes = probes
+            .into_iter()
+            .map(|spec| Probe::parse(&spec, self.builder.names()).map(|probe| (spec, probe)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sim_err)?;
+        let circuit = self.builder.circuit_mut();
+        let outcome = py
+            .allow_threads(|| {
+                circuit.try_solve_state()?;
+                let config = RunConfig {
+                    dt,
+                    n_steps: steps,
+                    progress_every: 0,
+                    start_step: 0,
+                };
+                let never = AtomicBool::new(false);
+                run_with_progress(circuit, Recording::new(probes), config, &never, |_| {})
+            })
+            .map_err(sim_err)?;
+
+        let dict = PyDict::new_bound(py);
+        let recording = outcome.recording;
+        let labels = recording.labels().map(String::from).collect::<Vec<_>>();
+        dict.set_item("time", recording.time.into_pyarray_bound(py))?;
+        for (label, channel) in labels.into_iter().zip(recording.channels) {
+            dict.set_item(label, channel.into_pyarray_bound(py))?;
+        }
+        Ok(dict)
+    }
+
+    /// Present voltage of a net relative to ground.
+    fn voltage(&self, net: &str) -> PyResult<f64> {
+        let net = self
+            .builder
+            .names()
+            .net(net)
+            .ok_or_else(|| PyKeyError::new_err(format!("no net named {net:?}")))?;
+        Ok(Probe::Net(net).sample(self.builder.circuit()))
+    }
+}
+
+impl Circuit {
+    fn component(&self, name: &str) -> PyResult<ComponentId> {
+        self.builder
+            .names()
+            .component(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no component named {name:?}")))
+    }
+}
+
+#[pymodule]
+fn esc_sim(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Circuit>()?;
+    m.add(
+        "SimulationError",
+        m.py().get_type_bound::<SimulationError>(),
+    )?;
+    Ok(())
+}
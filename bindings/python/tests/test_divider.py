@@ -0,0 +1,39 @@
+"""Smoke test of the bindings: `maturin develop && pytest tests` from bindings/python."""
+
+import numpy as np
+import pytest
+
+import esc_sim
+
+
+def divider():
+    c = esc_sim.Circuit()
+    c.source("V1", "gnd", "in", 10.0)
+    c.resistor("R1", "in", "out", 3e3)
+    c.resistor("R2", "out", "gnd", 1e3)
+    return c
+
+
+def test_divider_voltage():
+    c = divider()
+    run = c.run(1e-6, 10, ["net:out", "comp:R1.current"])
+    assert isinstance(run["time"], np.ndarray)
+    assert run["time"].shape == (11,)
+    assert run["net:out"][-1] == pytest.approx(2.5, rel=1e-3)
+    assert run["comp:R1.current"][-1] == pytest.approx(2.5e-3, rel=1e-3)
+
+
+def test_mutation_between_runs():
+    c = divider()
+    c.run(1e-6, 10, ["net:out"])
+    c.set_parameter("R2", 3e3)
+    c.set_parameter("V1", 4.0)
+    assert c.run(1e-6, 10, ["net:out"])["net:out"][-1] == pytest.approx(2.0, rel=1e-3)
+
+
+def test_errors_are_exceptions():
+    c = divider()
+    with pytest.raises(esc_sim.SimulationError, match="a component is already named"):
+        c.resistor("R1", "in", "gnd", 1.0)
+    with pytest.raises(esc_sim.SimulationError, match="no net named"):
+        c.run(1e-6, 1, ["net:nowhere"])
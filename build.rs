@@ -0,0 +1,25 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // `tests/ffi.rs` compiles C against the library for the target it runs on.
+    println!(
+        "cargo:rustc-env=ESC_SIM_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+/// Regenerate `include/esc_sim.h` from the `extern "C"` items of `src/ffi.rs`.
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap();
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{crate_dir}/src/ffi.rs"))
+        .generate()
+        .expect("generating include/esc_sim.h")
+        .write_to_file(format!("{crate_dir}/include/esc_sim.h"));
+}
@@ -0,0 +1,52 @@
+/* Discharges 1 uF charged to 1 V through 1 kOhm and checks the curve against exp(-t / RC).
+ *
+ *     cargo build --release --features ffi
+ *     cc examples/ffi/rc_decay.c -Iinclude -Ltarget/release -lesc_sim_test -lm -o target/rc_decay
+ *     LD_LIBRARY_PATH=target/release target/rc_decay
+ *
+ * `cargo test --features ffi --test ffi` does the same against the debug library.
+ */
+#include <math.h>
+#include <stdio.h>
+
+#include "esc_sim.h"
+
+#define CHECK(call)                                                            \
+    do {                                                                       \
+        int status = (call);                                                   \
+        if (status != ESC_OK) {                                                \
+            fprintf(stderr, "%s failed with %d\n", #call, status);             \
+            return 1;                                                          \
+        }                                                                      \
+    } while (0)
+
+int main(void) {
+    const double r = 1e3, c = 1e-6, dt = 1e-6;
+    EscCircuit *circuit = esc_circuit_new();
+    size_t gnd, top, capacitor;
+    CHECK(esc_circuit_add_net(circuit, &gnd));
+    CHECK(esc_circuit_add_net(circuit, &top));
+    CHECK(esc_circuit_add_resistor(circuit, top, gnd, r, NULL));
+    CHECK(esc_circuit_add_capacitor(circuit, gnd, top, c, &capacitor));
+    CHECK(esc_circuit_set_capacitor_voltage(circuit, capacitor, 1.0));
+
+    double worst = 0.0;
+    for (int step = 1; step <= 3000; step++) {
+        CHECK(esc_circuit_tick(circuit, dt));
+        double v_top, v_gnd;
+        CHECK(esc_circuit_net_voltage(circuit, top, &v_top));
+        CHECK(esc_circuit_net_voltage(circuit, gnd, &v_gnd));
+        double error = fabs(v_top - v_gnd - exp(-step * dt / (r * c)));
+        if (error > worst) {
+            worst = error;
+        }
+    }
+    esc_circuit_free(circuit);
+
+    printf("largest deviation from exp(-t / RC) over 3 RC: %.2e V\n", worst);
+    if (worst > 1e-2) {
+        fprintf(stderr, "decay curve off\n");
+        return 1;
+    }
+    return 0;
+}
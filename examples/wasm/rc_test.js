@@ -0,0 +1,54 @@
+import init, { Circuit } from "./pkg/esc_sim_test.js";
+
+const log = (line) => (document.getElementById("log").textContent += line + "\n");
+
+const { memory } = await init();
+
+// make_rc_test: C1 rings against L1 + L2, C2 against L3, both capacitors starting at 10 V.
+const circuit = new Circuit();
+const [gnd, b, c, d, e] = Array.from({ length: 5 }, () => circuit.addNet());
+const c1 = circuit.addCapacitor(gnd, b, 0.1);
+circuit.addInductor(b, c, 0.1);
+circuit.addInductor(c, gnd, 0.1);
+const c2 = circuit.addCapacitor(d, e, 0.1);
+const l3 = circuit.addInductor(e, d, 0.2);
+circuit.setCapacitorVoltage(c1, 10);
+circuit.setCapacitorVoltage(c2, 10);
+const iL3 = circuit.addComponentProbe(l3, "current");
+circuit.solveState();
+
+const dt = 1e-4;
+const steps = 10000;
+const tank1 = new Float64Array(steps);
+const tank2 = new Float64Array(steps);
+const before = circuit.netVoltages();
+let unconverged = 0;
+for (let i = 0; i < steps; i++) {
+  if (!circuit.tick(dt)) unconverged++;
+  // a view straight into wasm memory, retaken after every tick, not a copy.
+  const v = new Float64Array(memory.buffer, circuit.voltagesPtr(), circuit.nNets());
+  tank1[i] = v[b] - v[gnd];
+  tank2[i] = v[e] - v[d];
+}
+const after = circuit.netVoltages();
+
+// tick() has to move the tanks off their initial 10 V.
+if (Math.abs(after[b] - after[gnd] - (before[b] - before[gnd])) < 1e-6) {
+  throw new Error("tick() did not advance the voltages");
+}
+log(`t = ${steps * dt} s, V(C1) = ${tank1[steps - 1].toFixed(3)} V, ` +
+  `V(C2) = ${tank2[steps - 1].toFixed(3)} V, I(L3) = ${circuit.readProbe(iL3).toFixed(3)} A`);
+log(`${unconverged} of ${steps} ticks did not converge`);
+
+const canvas = document.getElementById("plot");
+const ctx = canvas.getContext("2d");
+for (const [trace, color] of [[tank1, "steelblue"], [tank2, "darkorange"]]) {
+  ctx.strokeStyle = color;
+  ctx.beginPath();
+  trace.forEach((v, i) => {
+    const x = (i / steps) * canvas.width;
+    const y = canvas.height / 2 - (v / 10) * (canvas.height / 2 - 4);
+    i === 0 ? ctx.moveTo(x, y) : ctx.lineTo(x, y);
+  });
+  ctx.stroke();
+}
@@ -0,0 +1,159 @@
+/* C interface of esc_sim_test.
+ *
+ * Build the library with
+ *     cargo build --release --features ffi
+ * and link against target/release/libesc_sim_test.so (or .dylib / .dll).
+ *
+ * Every function but esc_circuit_new/free returns an ESC_* status. Nets and components are
+ * numbered from 0 in creation order; net voltages are absolute, so take differences to a
+ * reference net. Two-terminal components connect nets a (terminal 0) and b (terminal 1), with
+ * sources and initial capacitor voltages setting V(b) - V(a).
+ */
+
+#ifndef ESC_SIM_H
+#define ESC_SIM_H
+
+/* Generated from src/ffi.rs by cbindgen (see build.rs); don't edit by hand. */
+
+#include <stddef.h>
+
+#define ESC_OK 0
+
+#define ESC_ERR_NULL 1
+
+#define ESC_ERR_INVALID_ID 2
+
+#define ESC_ERR_WRONG_KIND 3
+
+/*
+ The tick or solve ran but did not converge.
+ */
+#define ESC_ERR_NOT_CONVERGED 4
+
+#define ESC_ERR_NON_FINITE 5
+
+#define ESC_ERR_PANIC 6
+
+/*
+ A small-signal system without a unique solution, see `SimError::SingularAcSystem`.
+ */
+#define ESC_ERR_SINGULAR 7
+
+typedef struct EscCircuit EscCircuit;
+
+/*
+ Parameters of `esc_circuit_add_mosfet`, the level-1 model of `MOSFETComponentValue`.
+ */
+typedef struct EscMosfetParams {
+    /*
+     Nonzero for P-channel.
+     */
+    int p_channel;
+    double beta;
+    double threshold_voltage;
+    double body_diode_saturation_current;
+    double body_diode_ideality_factor;
+} EscMosfetParams;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ A new, empty circuit, to be released with `esc_circuit_free`.
+ */
+struct EscCircuit *esc_circuit_new(void);
+
+/*
+ # Safety
+ `circuit` must be null or a handle from `esc_circuit_new` that has not been freed.
+ */
+void esc_circuit_free(struct EscCircuit *circuit);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle, `net_out` null or valid for writes.
+ */
+int esc_circuit_add_net(struct EscCircuit *circuit, size_t *net_out);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle, `component_out` null or valid for writes.
+ */
+int esc_circuit_add_resistor(struct EscCircuit *circuit,
+                             size_t a,
+                             size_t b,
+                             double ohms,
+                             size_t *component_out);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle, `component_out` null or valid for writes.
+ */
+int esc_circuit_add_capacitor(struct EscCircuit *circuit,
+                              size_t a,
+                              size_t b,
+                              double farads,
+                              size_t *component_out);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle, `component_out` null or valid for writes.
+ */
+int esc_circuit_add_inductor(struct EscCircuit *circuit,
+                             size_t a,
+                             size_t b,
+                             double henries,
+                             size_t *component_out);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle, `component_out` null or valid for writes.
+ */
+int esc_circuit_add_source(struct EscCircuit *circuit,
+                           size_t a,
+                           size_t b,
+                           double volts,
+                           size_t *component_out);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle, `params` null or valid for reads and
+ `component_out` null or valid for writes.
+ */
+int esc_circuit_add_mosfet(struct EscCircuit *circuit,
+                           size_t source,
+                           size_t gate,
+                           size_t drain,
+                           const struct EscMosfetParams *params,
+                           size_t *component_out);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle.
+ */
+int esc_circuit_set_capacitor_voltage(struct EscCircuit *circuit, size_t component, double volts);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle.
+ */
+int esc_circuit_set_source(struct EscCircuit *circuit, size_t component, double volts);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle.
+ */
+int esc_circuit_tick(struct EscCircuit *circuit, double dt);
+
+/*
+ # Safety
+ `circuit` must be null or a live handle, `volts_out` null or valid for writes.
+ */
+int esc_circuit_net_voltage(struct EscCircuit *circuit, size_t net, double *volts_out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ESC_SIM_H */
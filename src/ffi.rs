@@ -0,0 +1,285 @@
+//! C interface to the simulator, see `include/esc_sim.h`.
+//!
+//! Circuits are opaque `EscCircuit` handles; every function returns an `ESC_*` status code
+//! instead of panicking across the boundary.
+
+use std::{
+    ffi::c_int,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use crate::sim::{
+    components::{LinearComponentValue, MOSFETComponentValue, MOSFETDopingType},
+    error::SimError,
+    CircuitState, ComponentMut, ComponentValueEnum,
+};
+
+pub const ESC_OK: c_int = 0;
+pub const ESC_ERR_NULL: c_int = 1;
+pub const ESC_ERR_INVALID_ID: c_int = 2;
+pub const ESC_ERR_WRONG_KIND: c_int = 3;
+/// The tick or solve ran but did not converge.
+pub const ESC_ERR_NOT_CONVERGED: c_int = 4;
+pub const ESC_ERR_NON_FINITE: c_int = 5;
+pub const ESC_ERR_PANIC: c_int = 6;
+/// A small-signal system without a unique solution, see `SimError::SingularAcSystem`.
+pub const ESC_ERR_SINGULAR: c_int = 7;
+
+pub struct EscCircuit(CircuitState);
+
+/// Parameters of `esc_circuit_add_mosfet`, the level-1 model of `MOSFETComponentValue`.
+#[repr(C)]
+pub struct EscMosfetParams {
+    /// Nonzero for P-channel.
+    pub p_channel: c_int,
+    pub beta: f64,
+    pub threshold_voltage: f64,
+    pub body_diode_saturation_current: f64,
+    pub body_diode_ideality_factor: f64,
+}
+
+impl From<SimError> for c_int {
+    fn from(err: SimError) -> Self {
+        match err {
+            SimError::UnknownComponent(_) | SimError::UnknownNetId(_) => ESC_ERR_INVALID_ID,
+            SimError::WrongComponentKind { .. } => ESC_ERR_WRONG_KIND,
+            SimError::NonFiniteValue { .. } => ESC_ERR_NON_FINITE,
+            SimError::NotConverged(_) => ESC_ERR_NOT_CONVERGED,
+            // the C interface doesn't name components or signals or read netlists, probe specs
+            // or values as text
+            SimError::DuplicateName(_)
+            | SimError::UnknownNet(_)
+            | SimError::DuplicateNetAlias { .. }
+            | SimError::UnknownSignal(_)
+            | SimError::WrongNetCount { .. }
+            | SimError::Netlist { .. }
+            | SimError::InvalidProbe { .. }
+            | SimError::InvalidValue(_)
+            | SimError::UnknownPart { .. } => ESC_ERR_PANIC,
+            // nor turns on `SolverConfig::validate` or `SolverConfig::strict_dt`
+            SimError::InvalidCircuit(_) | SimError::TimeStepTooLarge { .. } => ESC_ERR_PANIC,
+            // nor has simulation sets
+            SimError::UnknownCircuit(_) | SimError::DuplicateCircuit(_) => ESC_ERR_PANIC,
+            SimError::SingularAcSystem(_) => ESC_ERR_SINGULAR,
+        }
+    }
+}
+
+/// Run `f` on the circuit behind `circuit`, turning null handles and panics into status codes.
+unsafe fn with_circuit(
+    circuit: *mut EscCircuit,
+    f: impl FnOnce(&mut CircuitState) -> Result<c_int, c_int>,
+) -> c_int {
+    let Some(circuit) = circuit.as_mut() else {
+        return ESC_ERR_NULL;
+    };
+    catch_unwind(AssertUnwindSafe(|| f(&mut circuit.0))).map_or(ESC_ERR_PANIC, |status| {
+        status.unwrap_or_else(|status| status)
+    })
+}
+
+/// Write `value` to `out` (if not null).
+unsafe fn write_out<T>(out: *mut T, value: T) {
+    if let Some(out) = out.as_mut() {
+        *out = value;
+    }
+}
+
+unsafe fn add_component(
+    circuit: *mut EscCircuit,
+    value: ComponentValueEnum,
+    nets: &[usize],
+    component_out: *mut usize,
+) -> c_int {
+    with_circuit(circuit, |circuit| {
+        if nets.iter().any(|&net| net >= circuit.n_nets()) {
+            return Err(ESC_ERR_INVALID_ID);
+        }
+        let component = circuit.create_component(value, nets);
+        write_out(component_out, component);
+        Ok(ESC_OK)
+    })
+}
+
+/// A new, empty circuit, to be released with `esc_circuit_free`.
+#[no_mangle]
+pub extern "C" fn esc_circuit_new() -> *mut EscCircuit {
+    Box::into_raw(Box::new(EscCircuit(CircuitState::new_empty())))
+}
+
+/// # Safety
+/// `circuit` must be null or a handle from `esc_circuit_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_free(circuit: *mut EscCircuit) {
+    if !circuit.is_null() {
+        drop(Box::from_raw(circuit));
+    }
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle, `net_out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_add_net(
+    circuit: *mut EscCircuit,
+    net_out: *mut usize,
+) -> c_int {
+    with_circuit(circuit, |circuit| {
+        write_out(net_out, circuit.create_net());
+        Ok(ESC_OK)
+    })
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle, `component_out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_add_resistor(
+    circuit: *mut EscCircuit,
+    a: usize,
+    b: usize,
+    ohms: f64,
+    component_out: *mut usize,
+) -> c_int {
+    let value = ComponentValueEnum::Linear(LinearComponentValue::Resistive(ohms));
+    add_component(circuit, value, &[a, b], component_out)
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle, `component_out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_add_capacitor(
+    circuit: *mut EscCircuit,
+    a: usize,
+    b: usize,
+    farads: f64,
+    component_out: *mut usize,
+) -> c_int {
+    let value = ComponentValueEnum::Linear(LinearComponentValue::Capacitive(farads));
+    add_component(circuit, value, &[a, b], component_out)
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle, `component_out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_add_inductor(
+    circuit: *mut EscCircuit,
+    a: usize,
+    b: usize,
+    henries: f64,
+    component_out: *mut usize,
+) -> c_int {
+    let value = ComponentValueEnum::Linear(LinearComponentValue::Inductive(henries));
+    add_component(circuit, value, &[a, b], component_out)
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle, `component_out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_add_source(
+    circuit: *mut EscCircuit,
+    a: usize,
+    b: usize,
+    volts: f64,
+    component_out: *mut usize,
+) -> c_int {
+    let value = ComponentValueEnum::Linear(LinearComponentValue::Source(volts));
+    add_component(circuit, value, &[a, b], component_out)
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle, `params` null or valid for reads and
+/// `component_out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_add_mosfet(
+    circuit: *mut EscCircuit,
+    source: usize,
+    gate: usize,
+    drain: usize,
+    params: *const EscMosfetParams,
+    component_out: *mut usize,
+) -> c_int {
+    let Some(params) = params.as_ref() else {
+        return ESC_ERR_NULL;
+    };
+    let value = ComponentValueEnum::MOSFET(MOSFETComponentValue {
+        ty: if params.p_channel != 0 {
+            MOSFETDopingType::PChannel
+        } else {
+            MOSFETDopingType::NChannel
+        },
+        beta: params.beta,
+        threshold_voltage: params.threshold_voltage,
+        body_diode_saturation_current: params.body_diode_saturation_current,
+        body_diode_ideality_facotor: params.body_diode_ideality_factor,
+        saturation_knee: 8.0,
+        multiplicity: 1.0,
+        avalanche: None,
+    });
+    add_component(circuit, value, &[source, gate, drain], component_out)
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_set_capacitor_voltage(
+    circuit: *mut EscCircuit,
+    component: usize,
+    volts: f64,
+) -> c_int {
+    with_circuit(circuit, |circuit| {
+        circuit.set_initial_capacitor_voltage(component, volts)?;
+        Ok(ESC_OK)
+    })
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_set_source(
+    circuit: *mut EscCircuit,
+    component: usize,
+    volts: f64,
+) -> c_int {
+    with_circuit(circuit, |circuit| {
+        if component >= circuit.n_components() {
+            return Err(ESC_ERR_INVALID_ID);
+        }
+        match circuit.component_mut(component) {
+            ComponentMut::Linear(v) => match &mut v.value {
+                LinearComponentValue::Source(e) => *e = volts,
+                _ => return Err(ESC_ERR_WRONG_KIND),
+            },
+            _ => return Err(ESC_ERR_WRONG_KIND),
+        }
+        Ok(ESC_OK)
+    })
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_tick(circuit: *mut EscCircuit, dt: f64) -> c_int {
+    with_circuit(circuit, |circuit| {
+        Ok(if circuit.try_tick(dt)? {
+            ESC_OK
+        } else {
+            ESC_ERR_NOT_CONVERGED
+        })
+    })
+}
+
+/// # Safety
+/// `circuit` must be null or a live handle, `volts_out` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn esc_circuit_net_voltage(
+    circuit: *mut EscCircuit,
+    net: usize,
+    volts_out: *mut f64,
+) -> c_int {
+    with_circuit(circuit, |circuit| {
+        if net >= circuit.n_nets() {
+            return Err(ESC_ERR_INVALID_ID);
+        }
+        write_out(volts_out, circuit.net_voltage(net));
+        Ok(ESC_OK)
+    })
+}
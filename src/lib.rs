@@ -0,0 +1,8 @@
+pub mod linalg;
+pub mod sim;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -3,6 +3,10 @@ use std::{
     ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub, SubAssign},
 };
 
+pub mod banded;
+pub mod sparse;
+pub mod view;
+
 pub trait Field:
     Sized
     + Clone
@@ -20,6 +24,12 @@ pub trait Field:
     + From<f32>
 {
     // fn from_i32(n: i32) -> Self;
+    /// Size of the element, used to pick pivots.
+    fn magnitude(self) -> f64;
+    /// The complex conjugate, the element itself for real fields.
+    fn conj(self) -> Self {
+        self
+    }
 }
 macro_rules! impl_Field {
     ($($T: ident),*) => {$(
@@ -27,11 +37,140 @@ macro_rules! impl_Field {
             // fn from_i32(n: i32) -> Self {
             //     n as Self
             // }
+            fn magnitude(self) -> f64 {
+                f64::from(self.abs())
+            }
         }
     )*};
 }
 impl_Field!(f32, f64);
 
+/// A `Field` of real numbers with the usual elementary functions.
+pub trait RealField: Field + PartialOrd {
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn atan2(self, x: Self) -> Self;
+}
+macro_rules! impl_RealField {
+    ($($T: ident),*) => {$(
+        impl RealField for $T {
+            fn sqrt(self) -> Self {
+                $T::sqrt(self)
+            }
+            fn abs(self) -> Self {
+                $T::abs(self)
+            }
+            fn sin(self) -> Self {
+                $T::sin(self)
+            }
+            fn cos(self) -> Self {
+                $T::cos(self)
+            }
+            fn atan2(self, x: Self) -> Self {
+                $T::atan2(self, x)
+            }
+        }
+    )*};
+}
+impl_RealField!(f32, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex<T: RealField> {
+    pub re: T,
+    pub im: T,
+}
+impl<T: RealField> Complex<T> {
+    pub fn new(re: T, im: T) -> Self {
+        Self { re, im }
+    }
+    /// The complex number of magnitude `r` at the phase angle `theta` in radians.
+    pub fn from_polar(r: T, theta: T) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+    pub fn conj(self) -> Self {
+        Self::new(self.re, T::from(0) - self.im)
+    }
+    pub fn norm_sq(self) -> T {
+        self.re * self.re + self.im * self.im
+    }
+    pub fn abs(self) -> T {
+        self.norm_sq().sqrt()
+    }
+    /// Phase angle in radians, in `(-pi, pi]`.
+    pub fn arg(self) -> T {
+        self.im.atan2(self.re)
+    }
+}
+impl<T: RealField> From<i16> for Complex<T> {
+    fn from(value: i16) -> Self {
+        Self::new(value.into(), 0.into())
+    }
+}
+impl<T: RealField> From<f32> for Complex<T> {
+    fn from(value: f32) -> Self {
+        Self::new(value.into(), 0.into())
+    }
+}
+impl<T: RealField> Add<Self> for Complex<T> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+impl<T: RealField> Sub<Self> for Complex<T> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+impl<T: RealField> Mul<Self> for Complex<T> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+impl<T: RealField> Div<Self> for Complex<T> {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        let d = rhs.norm_sq();
+        let n = self * rhs.conj();
+        Self::new(n.re / d, n.im / d)
+    }
+}
+impl<T: RealField> AddAssign<Self> for Complex<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl<T: RealField> SubAssign<Self> for Complex<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl<T: RealField> MulAssign<Self> for Complex<T> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+impl<T: RealField> DivAssign<Self> for Complex<T> {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+impl<T: RealField> Field for Complex<T> {
+    fn magnitude(self) -> f64 {
+        self.re.magnitude().hypot(self.im.magnitude())
+    }
+    fn conj(self) -> Self {
+        Self::new(self.re, T::from(0) - self.im)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Mat<T: Field> {
     n_rows: usize,
@@ -51,9 +190,29 @@ impl<T: Field> Mat<T> {
             data: Vec::from_iter((0..COLS).flat_map(|j| (0..ROWS).map(move |i| data[i][j]))),
         }
     }
+    pub fn zeros(n_rows: usize, n_cols: usize) -> Self {
+        Self {
+            n_rows,
+            n_cols,
+            data: vec![0.into(); n_rows * n_cols],
+        }
+    }
+    pub fn identity(n: usize) -> Self {
+        let mut out = Self::zeros(n, n);
+        for i in 0..n {
+            out[[i, i]] = 1.into();
+        }
+        out
+    }
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
     pub fn from_raw(n_cols: usize, data: Vec<T>) {
         assert!(
-            data.len() % n_cols == 0,
+            data.len().is_multiple_of(n_cols),
             "Raw data does not have correct number of elements for the number of rows"
         );
     }
@@ -89,37 +248,86 @@ impl<T: Field> Mat<T> {
         self.transpose();
         self
     }
+    /// Invert in place, by `solve` against the identity.
+    ///
+    /// Panics if `self` is singular, see `try_inverse`.
     pub fn inverse(&mut self) {
+        *self = self.try_inverse().expect("Matrix is singular.");
+    }
+    /// The inverse, `None` if `self` is singular.
+    pub fn try_inverse(&self) -> Option<Self> {
         _assert_square!(self);
-        todo!("inverse");
+        self.solve(&Self::identity(self.n_rows))
     }
     pub fn i(mut self) -> Self {
         self.inverse();
         self
     }
 
-    pub fn matmul(&self, rhs: &Self) -> Self {
+    /// Solve `self * x = rhs` by gaussian elimination with partial pivoting.
+    ///
+    /// Returns `None` if `self` is singular.
+    pub fn solve(&self, rhs: &Self) -> Option<Self> {
+        _assert_square!(self);
         assert_eq!(
-            self.n_cols, rhs.n_rows,
-            "Matrix dimensions are not compatible for matmul."
+            self.n_rows, rhs.n_rows,
+            "Matrix dimensions are not compatible for solve."
         );
-        let mut out = Mat {
-            n_cols: rhs.n_cols,
-            n_rows: self.n_rows,
-            data: Vec::with_capacity(self.n_rows * rhs.n_cols),
-        };
-
-        for i in 0..self.n_rows {
-            for j in 0..rhs.n_cols {
-                let mut accum = 0.into();
-                for k in 0..self.n_cols {
-                    accum += self[[i, k]] * rhs[[k, j]];
+        let n = self.n_rows;
+        let mut a = self.clone();
+        let mut x = rhs.clone();
+        for k in 0..n {
+            let pivot =
+                (k..n).max_by(|&i, &j| a[[i, k]].magnitude().total_cmp(&a[[j, k]].magnitude()))?;
+            if a[[pivot, k]].magnitude() == 0.0 {
+                return None;
+            }
+            for j in 0..n {
+                a.swap([k, j], [pivot, j]);
+            }
+            for j in 0..x.n_cols {
+                x.swap([k, j], [pivot, j]);
+            }
+            for i in k + 1..n {
+                let factor = a[[i, k]] / a[[k, k]];
+                for j in k..n {
+                    let v = a[[k, j]];
+                    a[[i, j]] -= factor * v;
+                }
+                for j in 0..x.n_cols {
+                    let v = x[[k, j]];
+                    x[[i, j]] -= factor * v;
+                }
+            }
+        }
+        for j in 0..x.n_cols {
+            for i in (0..n).rev() {
+                let mut accum = x[[i, j]];
+                for k in i + 1..n {
+                    accum -= a[[i, k]] * x[[k, j]];
                 }
-                out.data.push(accum);
+                x[[i, j]] = accum / a[[i, i]];
             }
         }
+        Some(x)
+    }
 
-        out
+    pub fn matmul(&self, rhs: &Self) -> Self {
+        self.as_view().matmul(&rhs.as_view())
+    }
+    /// The number of diagonals below the main one and above it (`[lower, upper]`) holding
+    /// entries of magnitude above `tolerance`, see `banded::BandedMat::from_mat`.
+    pub fn bandwidths(&self, tolerance: f64) -> [usize; 2] {
+        let mut bandwidths = [0; 2];
+        for j in 0..self.n_cols {
+            for i in 0..self.n_rows {
+                if self[[i, j]].magnitude() > tolerance {
+                    bandwidths[0] = bandwidths[0].max(i.saturating_sub(j));
+                    bandwidths[1] = bandwidths[1].max(j.saturating_sub(i));
+                }
+            }
+        }
+        bandwidths
     }
     pub fn to_scalar(self) -> T {
         assert_eq!(
@@ -133,6 +341,33 @@ impl<T: Field> Mat<T> {
         self.data[0]
     }
 }
+impl<T: RealField> Mat<T> {
+    /// Least-squares solution of the overdetermined `self * x = rhs`, minimizing the sum of
+    /// squared residuals of each column of `rhs`, from the normal equations.
+    ///
+    /// Returns `None` if the columns of `self` are linearly dependent.
+    pub fn lstsq(&self, rhs: &Self) -> Option<Self> {
+        assert_eq!(
+            self.n_rows, rhs.n_rows,
+            "Matrix dimensions are not compatible for lstsq."
+        );
+        let n = self.n_cols;
+        let mut normal = Self::zeros(n, n);
+        let mut projected = Self::zeros(n, rhs.n_cols);
+        for k in 0..self.n_rows {
+            for i in 0..n {
+                let a = self[[k, i]];
+                for j in 0..n {
+                    normal[[i, j]] += a * self[[k, j]];
+                }
+                for j in 0..rhs.n_cols {
+                    projected[[i, j]] += a * rhs[[k, j]];
+                }
+            }
+        }
+        normal.solve(&projected)
+    }
+}
 impl<T: Field> Index<[usize; 2]> for Mat<T> {
     type Output = T;
     fn index(&self, [i, j]: [usize; 2]) -> &Self::Output {
@@ -0,0 +1,210 @@
+//! Square matrices whose entries are all within a few diagonals of the main one, as the nodal
+//! matrices of ladder networks (the RC sections of a winding model, a long LC filter) are, and
+//! their solves in `O(n l (l + u))` for `l` diagonals below the main one and `u` above, rather than
+//! the `O(n^3)` of `Mat::solve`.
+
+use std::ops::{Index, IndexMut};
+
+use super::{Field, Mat};
+
+/// A square matrix with nonzero entries only within `lower` diagonals below the main one and
+/// `upper` above it.
+///
+/// Stored by rows, each with room for the `lower` further diagonals above the band that the row
+/// exchanges of `solve` fill in.
+#[derive(Debug, Clone)]
+pub struct BandedMat<T: Field> {
+    n: usize,
+    lower: usize,
+    upper: usize,
+    data: Vec<T>,
+}
+impl<T: Field> BandedMat<T> {
+    pub fn zeros(n: usize, lower: usize, upper: usize) -> Self {
+        Self {
+            n,
+            lower,
+            upper,
+            data: vec![0.into(); n * (2 * lower + upper + 1)],
+        }
+    }
+    /// The band of `mat` (see `Mat::bandwidths`), `None` if it isn't square or the band covers
+    /// more than half of it, where the dense solve costs about as much.
+    pub fn from_mat(mat: &Mat<T>, tolerance: f64) -> Option<Self> {
+        let n = mat.n_rows();
+        if mat.n_cols() != n {
+            return None;
+        }
+        let [lower, upper] = mat.bandwidths(tolerance);
+        if 2 * (lower + upper) > n {
+            return None;
+        }
+        let mut out = Self::zeros(n, lower, upper);
+        for i in 0..n {
+            for j in i.saturating_sub(lower)..=(i + upper).min(n - 1) {
+                out[[i, j]] = mat[[i, j]];
+            }
+        }
+        Some(out)
+    }
+    pub fn to_mat(&self) -> Mat<T> {
+        let mut out = Mat::zeros(self.n, self.n);
+        for i in 0..self.n {
+            for j in i.saturating_sub(self.lower)..=(i + self.upper).min(self.n - 1) {
+                out[[i, j]] = self[[i, j]];
+            }
+        }
+        out
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+    pub fn lower(&self) -> usize {
+        self.lower
+    }
+    pub fn upper(&self) -> usize {
+        self.upper
+    }
+    /// Entry `[i, j]`, zero outside the band.
+    pub fn get(&self, i: usize, j: usize) -> T {
+        if j + self.lower < i || j > i + self.upper {
+            0.into()
+        } else {
+            self[[i, j]]
+        }
+    }
+
+    /// Row `i`, column `j`, which may be up to `lower + upper` above the diagonal.
+    fn raw_index(&self, i: usize, j: usize) -> usize {
+        debug_assert!(
+            i < self.n && j < self.n && j + self.lower >= i && j <= i + self.lower + self.upper,
+            "Index out of the band."
+        );
+        i * (2 * self.lower + self.upper + 1) + j + self.lower - i
+    }
+
+    /// Solve `self * x = rhs` by banded LU with partial pivoting.
+    ///
+    /// Returns `None` if `self` is singular.
+    pub fn solve(&self, rhs: &Mat<T>) -> Option<Mat<T>> {
+        assert_eq!(
+            self.n,
+            rhs.n_rows(),
+            "Matrix dimensions are not compatible for solve."
+        );
+        let (n, lower) = (self.n, self.lower);
+        // the last column a row can reach once rows `lower` below it were exchanged into it.
+        let reach = lower + self.upper;
+        let mut a = self.clone();
+        let mut x = rhs.clone();
+        for k in 0..n {
+            let last = (k + lower).min(n - 1);
+            let end = (k + reach).min(n - 1);
+            let pivot = (k..=last).max_by(|&i, &j| {
+                let a_i = a.data[a.raw_index(i, k)].magnitude();
+                a_i.total_cmp(&a.data[a.raw_index(j, k)].magnitude())
+            })?;
+            if a.data[a.raw_index(pivot, k)].magnitude() == 0.0 {
+                return None;
+            }
+            if pivot != k {
+                for j in k..=end {
+                    let (p, q) = (a.raw_index(k, j), a.raw_index(pivot, j));
+                    a.data.swap(p, q);
+                }
+                for j in 0..x.n_cols() {
+                    let v = x[[k, j]];
+                    x[[k, j]] = x[[pivot, j]];
+                    x[[pivot, j]] = v;
+                }
+            }
+            let diagonal = a.data[a.raw_index(k, k)];
+            for i in k + 1..=last {
+                let factor = a.data[a.raw_index(i, k)] / diagonal;
+                for j in k..=end {
+                    let v = a.data[a.raw_index(k, j)];
+                    let index = a.raw_index(i, j);
+                    a.data[index] -= factor * v;
+                }
+                for j in 0..x.n_cols() {
+                    let v = x[[k, j]];
+                    x[[i, j]] -= factor * v;
+                }
+            }
+        }
+        for j in 0..x.n_cols() {
+            for i in (0..n).rev() {
+                let mut accum = x[[i, j]];
+                for k in i + 1..=(i + reach).min(n - 1) {
+                    accum -= a.data[a.raw_index(i, k)] * x[[k, j]];
+                }
+                x[[i, j]] = accum / a.data[a.raw_index(i, i)];
+            }
+        }
+        Some(x)
+    }
+
+    /// Solve `self * x = rhs` for a tridiagonal `self` (`lower` and `upper` at most 1) by the
+    /// Thomas algorithm: elimination without pivoting, in `O(n)`. Stable for the diagonally
+    /// dominant matrices of RC and RL ladders; `solve` for the rest.
+    ///
+    /// Returns `None` if elimination meets a zero pivot.
+    pub fn solve_tridiagonal(&self, rhs: &Mat<T>) -> Option<Mat<T>> {
+        assert!(
+            self.lower <= 1 && self.upper <= 1,
+            "Matrix must be tridiagonal."
+        );
+        assert_eq!(
+            self.n,
+            rhs.n_rows(),
+            "Matrix dimensions are not compatible for solve."
+        );
+        let n = self.n;
+        let mut x = rhs.clone();
+        // the superdiagonal of the eliminated matrix, its diagonal scaled to 1.
+        let mut upper = vec![T::from(0); n];
+        for i in 0..n {
+            let mut pivot = self.get(i, i);
+            if i > 0 {
+                let below = self.get(i, i - 1);
+                pivot -= below * upper[i - 1];
+                for j in 0..x.n_cols() {
+                    let v = x[[i - 1, j]];
+                    x[[i, j]] -= below * v;
+                }
+            }
+            if pivot.magnitude() == 0.0 {
+                return None;
+            }
+            if i + 1 < n {
+                upper[i] = self.get(i, i + 1) / pivot;
+            }
+            for j in 0..x.n_cols() {
+                x[[i, j]] /= pivot;
+            }
+        }
+        for j in 0..x.n_cols() {
+            for i in (0..n.saturating_sub(1)).rev() {
+                let v = x[[i + 1, j]];
+                x[[i, j]] -= upper[i] * v;
+            }
+        }
+        Some(x)
+    }
+}
+impl<T: Field> Index<[usize; 2]> for BandedMat<T> {
+    type Output = T;
+    /// Panics outside the band in debug builds, see `get`.
+    fn index(&self, [i, j]: [usize; 2]) -> &Self::Output {
+        debug_assert!(j <= i + self.upper, "Index out of the band.");
+        &self.data[self.raw_index(i, j)]
+    }
+}
+impl<T: Field> IndexMut<[usize; 2]> for BandedMat<T> {
+    fn index_mut(&mut self, [i, j]: [usize; 2]) -> &mut Self::Output {
+        debug_assert!(j <= i + self.upper, "Index out of the band.");
+        let k = self.raw_index(i, j);
+        &mut self.data[k]
+    }
+}
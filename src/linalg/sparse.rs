@@ -0,0 +1,374 @@
+//! Sparse matrices in compressed sparse row form and iterative solves of them: conjugate
+//! gradients for symmetric positive definite systems (conductance matrices), BiCGSTAB for the
+//! rest (controlled sources, the complex MNA systems of `sim::ac`), each with an optional
+//! Jacobi or ILU(0) preconditioner.
+
+use super::{Field, Mat, RealField};
+
+/// A matrix of `n_rows` rows stored as the column and value of each nonzero entry, row by row,
+/// columns ascending within a row.
+#[derive(Debug, Clone)]
+pub struct CsrMat<T: Field> {
+    n_rows: usize,
+    n_cols: usize,
+    /// The entries of row `i` are `row_starts[i]..row_starts[i + 1]`.
+    row_starts: Vec<usize>,
+    cols: Vec<usize>,
+    values: Vec<T>,
+}
+impl<T: Field> CsrMat<T> {
+    /// The matrix with the entries `(i, j, value)`, those at the same place summed (as stamps
+    /// are).
+    pub fn from_triplets(
+        n_rows: usize,
+        n_cols: usize,
+        triplets: impl IntoIterator<Item = (usize, usize, T)>,
+    ) -> Self {
+        let mut triplets = triplets.into_iter().collect::<Vec<_>>();
+        triplets.sort_by_key(|&(i, j, _)| (i, j));
+        let mut row_starts = vec![0; n_rows + 1];
+        let mut cols = Vec::with_capacity(triplets.len());
+        let mut values: Vec<T> = Vec::with_capacity(triplets.len());
+        let mut last = None;
+        for (i, j, value) in triplets {
+            assert!(i < n_rows && j < n_cols, "Index out of bounds.");
+            if last == Some((i, j)) {
+                *values.last_mut().unwrap() += value;
+                continue;
+            }
+            last = Some((i, j));
+            row_starts[i + 1] += 1;
+            cols.push(j);
+            values.push(value);
+        }
+        for i in 0..n_rows {
+            row_starts[i + 1] += row_starts[i];
+        }
+        Self {
+            n_rows,
+            n_cols,
+            row_starts,
+            cols,
+            values,
+        }
+    }
+    /// The entries of `mat` of magnitude above `tolerance`.
+    pub fn from_mat(mat: &Mat<T>, tolerance: f64) -> Self {
+        let triplets = (0..mat.n_rows()).flat_map(|i| {
+            (0..mat.n_cols())
+                .map(move |j| (i, j, mat[[i, j]]))
+                .filter(|&(_, _, value)| value.magnitude() > tolerance)
+        });
+        Self::from_triplets(mat.n_rows(), mat.n_cols(), triplets)
+    }
+    pub fn to_mat(&self) -> Mat<T> {
+        let mut out = Mat::zeros(self.n_rows, self.n_cols);
+        for i in 0..self.n_rows {
+            for (j, value) in self.row(i) {
+                out[[i, j]] = value;
+            }
+        }
+        out
+    }
+
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+    /// The number of entries stored.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+    /// The `(column, value)` of each entry of row `i`.
+    pub fn row(&self, i: usize) -> impl Iterator<Item = (usize, T)> + '_ {
+        let range = self.row_starts[i]..self.row_starts[i + 1];
+        self.cols[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.values[range].iter().copied())
+    }
+    /// Entry `[i, j]`, zero where none is stored.
+    pub fn get(&self, i: usize, j: usize) -> T {
+        self.position(i, j).map_or(0.into(), |k| self.values[k])
+    }
+    fn position(&self, i: usize, j: usize) -> Option<usize> {
+        let start = self.row_starts[i];
+        self.cols[start..self.row_starts[i + 1]]
+            .binary_search(&j)
+            .ok()
+            .map(|k| start + k)
+    }
+
+    /// `self * x`.
+    pub fn matvec(&self, x: &[T]) -> Vec<T> {
+        assert_eq!(
+            self.n_cols,
+            x.len(),
+            "Matrix dimensions are not compatible for matvec."
+        );
+        (0..self.n_rows)
+            .map(|i| {
+                self.row(i)
+                    .fold(0.into(), |accum: T, (j, value)| accum + value * x[j])
+            })
+            .collect()
+    }
+
+    /// Solve `self * x = b` by BiCGSTAB (right preconditioned), for any square system, starting
+    /// over from the solution so far where it breaks down.
+    pub fn solve_bicgstab(&self, b: &[T], config: &IterativeConfig) -> (Vec<T>, IterativeReport) {
+        self.assert_solvable(b);
+        let preconditioner = Preconditioned::new(self, config.preconditioner);
+        let n = self.n_rows;
+        let b_norm = norm(b);
+        let mut x = vec![T::from(0); n];
+        let mut r = b.to_vec();
+        let mut r_hat = r.clone();
+        let (mut rho, mut alpha, mut omega) = (T::from(1), T::from(1), T::from(1));
+        let mut v = vec![T::from(0); n];
+        let mut p = vec![T::from(0); n];
+        let mut report = IterativeReport::start(norm(&r), b_norm);
+        while !report.converged(config) && report.iterations < config.max_iterations {
+            report.iterations += 1;
+            let rho_next = dot(&r_hat, &r);
+            if rho_next.magnitude() <= f64::EPSILON * norm(&r_hat) * norm(&r) {
+                // breakdown, `r` (all but) orthogonal to the shadow residual: start over from
+                // `x` with `r` as the shadow residual, as the first iteration would.
+                r_hat.clone_from(&r);
+                (rho, alpha, omega) = (T::from(1), T::from(1), T::from(1));
+                v.fill(T::from(0));
+                p.fill(T::from(0));
+                continue;
+            }
+            let beta = (rho_next / rho) * (alpha / omega);
+            rho = rho_next;
+            for k in 0..n {
+                p[k] = r[k] + beta * (p[k] - omega * v[k]);
+            }
+            let y = preconditioner.apply(&p);
+            v = self.matvec(&y);
+            alpha = rho / dot(&r_hat, &v);
+            let s = (0..n).map(|k| r[k] - alpha * v[k]).collect::<Vec<_>>();
+            for k in 0..n {
+                x[k] += alpha * y[k];
+            }
+            report.set_residual(norm(&s), b_norm);
+            if report.converged(config) {
+                break;
+            }
+            let z = preconditioner.apply(&s);
+            let t = self.matvec(&z);
+            let t_t = dot(&t, &t);
+            if t_t.magnitude() == 0.0 {
+                break;
+            }
+            omega = dot(&t, &s) / t_t;
+            for k in 0..n {
+                x[k] += omega * z[k];
+                r[k] = s[k] - omega * t[k];
+            }
+            report.set_residual(norm(&r), b_norm);
+        }
+        (x, report)
+    }
+
+    fn assert_solvable(&self, b: &[T]) {
+        assert_eq!(self.n_rows, self.n_cols, "Matrix must be square.");
+        assert_eq!(
+            self.n_rows,
+            b.len(),
+            "Matrix dimensions are not compatible for solve."
+        );
+    }
+}
+impl<T: RealField> CsrMat<T> {
+    /// Solve `self * x = b` by preconditioned conjugate gradients, for a symmetric positive
+    /// definite `self`; on any other it may not converge.
+    pub fn solve_cg(&self, b: &[T], config: &IterativeConfig) -> (Vec<T>, IterativeReport) {
+        self.assert_solvable(b);
+        let preconditioner = Preconditioned::new(self, config.preconditioner);
+        let n = self.n_rows;
+        let b_norm = norm(b);
+        let mut x = vec![T::from(0); n];
+        let mut r = b.to_vec();
+        let mut z = preconditioner.apply(&r);
+        let mut p = z.clone();
+        let mut r_z = dot(&r, &z);
+        let mut report = IterativeReport::start(norm(&r), b_norm);
+        while !report.converged(config) && report.iterations < config.max_iterations {
+            report.iterations += 1;
+            let a_p = self.matvec(&p);
+            let p_a_p = dot(&p, &a_p);
+            if p_a_p.magnitude() == 0.0 {
+                break;
+            }
+            let alpha = r_z / p_a_p;
+            for k in 0..n {
+                x[k] += alpha * p[k];
+                r[k] -= alpha * a_p[k];
+            }
+            report.set_residual(norm(&r), b_norm);
+            z = preconditioner.apply(&r);
+            let r_z_next = dot(&r, &z);
+            let beta = r_z_next / r_z;
+            r_z = r_z_next;
+            for k in 0..n {
+                p[k] = z[k] + beta * p[k];
+            }
+        }
+        (x, report)
+    }
+}
+
+/// `sum conj(a_k) b_k`.
+fn dot<T: Field>(a: &[T], b: &[T]) -> T {
+    a.iter()
+        .zip(b)
+        .fold(0.into(), |accum: T, (&a, &b)| accum + a.conj() * b)
+}
+fn norm<T: Field>(x: &[T]) -> f64 {
+    x.iter()
+        .map(|x| x.magnitude() * x.magnitude())
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// How `CsrMat::solve_cg` and `solve_bicgstab` precondition the system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Preconditioner {
+    None,
+    /// Scale each row by its diagonal entry; rows without one (the branch rows of an MNA
+    /// system) are left as they are.
+    Jacobi,
+    /// The incomplete LU factorization on the pattern of the matrix, no fill-in. A zero pivot
+    /// is taken as 1.
+    Ilu0,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IterativeConfig {
+    /// Stop once the residual is at most this fraction of `b` (2-norms).
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    pub preconditioner: Preconditioner,
+}
+impl Default for IterativeConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-10,
+            max_iterations: 1000,
+            preconditioner: Preconditioner::Ilu0,
+        }
+    }
+}
+
+/// How an iterative solve went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterativeReport {
+    pub iterations: usize,
+    /// `|b - A x| / |b|` at the end, 0 for `b = 0`.
+    pub relative_residual: f64,
+}
+impl IterativeReport {
+    fn start(residual: f64, b_norm: f64) -> Self {
+        let mut report = Self {
+            iterations: 0,
+            relative_residual: 0.0,
+        };
+        report.set_residual(residual, b_norm);
+        report
+    }
+    fn set_residual(&mut self, residual: f64, b_norm: f64) {
+        self.relative_residual = if b_norm > 0.0 {
+            residual / b_norm
+        } else {
+            residual
+        };
+    }
+    /// Whether the residual is within `config.tolerance`.
+    pub fn converged(&self, config: &IterativeConfig) -> bool {
+        self.relative_residual <= config.tolerance
+    }
+}
+
+/// A `Preconditioner` set up for one matrix: `apply` gives `M^-1 x`.
+enum Preconditioned<'a, T: Field> {
+    None,
+    Jacobi(Vec<T>),
+    Ilu0 {
+        a: &'a CsrMat<T>,
+        /// The factors `L` (unit diagonal, below) and `U` in the pattern of `a`.
+        lu: Vec<T>,
+        diagonal: Vec<Option<usize>>,
+    },
+}
+impl<'a, T: Field> Preconditioned<'a, T> {
+    fn new(a: &'a CsrMat<T>, preconditioner: Preconditioner) -> Self {
+        let one = T::from(1);
+        let nonzero = |d: T| if d.magnitude() == 0.0 { one } else { d };
+        match preconditioner {
+            Preconditioner::None => Self::None,
+            Preconditioner::Jacobi => {
+                Self::Jacobi((0..a.n_rows).map(|i| one / nonzero(a.get(i, i))).collect())
+            }
+            Preconditioner::Ilu0 => {
+                let diagonal = (0..a.n_rows).map(|i| a.position(i, i)).collect::<Vec<_>>();
+                let mut lu = a.values.clone();
+                for i in 0..a.n_rows {
+                    let row = a.row_starts[i]..a.row_starts[i + 1];
+                    for k_pos in row.clone() {
+                        let k = a.cols[k_pos];
+                        if k >= i {
+                            break;
+                        }
+                        let pivot = diagonal[k].map_or(one, |d| nonzero(lu[d]));
+                        lu[k_pos] /= pivot;
+                        let factor = lu[k_pos];
+                        for j_pos in k_pos + 1..row.end {
+                            if let Some(k_j) = a.position(k, a.cols[j_pos]) {
+                                let v = lu[k_j];
+                                lu[j_pos] -= factor * v;
+                            }
+                        }
+                    }
+                }
+                Self::Ilu0 { a, lu, diagonal }
+            }
+        }
+    }
+    fn apply(&self, x: &[T]) -> Vec<T> {
+        match self {
+            Self::None => x.to_vec(),
+            Self::Jacobi(inverse) => x.iter().zip(inverse).map(|(&x, &d)| x * d).collect(),
+            Self::Ilu0 { a, lu, diagonal } => {
+                let one = T::from(1);
+                let n = a.n_rows;
+                let mut y = x.to_vec();
+                let row = |i: usize| {
+                    let range = a.row_starts[i]..a.row_starts[i + 1];
+                    a.cols[range.clone()].iter().copied().zip(&lu[range])
+                };
+                for i in 0..n {
+                    for (k, &l) in row(i).take_while(|&(k, _)| k < i) {
+                        let v = y[k];
+                        y[i] -= l * v;
+                    }
+                }
+                for i in (0..n).rev() {
+                    for (j, &u) in row(i).filter(|&(j, _)| j > i) {
+                        let v = y[j];
+                        y[i] -= u * v;
+                    }
+                    let pivot = diagonal[i].map_or(one, |d| lu[d]);
+                    y[i] /= if pivot.magnitude() == 0.0 { one } else { pivot };
+                }
+                y
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Borrowed rectangular blocks of a `Mat`, read and written in place rather than copied out: the
+//! rows and columns of the non-reference nets of a nodal matrix, one block of a block system.
+//!
+//! A block keeps the column-major layout of its matrix, each of its columns a contiguous run of
+//! the matrix's data `stride` (the matrix's row count) after the last. Disjoint mutable blocks
+//! come from splitting between columns (`Mat::split_at_col_mut`), which splits the data in two;
+//! blocks side by side across rows share the runs of their columns, so can only be written one
+//! at a time.
+
+use std::ops::{Index, IndexMut, Range};
+
+use super::{Field, Mat};
+
+/// A block of a `Mat`, see `Mat::view`.
+#[derive(Debug, Clone, Copy)]
+pub struct MatView<'a, T: Field> {
+    /// From entry `[0, 0]` of the block to its last entry.
+    data: &'a [T],
+    stride: usize,
+    n_rows: usize,
+    n_cols: usize,
+}
+
+/// A block of a `Mat` to write, see `Mat::view_mut`.
+#[derive(Debug)]
+pub struct MatViewMut<'a, T: Field> {
+    /// From entry `[0, 0]` of the block to its last entry.
+    data: &'a mut [T],
+    stride: usize,
+    n_rows: usize,
+    n_cols: usize,
+}
+
+/// The range of `data` (of columns `stride` apart) covered by the block at `rows`, `cols`.
+fn block_range(
+    stride: usize,
+    [n_rows, n_cols]: [usize; 2],
+    rows: &Range<usize>,
+    cols: &Range<usize>,
+) -> Range<usize> {
+    assert!(
+        rows.start <= rows.end
+            && rows.end <= n_rows
+            && cols.start <= cols.end
+            && cols.end <= n_cols,
+        "View out of bounds."
+    );
+    if rows.is_empty() || cols.is_empty() {
+        return 0..0;
+    }
+    let start = rows.start + cols.start * stride;
+    start..rows.end + (cols.end - 1) * stride
+}
+
+impl<T: Field> Mat<T> {
+    /// The whole matrix as a `MatView`.
+    pub fn as_view(&self) -> MatView<'_, T> {
+        self.view(0..self.n_rows, 0..self.n_cols)
+    }
+    /// The block at rows `rows` and columns `cols`.
+    pub fn view(&self, rows: Range<usize>, cols: Range<usize>) -> MatView<'_, T> {
+        let range = block_range(self.n_rows, [self.n_rows, self.n_cols], &rows, &cols);
+        MatView {
+            data: &self.data[range],
+            stride: self.n_rows,
+            n_rows: rows.len(),
+            n_cols: cols.len(),
+        }
+    }
+    pub fn as_view_mut(&mut self) -> MatViewMut<'_, T> {
+        MatViewMut {
+            stride: self.n_rows,
+            n_rows: self.n_rows,
+            n_cols: self.n_cols,
+            data: &mut self.data,
+        }
+    }
+    pub fn view_mut(&mut self, rows: Range<usize>, cols: Range<usize>) -> MatViewMut<'_, T> {
+        self.as_view_mut().into_view(rows, cols)
+    }
+    /// Columns `..j` and `j..`, to write both at once.
+    pub fn split_at_col_mut(&mut self, j: usize) -> (MatViewMut<'_, T>, MatViewMut<'_, T>) {
+        self.as_view_mut().split_at_col(j)
+    }
+}
+
+impl<'a, T: Field> MatView<'a, T> {
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+    /// Column `j`, contiguous in the matrix's data.
+    pub fn col(&self, j: usize) -> &'a [T] {
+        assert!(j < self.n_cols, "Index out of bounds.");
+        if self.n_rows == 0 {
+            return &[];
+        }
+        &self.data[j * self.stride..j * self.stride + self.n_rows]
+    }
+    /// The block at rows `rows` and columns `cols` of this one.
+    pub fn view(&self, rows: Range<usize>, cols: Range<usize>) -> MatView<'a, T> {
+        let range = block_range(self.stride, [self.n_rows, self.n_cols], &rows, &cols);
+        MatView {
+            data: &self.data[range],
+            stride: self.stride,
+            n_rows: rows.len(),
+            n_cols: cols.len(),
+        }
+    }
+    /// A copy of the block as a matrix of its own.
+    pub fn to_mat(&self) -> Mat<T> {
+        Mat {
+            n_rows: self.n_rows,
+            n_cols: self.n_cols,
+            data: (0..self.n_cols)
+                .flat_map(|j| self.col(j))
+                .copied()
+                .collect(),
+        }
+    }
+    pub fn tr(&self) -> T {
+        let mut accum: T = 0.into();
+        for i in 0..usize::min(self.n_rows, self.n_cols) {
+            accum += self[[i, i]];
+        }
+        accum
+    }
+    pub fn matmul(&self, rhs: &MatView<'_, T>) -> Mat<T> {
+        assert_eq!(
+            self.n_cols, rhs.n_rows,
+            "Matrix dimensions are not compatible for matmul."
+        );
+        let mut out = Mat {
+            n_cols: rhs.n_cols,
+            n_rows: self.n_rows,
+            data: Vec::with_capacity(self.n_rows * rhs.n_cols),
+        };
+
+        // column by column, as `data` is stored.
+        for j in 0..rhs.n_cols {
+            for i in 0..self.n_rows {
+                let mut accum = 0.into();
+                for k in 0..self.n_cols {
+                    accum += self[[i, k]] * rhs[[k, j]];
+                }
+                out.data.push(accum);
+            }
+        }
+
+        out
+    }
+}
+
+impl<'a, T: Field> MatViewMut<'a, T> {
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+    /// Read the block, for the operations of `MatView`.
+    pub fn as_view(&self) -> MatView<'_, T> {
+        MatView {
+            data: self.data,
+            stride: self.stride,
+            n_rows: self.n_rows,
+            n_cols: self.n_cols,
+        }
+    }
+    /// Write the block through a shorter borrow, keeping this one.
+    pub fn reborrow(&mut self) -> MatViewMut<'_, T> {
+        MatViewMut {
+            data: self.data,
+            stride: self.stride,
+            n_rows: self.n_rows,
+            n_cols: self.n_cols,
+        }
+    }
+    pub fn col_mut(&mut self, j: usize) -> &mut [T] {
+        assert!(j < self.n_cols, "Index out of bounds.");
+        if self.n_rows == 0 {
+            return &mut [];
+        }
+        &mut self.data[j * self.stride..j * self.stride + self.n_rows]
+    }
+    /// The block at rows `rows` and columns `cols` of this one.
+    pub fn into_view(self, rows: Range<usize>, cols: Range<usize>) -> MatViewMut<'a, T> {
+        let range = block_range(self.stride, [self.n_rows, self.n_cols], &rows, &cols);
+        MatViewMut {
+            data: &mut self.data[range],
+            stride: self.stride,
+            n_rows: rows.len(),
+            n_cols: cols.len(),
+        }
+    }
+    /// Columns `..j` and `j..` of the block.
+    pub fn split_at_col(self, j: usize) -> (MatViewMut<'a, T>, MatViewMut<'a, T>) {
+        assert!(j <= self.n_cols, "View out of bounds.");
+        let (stride, n_rows, n_cols) = (self.stride, self.n_rows, self.n_cols);
+        // the right block starts at its first column, the left keeps everything before it.
+        let (left, right) = self.data.split_at_mut((j * stride).min(self.data.len()));
+        let left_len = if j == 0 || n_rows == 0 {
+            0
+        } else {
+            (j - 1) * stride + n_rows
+        };
+        (
+            MatViewMut {
+                data: &mut left[..left_len],
+                stride,
+                n_rows,
+                n_cols: j,
+            },
+            MatViewMut {
+                data: right,
+                stride,
+                n_rows,
+                n_cols: n_cols - j,
+            },
+        )
+    }
+    /// Overwrite the block with `src`, of the same size.
+    pub fn copy_from(&mut self, src: &MatView<'_, T>) {
+        assert_eq!(
+            [self.n_rows, self.n_cols],
+            [src.n_rows, src.n_cols],
+            "Matrix dimensions are not compatible for copy."
+        );
+        for j in 0..self.n_cols {
+            self.col_mut(j).copy_from_slice(src.col(j));
+        }
+    }
+    pub fn fill(&mut self, value: T) {
+        for j in 0..self.n_cols {
+            self.col_mut(j).fill(value);
+        }
+    }
+}
+
+impl<T: Field> Index<[usize; 2]> for MatView<'_, T> {
+    type Output = T;
+    fn index(&self, [i, j]: [usize; 2]) -> &Self::Output {
+        debug_assert!(i < self.n_rows && j < self.n_cols, "Index out of bounds.");
+        &self.data[i + j * self.stride]
+    }
+}
+impl<T: Field> Index<[usize; 2]> for MatViewMut<'_, T> {
+    type Output = T;
+    fn index(&self, [i, j]: [usize; 2]) -> &Self::Output {
+        debug_assert!(i < self.n_rows && j < self.n_cols, "Index out of bounds.");
+        &self.data[i + j * self.stride]
+    }
+}
+impl<T: Field> IndexMut<[usize; 2]> for MatViewMut<'_, T> {
+    fn index_mut(&mut self, [i, j]: [usize; 2]) -> &mut Self::Output {
+        debug_assert!(i < self.n_rows && j < self.n_cols, "Index out of bounds.");
+        &mut self.data[i + j * self.stride]
+    }
+}
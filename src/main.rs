@@ -1,14 +1,351 @@
-use linalg::Mat;
-use sim::{make_mosfet_test, make_rc_test};
-
-mod linalg;
-mod sim;
-
-fn main() {
-    // make_rc_test();
-    make_mosfet_test();
-    // let a = Mat::new([[0.0, 1.0], [1.0, 0.0]]);
-    // let b = Mat::new([[2.0], [3.0]]);
-    // dbg!(a * b);
-    // println!("Hello, world!");
+use std::{
+    fs, io,
+    process::ExitCode,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use esc_sim_test::sim::{
+    builder::NameMap,
+    devices::DeviceLibrary,
+    plot::SparklineSink,
+    probe::{Probe, Recording},
+    run::{run_with_plot, run_with_progress, PlotConfig, Progress, RunConfig, RunOutcome},
+    units::parse_si,
+    validate::Severity,
+    CircuitState,
+};
+#[cfg(feature = "serde")]
+use esc_sim_test::sim::{
+    checkpoint::{resume_from_checkpoint, run_with_checkpoints, CheckpointConfig},
+    circuit_file::CircuitFile,
+};
+
+const USAGE: &str = "\
+usage:
+  esc_sim_test run <circuit.ron|circuit.cir> --dt <step> --duration <time> [--probe <probe>]... [--out <file.csv>] [--progress]
+      [--sparkline <time>] [--checkpoint-every <time> [--checkpoint <file>]] [--devices <file>]
+  esc_sim_test run --resume <file> --duration <time> [--out <file.csv>] [--progress] [--sparkline <time>]
+      [--checkpoint-every <time> [--checkpoint <file>]]
+  esc_sim_test check <circuit.ron|circuit.cir> [--devices <file>]
+
+circuits are .ron descriptions (see sim::circuit_file, needs the serde feature) or SPICE
+netlists (see CircuitState::from_spice_netlist), whose MOSFETs may name a part of the builtin
+device library or of the --devices file (see sim::devices). times take SI
+prefixes (1e-7, 100ns, 10ms, 2µs). probes are `net:<net>` (voltage to node 0) or
+`comp:<component>.<current|voltage|power>`, all nets if none are given; the CSV goes to stdout
+without --out. --sparkline prints sparklines of the probes to stderr every <time> of simulated
+time. Ctrl-C stops a run early and still writes the samples so far. --checkpoint-every saves
+the run to the --checkpoint file (checkpoint.json) every <time> of simulated time, and --resume
+continues a saved run to a --duration counted from its start, with its circuit, probes and time
+step (both need the serde feature). check lists what CircuitState::validate finds and fails on
+errors.";
+
+/// Set by the SIGINT handler, so `run` can stop and write what it has.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+fn handle_interrupts() {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::Relaxed);
+    }
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+    }
+}
+#[cfg(not(unix))]
+fn handle_interrupts() {}
+
+fn main() -> ExitCode {
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.first().map(String::as_str) {
+        Some("run") => run(&args[1..]),
+        Some("check") => check(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The circuit at `path`: a `CircuitFile` if it ends in `.ron`, otherwise a SPICE netlist read
+/// with the builtin device library extended by the one at `devices`.
+fn load(path: &str, devices: Option<&str>) -> Result<(CircuitState, NameMap), String> {
+    if path.ends_with(".ron") {
+        if devices.is_some() {
+            return Err("--devices applies to SPICE netlists".into());
+        }
+        let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        return load_circuit_file(&text).map_err(|e| format!("{path}: {e}"));
+    }
+    let mut library = DeviceLibrary::builtin().clone();
+    if let Some(devices) = devices {
+        library.extend(DeviceLibrary::load(devices).map_err(|e| format!("{devices}: {e}"))?);
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    CircuitState::from_spice_netlist_with(&text, &library).map_err(|e| format!("{path}: {e}"))
+}
+
+fn time_arg(value: Option<&String>, flag: &str) -> Result<f64, String> {
+    let value = value.ok_or_else(|| format!("{flag} needs a value"))?;
+    match parse_si(value) {
+        Some(t) if t > 0.0 => Ok(t),
+        _ => Err(format!("{flag}: expected a positive time, got {value:?}")),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let mut path = None;
+    let mut dt = None;
+    let mut duration = None;
+    let mut probes = Vec::new();
+    let mut out = None;
+    let mut progress = false;
+    let mut sparkline = None;
+    let mut checkpoint_every = None;
+    let mut checkpoint = "checkpoint.json".to_string();
+    let mut resume = None;
+    let mut devices = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dt" => dt = Some(time_arg(args.next(), "--dt")?),
+            "--duration" => duration = Some(time_arg(args.next(), "--duration")?),
+            "--probe" => probes.push(args.next().ok_or("--probe needs a value")?.clone()),
+            "--out" => out = Some(args.next().ok_or("--out needs a value")?.clone()),
+            "--progress" => progress = true,
+            "--sparkline" => sparkline = Some(time_arg(args.next(), "--sparkline")?),
+            "--checkpoint-every" => {
+                checkpoint_every = Some(time_arg(args.next(), "--checkpoint-every")?)
+            }
+            "--checkpoint" => checkpoint = args.next().ok_or("--checkpoint needs a value")?.clone(),
+            "--devices" => devices = Some(args.next().ok_or("--devices needs a value")?.clone()),
+            "--resume" => resume = Some(args.next().ok_or("--resume needs a value")?.clone()),
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument {arg:?}\n\n{USAGE}")),
+        }
+    }
+    let duration = duration.ok_or("--duration is required")?;
+    let (mut circuit, recording, dt, start_step, initial_converged) = match resume {
+        Some(resume) => {
+            if path.is_some() || dt.is_some() || !probes.is_empty() || devices.is_some() {
+                return Err(
+                    "--resume takes the circuit, probes and --dt from the checkpoint".into(),
+                );
+            }
+            let (circuit, recording, dt, steps_done) = load_checkpoint(&resume)?;
+            (circuit, recording, dt, steps_done, true)
+        }
+        None => {
+            let path = path.ok_or(USAGE)?;
+            let dt = dt.ok_or("--dt is required")?;
+            let (mut circuit, recording) = load_with_probes(&path, devices.as_deref(), probes)?;
+            // start from the initial conditions of the netlist, like `.tran ... uic`.
+            let initial_converged = circuit.try_solve_state().map_err(|e| e.to_string())?;
+            (circuit, recording, dt, 0, initial_converged)
+        }
+    };
+    let n_steps = (duration / dt).round() as usize;
+    let config = RunConfig {
+        dt,
+        n_steps,
+        progress_every: if progress { (n_steps / 100).max(1) } else { 0 },
+        start_step,
+    };
+    handle_interrupts();
+    let on_progress = |p: &Progress| {
+        eprintln!(
+            "{:3}%  t = {:e} s  ({:.1} s elapsed, last solve {} iterations)",
+            100 * p.steps_done / n_steps,
+            p.sim_time,
+            p.wall_time.as_secs_f64(),
+            p.last_solve.iterations
+        );
+    };
+    let mut sink = SparklineSink::stderr(60);
+    let plot = sparkline.map(|every| PlotConfig {
+        sink: &mut sink,
+        every,
+    });
+    let outcome = match checkpoint_every {
+        Some(every) => {
+            let every = ((every / dt).round() as usize).max(1);
+            run_checkpointed(
+                &mut circuit,
+                recording,
+                config,
+                on_progress,
+                plot,
+                &checkpoint,
+                every,
+            )?
+        }
+        None => match plot {
+            Some(plot) => run_with_plot(
+                &mut circuit,
+                recording,
+                config,
+                &INTERRUPTED,
+                on_progress,
+                plot,
+            ),
+            None => run_with_progress(&mut circuit, recording, config, &INTERRUPTED, on_progress),
+        }
+        .map_err(|e| e.to_string())?,
+    };
+    let unconverged = outcome.unconverged + usize::from(!initial_converged);
+    if unconverged > 0 {
+        eprintln!(
+            "warning: {unconverged} of {} solves did not converge",
+            outcome.steps_done - start_step + usize::from(start_step == 0)
+        );
+    }
+
+    let recording = outcome.recording;
+    match out {
+        Some(out) => {
+            let file = fs::File::create(&out).map_err(|e| format!("{out}: {e}"))?;
+            recording.write_csv(io::BufWriter::new(file))
+        }
+        None => recording.write_csv(io::stdout().lock()),
+    }
+    .map_err(|e| e.to_string())?;
+    if outcome.cancelled {
+        return Err(format!(
+            "interrupted after {} of {n_steps} steps",
+            outcome.steps_done
+        ));
+    }
+    Ok(())
+}
+
+/// The circuit at `path` and a recording of `probes`, or of all its nets if none are given.
+fn load_with_probes(
+    path: &str,
+    devices: Option<&str>,
+    probes: Vec<String>,
+) -> Result<(CircuitState, Recording), String> {
+    let (circuit, names) = load(path, devices)?;
+    let probes = if probes.is_empty() {
+        let mut nets = names
+            .nets
+            .iter()
+            .filter(|&(_, &net)| net != 0)
+            .collect::<Vec<_>>();
+        nets.sort_by_key(|&(_, &net)| net);
+        nets.into_iter()
+            .map(|(name, &net)| (format!("net:{name}"), Probe::Net(net)))
+            .collect()
+    } else {
+        probes
+            .into_iter()
+            .map(|spec| Probe::parse(&spec, &names).map(|probe| (spec, probe)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    Ok((circuit, Recording::new(probes)))
+}
+
+#[cfg(feature = "serde")]
+fn load_circuit_file(text: &str) -> Result<(CircuitState, NameMap), String> {
+    let file = CircuitFile::from_ron(text).map_err(|e| e.to_string())?;
+    file.build().map_err(|e| e.to_string())
+}
+#[cfg(not(feature = "serde"))]
+fn load_circuit_file(_: &str) -> Result<(CircuitState, NameMap), String> {
+    Err(".ron circuits need the serde feature".into())
+}
+
+#[cfg(feature = "serde")]
+fn load_checkpoint(path: &str) -> Result<(CircuitState, Recording, f64, usize), String> {
+    let checkpoint = resume_from_checkpoint(path).map_err(|e| format!("{path}: {e}"))?;
+    Ok((
+        checkpoint.circuit,
+        checkpoint.recording,
+        checkpoint.dt,
+        checkpoint.steps_done,
+    ))
+}
+#[cfg(not(feature = "serde"))]
+fn load_checkpoint(_: &str) -> Result<(CircuitState, Recording, f64, usize), String> {
+    Err("--resume needs the serde feature".into())
+}
+
+/// The run with a checkpoint written to `path` every `every` steps.
+#[cfg(feature = "serde")]
+fn run_checkpointed(
+    circuit: &mut CircuitState,
+    recording: Recording,
+    config: RunConfig,
+    on_progress: impl FnMut(&Progress),
+    plot: Option<PlotConfig<'_>>,
+    path: &str,
+    every: usize,
+) -> Result<RunOutcome, String> {
+    let checkpoints = CheckpointConfig {
+        path: path.as_ref(),
+        every,
+    };
+    run_with_checkpoints(
+        circuit,
+        recording,
+        config,
+        &INTERRUPTED,
+        on_progress,
+        plot,
+        checkpoints,
+    )
+    .map_err(|e| format!("{path}: {e}"))
+}
+#[cfg(not(feature = "serde"))]
+fn run_checkpointed(
+    _: &mut CircuitState,
+    _: Recording,
+    _: RunConfig,
+    _: impl FnMut(&Progress),
+    _: Option<PlotConfig<'_>>,
+    _: &str,
+    _: usize,
+) -> Result<RunOutcome, String> {
+    Err("--checkpoint-every needs the serde feature".into())
+}
+
+fn check(args: &[String]) -> Result<(), String> {
+    let (path, devices) = match args {
+        [path] => (path, None),
+        [path, flag, devices] if flag == "--devices" => (path, Some(devices.as_str())),
+        _ => return Err(USAGE.to_string()),
+    };
+    let (circuit, names) = load(path, devices)?;
+    println!(
+        "{path}: {} nets, {} components",
+        circuit.n_nets(),
+        circuit.n_components()
+    );
+    // notes and warnings don't fail the check.
+    let mut failures = 0;
+    for warning in circuit.validate() {
+        println!(
+            "{}: {}",
+            warning.severity(),
+            warning.describe(&circuit, Some(&names))
+        );
+        if warning.severity() == Severity::Error {
+            failures += 1;
+        }
+    }
+    if failures > 0 {
+        return Err(format!("{failures} errors"));
+    }
+    Ok(())
 }
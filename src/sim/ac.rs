@@ -0,0 +1,302 @@
+use crate::linalg::{sparse::CsrMat, Complex, Mat};
+
+use super::{
+    components::{ComponentParameter, LinearComponentValue},
+    f,
+    solver::LinearSolver,
+    CircuitState, ComponentId, ComponentValueEnum, NetId,
+};
+
+pub type Cf = Complex<f>;
+
+/// Complex modified-nodal system assembled from the small-signal stamps of every component at
+/// one angular frequency.
+///
+/// Unknowns are the voltages of all non-reference nets followed by the currents of branches
+/// defined by their voltage (sources, closed switches and inductors).
+#[derive(Debug, Clone)]
+pub struct AcSystem {
+    pub omega: f,
+    input: ComponentId,
+    component: ComponentId,
+    /// Row/column of each net, `None` for the reference net of its connected group.
+    net_rows: Vec<Option<usize>>,
+    /// The reference net of the group of each net.
+    groups: Vec<NetId>,
+    n_nets: usize,
+    g: Vec<(usize, usize, Cf)>,
+    branches: Vec<Branch>,
+    /// Test currents into nets, see `CircuitState::measure_impedance`.
+    injected: Vec<(NetId, Cf)>,
+    /// `SolverConfig::linear_solver` of the circuit.
+    linear_solver: LinearSolver,
+}
+#[derive(Debug, Clone, Copy)]
+struct Branch {
+    nets: [NetId; 2],
+    impedance: Cf,
+    emf: Cf,
+}
+impl AcSystem {
+    fn new(circuit: &CircuitState, input: ComponentId, omega: f) -> Self {
+        // ground the first net of each group of nets connected through components.
+        let mut group = (0..circuit.nets.len()).collect::<Vec<_>>();
+        fn find(group: &mut [usize], i: usize) -> usize {
+            if group[i] != i {
+                group[i] = find(group, group[i]);
+            }
+            group[i]
+        }
+        for (_, component) in circuit.components() {
+            let nets = component.as_dyn().nets();
+            for pair in nets.windows(2) {
+                let (a, b) = (find(&mut group, pair[0]), find(&mut group, pair[1]));
+                group[a.max(b)] = a.min(b);
+            }
+        }
+        let mut n_nets = 0;
+        let net_rows = (0..circuit.nets.len())
+            .map(|net_i| {
+                if find(&mut group, net_i) == net_i {
+                    None
+                } else {
+                    n_nets += 1;
+                    Some(n_nets - 1)
+                }
+            })
+            .collect();
+        Self {
+            omega,
+            input,
+            component: 0,
+            net_rows,
+            groups: (0..circuit.nets.len())
+                .map(|net_i| find(&mut group, net_i))
+                .collect(),
+            n_nets,
+            g: Vec::new(),
+            branches: Vec::new(),
+            injected: Vec::new(),
+            linear_solver: circuit.solver.linear_solver,
+        }
+    }
+
+    /// Whether the component being stamped is the excitation of this analysis.
+    pub fn is_input(&self) -> bool {
+        self.component == self.input
+    }
+
+    fn add(&mut self, i: Option<usize>, j: Option<usize>, y: Cf) {
+        if let (Some(i), Some(j)) = (i, j) {
+            self.g.push((i, j, y));
+        }
+    }
+    /// Admittance `y` between nets `a` and `b`.
+    pub fn add_admittance(&mut self, a: NetId, b: NetId, y: Cf) {
+        self.add_transadmittance([a, b], [a, b], y);
+    }
+    /// Current `y * (V(ctrl[0]) - V(ctrl[1]))` flowing from `out[0]` to `out[1]` through the component.
+    pub fn add_transadmittance(&mut self, out: [NetId; 2], ctrl: [NetId; 2], y: Cf) {
+        let (o0, o1) = (self.net_rows[out[0]], self.net_rows[out[1]]);
+        let (c0, c1) = (self.net_rows[ctrl[0]], self.net_rows[ctrl[1]]);
+        let neg_y = Cf::from(0) - y;
+        self.add(o0, c0, y);
+        self.add(o0, c1, neg_y);
+        self.add(o1, c0, neg_y);
+        self.add(o1, c1, y);
+    }
+    /// Branch enforcing `V(nets[1]) - V(nets[0]) = emf`.
+    pub fn add_voltage_branch(&mut self, nets: [NetId; 2], emf: Cf) {
+        self.branches.push(Branch {
+            nets,
+            impedance: 0.into(),
+            emf,
+        });
+    }
+    /// Branch enforcing `V(nets[1]) - V(nets[0]) = -impedance * I`, with `I` flowing from
+    /// `nets[0]` to `nets[1]` through the component. Unlike an admittance this stays well defined
+    /// for zero impedance.
+    pub fn add_impedance_branch(&mut self, nets: [NetId; 2], impedance: Cf) {
+        self.branches.push(Branch {
+            nets,
+            impedance,
+            emf: 0.into(),
+        });
+    }
+
+    /// Net voltages (with reference nets at zero), or `None` if the system is singular.
+    ///
+    /// Solved as `SolverConfig::linear_solver` says, directly when the iterative solve doesn't
+    /// converge.
+    fn solve(&self) -> Option<Vec<Cf>> {
+        let n = self.n_nets + self.branches.len();
+        let mut entries = self.g.clone();
+        let mut rhs = vec![Cf::from(0); n];
+        for (branch_i, branch) in self.branches.iter().enumerate() {
+            let k = self.n_nets + branch_i;
+            // branch current leaves `nets[0]` into the branch and enters `nets[1]`.
+            if let Some(i) = self.net_rows[branch.nets[0]] {
+                entries.push((i, k, 1.into()));
+                entries.push((k, i, Cf::from(0) - 1.into()));
+            }
+            if let Some(i) = self.net_rows[branch.nets[1]] {
+                entries.push((i, k, Cf::from(0) - 1.into()));
+                entries.push((k, i, 1.into()));
+            }
+            entries.push((k, k, branch.impedance));
+            rhs[k] = branch.emf;
+        }
+        for &(net_i, current) in &self.injected {
+            if let Some(i) = self.net_rows[net_i] {
+                rhs[i] += current;
+            }
+        }
+        let iterative = match self.linear_solver {
+            LinearSolver::Direct => None,
+            LinearSolver::Iterative(config) => {
+                let (x, report) = CsrMat::from_triplets(n, n, entries.iter().copied())
+                    .solve_bicgstab(&rhs, &config);
+                if !report.converged(&config) {
+                    trace_event!(
+                        warn,
+                        omega = self.omega,
+                        iterations = report.iterations,
+                        residual = report.relative_residual,
+                        "iterative AC solve didn't converge, solving directly"
+                    );
+                }
+                report.converged(&config).then_some(x)
+            }
+        };
+        let x = match iterative {
+            Some(x) => x,
+            None => {
+                let mut a = Mat::<Cf>::zeros(n, n);
+                let mut b = Mat::<Cf>::zeros(n, 1);
+                for (i, j, y) in entries {
+                    a[[i, j]] += y;
+                }
+                for (i, &value) in rhs.iter().enumerate() {
+                    b[[i, 0]] = value;
+                }
+                let x = a.solve(&b)?;
+                (0..n).map(|i| x[[i, 0]]).collect()
+            }
+        };
+        Some(
+            self.net_rows
+                .iter()
+                .map(|row| row.map_or(0.into(), |i| x[i]))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AcPoint {
+    pub frequency: f,
+    pub response: Cf,
+    pub magnitude: f,
+    /// Radians.
+    pub phase: f,
+}
+impl AcPoint {
+    pub fn magnitude_db(&self) -> f {
+        20.0 * self.magnitude.log10()
+    }
+}
+
+/// See `CircuitState::measure_thevenin`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thevenin {
+    pub open_circuit_voltage: f,
+    pub resistance: f,
+}
+
+impl CircuitState {
+    /// Small-signal transfer from the `input` source (driven with a 1 V excitation) to
+    /// `V(output[1]) - V(output[0])`, linearized about the present operating point.
+    ///
+    /// All other sources are held at their operating point (shorted for AC). Returns `None` if
+    /// the linearized system is singular at some frequency.
+    pub fn ac_analysis(
+        &self,
+        input: ComponentId,
+        output: [NetId; 2],
+        frequencies: &[f],
+    ) -> Option<Vec<AcPoint>> {
+        frequencies
+            .iter()
+            .map(|&frequency| {
+                let v = self.ac_system(input, frequency).solve()?;
+                let response = v[output[1]] - v[output[0]];
+                Some(AcPoint {
+                    frequency,
+                    response,
+                    magnitude: response.abs(),
+                    phase: response.arg(),
+                })
+            })
+            .collect()
+    }
+
+    /// Small-signal impedance between `net_a` and `net_b` at `frequency`, linearized about the
+    /// present operating point: `V(net_a) - V(net_b)` per unit of current driven into `net_a`
+    /// and out of `net_b`, with all sources shorted.
+    ///
+    /// Returns `None` if no components join the nets or the linearized system is singular.
+    pub fn measure_impedance(&self, net_a: NetId, net_b: NetId, frequency: f) -> Option<Cf> {
+        let mut system = self.ac_system(ComponentId::MAX, frequency);
+        if system.groups[net_a] != system.groups[net_b] {
+            return None;
+        }
+        system.injected = vec![(net_a, 1.into()), (net_b, Cf::from(0) - 1.into())];
+        let v = system.solve()?;
+        Some(v[net_a] - v[net_b])
+    }
+
+    /// Thevenin equivalent of the circuit between `net_a` and `net_b` at DC, from two load
+    /// tests: the DC operating point is solved with each of two resistors across the nets,
+    /// sized from the small-signal resistance there, and the open-circuit voltage
+    /// `V(net_a) - V(net_b)` and series resistance fitted through both. The circuit itself is
+    /// left untouched.
+    ///
+    /// Returns `None` if the nets aren't joined or either operating point doesn't converge.
+    pub fn measure_thevenin(&self, net_a: NetId, net_b: NetId) -> Option<Thevenin> {
+        let estimate = self.measure_impedance(net_a, net_b, 0.0)?.re.abs();
+        // the relaxation solver handles loads close to the source resistance best.
+        let scale = if estimate > 1e-9 { estimate } else { 1.0 };
+        let mut circuit = self.clone();
+        let load = circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Resistive(scale)),
+            &[net_a, net_b],
+        );
+        let mut points = [(0.0, 0.0); 2];
+        for (point, r) in points.iter_mut().zip([scale, 3.0 * scale]) {
+            *circuit
+                .component_mut(load)
+                .parameter_mut(ComponentParameter::Value)? = r;
+            if !circuit.dc_operating_point() {
+                return None;
+            }
+            let v = circuit.net_voltage(net_a) - circuit.net_voltage(net_b);
+            *point = (v, v / r);
+        }
+        let [(v1, i1), (v2, i2)] = points;
+        let resistance = (v1 - v2) / (i2 - i1);
+        Some(Thevenin {
+            open_circuit_voltage: v1 + i1 * resistance,
+            resistance,
+        })
+    }
+
+    /// The small-signal system at `frequency`, with `input` as its excitation.
+    fn ac_system(&self, input: ComponentId, frequency: f) -> AcSystem {
+        let mut system = AcSystem::new(self, input, std::f64::consts::TAU * frequency);
+        for (component_i, component) in self.components() {
+            system.component = component_i;
+            component.as_dyn().stamp_ac(&self.nets, &mut system);
+        }
+        system
+    }
+}
@@ -0,0 +1,30 @@
+//! The avalanche energy MOSFETs driven past their breakdown voltage (see `Avalanche`) have
+//! dissipated, as in the unclamped inductive turn-off of a phase with no freewheeling path, and
+//! the ones it took past their `E_AS` rating.
+
+use super::{CircuitState, ComponentId, ComponentRef, Scalar};
+
+impl<S: Scalar> CircuitState<S> {
+    /// Energy in joules the MOSFET `component` has dissipated in avalanche, see
+    /// `MOSFETComponentState::avalanche_energy`; `None` if it isn't a MOSFET.
+    pub fn avalanche_energy(&self, component: ComponentId) -> Option<S> {
+        match self.component(component) {
+            ComponentRef::MOSFET(state) => Some(state.avalanche_energy),
+            _ => None,
+        }
+    }
+
+    /// The MOSFETs whose avalanche energy exceeds their `Avalanche::rated_energy`, with it, in
+    /// `ComponentId` order.
+    pub fn avalanche_overloads(&self) -> Vec<(ComponentId, S)> {
+        self.components()
+            .filter_map(|(component, state)| match state {
+                ComponentRef::MOSFET(state) => {
+                    let rated = state.value.avalanche?.rated_energy?;
+                    (state.avalanche_energy > rated).then_some((component, state.avalanche_energy))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
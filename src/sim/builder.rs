@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+
+use super::{
+    components::{
+        ComparatorComponentValue, ConverterComponentValue, GateDriverComponentValue,
+        LinearComponentValue, MOSFETComponentValue, PwmWave,
+    },
+    devices::DeviceLibrary,
+    error::SimError,
+    f, CircuitState, ComponentId, ComponentValueEnum, NetId, Scalar,
+};
+
+/// Names of the nets and components of a circuit made by `CircuitBuilder`.
+#[derive(Debug, Clone, Default)]
+pub struct NameMap {
+    pub nets: HashMap<String, NetId>,
+    pub components: HashMap<String, ComponentId>,
+}
+impl NameMap {
+    pub fn net(&self, name: &str) -> Option<NetId> {
+        self.nets.get(name).copied()
+    }
+    pub fn component(&self, name: &str) -> Option<ComponentId> {
+        self.components.get(name).copied()
+    }
+
+    /// The names inside the subcircuit instance at `path` (see `CircuitBuilder::instance`)
+    /// relative to it, e.g. `Q_high` for `bridge_a/Q_high` in `scope("bridge_a")`. Ports are
+    /// named by the outer nets they connect to, so they aren't included.
+    pub fn scope(&self, path: &str) -> NameMap {
+        let prefix = format!("{path}{PATH_SEPARATOR}");
+        let strip =
+            |(name, &id): (&String, &usize)| Some((name.strip_prefix(&prefix)?.to_string(), id));
+        NameMap {
+            nets: self.nets.iter().filter_map(strip).collect(),
+            components: self.components.iter().filter_map(strip).collect(),
+        }
+    }
+}
+
+/// Separates the instance names of the path of a net, component or signal inside a subcircuit
+/// instance, see `CircuitBuilder::instance`.
+pub const PATH_SEPARATOR: char = '/';
+
+/// Builds a `CircuitState` with nets and components referred to by name; nets are created the
+/// first time they are named, so the first net named is net 0 (ground in
+/// `CircuitState::to_spice_netlist`).
+#[derive(Debug, Clone)]
+pub struct CircuitBuilder<S: Scalar = f> {
+    circuit: CircuitState<S>,
+    names: NameMap,
+}
+impl<S: Scalar> Default for CircuitBuilder<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<S: Scalar> CircuitBuilder<S> {
+    pub fn new() -> Self {
+        Self {
+            circuit: CircuitState::new_empty(),
+            names: NameMap::default(),
+        }
+    }
+
+    /// The net called `name`, created with `name` as its alias if no net of the circuit has the
+    /// name or alias yet.
+    pub fn net(&mut self, name: &str) -> NetId {
+        if let Some(net) = self.names.net(name) {
+            return net;
+        }
+        let net = match self.circuit.net_by_alias(name) {
+            Some(net) => net,
+            None => {
+                let net = self.circuit.create_net();
+                self.circuit
+                    .add_net_alias(net, name)
+                    .expect("no net has the alias");
+                net
+            }
+        };
+        self.names.nets.insert(name.to_string(), net);
+        net
+    }
+
+    /// Add a component called `name` connected to the nets named `nets` (in terminal order).
+    ///
+    /// Errors if the name is taken or `nets` doesn't match the component's terminals.
+    pub fn component(
+        &mut self,
+        name: &str,
+        value: ComponentValueEnum<S>,
+        nets: &[&str],
+    ) -> Result<&mut Self, SimError> {
+        if nets.len() != value.n_terminals() {
+            return Err(SimError::WrongNetCount {
+                name: name.to_string(),
+                expected: value.n_terminals(),
+                found: nets.len(),
+            });
+        }
+        let nets = nets.iter().map(|net| self.net(net)).collect::<Vec<_>>();
+        let component = self.circuit.create_component_named(value, &nets, name)?;
+        self.names.components.insert(name.to_string(), component);
+        Ok(self)
+    }
+    /// Add a component called `name` with each of its terminals (by their
+    /// `ComponentValue::terminal_names`) connected to the net named alongside, in any order:
+    /// `[("drain", "phase"), ("gate", "gate_low"), ("source", "gnd")]`.
+    ///
+    /// Errors like `component`, or if a terminal is left out. Naming a terminal the component
+    /// doesn't have or one twice is a bug of the caller, asserted in debug builds and taken as
+    /// leaving a terminal out otherwise.
+    pub fn component_by_terminals(
+        &mut self,
+        name: &str,
+        value: ComponentValueEnum<S>,
+        terminals: &[(&str, &str)],
+    ) -> Result<&mut Self, SimError> {
+        let terminal_names = value.terminal_names();
+        let mut nets = vec![None; terminal_names.len()];
+        for &(terminal, net) in terminals {
+            let terminal_i = terminal_names.iter().position(|&t| t == terminal);
+            debug_assert!(
+                terminal_i.is_some(),
+                "{name}: no terminal {terminal:?}, expected one of {terminal_names:?}"
+            );
+            debug_assert!(
+                terminal_i.is_none_or(|i| nets[i].is_none()),
+                "{name}: terminal {terminal:?} connected twice"
+            );
+            if let Some(i) = terminal_i.filter(|&i| nets[i].is_none()) {
+                nets[i] = Some(net);
+            }
+        }
+        match nets.iter().copied().collect::<Option<Vec<_>>>() {
+            Some(nets) if terminals.len() == nets.len() => self.component(name, value, &nets),
+            _ => Err(SimError::WrongNetCount {
+                name: name.to_string(),
+                expected: terminal_names.len(),
+                found: nets.iter().flatten().count(),
+            }),
+        }
+    }
+    fn linear(
+        &mut self,
+        name: &str,
+        a: &str,
+        b: &str,
+        value: LinearComponentValue<S>,
+    ) -> Result<&mut Self, SimError> {
+        self.component(name, ComponentValueEnum::Linear(value), &[a, b])
+    }
+    pub fn resistor(&mut self, name: &str, a: &str, b: &str, r: S) -> Result<&mut Self, SimError> {
+        self.linear(name, a, b, LinearComponentValue::Resistive(r))
+    }
+    pub fn capacitor(&mut self, name: &str, a: &str, b: &str, c: S) -> Result<&mut Self, SimError> {
+        self.linear(name, a, b, LinearComponentValue::Capacitive(c))
+    }
+    pub fn inductor(&mut self, name: &str, a: &str, b: &str, l: S) -> Result<&mut Self, SimError> {
+        self.linear(name, a, b, LinearComponentValue::Inductive(l))
+    }
+    /// A voltage source with `V(b) - V(a) = v`.
+    pub fn source(&mut self, name: &str, a: &str, b: &str, v: S) -> Result<&mut Self, SimError> {
+        self.linear(name, a, b, LinearComponentValue::Source(v))
+    }
+    /// An ideal ammeter from `a` to `b`: a 0 V source, so it stands in for a wire between the two
+    /// nets. Its current (`Probe::Component(.., Quantity::Current)`, `comp:<name>.current`) is
+    /// the current of that wire from `a` to `b`.
+    pub fn ammeter(&mut self, name: &str, a: &str, b: &str) -> Result<&mut Self, SimError> {
+        self.source(name, a, b, S::from(0))
+    }
+    /// A voltage source with `V(b) - V(a) = gain * signal + offset`, driven through
+    /// `CircuitState::set_signal`.
+    pub fn signal_source(
+        &mut self,
+        name: &str,
+        a: &str,
+        b: &str,
+        signal: &str,
+        gain: S,
+        offset: S,
+    ) -> Result<&mut Self, SimError> {
+        self.source(name, a, b, S::from(0))?;
+        let component = self.names.component(name).unwrap();
+        self.circuit.bind_signal(signal, component, gain, offset)?;
+        Ok(self)
+    }
+    /// A source with `V(b) - V(a)` following `wave`.
+    pub fn pwm_source(
+        &mut self,
+        name: &str,
+        a: &str,
+        b: &str,
+        wave: PwmWave<S>,
+    ) -> Result<&mut Self, SimError> {
+        self.linear(name, a, b, LinearComponentValue::Pwm(wave))
+    }
+    /// A switch with the default off-resistance, see `LinearComponentValue::switch`.
+    pub fn switch(
+        &mut self,
+        name: &str,
+        a: &str,
+        b: &str,
+        closed: bool,
+    ) -> Result<&mut Self, SimError> {
+        self.linear(name, a, b, LinearComponentValue::switch(closed))
+    }
+    pub fn mosfet(
+        &mut self,
+        name: &str,
+        value: MOSFETComponentValue<S>,
+        source: &str,
+        gate: &str,
+        drain: &str,
+    ) -> Result<&mut Self, SimError> {
+        self.component(
+            name,
+            ComponentValueEnum::MOSFET(value),
+            &[source, gate, drain],
+        )
+    }
+    /// `mosfet` with the parameters of the part `part` of `devices`, e.g.
+    /// `DeviceLibrary::builtin()`.
+    ///
+    /// Errors like `mosfet`, or with the closest part names if `devices` has no such part.
+    pub fn mosfet_part(
+        &mut self,
+        name: &str,
+        devices: &DeviceLibrary,
+        part: &str,
+        source: &str,
+        gate: &str,
+        drain: &str,
+    ) -> Result<&mut Self, SimError> {
+        self.mosfet(name, devices.mosfet(part)?, source, gate, drain)
+    }
+
+    /// A gate driver switching `output` between `vdd` and `vss` after `input`, see
+    /// `GateDriverComponentValue`.
+    pub fn gate_driver(
+        &mut self,
+        name: &str,
+        value: GateDriverComponentValue<S>,
+        [vss, vdd, input, output]: [&str; 4],
+    ) -> Result<&mut Self, SimError> {
+        self.component(
+            name,
+            ComponentValueEnum::GateDriver(value),
+            &[vss, vdd, input, output],
+        )
+    }
+    /// A comparator driving `V(out_plus) - V(out_minus)` after `V(in_plus) - V(in_minus)`, see
+    /// `ComparatorComponentValue`.
+    pub fn comparator(
+        &mut self,
+        name: &str,
+        value: ComparatorComponentValue<S>,
+        [in_minus, in_plus]: [&str; 2],
+        [out_minus, out_plus]: [&str; 2],
+    ) -> Result<&mut Self, SimError> {
+        self.component(
+            name,
+            ComponentValueEnum::Comparator(value),
+            &[out_minus, in_minus, in_plus, out_plus],
+        )
+    }
+    /// A DC-DC converter regulating `V(out_plus) - V(out_minus)` from `V(in_plus) - V(in_minus)`,
+    /// see `ConverterComponentValue`.
+    pub fn converter(
+        &mut self,
+        name: &str,
+        value: ConverterComponentValue<S>,
+        [in_minus, in_plus]: [&str; 2],
+        [out_minus, out_plus]: [&str; 2],
+    ) -> Result<&mut Self, SimError> {
+        self.component(
+            name,
+            ComponentValueEnum::Converter(value),
+            &[out_minus, in_minus, in_plus, out_plus],
+        )
+    }
+
+    /// Add a copy of the circuit built by `subcircuit` as the instance `name`. Its net called
+    /// `port` is the net called `net` here for each `(port, net)` of `ports`; its other nets,
+    /// its components and the signals driving its sources are named by the path
+    /// `<name>/<their name>`, so instances of instances nest. Unnamed nets and components stay
+    /// unnamed.
+    ///
+    /// The copies start from the values of the components, not their present state. Errors if
+    /// a port isn't a net of `subcircuit` or a path is taken.
+    pub fn instance(
+        &mut self,
+        name: &str,
+        subcircuit: &CircuitBuilder<S>,
+        ports: &[(&str, &str)],
+    ) -> Result<&mut Self, SimError> {
+        let path = |inner: &str| format!("{name}{PATH_SEPARATOR}{inner}");
+        let mut nets = vec![None; subcircuit.circuit.n_nets()];
+        for &(port, net) in ports {
+            let inner = subcircuit
+                .names
+                .net(port)
+                .ok_or_else(|| SimError::UnknownNet(path(port)))?;
+            nets[inner] = Some(self.net(net));
+        }
+        // internal nets in the order of the subcircuit's, so instances are numbered alike.
+        let mut inner_names = vec![None; nets.len()];
+        for (inner, &inner_net) in &subcircuit.names.nets {
+            inner_names[inner_net] = Some(inner.as_str());
+        }
+        let nets = nets
+            .into_iter()
+            .zip(inner_names)
+            .map(|(net, inner)| match (net, inner) {
+                (Some(net), _) => net,
+                (None, Some(inner)) => self.net(&path(inner)),
+                (None, None) => self.circuit.create_net(),
+            })
+            .collect::<Vec<_>>();
+        let mut components = Vec::new();
+        for (inner, component) in subcircuit.circuit.components() {
+            let value = component.value();
+            let connected = component
+                .as_dyn()
+                .nets()
+                .iter()
+                .map(|&net| nets[net])
+                .collect::<Vec<_>>();
+            components.push(match subcircuit.circuit.component_name(inner) {
+                Some(inner) => {
+                    let component =
+                        self.circuit
+                            .create_component_named(value, &connected, &path(inner))?;
+                    self.names.components.insert(path(inner), component);
+                    component
+                }
+                None => self.circuit.create_component(value, &connected),
+            });
+        }
+        for signal in subcircuit.circuit.signal_bus().signals() {
+            for binding in &signal.bindings {
+                self.circuit.bind_signal(
+                    &path(&signal.name),
+                    components[binding.component],
+                    binding.gain,
+                    binding.offset,
+                )?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// The circuit built so far.
+    pub fn circuit(&self) -> &CircuitState<S> {
+        &self.circuit
+    }
+    /// The circuit built so far, to set initial conditions or run it before adding more.
+    pub fn circuit_mut(&mut self) -> &mut CircuitState<S> {
+        &mut self.circuit
+    }
+    pub fn names(&self) -> &NameMap {
+        &self.names
+    }
+
+    /// The finished circuit and its names, leaving the builder empty.
+    pub fn build(&mut self) -> (CircuitState<S>, NameMap) {
+        let this = std::mem::take(self);
+        (this.circuit, this.names)
+    }
+}
+
+/// Declare a circuit inline through `CircuitBuilder`:
+///
+/// ```ignore
+/// let rc = circuit! {
+///     ground: gnd;
+///     V1: source(5.0) [gnd, vin];
+///     R1: resistor("4.7k") [vin, vout];
+///     C1: capacitor("100n") [vout, gnd];
+/// }?;
+/// rc.circuit.component_name(rc.R1);
+/// ```
+///
+/// Evaluates to `Result<_, SimError>` of a struct with the `circuit`, its `names`, the ground net
+/// id and the `ComponentId` of each component as fields (so a repeated name doesn't compile).
+/// The ground net is net 0. Kinds are `resistor`, `capacitor`, `inductor`, `source`, `ammeter`
+/// (taking nothing), `switch` (taking `closed`) and `mosfet` (a `MOSFETComponentValue`, nets
+/// `[source, gate, drain]`), whose number of nets is checked at compile time, and `component`
+/// taking any `ComponentValueEnum`, checked when the circuit is built. The values of `resistor`,
+/// `capacitor`, `inductor` and `source` are numbers or strings for `units::parse_si`.
+#[macro_export]
+macro_rules! circuit {
+    (
+        ground: $ground:ident;
+        $($name:ident : $kind:ident ($($args:tt)*) [$($net:ident),* $(,)?];)*
+    ) => {{
+        // callers rarely read every component's id.
+        #[allow(non_snake_case, dead_code)]
+        #[derive(Debug)]
+        struct Circuit {
+            circuit: $crate::sim::CircuitState,
+            names: $crate::sim::builder::NameMap,
+            $ground: $crate::sim::NetId,
+            $($name: $crate::sim::ComponentId,)*
+        }
+        (|| -> Result<Circuit, $crate::sim::error::SimError> {
+            let mut builder = $crate::sim::builder::CircuitBuilder::new();
+            let $ground = builder.net(stringify!($ground));
+            $($crate::circuit!(@add builder, $name, $kind ($($args)*) [$($net),*]);)*
+            let (circuit, names) = builder.build();
+            Ok(Circuit {
+                $($name: names.component(stringify!($name)).unwrap(),)*
+                circuit,
+                names,
+                $ground,
+            })
+        })()
+    }};
+
+    (@add $b:ident, $name:ident, resistor ($v:expr) [$x:ident, $y:ident]) => {
+        let value = $crate::sim::units::SiValue::si_value($v)?;
+        $b.resistor(stringify!($name), stringify!($x), stringify!($y), value)?;
+    };
+    (@add $b:ident, $name:ident, capacitor ($v:expr) [$x:ident, $y:ident]) => {
+        let value = $crate::sim::units::SiValue::si_value($v)?;
+        $b.capacitor(stringify!($name), stringify!($x), stringify!($y), value)?;
+    };
+    (@add $b:ident, $name:ident, inductor ($v:expr) [$x:ident, $y:ident]) => {
+        let value = $crate::sim::units::SiValue::si_value($v)?;
+        $b.inductor(stringify!($name), stringify!($x), stringify!($y), value)?;
+    };
+    (@add $b:ident, $name:ident, source ($v:expr) [$x:ident, $y:ident]) => {
+        let value = $crate::sim::units::SiValue::si_value($v)?;
+        $b.source(stringify!($name), stringify!($x), stringify!($y), value)?;
+    };
+    (@add $b:ident, $name:ident, ammeter () [$x:ident, $y:ident]) => {
+        $b.ammeter(stringify!($name), stringify!($x), stringify!($y))?;
+    };
+    (@add $b:ident, $name:ident, switch ($closed:expr) [$x:ident, $y:ident]) => {
+        $b.switch(stringify!($name), stringify!($x), stringify!($y), $closed)?;
+    };
+    (@add $b:ident, $name:ident, mosfet ($v:expr) [$s:ident, $g:ident, $d:ident]) => {
+        $b.mosfet(stringify!($name), $v, stringify!($s), stringify!($g), stringify!($d))?;
+    };
+    (@add $b:ident, $name:ident, component ($v:expr) [$($net:ident),*]) => {
+        $b.component(stringify!($name), $v, &[$(stringify!($net)),*])?;
+    };
+    (@add $b:ident, $name:ident, $kind:ident $($rest:tt)*) => {
+        compile_error!(concat!(
+            "`", stringify!($name), "`: unknown kind `", stringify!($kind),
+            "` or wrong number of nets for it"
+        ));
+    };
+}
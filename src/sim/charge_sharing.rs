@@ -0,0 +1,208 @@
+//! Closing a switch between capacitors at different voltages. Ideal elements would pass an
+//! impulse of current, which relaxation either can't follow or follows losing charge, so the
+//! tick at which the switch closes settles the capacitors to the voltages charge conservation
+//! gives before it solves, and records the `1/2 C dV^2` this dissipates (see
+//! `SolverConfig::share_charge`).
+
+use super::{components::LinearComponentValue, CircuitState, ComponentId, ComponentState, Scalar};
+
+/// The charge sharing of one tick, see `CircuitState::last_charge_sharing`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChargeSharing<S: Scalar> {
+    /// The switches that closed since the tick before.
+    pub switches: Vec<ComponentId>,
+    /// The capacitors whose charge changed.
+    pub capacitors: Vec<ComponentId>,
+    /// Energy turned into heat by the redistribution, in joules: the stored energy the
+    /// capacitors lost plus what nets outside the joined ones delivered.
+    pub dissipated: S,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct ChargeSharingState<S: Scalar> {
+    /// Whether each switch was closed at the last tick, by `ComponentId`.
+    closed: Vec<bool>,
+    last: Option<ChargeSharing<S>>,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// The charge sharing at the start of the last tick, if a switch closed onto plain
+    /// capacitors (`LinearComponentValue::Capacitive`) then.
+    pub fn last_charge_sharing(&self) -> Option<&ChargeSharing<S>> {
+        self.charge_sharing.last.as_ref()
+    }
+
+    /// Redistribute the charge of the capacitors on the nets that switches closed since the
+    /// last tick join, with the nets outside them held at their voltages.
+    pub(super) fn share_charge(&mut self) {
+        self.charge_sharing.last = None;
+        let mut closed = std::mem::take(&mut self.charge_sharing.closed);
+        closed.resize(self.slots.len(), false);
+        let mut switches = Vec::new();
+        // nets joined by closed switches, each pointing towards the lowest of its group.
+        let mut group = (0..self.nets.len()).collect::<Vec<_>>();
+        fn find(group: &mut [usize], i: usize) -> usize {
+            if group[i] != i {
+                group[i] = find(group, group[i]);
+            }
+            group[i]
+        }
+        for (component_i, component) in self.linear_components() {
+            let is_closed = matches!(
+                component.value,
+                LinearComponentValue::Switch { closed: true, .. } | LinearComponentValue::Jumper
+            );
+            if is_closed {
+                if !closed[component_i] {
+                    switches.push(component_i);
+                }
+                let nets = component.nets();
+                let (a, b) = (find(&mut group, nets[0]), find(&mut group, nets[1]));
+                group[a.max(b)] = a.min(b);
+            }
+            closed[component_i] = is_closed;
+        }
+        self.charge_sharing.closed = closed;
+        if switches.is_empty() {
+            return;
+        }
+
+        // one unknown voltage per group a new switch closed in.
+        let mut unknown = vec![None; self.nets.len()];
+        let mut n_unknowns = 0;
+        for &switch in &switches {
+            let root = find(&mut group, self.component(switch).as_dyn().nets()[0]);
+            if unknown[root].is_none() {
+                unknown[root] = Some(n_unknowns);
+                n_unknowns += 1;
+            }
+        }
+        let unknown_of = |group: &mut [usize], net: usize| unknown[find(group, net)];
+
+        // charge conservation of every group, `sum over its capacitor plates of C (V_group -
+        // V_other) = the charge on those plates now`, with `V(nets[1]) - V(nets[0]) = -q[0] / C`.
+        let mut a = vec![vec![S::from(0); n_unknowns]; n_unknowns];
+        let mut b = vec![S::from(0); n_unknowns];
+        let mut capacitors = Vec::new();
+        for (component_i, component) in self.linear_components() {
+            let LinearComponentValue::Capacitive(c) = component.value else {
+                continue;
+            };
+            let nets = component.nets();
+            let (u0, u1) = (
+                unknown_of(&mut group, nets[0]),
+                unknown_of(&mut group, nets[1]),
+            );
+            if u0.is_none() && u1.is_none() {
+                continue;
+            }
+            capacitors.push((component_i, c, component.q[0], [nets[0], nets[1]]));
+            if find(&mut group, nets[0]) == find(&mut group, nets[1]) {
+                // shorted: both plates are in the group, and it discharges fully.
+                continue;
+            }
+            let q = component.q[0];
+            for (u, other_u, other_net, plate) in [(u1, u0, nets[0], -q), (u0, u1, nets[1], q)] {
+                let Some(u) = u else { continue };
+                a[u][u] += c;
+                b[u] += plate;
+                match other_u {
+                    Some(other_u) => a[u][other_u] -= c,
+                    None => b[u] += c * self.nets[other_net].voltage,
+                }
+            }
+        }
+        if capacitors.is_empty() {
+            return;
+        }
+        // groups without capacitors to other groups keep their voltages.
+        let held = (0..n_unknowns)
+            .map(|u| a[u][u] == S::from(0))
+            .collect::<Vec<_>>();
+        for (u, row) in a.iter_mut().enumerate() {
+            if held[u] {
+                row[u] = S::from(1);
+            }
+        }
+        let Some(voltages) = solve_dense(a, b) else {
+            // floating capacitors only: conservation doesn't fix their voltages.
+            return;
+        };
+
+        let voltage = |group: &mut [usize], net: usize| match unknown_of(group, net) {
+            Some(u) if !held[u] => voltages[u],
+            _ => self.nets[net].voltage,
+        };
+        let mut dissipated = S::from(0);
+        let mut new_charges = Vec::with_capacity(capacitors.len());
+        for &(component_i, c, q, nets) in &capacitors {
+            let (v0, v1) = (voltage(&mut group, nets[0]), voltage(&mut group, nets[1]));
+            let q_new = if find(&mut group, nets[0]) == find(&mut group, nets[1]) {
+                S::from(0)
+            } else {
+                -c * (v1 - v0)
+            };
+            dissipated += (q * q - q_new * q_new) / (S::from(2) * c);
+            // energy delivered by the held nets, from the charge they moved onto the plates.
+            if unknown_of(&mut group, nets[0]).is_none() {
+                dissipated += v0 * (q_new - q);
+            }
+            if unknown_of(&mut group, nets[1]).is_none() {
+                dissipated -= v1 * (q_new - q);
+            }
+            new_charges.push((component_i, q_new));
+        }
+        for &(component_i, q_new) in &new_charges {
+            let (_, i) = self.slots[component_i];
+            self.pools.linear[i].q[0] = q_new;
+        }
+        for net_i in 0..self.nets.len() {
+            if let Some(u) = unknown_of(&mut group, net_i) {
+                if !held[u] {
+                    self.nets[net_i].voltage = voltages[u];
+                }
+            }
+        }
+        self.charge_sharing.last = Some(ChargeSharing {
+            switches,
+            capacitors: new_charges.into_iter().map(|(c, _)| c).collect(),
+            dissipated,
+        });
+    }
+}
+
+/// `x` with `a x = b` by Gaussian elimination with partial pivoting, or `None` if `a` is
+/// singular.
+fn solve_dense<S: Scalar>(mut a: Vec<Vec<S>>, mut b: Vec<S>) -> Option<Vec<S>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| {
+            a[i][col]
+                .abs()
+                .partial_cmp(&a[j][col].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        if a[pivot][col].abs() <= S::from_f64(1e-300) {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = a[row][col] / a[col][col];
+            let (pivot_rows, rows) = a.split_at_mut(row);
+            for (x, &p) in rows[0][col..].iter_mut().zip(&pivot_rows[col][col..]) {
+                *x -= factor * p;
+            }
+            let v = b[col];
+            b[row] -= factor * v;
+        }
+    }
+    let mut x = vec![S::from(0); n];
+    for row in (0..n).rev() {
+        let sum = (row + 1..n).fold(b[row], |sum, k| sum - a[row][k] * x[k]);
+        x[row] = sum / a[row][row];
+    }
+    Some(x)
+}
@@ -0,0 +1,171 @@
+//! Saving a run partway and resuming it later (feature `serde`). A checkpoint holds the whole
+//! `CircuitState` (net and component states, simulation time, solver history, faults, signals
+//! and the PRNG state of every noise source), the recording so far including its partly filled
+//! decimation bucket, the time step and the number of steps done, as JSON. A run resumed from it
+//! continues bit for bit as the uninterrupted run would have.
+//!
+//! A circuit with a NaN or infinite value in its state (a diverged solve) writes, but can't be
+//! read back: JSON has no numbers for them. Recorded samples that aren't finite are kept.
+
+use std::{error::Error, fmt, fs, io, path::Path, sync::atomic::AtomicBool};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::SimError,
+    f,
+    probe::Recording,
+    run::{self, PlotConfig, Progress, RunConfig, RunOutcome},
+    CircuitState,
+};
+
+/// Version of the checkpoint format; `resume_from_checkpoint` refuses any other.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// The file is not a checkpoint, or one that doesn't match the simulator.
+    Format(String),
+    /// A checkpoint of another format version.
+    Version {
+        found: u32,
+        expected: u32,
+    },
+    Sim(SimError),
+}
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Format(message) => write!(f, "reading the checkpoint: {message}"),
+            Self::Version { found, expected } => write!(
+                f,
+                "checkpoint format version {found}, this simulator reads version {expected}"
+            ),
+            Self::Sim(err) => write!(f, "{err}"),
+        }
+    }
+}
+impl Error for CheckpointError {}
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+impl From<SimError> for CheckpointError {
+    fn from(err: SimError) -> Self {
+        Self::Sim(err)
+    }
+}
+
+/// A run as `resume_from_checkpoint` reads it back.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Checkpoint {
+    pub circuit: CircuitState,
+    pub recording: Recording,
+    pub dt: f,
+    pub steps_done: usize,
+}
+impl Checkpoint {
+    /// The `RunConfig` continuing the run to `n_steps` steps in total.
+    pub fn run_config(&self, n_steps: usize, progress_every: usize) -> RunConfig {
+        RunConfig {
+            dt: self.dt,
+            n_steps,
+            progress_every,
+            start_step: self.steps_done,
+        }
+    }
+}
+
+/// `Checkpoint` as written, borrowing the run.
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    version: u32,
+    circuit: &'a CircuitState,
+    recording: &'a Recording,
+    dt: f,
+    steps_done: usize,
+}
+
+/// Read ahead of the rest, so a checkpoint of another version fails on that and not on
+/// whatever changed in its layout.
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// Save a run `steps_done` steps of `dt` in to `path`. The file is written next to `path` and
+/// moved over it when complete, so a write cut short leaves the previous checkpoint intact.
+pub fn write_checkpoint(
+    path: impl AsRef<Path>,
+    circuit: &CircuitState,
+    recording: &Recording,
+    dt: f,
+    steps_done: usize,
+) -> Result<(), CheckpointError> {
+    let path = path.as_ref();
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let json = serde_json::to_string(&CheckpointRef {
+        version: CHECKPOINT_VERSION,
+        circuit,
+        recording,
+        dt,
+        steps_done,
+    })
+    .map_err(|e| CheckpointError::Format(e.to_string()))?;
+    fs::write(&partial, json)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Read a checkpoint `write_checkpoint` saved, to continue the run with `Checkpoint::run_config`.
+pub fn resume_from_checkpoint(path: impl AsRef<Path>) -> Result<Checkpoint, CheckpointError> {
+    let json = fs::read_to_string(path)?;
+    let header = serde_json::from_str::<Header>(&json)
+        .map_err(|e| CheckpointError::Format(e.to_string()))?;
+    if header.version != CHECKPOINT_VERSION {
+        return Err(CheckpointError::Version {
+            found: header.version,
+            expected: CHECKPOINT_VERSION,
+        });
+    }
+    serde_json::from_str(&json).map_err(|e| CheckpointError::Format(e.to_string()))
+}
+
+/// Where and how often `run_with_checkpoints` saves the run.
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointConfig<'a> {
+    pub path: &'a Path,
+    /// Steps between checkpoints, counted from the start of the run (not of the resumption).
+    pub every: usize,
+}
+
+/// `run::run_with_progress` (or `run_with_plot` with a `plot`), overwriting the checkpoint at
+/// `checkpoints.path` every `checkpoints.every` steps.
+pub fn run_with_checkpoints(
+    circuit: &mut CircuitState,
+    recording: Recording,
+    config: RunConfig,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(&Progress),
+    plot: Option<PlotConfig<'_>>,
+    checkpoints: CheckpointConfig<'_>,
+) -> Result<RunOutcome, CheckpointError> {
+    run::run(
+        circuit,
+        recording,
+        config,
+        cancel,
+        on_progress,
+        plot,
+        |circuit, recording, step| {
+            if checkpoints.every > 0 && step % checkpoints.every == 0 {
+                write_checkpoint(checkpoints.path, circuit, recording, config.dt, step)?;
+            }
+            Ok(())
+        },
+    )
+}
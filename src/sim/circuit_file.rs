@@ -0,0 +1,93 @@
+//! Circuits described in RON (feature `serde`), the `circuit.ron` files of the binary: each
+//! component by name, with its `ComponentValueEnum` and the nets of its terminals in order, and
+//! optionally the voltage a capacitor starts at, e.g.
+//!
+//! ```text
+//! (
+//!     components: [
+//!         (name: "V1", value: Linear(Source(1.0)), nets: ["gnd", "in"]),
+//!         (name: "R1", value: Linear(Resistive(1000.0)), nets: ["in", "out"]),
+//!         (name: "C1", value: Linear(Capacitive(1e-6)), nets: ["gnd", "out"], initial_voltage: Some(0.5)),
+//!     ],
+//! )
+//! ```
+//!
+//! Nets are created as they are first named (see `CircuitBuilder`), so the first net named is
+//! net 0, and every net's name is its alias.
+
+use std::{error::Error, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    builder::{CircuitBuilder, NameMap},
+    error::SimError,
+    f, CircuitState, ComponentValueEnum,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitFile {
+    pub components: Vec<ComponentSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentSpec {
+    pub name: String,
+    pub value: ComponentValueEnum,
+    /// The net of each terminal, in the order of `ComponentValue::terminal_names`.
+    pub nets: Vec<String>,
+    /// `CircuitState::set_initial_capacitor_voltage` of a capacitor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_voltage: Option<f>,
+}
+
+#[derive(Debug)]
+pub enum CircuitFileError {
+    /// The text is not a circuit description.
+    Parse(String),
+    Sim(SimError),
+}
+impl fmt::Display for CircuitFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(message) => write!(f, "reading the circuit: {message}"),
+            Self::Sim(err) => write!(f, "{err}"),
+        }
+    }
+}
+impl Error for CircuitFileError {}
+impl From<SimError> for CircuitFileError {
+    fn from(err: SimError) -> Self {
+        Self::Sim(err)
+    }
+}
+
+impl CircuitFile {
+    pub fn from_ron(text: &str) -> Result<Self, CircuitFileError> {
+        ron::from_str(text).map_err(|e| CircuitFileError::Parse(e.to_string()))
+    }
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("circuit descriptions serialize")
+    }
+
+    /// The circuit, with its capacitors charged to their `initial_voltage`s.
+    ///
+    /// Errors like `CircuitBuilder::component`, or if a component with an `initial_voltage`
+    /// isn't a capacitor.
+    pub fn build(&self) -> Result<(CircuitState, NameMap), CircuitFileError> {
+        let mut builder = CircuitBuilder::new();
+        for spec in &self.components {
+            let nets = spec.nets.iter().map(String::as_str).collect::<Vec<_>>();
+            builder.component(&spec.name, spec.value, &nets)?;
+        }
+        let (mut circuit, names) = builder.build();
+        for spec in &self.components {
+            if let Some(volts) = spec.initial_voltage {
+                let component = names.component(&spec.name).expect("was just added");
+                circuit.set_initial_capacitor_voltage(component, volts)?;
+            }
+        }
+        Ok((circuit, names))
+    }
+}
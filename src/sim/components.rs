@@ -1,263 +1,1346 @@
-use crate::sim::{converged, Lerp};
+use std::collections::VecDeque;
 
-use super::{f, ComponentState, ComponentValue, HasConverged, NetState};
+use crate::sim::{converged, largest_change};
+
+use super::{
+    ac::{AcSystem, Cf},
+    f,
+    math::{lerp, soft_min},
+    power::PowerKind,
+    random::Rng,
+    solver::Limiter,
+    ComponentState, ComponentValue, HasConverged, NetId, NetStamps, NetState, Scalar,
+};
+
+/// A scalar parameter of a component value that analyses may vary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ComponentParameter {
+    /// The single value of a linear component (capacitance, resistance, inductance or source voltage,
+    /// the high voltage of a PWM source), or the offset of a noise source.
+    Value,
+    Beta,
+    ThresholdVoltage,
+}
 
 // ---------------------- LINEAR COMPONENTS ----------------------
 // [capacitors, resistors, inductors, sources]
 
 #[derive(Debug, Clone, Copy)]
-pub enum LinearComponentValue {
-    Capacitive(f),
-    Resistive(f),
-    Inductive(f),
-    Source(f),
-    Switch { closed: bool },
-}
-#[derive(Debug)]
-pub struct LinearComponentState {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinearComponentValue<S: Scalar = f> {
+    Capacitive(S),
+    /// A capacitor with its equivalent series resistance and inductance in the same branch:
+    /// `V = Q / capacitance + esr I + esl dI/dt`, and optionally a `leakage_resistance` directly
+    /// across the capacitance that discharges it.
+    LossyCapacitive {
+        capacitance: S,
+        esr: S,
+        esl: S,
+        leakage_resistance: Option<S>,
+    },
+    Resistive(S),
+    Inductive(S),
+    /// An inductor whose incremental inductance `L(I) = inductance / (1 + (I / saturation_current)^2)`
+    /// falls off as its core saturates, with the winding resistance in the same branch:
+    /// `V = L(I) dI/dt + series_resistance I`.
+    SaturatingInductive {
+        inductance: S,
+        saturation_current: S,
+        series_resistance: S,
+    },
+    Source(S),
+    /// A source (like `Source`) switching between two voltages, as a PWM gate drive.
+    Pwm(PwmWave<S>),
+    /// An ideal switch while closed. While open, a resistor of `off_resistance` (see
+    /// `LinearComponentValue::switch`), or no connection at all for `None`.
+    Switch {
+        closed: bool,
+        off_resistance: Option<S>,
+    },
+    /// A permanent ideal connection (a star point, a sense tap, a solder jumper): a zero-volt
+    /// branch like a closed `Switch`, but one `CircuitBuilder::build_collapsed` may merge away.
+    Jumper,
+}
+
+/// `off_resistance` of `LinearComponentValue::switch`: negligible next to any real load, but it
+/// keeps nets behind an open switch at a defined voltage.
+pub const SWITCH_OFF_RESISTANCE: f = 1e9;
+
+impl<S: Scalar> LinearComponentValue<S> {
+    /// A switch that is `SWITCH_OFF_RESISTANCE` while open.
+    pub fn switch(closed: bool) -> Self {
+        Self::Switch {
+            closed,
+            off_resistance: Some(S::from_f64(SWITCH_OFF_RESISTANCE)),
+        }
+    }
+
+    pub fn resistor_kohm(kohm: S) -> Self {
+        Self::Resistive(kohm * S::from_f64(1e3))
+    }
+    pub fn resistor_megohm(megohm: S) -> Self {
+        Self::Resistive(megohm * S::from_f64(1e6))
+    }
+    // dividing by exact powers of ten, so `capacitor_nf(100.0)` is exactly `100e-9`.
+    pub fn capacitor_pf(pf: S) -> Self {
+        Self::Capacitive(pf / S::from_f64(1e12))
+    }
+    pub fn capacitor_nf(nf: S) -> Self {
+        Self::Capacitive(nf / S::from_f64(1e9))
+    }
+    pub fn capacitor_uf(uf: S) -> Self {
+        Self::Capacitive(uf / S::from_f64(1e6))
+    }
+    pub fn inductor_nh(nh: S) -> Self {
+        Self::Inductive(nh / S::from_f64(1e9))
+    }
+    pub fn inductor_uh(uh: S) -> Self {
+        Self::Inductive(uh / S::from_f64(1e6))
+    }
+    pub fn inductor_mh(mh: S) -> Self {
+        Self::Inductive(mh / S::from_f64(1e3))
+    }
+
+    /// The single component equivalent to `m` copies of this one connected in parallel.
+    pub fn parallel(self, m: S) -> Self {
+        match self {
+            Self::Capacitive(c) => Self::Capacitive(c * m),
+            Self::LossyCapacitive {
+                capacitance,
+                esr,
+                esl,
+                leakage_resistance,
+            } => Self::LossyCapacitive {
+                capacitance: capacitance * m,
+                esr: esr / m,
+                esl: esl / m,
+                leakage_resistance: leakage_resistance.map(|r| r / m),
+            },
+            Self::Resistive(r) => Self::Resistive(r / m),
+            Self::Inductive(l) => Self::Inductive(l / m),
+            Self::SaturatingInductive {
+                inductance,
+                saturation_current,
+                series_resistance,
+            } => Self::SaturatingInductive {
+                inductance: inductance / m,
+                saturation_current: saturation_current * m,
+                series_resistance: series_resistance / m,
+            },
+            Self::Switch {
+                closed,
+                off_resistance,
+            } => Self::Switch {
+                closed,
+                off_resistance: off_resistance.map(|r| r / m),
+            },
+            Self::Source(_) | Self::Pwm(_) | Self::Jumper => self,
+        }
+    }
+}
+
+/// The voltage of `LinearComponentValue::Pwm`: `high` for the first `duty` (within `[0, 1]`)
+/// of each `period` from `delay` on, `low` before `delay` and for the rest of each period, with
+/// the imperfections of a timer `timing` adds.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PwmWave<S: Scalar = f> {
+    pub low: S,
+    pub high: S,
+    pub period: S,
+    pub duty: S,
+    pub delay: S,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timing: PwmTiming<S>,
+}
+
+/// How a `PwmWave` departs from ideal timing, as an MCU timer does. The default is ideal: the
+/// wave then follows `PwmWave::voltage` exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PwmTiming<S: Scalar = f> {
+    /// Round the duty to the nearest of `2^bits` steps, as a compare register of this width.
+    pub duty_bits: Option<u32>,
+    /// Standard deviation of the length of each period, in seconds, drawn from a gaussian when
+    /// the period starts. The on time stays `duty * period`.
+    pub period_jitter: S,
+    /// Seed of the jitter.
+    pub seed: u64,
+    /// Take a new `duty` at the start of the next period, like a shadow register, instead of
+    /// right away.
+    pub latch_duty: bool,
+}
+impl<S: Scalar> Default for PwmTiming<S> {
+    fn default() -> Self {
+        Self {
+            duty_bits: None,
+            period_jitter: S::from(0),
+            seed: 0,
+            latch_duty: false,
+        }
+    }
+}
+impl<S: Scalar> PwmTiming<S> {
+    pub fn is_ideal(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The period of a non-ideal `PwmWave` under way, drawn when it started.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct PwmSchedule<S: Scalar> {
+    start: S,
+    end: S,
+    /// The duty taken at `start`, for `PwmTiming::latch_duty`.
+    duty: S,
+    rng: Rng,
+}
+impl<S: Scalar> PwmWave<S> {
+    /// A wave with ideal timing.
+    pub fn new(low: S, high: S, period: S, duty: S, delay: S) -> Self {
+        Self {
+            low,
+            high,
+            period,
+            duty,
+            delay,
+            timing: PwmTiming::default(),
+        }
+    }
+
+    /// Times within this fraction of a period before an edge count as past it, so a step meant
+    /// to land on an edge sees the new voltage despite rounding.
+    const EDGE_TOLERANCE: f = 1e-9;
+
+    /// Periods since `delay` at time `t`, with `EDGE_TOLERANCE`.
+    fn periods(&self, t: S) -> f {
+        (t.to_f64() - self.delay.to_f64()) / self.period.to_f64() + Self::EDGE_TOLERANCE
+    }
+    fn duty(&self) -> f {
+        self.duty.to_f64().clamp(0.0, 1.0)
+    }
+    pub fn voltage(&self, t: S) -> S {
+        let periods = self.periods(t);
+        if periods >= 0.0 && periods.fract() < self.duty() {
+            self.high
+        } else {
+            self.low
+        }
+    }
+    /// Time from `t` to the next edge after it (or to the start at `delay`).
+    pub fn time_to_next_edge(&self, t: S) -> S {
+        let periods = self.periods(t);
+        let to_edge = if periods < 0.0 {
+            -periods
+        } else if periods.fract() < self.duty() {
+            self.duty() - periods.fract()
+        } else {
+            1.0 - periods.fract()
+        };
+        S::from_f64((to_edge + Self::EDGE_TOLERANCE) * self.period.to_f64())
+    }
+
+    /// `duty` within `[0, 1]` and rounded to `PwmTiming::duty_bits`.
+    fn quantized_duty(&self) -> S {
+        let duty = S::from_f64(self.duty());
+        match self.timing.duty_bits {
+            Some(bits) => {
+                let steps = S::from_f64(2f64.powi(bits as i32));
+                S::from_f64((duty * steps).to_f64().round()) / steps
+            }
+            None => duty,
+        }
+    }
+    /// The schedule from `delay`, its first period drawn.
+    fn first_period(&self) -> PwmSchedule<S> {
+        let mut schedule = PwmSchedule {
+            start: self.delay,
+            end: self.delay,
+            duty: S::from(0),
+            rng: Rng::new(self.timing.seed),
+        };
+        self.next_period(&mut schedule);
+        schedule
+    }
+    /// Start the period after the one of `schedule`.
+    fn next_period(&self, schedule: &mut PwmSchedule<S>) {
+        let jitter = self.timing.period_jitter * S::from_f64(schedule.rng.gaussian());
+        // a timer can't count backwards: keep every period at least a tenth of the nominal.
+        let length = (self.period + jitter).max(self.period * S::from_f64(0.1));
+        schedule.start = schedule.end;
+        schedule.end = schedule.start + length;
+        schedule.duty = self.quantized_duty();
+    }
+    /// The end of the on time of the period of `schedule`.
+    fn schedule_on_until(&self, schedule: &PwmSchedule<S>) -> S {
+        let duty = if self.timing.latch_duty {
+            schedule.duty
+        } else {
+            self.quantized_duty()
+        };
+        let on_until = schedule.start + duty * self.period;
+        on_until.min(schedule.end)
+    }
+    /// `voltage` by `schedule`, which `t` is in the period of (or before the first of).
+    fn schedule_voltage(&self, schedule: &PwmSchedule<S>, t: S) -> S {
+        let tolerance = self.period * S::from_f64(Self::EDGE_TOLERANCE);
+        let t = t + tolerance;
+        if t >= schedule.start && t < self.schedule_on_until(schedule) {
+            self.high
+        } else {
+            self.low
+        }
+    }
+    /// `time_to_next_edge` by `schedule`.
+    fn schedule_time_to_next_edge(&self, schedule: &PwmSchedule<S>, t: S) -> S {
+        let tolerance = self.period * S::from_f64(Self::EDGE_TOLERANCE);
+        let on_until = self.schedule_on_until(schedule);
+        let edge = if t + tolerance < schedule.start {
+            schedule.start
+        } else if t + tolerance < on_until {
+            on_until
+        } else {
+            schedule.end
+        };
+        edge - t + tolerance
+    }
+}
+
+/// `L(I)` of `LinearComponentValue::SaturatingInductive`.
+pub(super) fn saturating_inductance<S: Scalar>(inductance: S, saturation_current: S, i: S) -> S {
+    let x = i / saturation_current;
+    inductance / (S::from(1) + x * x)
+}
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinearComponentState<S: Scalar = f> {
     connected_nets_i: [usize; 2],
-    pub value: LinearComponentValue,
+    pub value: LinearComponentValue<S>,
     /// `= [Q, Q', Q''] = [Q, I, d/dt I]`, where `Q` is charge and `I` is current from terminal 0 to 1.
-    pub q: [f; 3],
-    pub offset_emf: f,
+    pub q: [S; 3],
+    pub offset_emf: S,
+    /// Largest change of `q` in the last `purturb_from_nets`.
+    last_residual: S,
+    /// Simulation time as of the last `tick`, for `LinearComponentValue::Pwm`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    time: S,
+    /// The period under way of a `Pwm` with non-ideal `PwmTiming`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pwm_schedule: Option<PwmSchedule<S>>,
+    /// Ratings of a resistor, see `CircuitState::set_resistor_rating`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    rating: Option<ResistorRating<S>>,
+    /// Temperature in kelvin the resistance of a `Resistive` is at, by the `tempco` of its
+    /// rating. Set from `Environment::ambient` at every tick unless `temperature_held`.
+    #[cfg_attr(feature = "serde", serde(default = "nominal_temperature"))]
+    temperature: S,
+    /// Whether `temperature` is held, by the `ThermalNetwork` the component is bound to,
+    /// instead of following the ambient temperature.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub temperature_held: bool,
+    /// The fuse a `Switch` stands for, see `CircuitState::set_fuse`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    fuse: Option<Fuse<S>>,
 }
 
-impl ComponentValue for LinearComponentValue {
-    type State = LinearComponentState;
+/// A fuse (see `CircuitState::set_fuse`): the `Switch` it is set on opens for good at the end of
+/// the tick the `I^2 t` let through since reaches `i2t`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fuse<S: Scalar = f> {
+    /// Melting integral in A^2 s.
+    pub i2t: S,
+    /// `integral of I^2 dt` so far.
+    pub let_through: S,
+}
+impl<S: Scalar> Fuse<S> {
+    pub fn new(i2t: S) -> Self {
+        Self {
+            i2t,
+            let_through: S::from(0),
+        }
+    }
+    pub fn blown(&self) -> bool {
+        self.let_through >= self.i2t
+    }
+}
+
+/// Ratings of a resistor (see `CircuitState::set_resistor_rating`), for the power it may
+/// dissipate and how its resistance moves with temperature.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResistorRating<S: Scalar = f> {
+    /// Average dissipation in watts the part is rated for, checked by a `LossAccumulator`;
+    /// `None` for no limit.
+    pub power: Option<S>,
+    /// Temperature coefficient of the resistance per kelvin (`100e-6` for 100 ppm/K), the
+    /// resistance given for `NOMINAL_TEMPERATURE`.
+    pub tempco: S,
+}
+
+#[cfg(feature = "serde")]
+fn nominal_temperature<S: Scalar>() -> S {
+    S::from_f64(NOMINAL_TEMPERATURE)
+}
+
+impl<S: Scalar> ComponentValue<S> for LinearComponentValue<S> {
+    type State = LinearComponentState<S>;
     fn n_terminals(&self) -> usize {
         2
     }
+    fn terminal_names(&self) -> &'static [&'static str] {
+        &["-", "+"]
+    }
     fn create(&self, connected_nets_i: &[usize]) -> Self::State {
         LinearComponentState::new(*self, connected_nets_i)
     }
 }
 
-impl LinearComponentState {
-    fn new(value: LinearComponentValue, connected_nets_i: &[usize]) -> Self {
+impl<S: Scalar> LinearComponentState<S> {
+    fn new(value: LinearComponentValue<S>, connected_nets_i: &[usize]) -> Self {
         let mut this = Self {
             connected_nets_i: [0, 0],
             value,
-            q: [0.0; 3],
-            offset_emf: 0.0,
+            q: [S::from(0); 3],
+            offset_emf: S::from(0),
+            last_residual: S::from(0),
+            time: S::from(0),
+            pwm_schedule: None,
+            rating: None,
+            temperature: S::from_f64(NOMINAL_TEMPERATURE),
+            temperature_held: false,
+            fuse: None,
         };
         this.set_nets(connected_nets_i);
+        this.advance_pwm();
         this
     }
+
+    pub fn rating(&self) -> Option<ResistorRating<S>> {
+        self.rating
+    }
+    /// Replace the ratings, rescaling a `Resistive` from the temperature coefficient of the old
+    /// ones to that of the new.
+    pub fn set_rating(&mut self, rating: Option<ResistorRating<S>>) {
+        let before = self.temperature_factor();
+        self.rating = rating;
+        self.rescale_resistance(before);
+    }
+    pub fn temperature(&self) -> S {
+        self.temperature
+    }
+    /// Move to `temperature` (kelvin), rescaling a `Resistive` by the `tempco` of its rating.
+    pub fn set_temperature(&mut self, temperature: S) {
+        let before = self.temperature_factor();
+        self.temperature = temperature;
+        self.rescale_resistance(before);
+    }
+    /// Take the resistance of a `Resistive` just set as its value at `NOMINAL_TEMPERATURE`, and
+    /// scale it to the present temperature.
+    pub(super) fn scale_to_temperature(&mut self) {
+        self.rescale_resistance(S::from(1));
+    }
+    /// `1 + tempco (T - NOMINAL_TEMPERATURE)`, the resistance at the present temperature per
+    /// that at the nominal one.
+    fn temperature_factor(&self) -> S {
+        match self.rating {
+            Some(rating) => {
+                S::from(1) + rating.tempco * (self.temperature - S::from_f64(NOMINAL_TEMPERATURE))
+            }
+            None => S::from(1),
+        }
+    }
+    /// Scale a `Resistive` from the temperature factor `before` to the present one.
+    fn rescale_resistance(&mut self, before: S) {
+        let after = self.temperature_factor();
+        if let LinearComponentValue::Resistive(r) = &mut self.value {
+            if after != before {
+                *r = *r * after / before;
+            }
+        }
+    }
+
+    pub fn fuse(&self) -> Option<Fuse<S>> {
+        self.fuse
+    }
+    pub fn set_fuse(&mut self, fuse: Option<Fuse<S>>) {
+        self.fuse = fuse;
+    }
+
+    /// The voltage of a `Pwm` at the time of the last tick, by its schedule if it has one.
+    pub(super) fn pwm_voltage(&self, wave: &PwmWave<S>) -> S {
+        match &self.pwm_schedule {
+            Some(schedule) => wave.schedule_voltage(schedule, self.time),
+            None => wave.voltage(self.time),
+        }
+    }
+    /// Draw the periods of a `Pwm` with non-ideal timing up to the present time, starting its
+    /// schedule if it has none (or dropping it, for ideal timing).
+    fn advance_pwm(&mut self) {
+        let LinearComponentValue::Pwm(wave) = self.value else {
+            self.pwm_schedule = None;
+            return;
+        };
+        if wave.timing.is_ideal() {
+            self.pwm_schedule = None;
+            return;
+        }
+        let schedule = self.pwm_schedule.get_or_insert_with(|| wave.first_period());
+        let tolerance = wave.period * S::from_f64(PwmWave::<S>::EDGE_TOLERANCE);
+        while self.time + tolerance >= schedule.end {
+            wave.next_period(schedule);
+        }
+    }
 }
 
-impl ComponentState for LinearComponentState {
+impl<S: Scalar> ComponentState<S> for LinearComponentState<S> {
     fn set_nets(&mut self, connected_nets_i: &[usize]) {
         assert_eq!(
             connected_nets_i.len(),
             2,
             "can only create a linear component with exactly two connected nets."
         );
-        for i in 0..2 {
-            self.connected_nets_i[i] = connected_nets_i[i];
-        }
+        self.connected_nets_i.copy_from_slice(connected_nets_i);
+    }
+    fn nets(&self) -> &[NetId] {
+        &self.connected_nets_i
     }
 
-    fn impart_voltage_to_nets(&self, nets: &mut [NetState], step: f) {
+    fn impart_voltage_to_nets(&self, nets: &[NetState<S>], step: S, stamps: &mut NetStamps<S>) {
         let v_prev =
             nets[self.connected_nets_i[1]].voltage - nets[self.connected_nets_i[0]].voltage;
         let v_target = self.offset_emf
             + match self.value {
                 LinearComponentValue::Capacitive(c) => -self.q[0] / c,
+                LinearComponentValue::LossyCapacitive {
+                    capacitance,
+                    esr,
+                    esl,
+                    ..
+                } => -self.q[0] / capacitance - self.q[1] * esr - self.q[2] * esl,
                 LinearComponentValue::Resistive(r) => -self.q[1] * r,
                 LinearComponentValue::Inductive(l) => -self.q[2] * l,
+                LinearComponentValue::SaturatingInductive {
+                    inductance,
+                    saturation_current,
+                    series_resistance,
+                } => {
+                    -self.q[2] * saturating_inductance(inductance, saturation_current, self.q[1])
+                        - self.q[1] * series_resistance
+                }
                 LinearComponentValue::Source(v) => v,
-                LinearComponentValue::Switch { closed: true } => 0.0,
-                LinearComponentValue::Switch { closed: false } => return,
+                LinearComponentValue::Pwm(wave) => self.pwm_voltage(&wave),
+                LinearComponentValue::Switch { closed: true, .. }
+                | LinearComponentValue::Jumper => S::from(0),
+                LinearComponentValue::Switch {
+                    closed: false,
+                    off_resistance: Some(r),
+                } => -self.q[1] * r,
+                LinearComponentValue::Switch {
+                    closed: false,
+                    off_resistance: None,
+                } => return,
             };
-        let v_diff = (v_target - v_prev) * 0.5 * step;
+        let v_diff = (v_target - v_prev) * S::from_f64(0.5) * step;
+        // an open switch only holds up nets that are otherwise floating, against the unit weight
+        // of everything else it counts like a `1 ohm / off_resistance` conductance.
+        let weight = match self.value {
+            LinearComponentValue::Switch {
+                closed: false,
+                off_resistance: Some(r),
+            } => S::from(1) / r,
+            _ => S::from(1),
+        };
 
-        let net0 = &mut nets[self.connected_nets_i[0]];
-        net0.voltage_accumulator += net0.voltage - v_diff;
-        net0.voltage_accumulator_sources += 1;
-        let net1 = &mut nets[self.connected_nets_i[1]];
-        net1.voltage_accumulator += net1.voltage + v_diff;
-        net1.voltage_accumulator_sources += 1;
+        let [net0, net1] = self.connected_nets_i;
+        stamps.weighted_voltage(net0, nets[net0].voltage - v_diff, weight);
+        stamps.weighted_voltage(net1, nets[net1].voltage + v_diff, weight);
     }
-    fn impart_currents_to_nets(&self, nets: &mut [NetState]) {
-        if let LinearComponentValue::Switch { closed: false } = self.value {
+    fn impart_currents_to_nets(&self, stamps: &mut NetStamps<S>) {
+        if let LinearComponentValue::Switch {
+            closed: false,
+            off_resistance: None,
+        } = self.value
+        {
             return;
         }
         for i in 0..2 {
-            let net0 = &mut nets[self.connected_nets_i[0]];
-            net0.current[i] -= self.q[i + 1];
-            net0.current_sources += 1;
-            let net1 = &mut nets[self.connected_nets_i[1]];
-            net1.current[i] += self.q[i + 1];
-            net1.current_sources += 1;
+            stamps.current(self.connected_nets_i[0], i, -self.q[i + 1]);
+            stamps.current(self.connected_nets_i[1], i, self.q[i + 1]);
         }
     }
 
-    fn purturb_from_nets(&mut self, nets: &mut [NetState]) -> HasConverged {
+    fn purturb_from_nets(
+        &mut self,
+        nets: &[NetState<S>],
+        limiter: &mut Limiter<S>,
+    ) -> HasConverged {
         let v_target =
             nets[self.connected_nets_i[1]].voltage - nets[self.connected_nets_i[0]].voltage;
         let i_target = [0, 1].map(|i| {
             // self_current + avg( excess_current_flowing_in, -excess_current_flowing_out )
             // attempt to force the self current to accept excess inflowing and deliver exess outflowing current.
             self.q[i + 1]
-                + 0.5
+                + S::from_f64(0.5)
                     * (nets[self.connected_nets_i[0]].current[i]
                         - nets[self.connected_nets_i[1]].current[i])
         });
 
         // set `q` to attempt to satisfy the constraints of the different types of components.
-        const FACTOR_R: f = 1.0;
+        // a resistor's current moves to a blend of Ohm's law and the KCL target, `FACTOR_R` of
+        // the way to the target. Any weight short of 1 has the same fixed point, where both hold:
+        // 1 drops Ohm's law and 0 leaves series resistors unbalanced, and 0.5 converges about as
+        // fast as any weight between.
+        const FACTOR_R: f = 0.5;
         const FACTOR_L: f = 0.0;
         let mut q_next = self.q;
         match self.value {
-            LinearComponentValue::Capacitive(_) | LinearComponentValue::Source(_) => {
+            LinearComponentValue::Capacitive(_)
+            | LinearComponentValue::Source(_)
+            | LinearComponentValue::Pwm(_)
+            | LinearComponentValue::Jumper => {
                 // V = q[0] / C   // V = <const>
                 q_next[1] = i_target[0];
                 q_next[2] = i_target[1];
                 // q_next[2] = 0.0;
             }
+            LinearComponentValue::LossyCapacitive {
+                capacitance,
+                esr,
+                esl,
+                ..
+            } => {
+                // V = q[0] / C + q[1] ESR + q[2] ESL, solved like an inductor, resistor or
+                // capacitor depending on which parts are present.
+                let v_series = -v_target - self.q[0] / capacitance;
+                if esl > S::from(0) {
+                    q_next[2] = lerp(
+                        (v_series - self.q[1] * esr) / esl,
+                        i_target[1],
+                        S::from_f64(FACTOR_L),
+                    );
+                } else if esr > S::from(0) {
+                    q_next[1] = lerp(v_series / esr, i_target[0], S::from_f64(FACTOR_R));
+                    q_next[2] = i_target[1];
+                } else {
+                    q_next[1] = i_target[0];
+                    q_next[2] = i_target[1];
+                }
+            }
             LinearComponentValue::Resistive(r) => {
                 // V = q[1] R  ->  q[1] = V / R
-                q_next[1] = FACTOR_R.lerp(-v_target / r, i_target[0]);
+                q_next[1] = lerp(-v_target / r, i_target[0], S::from_f64(FACTOR_R));
                 q_next[2] = i_target[1];
                 // q_next[2] = 0.0;
-                // dbg!(v_target, i_target, self.q[1], q_next[1]);
             }
             LinearComponentValue::Inductive(l) => {
                 // V = q[2] L  ->  q[2] = V / L
-                q_next[2] = FACTOR_L.lerp(-v_target / l, i_target[1]);
-                // dbg!(v_target, i_target, self.q[2], q_next[2]);
+                q_next[2] = lerp(-v_target / l, i_target[1], S::from_f64(FACTOR_L));
             }
-            LinearComponentValue::Switch { closed } => {
+            LinearComponentValue::SaturatingInductive {
+                inductance,
+                saturation_current,
+                series_resistance,
+            } => {
+                // V = q[2] L(q[1]) + q[1] R  ->  q[2] = (V - q[1] R) / L(q[1])
+                let l = saturating_inductance(inductance, saturation_current, self.q[1]);
+                q_next[2] = lerp(
+                    -(v_target + self.q[1] * series_resistance) / l,
+                    i_target[1],
+                    S::from_f64(FACTOR_L),
+                );
+            }
+            LinearComponentValue::Switch {
+                closed,
+                off_resistance,
+            } => {
                 if closed {
                     q_next[1] = i_target[0];
                     q_next[2] = i_target[1];
+                } else if let Some(r) = off_resistance {
+                    // as a resistor
+                    q_next[1] = lerp(-v_target / r, i_target[0], S::from_f64(FACTOR_R));
+                    q_next[2] = i_target[1];
                 } else {
-                    q_next[1] = 0.0;
-                    q_next[2] = 0.0;
+                    q_next[1] = S::from(0);
+                    q_next[2] = S::from(0);
                 }
             }
         }
 
+        q_next[1] = limiter.current(q_next[1]);
         let converged = converged(self.q[1], q_next[1]) && converged(self.q[2], q_next[2]);
+        self.last_residual = largest_change(&self.q, &q_next);
         self.q = q_next;
         converged
     }
 
-    fn tick(&mut self, dt: f) {
+    fn tick(&mut self, t: S, dt: S) {
+        self.time = t + dt;
+        if matches!(self.value, LinearComponentValue::Pwm(_)) {
+            self.advance_pwm();
+        }
+        if let (
+            Some(fuse),
+            LinearComponentValue::Switch {
+                closed: closed @ true,
+                ..
+            },
+        ) = (&mut self.fuse, &mut self.value)
+        {
+            fuse.let_through += self.q[1] * self.q[1] * dt;
+            if fuse.blown() {
+                trace_event!(warn, nets = ?self.connected_nets_i, "fuse blown");
+                *closed = false;
+            }
+        }
         self.q[1] += self.q[2] * dt;
-        self.q[0] += self.q[1] * dt;
+        // part of the branch current bypasses the capacitance through its leakage resistance.
+        let leakage = match self.value {
+            LinearComponentValue::LossyCapacitive {
+                capacitance,
+                leakage_resistance: Some(r),
+                ..
+            } => self.q[0] / (capacitance * r),
+            _ => S::from(0),
+        };
+        self.q[0] += (self.q[1] - leakage) * dt;
+    }
+    fn terminal_charge(&self, dt: S, charge: &mut [S]) {
+        // the leakage current bypasses the capacitance but still flows through the terminals.
+        charge[0] -= self.q[1] * dt;
+        charge[1] += self.q[1] * dt;
+    }
+    fn state(&self) -> &[S] {
+        &self.q
+    }
+    /// Without `Q` but for a capacitance.
+    fn periodic_state(&self) -> &[S] {
+        match self.value {
+            LinearComponentValue::Capacitive(_) | LinearComponentValue::LossyCapacitive { .. } => {
+                &self.q
+            }
+            _ => &self.q[1..],
+        }
+    }
+    fn solved_state_mut(&mut self) -> &mut [S] {
+        &mut self.q[2..]
+    }
+    fn last_residual(&self) -> S {
+        self.last_residual
+    }
+    /// An open switch without off-resistance that carries no current and settled there: its
+    /// stamps are empty and `purturb_from_nets` would keep `q` at zero.
+    fn is_currently_inert(&self) -> bool {
+        matches!(
+            self.value,
+            LinearComponentValue::Switch {
+                closed: false,
+                off_resistance: None,
+            }
+        ) && self.q[1] == S::from(0)
+            && self.q[2] == S::from(0)
+            && self.last_residual == S::from(0)
+    }
+    /// Up to the next edge of a `Pwm`, and the `L / R` time constant of a `SaturatingInductive`
+    /// of its own winding resistance. Plain inductors and capacitors give none, their time
+    /// constants depend on the rest of the circuit.
+    fn max_dt_hint(&self, t: S) -> Option<S> {
+        match self.value {
+            LinearComponentValue::Pwm(wave) => Some(match &self.pwm_schedule {
+                Some(schedule) => wave.schedule_time_to_next_edge(schedule, t),
+                None => wave.time_to_next_edge(t),
+            }),
+            LinearComponentValue::SaturatingInductive {
+                inductance,
+                saturation_current,
+                series_resistance,
+            } if series_resistance > S::from(0) => Some(
+                saturating_inductance(inductance, saturation_current, self.q[1])
+                    / series_resistance,
+            ),
+            _ => None,
+        }
+    }
+
+    fn power_kind(&self) -> PowerKind {
+        match self.value {
+            LinearComponentValue::Capacitive(_)
+            | LinearComponentValue::LossyCapacitive { .. }
+            | LinearComponentValue::Inductive(_)
+            | LinearComponentValue::SaturatingInductive { .. } => PowerKind::Reactive,
+            LinearComponentValue::Source(_) | LinearComponentValue::Pwm(_) => PowerKind::Source,
+            LinearComponentValue::Resistive(_)
+            | LinearComponentValue::Switch { .. }
+            | LinearComponentValue::Jumper => PowerKind::Dissipative,
+        }
+    }
+    fn instantaneous_power(&self, nets: &[NetState<S>]) -> S {
+        if let LinearComponentValue::Switch {
+            closed: false,
+            off_resistance: None,
+        } = self.value
+        {
+            return S::from(0);
+        }
+        // `q[1]` flows from terminal 0 to terminal 1 through the component, so it absorbs `(V0 - V1) I`.
+        let v = nets[self.connected_nets_i[0]].voltage - nets[self.connected_nets_i[1]].voltage;
+        v * self.q[1]
+    }
+    fn dissipated_power(&self, nets: &[NetState<S>]) -> S {
+        match self.value {
+            LinearComponentValue::LossyCapacitive {
+                capacitance,
+                esr,
+                leakage_resistance,
+                ..
+            } => {
+                let v_c = self.q[0] / capacitance;
+                self.q[1] * self.q[1] * esr
+                    + leakage_resistance.map_or(S::from(0), |r| v_c * v_c / r)
+            }
+            LinearComponentValue::SaturatingInductive {
+                series_resistance, ..
+            } => self.q[1] * self.q[1] * series_resistance,
+            LinearComponentValue::Resistive(_) | LinearComponentValue::Switch { .. } => {
+                self.instantaneous_power(nets)
+            }
+            _ => S::from(0),
+        }
+    }
+    fn stored_energy(&self) -> S {
+        let half = S::from_f64(0.5);
+        match self.value {
+            LinearComponentValue::Capacitive(c) => half * self.q[0] * self.q[0] / c,
+            LinearComponentValue::LossyCapacitive {
+                capacitance, esl, ..
+            } => half * (self.q[0] * self.q[0] / capacitance + esl * self.q[1] * self.q[1]),
+            LinearComponentValue::Inductive(l) => half * l * self.q[1] * self.q[1],
+            LinearComponentValue::SaturatingInductive {
+                inductance,
+                saturation_current,
+                ..
+            } => {
+                // `integral of I L(I) dI`
+                let x = self.q[1] / saturation_current;
+                half * inductance
+                    * saturation_current
+                    * saturation_current
+                    * (S::from(1) + x * x).ln()
+            }
+            _ => S::from(0),
+        }
+    }
+
+    fn stamp_ac(&self, _nets: &[NetState<S>], system: &mut AcSystem) {
+        let [a, b] = self.connected_nets_i;
+        match self.value {
+            LinearComponentValue::Resistive(r) => {
+                system.add_admittance(a, b, Cf::new(1.0 / r.to_f64(), 0.0))
+            }
+            LinearComponentValue::Capacitive(c) => {
+                system.add_admittance(a, b, Cf::new(0.0, system.omega * c.to_f64()))
+            }
+            LinearComponentValue::LossyCapacitive {
+                capacitance,
+                esr,
+                esl,
+                leakage_resistance,
+            } => {
+                let (omega, c) = (system.omega, capacitance.to_f64());
+                // the capacitance alone, or in parallel with the leakage: `R / (1 + j omega R C)`
+                let z_c = match leakage_resistance.map(S::to_f64) {
+                    Some(r) => {
+                        let x = omega * r * c;
+                        Cf::new(r / (1.0 + x * x), -r * x / (1.0 + x * x))
+                    }
+                    // open at DC
+                    None if omega == 0.0 => return,
+                    None => Cf::new(0.0, -1.0 / (omega * c)),
+                };
+                let z = Cf::new(esr.to_f64(), omega * esl.to_f64()) + z_c;
+                system.add_impedance_branch([a, b], z)
+            }
+            LinearComponentValue::Inductive(l) => {
+                system.add_impedance_branch([a, b], Cf::new(0.0, system.omega * l.to_f64()))
+            }
+            LinearComponentValue::SaturatingInductive {
+                inductance,
+                saturation_current,
+                series_resistance,
+            } => {
+                let l = saturating_inductance(inductance, saturation_current, self.q[1]);
+                system.add_impedance_branch(
+                    [a, b],
+                    Cf::new(series_resistance.to_f64(), system.omega * l.to_f64()),
+                )
+            }
+            LinearComponentValue::Source(_) | LinearComponentValue::Pwm(_) => {
+                let emf = if system.is_input() {
+                    1.into()
+                } else {
+                    0.into()
+                };
+                system.add_voltage_branch([a, b], emf);
+            }
+            LinearComponentValue::Switch { closed: true, .. } | LinearComponentValue::Jumper => {
+                system.add_voltage_branch([a, b], 0.into())
+            }
+            LinearComponentValue::Switch {
+                closed: false,
+                off_resistance: Some(r),
+            } => system.add_admittance(a, b, Cf::new(1.0 / r.to_f64(), 0.0)),
+            LinearComponentValue::Switch {
+                closed: false,
+                off_resistance: None,
+            } => {}
+        }
     }
 }
 
 // ---------------------- MOSFETS ----------------------
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MOSFETDopingType {
     PChannel,
     NChannel,
 }
 #[derive(Debug, Clone, Copy)]
-pub struct MOSFETComponentValue {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MOSFETComponentValue<S: Scalar = f> {
     pub ty: MOSFETDopingType,
-    pub beta: f,
-    pub threshold_voltage: f,
-    pub body_diode_saturation_current: f,
-    pub body_diode_ideality_facotor: f,
+    /// Transconductance parameter at `NOMINAL_TEMPERATURE`, see `MOSFETComponentState::beta`.
+    pub beta: S,
+    pub threshold_voltage: S,
+    pub body_diode_saturation_current: S,
+    pub body_diode_ideality_facotor: S,
+    /// Sharpness `m` of the triode/saturation knee. The channel current is the square law in
+    /// `v_eff = v_ds v_ctrl / (v_ds^m + v_ctrl^m)^(1/m)`, a soft minimum of `v_ds` and `v_ctrl`;
+    /// larger is sharper, infinity gives back the piecewise model.
+    pub saturation_knee: S,
+    /// Number of identical devices in parallel this component stands for; all currents and
+    /// conductances are scaled by it.
+    pub multiplicity: S,
+    /// Avalanche breakdown of the drain-source junction, `None` for a device that takes any
+    /// `v_ds`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub avalanche: Option<Avalanche<S>>,
+}
+
+/// Avalanche breakdown of a MOSFET: above `breakdown_voltage` the junction conducts
+/// `conductance (v_ds - breakdown_voltage)` (per device) alongside the channel, clamping the
+/// drain of an unclamped inductive turn-off, and the energy it dissipates so is accumulated in
+/// `MOSFETComponentState::avalanche_energy`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Avalanche<S: Scalar = f> {
+    /// `V_(BR)DSS`, of the sign of `v_ds` for either doping type.
+    pub breakdown_voltage: S,
+    /// Slope of the clamp in siemens; the steeper, the closer `v_ds` stays to
+    /// `breakdown_voltage`, but one far more conductive than the rest of the circuit stalls
+    /// the solver (10 S converges against a 10 ohm load, 100 S doesn't).
+    pub conductance: S,
+    /// `E_AS`, the avalanche energy in joules the part is rated for, checked by
+    /// `CircuitState::avalanche_overloads`; `None` for no limit.
+    pub rated_energy: Option<S>,
 }
 
-#[derive(Debug)]
-pub struct MOSFETComponentState {
+/// See `MOSFETComponentState::region`, with `v_ds` and `v_ctrl = v_gs - threshold_voltage` of
+/// the sign of the doping type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MOSFETRegion {
+    /// `v_ds <= 0`: only the body diode conducts.
+    BodyDiode,
+    /// `v_ctrl <= 0`, `v_ds > 0`.
+    Off,
+    /// `0 < v_ds < v_ctrl`: the channel is a resistance, as when fully on.
+    Triode,
+    /// `v_ds >= v_ctrl > 0`: past the knee the channel holds the current and takes the voltage,
+    /// as while switching.
+    Saturation,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MOSFETComponentState<S: Scalar = f> {
     /// `[source, gate, drain]`
     connected_nets_i: [usize; 3],
-    pub value: MOSFETComponentValue,
-    pub i: [f; 2],
-    pub v_gs_positive: f,
-    pub temperature: f,
+    pub value: MOSFETComponentValue<S>,
+    /// `= [I, d/dt I]`, where `I` is the channel current from source to drain.
+    pub i: [S; 2],
+    pub v_gs_positive: S,
+    /// `[v_gs, v_ds]` (of the same sign as `v_gs_positive`) as last acted on by
+    /// `purturb_from_nets`, which `SolverConfig::limits` bound the next step from.
+    limited_voltages: [S; 2],
+    /// Junction temperature in kelvin. Set from `Environment::ambient` at every tick unless
+    /// `temperature_held`.
+    pub temperature: S,
+    /// Whether `temperature` is held, by hand or by the `ThermalNetwork` the MOSFET is bound
+    /// to, instead of following the ambient temperature.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub temperature_held: bool,
+    /// Largest change of `i` in the last `purturb_from_nets`.
+    last_residual: S,
+    /// Energy in joules dissipated in avalanche (see `MOSFETComponentValue::avalanche`) since
+    /// the device was created.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub avalanche_energy: S,
+    /// Power dissipated in avalanche at the last `purturb_from_nets`, added to
+    /// `avalanche_energy` by the tick.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    avalanche_power: S,
+    /// `i[0]` at the start of the last tick, for `sub_step`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tick_start_current: S,
+    /// The energy `sub_step` integrated over the last tick, if it was sub-stepped.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    sub_stepped_energy: Option<S>,
+}
+
+/// Where `MOSFETComponentValue::device_current` and `device_voltage` found a point, for the
+/// trace events of the solver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conduction {
+    Diode,
+    Channel,
+    Avalanche,
+    Closed,
+}
+#[cfg(feature = "tracing")]
+impl Conduction {
+    fn name(self) -> &'static str {
+        match self {
+            Conduction::Diode => "diode",
+            Conduction::Channel => "channel",
+            Conduction::Avalanche => "avalanche",
+            Conduction::Closed => "closed",
+        }
+    }
+}
+
+/// The device equations, as the solver uses them: `purturb_from_nets` settles on `i_ds` at the
+/// nets' voltages and `impart_voltage_to_nets` proposes `v_ds_for_current`, so curves plotted or
+/// fitted from these are the ones simulated.
+impl<S: Scalar> MOSFETComponentValue<S> {
+    /// Current into the drain (and out of the source) of all `multiplicity` devices at `v_gs`,
+    /// `v_ds` and the junction temperature `temperature` (kelvin), of the sign of `v_ds` for
+    /// either doping type: through the channel and in avalanche for `v_ds` of the sign of the
+    /// doping type, the body diode the other way.
+    pub fn i_ds(&self, v_gs: S, v_ds: S, temperature: S) -> S {
+        let sign = self.polarity();
+        let (i_ds, _) = self.device_current(v_gs * sign, v_ds * sign, temperature);
+        i_ds * self.multiplicity * sign
+    }
+    /// The `v_ds` at which all devices conduct `i_ds` (as `i_ds` gives it) at `v_gs` and
+    /// `temperature`, the inverse of `i_ds` in `v_ds`. `None` where the current doesn't set the
+    /// voltage: the channel is off (short of avalanche), or `i_ds` is at or beyond the
+    /// saturation current the channel approaches. The body diode is clamped at `e^64` times its
+    /// saturation current.
+    pub fn v_ds_for_current(&self, i_ds: S, v_gs: S, temperature: S) -> Option<S> {
+        let sign = self.polarity();
+        let (proposed, _) =
+            self.device_voltage(i_ds * sign / self.multiplicity, v_gs * sign, temperature);
+        proposed
+            .filter(|&(_, weight)| weight > S::from(0))
+            .map(|(v_ds, _)| v_ds * sign)
+    }
+
+    /// `beta` at `temperature`, `beta (T / NOMINAL_TEMPERATURE)^-1.5` (the mobility law of
+    /// level-1 SPICE), so the on-resistance rises as the device heats up.
+    pub fn beta_at(&self, temperature: S) -> S {
+        self.beta * (temperature / S::from_f64(NOMINAL_TEMPERATURE)).powf(S::from_f64(-1.5))
+    }
+    /// `body_diode_saturation_current` at `temperature`, scaled as SPICE scales diode saturation
+    /// currents (`XTI = 3`, `EG = 1.11 eV`): roughly doubling every 8 K near room temperature, so
+    /// the forward voltage at a given current falls by about 2 mV/K.
+    pub fn body_diode_saturation_current_at(&self, temperature: S) -> S {
+        let i_s = self.body_diode_saturation_current;
+        let n = self.body_diode_ideality_facotor;
+        let ratio = temperature / S::from_f64(NOMINAL_TEMPERATURE);
+        let v_t = temperature / S::from_f64(ELEMENTARY_CHARGE_OVER_BOLTZMANN_CONSTANT);
+        i_s * ratio.powf(S::from(3) / n)
+            * ((ratio - S::from(1)) * S::from_f64(SILICON_BAND_GAP) / (n * v_t)).exp()
+    }
+
+    /// 1 for N-channel devices, -1 for P-channel, the sign of their `v_gs` when on.
+    fn polarity(&self) -> S {
+        match self.ty {
+            MOSFETDopingType::PChannel => S::from(-1),
+            MOSFETDopingType::NChannel => S::from(1),
+        }
+    }
+    /// Drain current per device at `v_gs`, `v_ds` of the sign of the doping type, of that sign
+    /// too, and the region it flows in.
+    fn device_current(&self, v_gs: S, v_ds: S, temperature: S) -> (S, Conduction) {
+        let zero = S::from(0);
+        if v_ds <= zero {
+            let i_s = self.body_diode_saturation_current_at(temperature);
+            let i_ds = -i_s
+                * ((-v_ds * S::from_f64(ELEMENTARY_CHARGE_OVER_BOLTZMANN_CONSTANT)
+                    / (self.body_diode_ideality_facotor * temperature))
+                    .min(S::from(64))
+                    .exp()
+                    - S::from(1));
+            return (i_ds, Conduction::Diode);
+        }
+        let v_ctrl = v_gs - self.threshold_voltage;
+        let i_avalanche = self.avalanche_current(v_ds);
+        if v_ctrl > zero {
+            // triode and saturation, one smooth curve
+            (
+                self.channel_current(v_ctrl, v_ds, temperature) + i_avalanche,
+                Conduction::Channel,
+            )
+        } else if i_avalanche > zero {
+            (i_avalanche, Conduction::Avalanche)
+        } else {
+            (zero, Conduction::Closed)
+        }
+    }
+    /// The `v_ds` at which one device conducts `i_ds`, both of the sign of the doping type, at
+    /// `v_gs`, with how strongly it follows from the current (the output conductance relative to
+    /// that at `v_ds = 0`, 0 at or beyond saturation), and the region. `None` for a channel that
+    /// is off and doesn't avalanche, which leaves `v_ds` to the circuit.
+    fn device_voltage(&self, i_ds: S, v_gs: S, temperature: S) -> (Option<(S, S)>, Conduction) {
+        let zero = S::from(0);
+        if i_ds < zero {
+            // inverse of the body diode of `device_current`, clamped the same way so huge
+            // currents can't propose an infinite voltage.
+            let v_ds = -((-i_ds) / self.body_diode_saturation_current_at(temperature) + S::from(1))
+                .min(S::from(64).exp())
+                .ln()
+                * (self.body_diode_ideality_facotor * temperature
+                    / S::from_f64(ELEMENTARY_CHARGE_OVER_BOLTZMANN_CONSTANT));
+            return (Some((v_ds, S::from(1))), Conduction::Diode);
+        }
+        let v_ctrl = v_gs - self.threshold_voltage;
+        if v_ctrl > zero {
+            let proposed = match (
+                self.channel_voltage(v_ctrl, i_ds, temperature),
+                self.avalanche,
+            ) {
+                // past breakdown the junction takes what the channel doesn't.
+                (proposed, Some(avalanche))
+                    if proposed.is_none_or(|(v_ds, _)| v_ds > avalanche.breakdown_voltage) =>
+                {
+                    let v_br = avalanche.breakdown_voltage;
+                    let v_ds = v_br
+                        + (i_ds - self.channel_current(v_ctrl, v_br, temperature)).max(zero)
+                            / avalanche.conductance;
+                    (v_ds, S::from(1))
+                }
+                (Some(proposed), _) => proposed,
+                // at or beyond the saturation current, the limit of `weight -> 0`.
+                (None, _) => (zero, zero),
+            };
+            (Some(proposed), Conduction::Channel)
+        } else if let Some(avalanche) = self.avalanche.filter(|_| i_ds > zero) {
+            let v_ds = avalanche.breakdown_voltage + i_ds / avalanche.conductance;
+            (Some((v_ds, S::from(1))), Conduction::Avalanche)
+        } else {
+            (None, Conduction::Closed)
+        }
+    }
+
+    /// Avalanche current (per device) at `v_ds`, see `avalanche`.
+    fn avalanche_current(&self, v_ds: S) -> S {
+        self.avalanche.map_or(S::from(0), |avalanche| {
+            (v_ds - avalanche.breakdown_voltage).max(S::from(0)) * avalanche.conductance
+        })
+    }
+    /// Channel current (per device) for `v_ctrl, v_ds > 0`, see `saturation_knee`.
+    fn channel_current(&self, v_ctrl: S, v_ds: S, temperature: S) -> S {
+        let v_eff = soft_min(v_ds, v_ctrl, self.saturation_knee);
+        self.beta_at(temperature) * (v_ctrl * v_eff - v_eff * v_eff * S::from_f64(0.5))
+    }
+    /// The `v_ds` at which `channel_current` is `i_ds`, with the output conductance there relative
+    /// to that at `v_ds = 0` (`g_ds / (beta v_ctrl)`, falling from 1 to 0 towards saturation).
+    /// `None` if `i_ds` is at or above the saturation current `beta v_ctrl^2 / 2` it approaches.
+    fn channel_voltage(&self, v_ctrl: S, i_ds: S, temperature: S) -> Option<(S, S)> {
+        let m = self.saturation_knee;
+        let one = S::from(1);
+        let discriminant = v_ctrl * v_ctrl - S::from(2) * i_ds / self.beta_at(temperature);
+        if discriminant <= S::from(0) {
+            return None;
+        }
+        let v_eff = v_ctrl - discriminant.sqrt();
+        let r = v_eff / v_ctrl;
+        // `= v_eff / v_ds`
+        let v_eff_ratio = (one - r.powf(m)).powf(one / m);
+        Some((v_eff / v_eff_ratio, (one - r) * v_eff_ratio.powf(m + one)))
+    }
 }
 
-impl ComponentValue for MOSFETComponentValue {
-    type State = MOSFETComponentState;
+impl<S: Scalar> ComponentValue<S> for MOSFETComponentValue<S> {
+    type State = MOSFETComponentState<S>;
     fn n_terminals(&self) -> usize {
         3
     }
+    fn terminal_names(&self) -> &'static [&'static str] {
+        &["source", "gate", "drain"]
+    }
     fn create(&self, connected_nets_i: &[usize]) -> Self::State {
         MOSFETComponentState::new(*self, connected_nets_i)
     }
 }
 
-impl MOSFETComponentState {
-    fn new(value: MOSFETComponentValue, connected_nets_i: &[usize]) -> Self {
+impl<S: Scalar> MOSFETComponentState<S> {
+    /// Small-signal `[g_m, g_ds]` at the present terminal voltages, such that the drain-to-source
+    /// channel current changes by `g_m dV_gs + g_ds dV_ds` (for either doping type).
+    pub fn small_signal(&self, nets: &[NetState<S>]) -> [S; 2] {
+        let beta = self.beta();
+        let MOSFETComponentValue {
+            threshold_voltage: v_th,
+            ty: doping_type,
+            body_diode_ideality_facotor,
+            saturation_knee: m,
+            multiplicity,
+            ..
+        } = self.value;
+        let body_diode_saturation_current = self.body_diode_saturation_current();
+        let v_gs = nets[self.connected_nets_i[1]].voltage - nets[self.connected_nets_i[0]].voltage;
+        let v_ds = nets[self.connected_nets_i[2]].voltage - nets[self.connected_nets_i[0]].voltage;
+        let (v_gs, v_ds) = match doping_type {
+            MOSFETDopingType::PChannel => (-v_gs, -v_ds),
+            MOSFETDopingType::NChannel => (v_gs, v_ds),
+        };
+        let zero = S::from(0);
+        let [g_m, g_ds] = if v_ds > zero {
+            let v_ctrl = v_gs - v_th;
+            if v_ctrl <= zero {
+                [zero, zero]
+            } else {
+                // the soft minimum is symmetric, `d v_eff / d x = (v_eff / x)^(m + 1)` for both.
+                let v_eff = soft_min(v_ds, v_ctrl, m);
+                let exponent = m + S::from(1);
+                [
+                    beta * (v_eff + (v_ctrl - v_eff) * (v_eff / v_ctrl).powf(exponent)),
+                    beta * (v_ctrl - v_eff) * (v_eff / v_ds).powf(exponent),
+                ]
+            }
+        } else {
+            let n_vt = body_diode_ideality_facotor * self.temperature
+                / S::from_f64(ELEMENTARY_CHARGE_OVER_BOLTZMANN_CONSTANT);
+            [
+                zero,
+                body_diode_saturation_current / n_vt * (-v_ds / n_vt).min(S::from(64)).exp(),
+            ]
+        };
+        let g_avalanche = match self.value.avalanche {
+            Some(avalanche) if v_ds > avalanche.breakdown_voltage => avalanche.conductance,
+            _ => zero,
+        };
+        [g_m * multiplicity, (g_ds + g_avalanche) * multiplicity]
+    }
+
+    /// Where the device operates at the present terminal voltages.
+    pub fn region(&self, nets: &[NetState<S>]) -> MOSFETRegion {
+        let v_gs = nets[self.connected_nets_i[1]].voltage - nets[self.connected_nets_i[0]].voltage;
+        let v_ds = nets[self.connected_nets_i[2]].voltage - nets[self.connected_nets_i[0]].voltage;
+        let (v_gs, v_ds) = match self.value.ty {
+            MOSFETDopingType::PChannel => (-v_gs, -v_ds),
+            MOSFETDopingType::NChannel => (v_gs, v_ds),
+        };
+        let v_ctrl = v_gs - self.value.threshold_voltage;
+        if v_ds <= S::from(0) {
+            MOSFETRegion::BodyDiode
+        } else if v_ctrl <= S::from(0) {
+            MOSFETRegion::Off
+        } else if v_ds < v_ctrl {
+            MOSFETRegion::Triode
+        } else {
+            MOSFETRegion::Saturation
+        }
+    }
+
+    fn new(value: MOSFETComponentValue<S>, connected_nets_i: &[usize]) -> Self {
         let mut this = Self {
             connected_nets_i: [0; 3],
             value,
-            i: [0.0; 2],
-            v_gs_positive: 0.0,
-            temperature: 295.0,
+            i: [S::from(0); 2],
+            v_gs_positive: S::from(0),
+            limited_voltages: [S::from(0); 2],
+            temperature: S::from_f64(NOMINAL_TEMPERATURE),
+            temperature_held: false,
+            last_residual: S::from(0),
+            avalanche_energy: S::from(0),
+            avalanche_power: S::from(0),
+            tick_start_current: S::from(0),
+            sub_stepped_energy: None,
         };
         this.set_nets(connected_nets_i);
         this
     }
+
+    /// `value.beta` at the present temperature, see `MOSFETComponentValue::beta_at`.
+    pub fn beta(&self) -> S {
+        self.value.beta_at(self.temperature)
+    }
+    /// `value.body_diode_saturation_current` at the present temperature, see
+    /// `MOSFETComponentValue::body_diode_saturation_current_at`.
+    pub fn body_diode_saturation_current(&self) -> S {
+        self.value
+            .body_diode_saturation_current_at(self.temperature)
+    }
+
+    /// `i[0]` as the drain current, of the sign of `v_gs_positive`.
+    fn drain_current(&self, i: S) -> S {
+        match self.value.ty {
+            MOSFETDopingType::PChannel => i,
+            MOSFETDopingType::NChannel => -i,
+        }
+    }
 }
 
 const ELEMENTARY_CHARGE_OVER_BOLTZMANN_CONSTANT: f = 1.1604518121550082e+4;
+/// Band gap (eV) in the temperature scaling of `body_diode_saturation_current`.
+const SILICON_BAND_GAP: f = 1.11;
+/// Temperature (K) MOSFETs and resistors start at, and `MOSFETComponentValue::beta` and
+/// resistances (see `ResistorRating::tempco`) are given for.
+pub const NOMINAL_TEMPERATURE: f = 295.0;
 
-impl ComponentState for MOSFETComponentState {
+impl<S: Scalar> ComponentState<S> for MOSFETComponentState<S> {
     fn set_nets(&mut self, connected_nets_i: &[usize]) {
         assert_eq!(
             connected_nets_i.len(),
             3,
             "can only create a MOSFET with exactly three connected nets."
         );
-        for i in 0..3 {
-            self.connected_nets_i[i] = connected_nets_i[i];
-        }
+        self.connected_nets_i.copy_from_slice(connected_nets_i);
+    }
+    fn nets(&self) -> &[NetId] {
+        &self.connected_nets_i
     }
 
-    fn impart_voltage_to_nets(&self, nets: &mut [NetState], step: f) {
+    fn impart_voltage_to_nets(&self, nets: &[NetState<S>], step: S, stamps: &mut NetStamps<S>) {
         let MOSFETComponentValue {
-            beta,
-            threshold_voltage: v_th,
             ty: doping_type,
-            body_diode_ideality_facotor,
-            body_diode_saturation_current,
+            multiplicity,
+            ..
         } = self.value;
-        let i_ds = self.i[0];
+        // per device
+        let i_ds = self.i[0] / multiplicity;
         let i_ds = match doping_type {
             MOSFETDopingType::PChannel => i_ds,
             MOSFETDopingType::NChannel => -i_ds,
         };
-        let v_gs = self.v_gs_positive;
-
-        // dbg!("V", v_gs, i_ds, v_gs - v_th);
-        // const SATURATION_RESISTANCE: f = 1e9; // one gigaohm lol
-        let v_ds = if i_ds < 0.0 {
-            // dbg!("V: // diode //");
-            // body diode forward flow //
-            -((-i_ds) / body_diode_saturation_current + 1.0).ln()
-                * (body_diode_ideality_facotor * self.temperature
-                    / ELEMENTARY_CHARGE_OVER_BOLTZMANN_CONSTANT)
-        } else {
-            let v_ctrl = v_gs - v_th;
 
-            if v_ctrl > 0.0 {
-                // dbg!(v_ctrl * v_ctrl, 2.0 * i_ds / beta);
-                if v_ctrl * v_ctrl * 0.99999 > 2.0 * i_ds / beta {
-                    // dbg!("V: //TRIODE//");
-                    // linear/triode region //
-                    v_ctrl - (v_ctrl * v_ctrl - 2.0 * i_ds / beta).sqrt()
-                } else {
-                    // dbg!("V: //SATURATION//");
-                    // saturation region //
-                    return; // no influence on voltage
-                            // // have near infinite resistance for all current above saturation point
-                            // v_ctrl + SATURATION_RESISTANCE * (i_ds - beta * 0.5 * v_ctrl * v_ctrl)
-                }
-            } else {
-                // dbg!("V: //CLOSED//");
-                // closed region //
-                // no influence on voltage
-                return;
-            }
+        // `weight` is how strongly the proposed `v_ds` follows from `i_ds`, the channel barely
+        // sets it close to saturation.
+        let (proposed, _conduction) =
+            self.value
+                .device_voltage(i_ds, self.v_gs_positive, self.temperature);
+        trace_event!(trace, nets = ?self.connected_nets_i, region = _conduction.name(), "mosfet voltage");
+        // no influence on voltage when closed
+        let Some((v_ds, weight)) = proposed else {
+            return;
         };
         let v_ds = match doping_type {
             MOSFETDopingType::PChannel => -v_ds,
@@ -267,35 +1350,29 @@ impl ComponentState for MOSFETComponentState {
         let v_ds_prev =
             nets[self.connected_nets_i[2]].voltage - nets[self.connected_nets_i[0]].voltage;
 
-        let v_diff = (v_ds - v_ds_prev) * 0.5 * step;
-        // dbg!(i_ds, v_ds, v_ds_prev);
+        let v_diff = (v_ds - v_ds_prev) * S::from_f64(0.5) * step * weight;
 
-        let net_source = &mut nets[self.connected_nets_i[0]];
-        net_source.voltage_accumulator += net_source.voltage - v_diff;
-        net_source.voltage_accumulator_sources += 1;
-        let net_drain = &mut nets[self.connected_nets_i[2]];
-        net_drain.voltage_accumulator += net_drain.voltage + v_diff;
-        net_drain.voltage_accumulator_sources += 1;
+        let [net_source, _, net_drain] = self.connected_nets_i;
+        stamps.voltage(net_source, nets[net_source].voltage - v_diff);
+        stamps.voltage(net_drain, nets[net_drain].voltage + v_diff);
     }
 
-    fn impart_currents_to_nets(&self, nets: &mut [NetState]) {
+    fn impart_currents_to_nets(&self, stamps: &mut NetStamps<S>) {
         for i in 0..2 {
-            let net_source = &mut nets[self.connected_nets_i[0]];
-            net_source.current[i] -= self.i[i];
-            net_source.current_sources += 1;
-            let net_drain = &mut nets[self.connected_nets_i[2]];
-            net_drain.current[i] += self.i[i];
-            net_drain.current_sources += 1;
+            stamps.current(self.connected_nets_i[0], i, -self.i[i]);
+            stamps.current(self.connected_nets_i[2], i, self.i[i]);
         }
     }
 
-    fn purturb_from_nets(&mut self, nets: &mut [NetState]) -> HasConverged {
+    fn purturb_from_nets(
+        &mut self,
+        nets: &[NetState<S>],
+        limiter: &mut Limiter<S>,
+    ) -> HasConverged {
         let MOSFETComponentValue {
-            beta,
-            threshold_voltage: v_th,
             ty: doping_type,
-            body_diode_ideality_facotor,
-            body_diode_saturation_current,
+            multiplicity,
+            ..
         } = self.value;
 
         let v_gs = nets[self.connected_nets_i[1]].voltage - nets[self.connected_nets_i[0]].voltage;
@@ -304,63 +1381,992 @@ impl ComponentState for MOSFETComponentState {
             MOSFETDopingType::PChannel => (-v_gs, -v_ds),
             MOSFETDopingType::NChannel => (v_gs, v_ds),
         };
+        let [v_gs_prev, v_ds_prev] = self.limited_voltages;
+        let (v_gs, v_ds) = (
+            limiter.voltage_step(v_gs_prev, v_gs),
+            limiter.voltage_step(v_ds_prev, v_ds),
+        );
+        self.limited_voltages = [v_gs, v_ds];
 
-        // dbg!("P", v_gs, v_ds);
-
-        let i_ds = if v_ds > 0.0 {
-            let v_ctrl = v_gs - v_th;
-            beta * if v_ctrl > 0.0 {
-                if v_ds < v_ctrl {
-                    // dbg!("P: // linear/triode region //");
-                    // linear/triode region //
-                    v_ctrl * v_ds - v_ds * v_ds * 0.5
-                } else {
-                    // dbg!("P: // saturation region //");
-                    // saturation region //
-                    v_ctrl * v_ctrl * 0.5
-                }
-            } else {
-                // dbg!("P: // closed region //");
-                // closed region //
-                let i_next = [0.0; 2];
-                let converged = converged(self.i[0], i_next[0]) && converged(self.i[1], i_next[1]);
-                self.i = i_next;
-                return converged;
-            }
-        } else {
-            // dbg!("P: // diode //");
-            // body diode //
-            -body_diode_saturation_current
-                * ((-v_ds * ELEMENTARY_CHARGE_OVER_BOLTZMANN_CONSTANT
-                    / (body_diode_ideality_facotor * self.temperature))
-                    .min(64.0)
-                    .exp()
-                    - 1.0)
-        };
-        // dbg!(i_ds);
-        let i_ds = match doping_type {
-            MOSFETDopingType::PChannel => i_ds,
-            MOSFETDopingType::NChannel => -i_ds,
-        };
+        let zero = S::from(0);
+        let half = S::from_f64(0.5);
         let i_target = [0, 1].map(|i| {
             // self_current + avg( excess_current_flowing_in, -excess_current_flowing_out )
             // attempt to force the self current to accept excess inflowing and deliver exess outflowing current.
             self.i[i]
-                + 0.5
+                + half
                     * (nets[self.connected_nets_i[0]].current[i]
                         - nets[self.connected_nets_i[2]].current[i])
         });
-
-        let i_next = [0.5.lerp(i_ds, i_target[0]), i_target[1]];
+        // per device, of the sign of `i_ds`.
+        let forced = match doping_type {
+            MOSFETDopingType::PChannel => i_target[0],
+            MOSFETDopingType::NChannel => -i_target[0],
+        } / multiplicity;
+        let i_avalanche = self.value.avalanche_current(v_ds);
+        self.avalanche_power = v_ds * i_avalanche * multiplicity;
+        let (i_ds, conduction) = self.value.device_current(v_gs, v_ds, self.temperature);
+        // an off device that can break down still takes the drain current forced into it, as
+        // the junction approaching breakdown.
+        let conduction = match conduction {
+            Conduction::Closed if self.value.avalanche.is_some() && forced > zero => {
+                Conduction::Avalanche
+            }
+            conduction => conduction,
+        };
+        trace_event!(trace, nets = ?self.connected_nets_i, region = conduction.name(), "mosfet current");
+        if conduction == Conduction::Closed {
+            let i_next = [zero; 2];
+            let converged = converged(self.i[0], i_next[0])
+                && converged(self.i[1], i_next[1])
+                && converged(self.v_gs_positive, v_gs);
+            self.last_residual = largest_change(&self.i, &i_next);
+            self.i = i_next;
+            // or `impart_voltage_to_nets` goes on proposing the `v_ds` of the channel at the
+            // `v_gs` it last conducted at.
+            self.v_gs_positive = v_gs;
+            return converged;
+        }
+        let i_ds = match doping_type {
+            MOSFETDopingType::PChannel => i_ds,
+            MOSFETDopingType::NChannel => -i_ds,
+        } * multiplicity;
+
+        let i_next = [limiter.current(lerp(i_ds, i_target[0], half)), i_target[1]];
         let converged = converged(self.i[0], i_next[0])
             && converged(self.i[1], i_next[1])
             && converged(self.v_gs_positive, v_gs);
+        self.last_residual = largest_change(&self.i, &i_next);
         self.i = i_next;
         self.v_gs_positive = v_gs;
         converged
     }
 
-    fn tick(&mut self, dt: f) {
-        self.i[1] += self.i[2] * dt;
+    fn tick(&mut self, _t: S, dt: S) {
+        self.tick_start_current = self.i[0];
+        self.sub_stepped_energy = None;
+        self.i[0] += self.i[1] * dt;
+        let energy = self.avalanche_energy + self.avalanche_power * dt;
+        if let Some(rated) = self.value.avalanche.and_then(|a| a.rated_energy) {
+            if self.avalanche_energy <= rated && energy > rated {
+                trace_event!(
+                    warn,
+                    nets = ?self.connected_nets_i,
+                    energy = energy.to_f64(),
+                    rated = rated.to_f64(),
+                    "mosfet avalanche energy over its rating"
+                );
+            }
+        }
+        self.avalanche_energy = energy;
+    }
+    fn terminal_charge(&self, dt: S, charge: &mut [S]) {
+        // no gate current
+        charge[0] -= self.i[0] * dt;
+        charge[2] += self.i[0] * dt;
+    }
+    fn state(&self) -> &[S] {
+        &self.i
+    }
+    fn solved_state_mut(&mut self) -> &mut [S] {
+        &mut self.i[1..]
+    }
+    fn last_residual(&self) -> S {
+        self.last_residual
+    }
+
+    fn power_kind(&self) -> PowerKind {
+        PowerKind::Dissipative
+    }
+    fn instantaneous_power(&self, nets: &[NetState<S>]) -> S {
+        // `i[0]` flows from source to drain through the channel.
+        let v = nets[self.connected_nets_i[0]].voltage - nets[self.connected_nets_i[2]].voltage;
+        v * self.i[0]
+    }
+    fn is_stiff(&self) -> bool {
+        // the channel current moved by more than a percent of itself over the tick.
+        let change = (self.i[0] - self.tick_start_current).abs();
+        change > S::CONVERGENCE_EPSILON
+            && change > S::from_f64(0.01) * self.i[0].abs().max(self.tick_start_current.abs())
+    }
+    fn sub_step(&mut self, dt: S, k: usize, start: &[NetState<S>], nets: &[NetState<S>]) {
+        let [source, gate, drain] = self.connected_nets_i;
+        let sign = self.value.polarity();
+        let voltages = |nets: &[NetState<S>]| {
+            [gate, drain].map(|net| (nets[net].voltage - nets[source].voltage) * sign)
+        };
+        let [v_gs_0, v_ds_0] = voltages(start);
+        let [v_gs_1, v_ds_1] = voltages(nets);
+        let i_0 = self.drain_current(self.tick_start_current);
+        let i_1 = self.drain_current(self.i[0]);
+        let zero = S::from(0);
+        // the drain circuit seen from the device, `v_ds = v_open - r i`, through both solutions.
+        let load_line = if v_ds_0 >= zero && v_ds_1 >= zero && i_1 != i_0 {
+            let r = -(v_ds_1 - v_ds_0) / (i_1 - i_0);
+            Some((v_ds_0 + r * i_0, r)).filter(|&(v_open, r)| r > zero && v_open > zero)
+        } else {
+            None
+        };
+        let h = dt / S::from_f64(k as f);
+        let mut energy = zero;
+        for step in 1..=k {
+            let s = S::from_f64(step as f / k as f);
+            let v_gs = lerp(v_gs_0, v_gs_1, s);
+            let (v_ds, i) = match load_line {
+                Some((v_open, r)) => {
+                    // where the device crosses the load line at the interpolated gate voltage.
+                    let (mut low, mut high) = (zero, v_open);
+                    for _ in 0..60 {
+                        let v_ds = (low + high) * S::from_f64(0.5);
+                        let (i_ds, _) = self.value.device_current(v_gs, v_ds, self.temperature);
+                        if i_ds * self.value.multiplicity > (v_open - v_ds) / r {
+                            high = v_ds;
+                        } else {
+                            low = v_ds;
+                        }
+                    }
+                    let v_ds = (low + high) * S::from_f64(0.5);
+                    (v_ds, (v_open - v_ds) / r)
+                }
+                // a current the drain circuit holds (an inductor), or the body diode.
+                None => (lerp(v_ds_0, v_ds_1, s), lerp(i_0, i_1, s)),
+            };
+            energy += v_ds * i * h;
+        }
+        self.sub_stepped_energy = Some(energy);
+    }
+    fn sub_stepped_energy(&self) -> Option<S> {
+        self.sub_stepped_energy
+    }
+
+    fn stamp_ac(&self, nets: &[NetState<S>], system: &mut AcSystem) {
+        let [source, gate, drain] = self.connected_nets_i;
+        let [g_m, g_ds] = self.small_signal(nets).map(S::to_f64);
+        system.add_transadmittance([drain, source], [gate, source], Cf::new(g_m, 0.0));
+        system.add_admittance(drain, source, Cf::new(g_ds, 0.0));
+    }
+}
+
+// ---------------------- NOISE SOURCES ----------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NoiseDistribution {
+    Gaussian,
+    /// Flat over `[-sqrt(3) sigma, sqrt(3) sigma]`.
+    Uniform,
+}
+/// A voltage source (`V(nets[1]) - V(nets[0]) = offset + noise`) drawing a new random sample on
+/// every `tick`. The sample is held for the whole `solve_state`, so it never keeps the solver
+/// from converging.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoiseSourceComponentValue<S: Scalar = f> {
+    pub distribution: NoiseDistribution,
+    /// Standard deviation of the noise voltage, also when `bandwidth` limits it.
+    pub sigma: S,
+    pub offset: S,
+    /// Seed of the source's own PRNG, so runs are reproducible.
+    pub seed: u64,
+    /// Corner frequency (Hz) of a first-order low-pass applied to the samples, `None` for
+    /// independent samples each tick.
+    pub bandwidth: Option<S>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoiseSourceComponentState<S: Scalar = f> {
+    connected_nets_i: [usize; 2],
+    pub value: NoiseSourceComponentValue<S>,
+    rng: Rng,
+    /// Present noise voltage (without `offset`).
+    pub noise: S,
+    /// `= [I, d/dt I]`, where `I` is current from terminal 0 to 1.
+    pub i: [S; 2],
+    /// Largest change of `i` in the last `purturb_from_nets`.
+    last_residual: S,
+}
+
+impl<S: Scalar> ComponentValue<S> for NoiseSourceComponentValue<S> {
+    type State = NoiseSourceComponentState<S>;
+    fn n_terminals(&self) -> usize {
+        2
+    }
+    fn terminal_names(&self) -> &'static [&'static str] {
+        &["-", "+"]
+    }
+    fn create(&self, connected_nets_i: &[usize]) -> Self::State {
+        NoiseSourceComponentState::new(*self, connected_nets_i)
+    }
+}
+
+impl<S: Scalar> NoiseSourceComponentState<S> {
+    fn new(value: NoiseSourceComponentValue<S>, connected_nets_i: &[usize]) -> Self {
+        let mut this = Self {
+            connected_nets_i: [0, 0],
+            value,
+            rng: Rng::new(value.seed),
+            noise: S::from(0),
+            i: [S::from(0); 2],
+            last_residual: S::from(0),
+        };
+        this.set_nets(connected_nets_i);
+        this
+    }
+    /// `V(nets[1]) - V(nets[0])` until the next `tick`.
+    pub fn voltage(&self) -> S {
+        self.value.offset + self.noise
+    }
+    /// A zero-mean, unit-variance sample of `value.distribution`.
+    fn sample(&mut self) -> S {
+        S::from_f64(match self.value.distribution {
+            NoiseDistribution::Gaussian => self.rng.gaussian(),
+            NoiseDistribution::Uniform => (2.0 * self.rng.uniform() - 1.0) * 3f64.sqrt(),
+        })
+    }
+}
+
+impl<S: Scalar> ComponentState<S> for NoiseSourceComponentState<S> {
+    fn set_nets(&mut self, connected_nets_i: &[usize]) {
+        assert_eq!(
+            connected_nets_i.len(),
+            2,
+            "can only create a noise source with exactly two connected nets."
+        );
+        self.connected_nets_i.copy_from_slice(connected_nets_i);
+    }
+    fn nets(&self) -> &[NetId] {
+        &self.connected_nets_i
+    }
+
+    fn impart_voltage_to_nets(&self, nets: &[NetState<S>], step: S, stamps: &mut NetStamps<S>) {
+        let [net0, net1] = self.connected_nets_i;
+        let v_prev = nets[net1].voltage - nets[net0].voltage;
+        let v_diff = (self.voltage() - v_prev) * S::from_f64(0.5) * step;
+        stamps.voltage(net0, nets[net0].voltage - v_diff);
+        stamps.voltage(net1, nets[net1].voltage + v_diff);
+    }
+    fn impart_currents_to_nets(&self, stamps: &mut NetStamps<S>) {
+        for i in 0..2 {
+            stamps.current(self.connected_nets_i[0], i, -self.i[i]);
+            stamps.current(self.connected_nets_i[1], i, self.i[i]);
+        }
+    }
+
+    fn purturb_from_nets(
+        &mut self,
+        nets: &[NetState<S>],
+        limiter: &mut Limiter<S>,
+    ) -> HasConverged {
+        // like `LinearComponentValue::Source`, the current is whatever the circuit draws.
+        let mut i_next = [0, 1].map(|i| {
+            self.i[i]
+                + S::from_f64(0.5)
+                    * (nets[self.connected_nets_i[0]].current[i]
+                        - nets[self.connected_nets_i[1]].current[i])
+        });
+        i_next[0] = limiter.current(i_next[0]);
+        let converged = converged(self.i[0], i_next[0]) && converged(self.i[1], i_next[1]);
+        self.last_residual = largest_change(&self.i, &i_next);
+        self.i = i_next;
+        converged
+    }
+    fn tick(&mut self, _t: S, dt: S) {
+        self.i[0] += self.i[1] * dt;
+        let x = self.sample() * self.value.sigma;
+        self.noise = match self.value.bandwidth {
+            Some(bandwidth) => {
+                // `y += a (x - y)` has variance `a / (2 - a)` of its input's, scale the input
+                // up so the output keeps `sigma`.
+                let a = S::from(1) - (-S::from_f64(std::f64::consts::TAU) * bandwidth * dt).exp();
+                let x = x * ((S::from(2) - a) / a).sqrt();
+                self.noise + a * (x - self.noise)
+            }
+            None => x,
+        };
+    }
+    fn terminal_charge(&self, dt: S, charge: &mut [S]) {
+        charge[0] -= self.i[0] * dt;
+        charge[1] += self.i[0] * dt;
+    }
+    fn state(&self) -> &[S] {
+        &self.i
+    }
+    fn solved_state_mut(&mut self) -> &mut [S] {
+        &mut self.i[1..]
+    }
+    fn last_residual(&self) -> S {
+        self.last_residual
+    }
+
+    fn power_kind(&self) -> PowerKind {
+        PowerKind::Source
+    }
+    fn instantaneous_power(&self, nets: &[NetState<S>]) -> S {
+        let [net0, net1] = self.connected_nets_i;
+        (nets[net0].voltage - nets[net1].voltage) * self.i[0]
+    }
+    fn stamp_ac(&self, _nets: &[NetState<S>], system: &mut AcSystem) {
+        // a source with its present value, driven like `LinearComponentValue::Source`.
+        let emf = if system.is_input() {
+            1.into()
+        } else {
+            0.into()
+        };
+        system.add_voltage_branch(self.connected_nets_i, emf);
+    }
+}
+
+// ---------------------- GATE DRIVERS ----------------------
+
+/// A gate driver IC: `out` is pulled up to `vdd` through `source_resistance` while the logic
+/// input `in` is high and down to `vss` through `sink_resistance` while it is low, switching
+/// `propagation_delay` after the input, and held low while the supply `V(vdd) - V(vss)` is under
+/// the undervoltage lockout. The input and supply are sensed without drawing current; the gate
+/// charge comes from `vdd` and returns to `vss`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GateDriverComponentValue<S: Scalar = f> {
+    /// `V(in) - V(vss)` above which the input is high.
+    pub input_threshold: S,
+    pub propagation_delay: S,
+    pub source_resistance: S,
+    pub sink_resistance: S,
+    /// Supply voltage below which the output is locked low.
+    pub uvlo_threshold: S,
+    /// How far above `uvlo_threshold` the supply has to come back to release the lockout.
+    pub uvlo_hysteresis: S,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GateDriverComponentState<S: Scalar = f> {
+    /// `[vss, vdd, in, out]`
+    connected_nets_i: [usize; 4],
+    pub value: GateDriverComponentValue<S>,
+    /// `= [I, d/dt I]`, where `I` is the output current, from the rail pulling `out` through
+    /// the driver and out of `out`.
+    pub i: [S; 2],
+    /// `[V(vdd) - V(vss), V(in) - V(vss)]` at the last `purturb_from_nets`, the solution of the
+    /// last `solve_state`.
+    sensed: [S; 2],
+    /// Whether the input was last sensed high.
+    input_high: bool,
+    /// Input edges on their way through the propagation delay: when each reaches the output,
+    /// and the level it switches it to.
+    edges: VecDeque<(S, bool)>,
+    /// The level of the input as delayed to the output.
+    delayed_high: bool,
+    /// Whether the undervoltage lockout holds the output low.
+    pub locked_out: bool,
+    /// Simulation time as of the last `tick`.
+    time: S,
+    /// Largest change of `i` in the last `purturb_from_nets`.
+    last_residual: S,
+}
+
+impl<S: Scalar> ComponentValue<S> for GateDriverComponentValue<S> {
+    type State = GateDriverComponentState<S>;
+    fn n_terminals(&self) -> usize {
+        4
+    }
+    fn terminal_names(&self) -> &'static [&'static str] {
+        &["vss", "vdd", "in", "out"]
+    }
+    fn create(&self, connected_nets_i: &[usize]) -> Self::State {
+        GateDriverComponentState::new(*self, connected_nets_i)
+    }
+}
+
+impl<S: Scalar> GateDriverComponentState<S> {
+    fn new(value: GateDriverComponentValue<S>, connected_nets_i: &[usize]) -> Self {
+        let mut this = Self {
+            connected_nets_i: [0; 4],
+            value,
+            i: [S::from(0); 2],
+            sensed: [S::from(0); 2],
+            input_high: false,
+            edges: VecDeque::new(),
+            delayed_high: false,
+            // a driver starts unpowered, until its first tick sees the supply.
+            locked_out: true,
+            time: S::from(0),
+            last_residual: S::from(0),
+        };
+        this.set_nets(connected_nets_i);
+        this
+    }
+    /// Whether `out` is pulled up to `vdd` rather than down to `vss`.
+    pub fn output_high(&self) -> bool {
+        self.delayed_high && !self.locked_out
+    }
+    /// The rail `out` is pulled to and the resistance it is pulled through.
+    fn pull(&self) -> (NetId, S) {
+        let [vss, vdd, _, _] = self.connected_nets_i;
+        if self.output_high() {
+            (vdd, self.value.source_resistance)
+        } else {
+            (vss, self.value.sink_resistance)
+        }
+    }
+}
+
+impl<S: Scalar> ComponentState<S> for GateDriverComponentState<S> {
+    fn set_nets(&mut self, connected_nets_i: &[usize]) {
+        assert_eq!(
+            connected_nets_i.len(),
+            4,
+            "can only create a gate driver with exactly four connected nets."
+        );
+        self.connected_nets_i.copy_from_slice(connected_nets_i);
+    }
+    fn nets(&self) -> &[NetId] {
+        &self.connected_nets_i
+    }
+
+    fn impart_voltage_to_nets(&self, nets: &[NetState<S>], step: S, stamps: &mut NetStamps<S>) {
+        // as a `LinearComponentValue::Resistive` from the rail to `out`.
+        let (rail, r) = self.pull();
+        let out = self.connected_nets_i[3];
+        let v_prev = nets[out].voltage - nets[rail].voltage;
+        let v_diff = (-self.i[0] * r - v_prev) * S::from_f64(0.5) * step;
+        stamps.voltage(rail, nets[rail].voltage - v_diff);
+        stamps.voltage(out, nets[out].voltage + v_diff);
+    }
+    fn impart_currents_to_nets(&self, stamps: &mut NetStamps<S>) {
+        let (rail, _) = self.pull();
+        for i in 0..2 {
+            stamps.current(rail, i, -self.i[i]);
+            stamps.current(self.connected_nets_i[3], i, self.i[i]);
+        }
+    }
+
+    fn purturb_from_nets(
+        &mut self,
+        nets: &[NetState<S>],
+        limiter: &mut Limiter<S>,
+    ) -> HasConverged {
+        let [vss, vdd, input, out] = self.connected_nets_i;
+        self.sensed = [
+            nets[vdd].voltage - nets[vss].voltage,
+            nets[input].voltage - nets[vss].voltage,
+        ];
+        let (rail, r) = self.pull();
+        let v_target = nets[out].voltage - nets[rail].voltage;
+        let i_target = [0, 1]
+            .map(|i| self.i[i] + S::from_f64(0.5) * (nets[rail].current[i] - nets[out].current[i]));
+        let mut i_next = [
+            lerp(-v_target / r, i_target[0], S::from_f64(0.5)),
+            i_target[1],
+        ];
+        i_next[0] = limiter.current(i_next[0]);
+        let converged = converged(self.i[0], i_next[0]) && converged(self.i[1], i_next[1]);
+        self.last_residual = largest_change(&self.i, &i_next);
+        self.i = i_next;
+        converged
+    }
+    /// Act on the input and supply as sensed at the solution at `t`: start an input edge on its
+    /// way through the propagation delay, pass on those that got through by `t + dt`, and set
+    /// or release the lockout.
+    fn tick(&mut self, t: S, dt: S) {
+        self.time = t + dt;
+        self.i[0] += self.i[1] * dt;
+        let [supply, input] = self.sensed;
+        let value = &self.value;
+        if self.locked_out {
+            self.locked_out = supply < value.uvlo_threshold + value.uvlo_hysteresis;
+        } else {
+            self.locked_out = supply < value.uvlo_threshold;
+        }
+        let input_high = input > value.input_threshold;
+        if input_high != self.input_high {
+            self.input_high = input_high;
+            self.edges
+                .push_back((t + value.propagation_delay, input_high));
+        }
+        let tolerance = dt * S::from_f64(0.5);
+        while let Some(&(at, level)) = self.edges.front() {
+            if at > self.time + tolerance {
+                break;
+            }
+            self.delayed_high = level;
+            self.edges.pop_front();
+        }
+    }
+    fn terminal_charge(&self, dt: S, charge: &mut [S]) {
+        let rail = if self.output_high() { 1 } else { 0 };
+        charge[rail] -= self.i[0] * dt;
+        charge[3] += self.i[0] * dt;
+    }
+    fn state(&self) -> &[S] {
+        &self.i
+    }
+    fn solved_state_mut(&mut self) -> &mut [S] {
+        &mut self.i[1..]
+    }
+    fn last_residual(&self) -> S {
+        self.last_residual
+    }
+    /// Up to the next edge reaching the output.
+    fn max_dt_hint(&self, t: S) -> Option<S> {
+        self.edges.front().map(|&(at, _)| at - t)
+    }
+
+    fn power_kind(&self) -> PowerKind {
+        PowerKind::Dissipative
+    }
+    /// The loss in the drive resistance; the supply current is delivered by whatever feeds
+    /// `vdd`.
+    fn instantaneous_power(&self, nets: &[NetState<S>]) -> S {
+        let (rail, _) = self.pull();
+        (nets[rail].voltage - nets[self.connected_nets_i[3]].voltage) * self.i[0]
+    }
+    fn stamp_ac(&self, _nets: &[NetState<S>], system: &mut AcSystem) {
+        let (rail, r) = self.pull();
+        system.add_admittance(
+            rail,
+            self.connected_nets_i[3],
+            Cf::new(1.0 / r.to_f64(), 0.0),
+        );
+    }
+}
+
+// ---------------------- COMPARATORS ----------------------
+
+/// A comparator with a voltage output: `V(out+) - V(out-)` is `output_high` behind
+/// `output_resistance` while the input `V(in+) - V(in-)` is high and `output_low` while it is
+/// low, switching `propagation_delay` after the input. The input goes high above
+/// `hysteresis / 2` and low below `-hysteresis / 2`, decided on the solution of each tick (never
+/// within a solve, so the output can't chatter inside one); it is sensed without drawing current.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComparatorComponentValue<S: Scalar = f> {
+    pub output_high: S,
+    pub output_low: S,
+    /// Width of the band about zero the input has to cross to switch.
+    pub hysteresis: S,
+    pub propagation_delay: S,
+    pub output_resistance: S,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComparatorComponentState<S: Scalar = f> {
+    /// `[out-, in-, in+, out+]`, the output first and last as for two-terminal components.
+    connected_nets_i: [usize; 4],
+    pub value: ComparatorComponentValue<S>,
+    /// `= [I, d/dt I]`, where `I` is the output current, into `out-` and out of `out+`.
+    pub i: [S; 2],
+    /// `V(in+) - V(in-)` at the last `purturb_from_nets`, the solution of the last
+    /// `solve_state`.
+    sensed: S,
+    /// Whether the input was last decided high.
+    input_high: bool,
+    /// Input edges on their way through the propagation delay, as for a gate driver.
+    edges: VecDeque<(S, bool)>,
+    /// The level of the input as delayed to the output.
+    delayed_high: bool,
+    /// Simulation time as of the last `tick`.
+    time: S,
+    /// Largest change of `i` in the last `purturb_from_nets`.
+    last_residual: S,
+}
+
+impl<S: Scalar> ComponentValue<S> for ComparatorComponentValue<S> {
+    type State = ComparatorComponentState<S>;
+    fn n_terminals(&self) -> usize {
+        4
+    }
+    fn terminal_names(&self) -> &'static [&'static str] {
+        &["out-", "in-", "in+", "out+"]
+    }
+    fn create(&self, connected_nets_i: &[usize]) -> Self::State {
+        ComparatorComponentState::new(*self, connected_nets_i)
+    }
+}
+
+impl<S: Scalar> ComparatorComponentState<S> {
+    fn new(value: ComparatorComponentValue<S>, connected_nets_i: &[usize]) -> Self {
+        let mut this = Self {
+            connected_nets_i: [0; 4],
+            value,
+            i: [S::from(0); 2],
+            sensed: S::from(0),
+            input_high: false,
+            edges: VecDeque::new(),
+            delayed_high: false,
+            time: S::from(0),
+            last_residual: S::from(0),
+        };
+        this.set_nets(connected_nets_i);
+        this
+    }
+    /// Whether the output is at `output_high` rather than `output_low`.
+    pub fn output_high(&self) -> bool {
+        self.delayed_high
+    }
+    /// The open-circuit output voltage `V(out+) - V(out-)`.
+    pub fn output_level(&self) -> S {
+        if self.delayed_high {
+            self.value.output_high
+        } else {
+            self.value.output_low
+        }
+    }
+}
+
+impl<S: Scalar> ComponentState<S> for ComparatorComponentState<S> {
+    fn set_nets(&mut self, connected_nets_i: &[usize]) {
+        assert_eq!(
+            connected_nets_i.len(),
+            4,
+            "can only create a comparator with exactly four connected nets."
+        );
+        self.connected_nets_i.copy_from_slice(connected_nets_i);
+    }
+    fn nets(&self) -> &[NetId] {
+        &self.connected_nets_i
+    }
+
+    fn impart_voltage_to_nets(&self, nets: &[NetState<S>], step: S, stamps: &mut NetStamps<S>) {
+        // as a source of `output_level` in series with `output_resistance`.
+        let [out_minus, _, _, out_plus] = self.connected_nets_i;
+        let v_prev = nets[out_plus].voltage - nets[out_minus].voltage;
+        let v_target = self.output_level() - self.i[0] * self.value.output_resistance;
+        let v_diff = (v_target - v_prev) * S::from_f64(0.5) * step;
+        stamps.voltage(out_minus, nets[out_minus].voltage - v_diff);
+        stamps.voltage(out_plus, nets[out_plus].voltage + v_diff);
+    }
+    fn impart_currents_to_nets(&self, stamps: &mut NetStamps<S>) {
+        let [out_minus, _, _, out_plus] = self.connected_nets_i;
+        for i in 0..2 {
+            stamps.current(out_minus, i, -self.i[i]);
+            stamps.current(out_plus, i, self.i[i]);
+        }
+    }
+
+    fn purturb_from_nets(
+        &mut self,
+        nets: &[NetState<S>],
+        limiter: &mut Limiter<S>,
+    ) -> HasConverged {
+        let [out_minus, in_minus, in_plus, out_plus] = self.connected_nets_i;
+        self.sensed = nets[in_plus].voltage - nets[in_minus].voltage;
+        let v = nets[out_plus].voltage - nets[out_minus].voltage;
+        let i_target = [0, 1].map(|i| {
+            self.i[i] + S::from_f64(0.5) * (nets[out_minus].current[i] - nets[out_plus].current[i])
+        });
+        let mut i_next = [
+            lerp(
+                (self.output_level() - v) / self.value.output_resistance,
+                i_target[0],
+                S::from_f64(0.5),
+            ),
+            i_target[1],
+        ];
+        i_next[0] = limiter.current(i_next[0]);
+        let converged = converged(self.i[0], i_next[0]) && converged(self.i[1], i_next[1]);
+        self.last_residual = largest_change(&self.i, &i_next);
+        self.i = i_next;
+        converged
+    }
+    /// Decide the input as sensed at the solution at `t` against the hysteresis band, start an
+    /// edge on its way through the propagation delay, and pass on those that got through by
+    /// `t + dt`.
+    fn tick(&mut self, t: S, dt: S) {
+        self.time = t + dt;
+        self.i[0] += self.i[1] * dt;
+        let half_band = self.value.hysteresis * S::from_f64(0.5);
+        let input_high = if self.input_high {
+            self.sensed >= -half_band
+        } else {
+            self.sensed > half_band
+        };
+        if input_high != self.input_high {
+            self.input_high = input_high;
+            self.edges
+                .push_back((t + self.value.propagation_delay, input_high));
+        }
+        let tolerance = dt * S::from_f64(0.5);
+        while let Some(&(at, level)) = self.edges.front() {
+            if at > self.time + tolerance {
+                break;
+            }
+            self.delayed_high = level;
+            self.edges.pop_front();
+        }
+    }
+    fn terminal_charge(&self, dt: S, charge: &mut [S]) {
+        charge[0] -= self.i[0] * dt;
+        charge[3] += self.i[0] * dt;
+    }
+    fn state(&self) -> &[S] {
+        &self.i
+    }
+    fn solved_state_mut(&mut self) -> &mut [S] {
+        &mut self.i[1..]
+    }
+    fn last_residual(&self) -> S {
+        self.last_residual
+    }
+    /// Up to the next edge reaching the output.
+    fn max_dt_hint(&self, t: S) -> Option<S> {
+        self.edges.front().map(|&(at, _)| at - t)
+    }
+
+    fn power_kind(&self) -> PowerKind {
+        PowerKind::Source
+    }
+    /// Absorbed at the output, `(V(out-) - V(out+)) I`: negative while it drives a load.
+    fn instantaneous_power(&self, nets: &[NetState<S>]) -> S {
+        let [out_minus, _, _, out_plus] = self.connected_nets_i;
+        -(nets[out_plus].voltage - nets[out_minus].voltage) * self.i[0]
+    }
+    fn stamp_ac(&self, _nets: &[NetState<S>], system: &mut AcSystem) {
+        let [out_minus, _, _, out_plus] = self.connected_nets_i;
+        system.add_admittance(
+            out_minus,
+            out_plus,
+            Cf::new(1.0 / self.value.output_resistance.to_f64(), 0.0),
+        );
+    }
+}
+
+// ---------------------- CONVERTERS ----------------------
+
+/// An averaged (switching-cycle mean) DC-DC converter: `V(out+) - V(out-)` regulated to
+/// `output_voltage` by whatever current up to `current_limit` it takes, across an
+/// `output_capacitance` that carries the load beyond the limit, and the output power (the
+/// charging of the capacitance included) drawn from `V(in+) - V(in-)` at `efficiency`, as a
+/// current into `in+` and out of `in-`. The setpoint ramps up from 0 V linearly over
+/// `soft_start` from simulation time 0.
+///
+/// Regulation and the current limit are decided on the solution of each tick, as the output
+/// capacitance integrates. The converter doesn't sink current, and stops converting while the
+/// input is at or below 0 V.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConverterComponentValue<S: Scalar = f> {
+    pub output_voltage: S,
+    /// Output power per input power.
+    pub efficiency: S,
+    pub current_limit: S,
+    pub output_capacitance: S,
+    pub soft_start: S,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConverterComponentState<S: Scalar = f> {
+    /// `[out-, in-, in+, out+]`, the output first and last as for two-terminal components.
+    connected_nets_i: [usize; 4],
+    pub value: ConverterComponentValue<S>,
+    /// `= [I, d/dt I]`, where `I` is the output current, into `out-` and out of `out+`.
+    pub i: [S; 2],
+    /// The input current, into `in+` and out of `in-`.
+    pub i_in: S,
+    /// The voltage of the output capacitance.
+    v_out: S,
+    /// Current into the output capacitance over the last tick.
+    i_charge: S,
+    /// `V(in+) - V(in-)` at the last `purturb_from_nets`, the solution of the last
+    /// `solve_state`.
+    sensed: S,
+    /// Whether the last tick held the output current at `current_limit`.
+    limited: bool,
+    /// Simulation time as of the last `tick`.
+    time: S,
+    /// Largest change of `i` and `i_in` in the last `purturb_from_nets`.
+    last_residual: S,
+}
+
+impl<S: Scalar> ComponentValue<S> for ConverterComponentValue<S> {
+    type State = ConverterComponentState<S>;
+    fn n_terminals(&self) -> usize {
+        4
+    }
+    fn terminal_names(&self) -> &'static [&'static str] {
+        &["out-", "in-", "in+", "out+"]
+    }
+    fn create(&self, connected_nets_i: &[usize]) -> Self::State {
+        ConverterComponentState::new(*self, connected_nets_i)
+    }
+}
+
+impl<S: Scalar> ConverterComponentValue<S> {
+    /// The setpoint at simulation time `t`, along the soft-start ramp.
+    pub fn setpoint(&self, t: S) -> S {
+        if t >= self.soft_start {
+            self.output_voltage
+        } else {
+            self.output_voltage * t / self.soft_start
+        }
+    }
+}
+
+impl<S: Scalar> ConverterComponentState<S> {
+    fn new(value: ConverterComponentValue<S>, connected_nets_i: &[usize]) -> Self {
+        let mut this = Self {
+            connected_nets_i: [0; 4],
+            value,
+            i: [S::from(0); 2],
+            i_in: S::from(0),
+            v_out: S::from(0),
+            i_charge: S::from(0),
+            sensed: S::from(0),
+            limited: false,
+            time: S::from(0),
+            last_residual: S::from(0),
+        };
+        this.set_nets(connected_nets_i);
+        this
+    }
+    /// The voltage of the output capacitance, `V(out+) - V(out-)` once solved.
+    pub fn output_level(&self) -> S {
+        self.v_out
+    }
+    /// Whether the last tick held the output current at `current_limit`, the output falling
+    /// short of the setpoint.
+    pub fn current_limited(&self) -> bool {
+        self.limited
+    }
+    /// The current the converter delivered over the last tick, to the output and its
+    /// capacitance.
+    pub fn converted_current(&self) -> S {
+        self.i[0] + self.i_charge
+    }
+}
+
+impl<S: Scalar> ComponentState<S> for ConverterComponentState<S> {
+    fn set_nets(&mut self, connected_nets_i: &[usize]) {
+        assert_eq!(
+            connected_nets_i.len(),
+            4,
+            "can only create a converter with exactly four connected nets."
+        );
+        self.connected_nets_i.copy_from_slice(connected_nets_i);
+    }
+    fn nets(&self) -> &[NetId] {
+        &self.connected_nets_i
+    }
+
+    fn impart_voltage_to_nets(&self, nets: &[NetState<S>], step: S, stamps: &mut NetStamps<S>) {
+        // the output as a source of the capacitance voltage; the input is only a current.
+        let [out_minus, _, _, out_plus] = self.connected_nets_i;
+        let v_prev = nets[out_plus].voltage - nets[out_minus].voltage;
+        let v_diff = (self.v_out - v_prev) * S::from_f64(0.5) * step;
+        stamps.voltage(out_minus, nets[out_minus].voltage - v_diff);
+        stamps.voltage(out_plus, nets[out_plus].voltage + v_diff);
+    }
+    fn impart_currents_to_nets(&self, stamps: &mut NetStamps<S>) {
+        let [out_minus, in_minus, in_plus, out_plus] = self.connected_nets_i;
+        for i in 0..2 {
+            stamps.current(out_minus, i, -self.i[i]);
+            stamps.current(out_plus, i, self.i[i]);
+        }
+        stamps.current(in_plus, 0, -self.i_in);
+        stamps.current(in_minus, 0, self.i_in);
+    }
+
+    fn purturb_from_nets(
+        &mut self,
+        nets: &[NetState<S>],
+        limiter: &mut Limiter<S>,
+    ) -> HasConverged {
+        let [out_minus, in_minus, in_plus, out_plus] = self.connected_nets_i;
+        self.sensed = nets[in_plus].voltage - nets[in_minus].voltage;
+        let mut i_next = [0, 1].map(|i| {
+            self.i[i] + S::from_f64(0.5) * (nets[out_minus].current[i] - nets[out_plus].current[i])
+        });
+        i_next[0] = limiter.current(i_next[0]);
+        // the power balance `V_in I_in = V_out I_conv / efficiency`.
+        let i_in_next = if self.sensed > S::from(0) {
+            self.v_out * (i_next[0] + self.i_charge) / (self.value.efficiency * self.sensed)
+        } else {
+            S::from(0)
+        };
+        let converged = converged(self.i[0], i_next[0])
+            && converged(self.i[1], i_next[1])
+            && converged(self.i_in, i_in_next);
+        self.last_residual = largest_change(&self.i, &i_next).max((i_in_next - self.i_in).abs());
+        self.i = i_next;
+        self.i_in = i_in_next;
+        converged
+    }
+    /// Deliver the current that brings the output capacitance to the setpoint at `t + dt`,
+    /// within `0..=current_limit`, the load taking the rest from the capacitance.
+    fn tick(&mut self, t: S, dt: S) {
+        self.time = t + dt;
+        self.i[0] += self.i[1] * dt;
+        let capacitance = self.value.output_capacitance;
+        let needed = self.i[0] + capacitance * (self.value.setpoint(self.time) - self.v_out) / dt;
+        let delivered = if self.sensed > S::from(0) {
+            needed.max(S::from(0)).min(self.value.current_limit)
+        } else {
+            S::from(0)
+        };
+        self.limited = needed > self.value.current_limit;
+        self.i_charge = delivered - self.i[0];
+        self.v_out += self.i_charge * dt / capacitance;
+    }
+    fn terminal_charge(&self, dt: S, charge: &mut [S]) {
+        charge[0] -= self.i[0] * dt;
+        charge[1] += self.i_in * dt;
+        charge[2] -= self.i_in * dt;
+        charge[3] += self.i[0] * dt;
+    }
+    fn state(&self) -> &[S] {
+        &self.i
+    }
+    fn solved_state_mut(&mut self) -> &mut [S] {
+        &mut self.i[1..]
+    }
+    fn last_residual(&self) -> S {
+        self.last_residual
+    }
+    /// Up to the end of the soft-start ramp.
+    fn max_dt_hint(&self, t: S) -> Option<S> {
+        (t < self.value.soft_start).then(|| self.value.soft_start - t)
+    }
+
+    fn power_kind(&self) -> PowerKind {
+        PowerKind::Dissipative
+    }
+    /// `V_in I_in - V_out I`: the conversion losses, and the charging of the output capacitance.
+    fn instantaneous_power(&self, nets: &[NetState<S>]) -> S {
+        let [out_minus, in_minus, in_plus, out_plus] = self.connected_nets_i;
+        (nets[in_plus].voltage - nets[in_minus].voltage) * self.i_in
+            - (nets[out_plus].voltage - nets[out_minus].voltage) * self.i[0]
+    }
+    /// The conversion losses, `(1 - efficiency) V_in I_in`.
+    fn dissipated_power(&self, nets: &[NetState<S>]) -> S {
+        let [_, in_minus, in_plus, _] = self.connected_nets_i;
+        (S::from(1) - self.value.efficiency)
+            * (nets[in_plus].voltage - nets[in_minus].voltage)
+            * self.i_in
+    }
+    fn stored_energy(&self) -> S {
+        S::from_f64(0.5) * self.value.output_capacitance * self.v_out * self.v_out
+    }
+    /// The output stiff while regulating and the capacitance while current limited, the input a
+    /// constant-power load of incremental conductance `-I_in / V_in`.
+    fn stamp_ac(&self, _nets: &[NetState<S>], system: &mut AcSystem) {
+        let [out_minus, in_minus, in_plus, out_plus] = self.connected_nets_i;
+        if self.limited {
+            system.add_admittance(
+                out_minus,
+                out_plus,
+                Cf::new(0.0, system.omega * self.value.output_capacitance.to_f64()),
+            );
+        } else {
+            system.add_voltage_branch([out_minus, out_plus], 0.into());
+        }
+        if self.sensed > S::from(0) {
+            system.add_admittance(
+                in_minus,
+                in_plus,
+                Cf::new(-(self.i_in / self.sensed).to_f64(), 0.0),
+            );
+        }
     }
 }
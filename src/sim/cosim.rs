@@ -0,0 +1,232 @@
+use super::{
+    components::LinearComponentValue,
+    error::SimError,
+    f,
+    probe::Probe,
+    protection::{ProtectionBlock, ProtectionId},
+    CircuitState, ComponentId, ComponentMut,
+};
+
+pub type ProbeId = usize;
+pub type ControlId = usize;
+
+/// An input of the circuit that an external controller writes through `CoSim::write_control`.
+#[derive(Debug, Clone, Copy)]
+pub enum Control {
+    /// The voltage of a source (or the offset of a noise source).
+    Source(ComponentId),
+    /// A switch, closed for values above 0.5. It changes state only between steps, and with
+    /// `CoSim::set_min_dwell` at most once per dwell time.
+    Switch(ComponentId),
+    /// A duty cycle in `[0, 1]` chopping a source between `low` and `high` volts, or a switch
+    /// between open and closed, at `period`; each period starts high (`inverted` starts it low,
+    /// for the complementary side of a half-bridge).
+    Pwm {
+        component: ComponentId,
+        period: f,
+        low: f,
+        high: f,
+        inverted: bool,
+    },
+}
+
+/// Fixed-step stepping of a circuit by an external controller (such as firmware) that reads
+/// probes and writes controls between steps.
+///
+/// Controls written before `step_until` take effect from its first step, and hold until written
+/// again; probes read after it see the state at its end.
+#[derive(Debug, Clone)]
+pub struct CoSim {
+    circuit: CircuitState,
+    dt: f,
+    steps: usize,
+    probes: Vec<Probe>,
+    controls: Vec<(Control, f)>,
+    /// Dwell of each control, by `ControlId`.
+    dwell: Vec<Dwell>,
+    /// The state each control last set its component to and the time of its last edge, by
+    /// `ControlId`, for the blanking of `protections`.
+    edges: Vec<Option<(bool, f)>>,
+    protections: Vec<ProtectionBlock>,
+    unconverged: usize,
+}
+/// The minimum dwell of a switch control and the state it is held in.
+#[derive(Debug, Clone, Copy, Default)]
+struct Dwell {
+    min: f,
+    /// The state the switch was last set to and the time it changed to it.
+    last: Option<(bool, f)>,
+}
+impl CoSim {
+    pub fn new(circuit: CircuitState, dt: f) -> Self {
+        Self {
+            circuit,
+            dt,
+            steps: 0,
+            probes: Vec::new(),
+            controls: Vec::new(),
+            dwell: Vec::new(),
+            edges: Vec::new(),
+            protections: Vec::new(),
+            unconverged: 0,
+        }
+    }
+
+    pub fn add_probe(&mut self, probe: Probe) -> ProbeId {
+        self.probes.push(probe);
+        self.probes.len() - 1
+    }
+    /// Add a control starting at `value`.
+    pub fn add_control(&mut self, control: Control, value: f) -> ControlId {
+        self.controls.push((control, value));
+        self.dwell.push(Dwell::default());
+        self.edges.push(None);
+        self.controls.len() - 1
+    }
+    /// Hold the switch of a `Control::Switch` in each state for at least `min_dwell` seconds: a
+    /// value written sooner after it last toggled takes effect from the first step after the
+    /// dwell is over, if it still asks for the other state then. A protection forcing the
+    /// switch open does so right away, and starts a dwell open. Controls that don't switch
+    /// ignore it.
+    pub fn set_min_dwell(&mut self, control: ControlId, min_dwell: f) {
+        self.dwell[control].min = min_dwell;
+    }
+
+    /// Add a protection block, checked after every step from the next one on.
+    pub fn add_protection(&mut self, protection: ProtectionBlock) -> ProtectionId {
+        self.protections.push(protection);
+        self.protections.len() - 1
+    }
+    pub fn protection(&self, protection: ProtectionId) -> &ProtectionBlock {
+        &self.protections[protection]
+    }
+    /// For `ProtectionBlock::reset`.
+    pub fn protection_mut(&mut self, protection: ProtectionId) -> &mut ProtectionBlock {
+        &mut self.protections[protection]
+    }
+
+    pub fn read_probe(&self, probe: ProbeId) -> f {
+        self.probes[probe].sample(&self.circuit)
+    }
+    pub fn write_control(&mut self, control: ControlId, value: f) {
+        self.controls[control].1 = value;
+    }
+
+    /// Tick the circuit until the simulation time reaches `time` (rounded to whole steps).
+    pub fn step_until(&mut self, time: f) -> Result<(), SimError> {
+        while self.time() + 0.5 * self.dt < time {
+            self.apply_controls();
+            if !self.circuit.try_tick(self.dt)? {
+                self.unconverged += 1;
+            }
+            self.steps += 1;
+            self.check_protections();
+        }
+        Ok(())
+    }
+    /// Check every protection block on the state at the end of a step.
+    fn check_protections(&mut self) {
+        let time = self.time();
+        for protection in &mut self.protections {
+            let last_edge = protection
+                .gates
+                .iter()
+                .filter_map(|&gate| self.edges[gate].map(|(_, edge)| edge))
+                .reduce(f::max);
+            let sample = protection.probe.sample(&self.circuit);
+            protection.check(sample, time, last_edge);
+        }
+    }
+    /// Set every control's component for the step starting at the present time.
+    fn apply_controls(&mut self) {
+        let time = self.time();
+        for (control_i, (&(control, value), dwell)) in
+            self.controls.iter().zip(&mut self.dwell).enumerate()
+        {
+            let forced_low = self.protections.iter().any(|protection| {
+                protection.gates.contains(&control_i) && protection.forces_low(time, self.dt)
+            });
+            let (component, on, volts) = match control {
+                Control::Source(component) if forced_low => (component, false, 0.0),
+                Control::Source(component) => (component, value > 0.5, value),
+                // the protection overrides the dwell, as hardware would, and the switch then
+                // dwells open from when it forced it.
+                Control::Switch(component) if forced_low => {
+                    if !matches!(dwell.last, Some((false, _))) {
+                        dwell.last = Some((false, time));
+                    }
+                    (component, false, value)
+                }
+                Control::Switch(component) => {
+                    let requested = value > 0.5;
+                    let closed = match dwell.last {
+                        Some((closed, since))
+                            if closed == requested || time - since < dwell.min =>
+                        {
+                            closed
+                        }
+                        _ => {
+                            dwell.last = Some((requested, time));
+                            requested
+                        }
+                    };
+                    (component, closed, value)
+                }
+                Control::Pwm {
+                    component,
+                    period,
+                    low,
+                    high,
+                    inverted,
+                } => {
+                    // the phase mid-step, so a duty cycle rounds to whole steps.
+                    let phase = ((time + 0.5 * self.dt) / period).fract();
+                    let on = (phase < value) != inverted && !forced_low;
+                    (component, on, if on { high } else { low })
+                }
+            };
+            let edge = &mut self.edges[control_i];
+            match *edge {
+                Some((last_on, _)) if last_on == on => {}
+                // the first step is an edge only if it turns the component on.
+                None if !on => *edge = Some((false, f::NEG_INFINITY)),
+                _ => *edge = Some((on, time)),
+            }
+            set_input(&mut self.circuit, component, on, volts);
+        }
+    }
+
+    /// Simulation time, in seconds.
+    pub fn time(&self) -> f {
+        self.steps as f * self.dt
+    }
+    pub fn dt(&self) -> f {
+        self.dt
+    }
+    /// Steps whose `solve_state` did not converge so far.
+    pub fn unconverged(&self) -> usize {
+        self.unconverged
+    }
+    pub fn circuit(&self) -> &CircuitState {
+        &self.circuit
+    }
+    pub fn circuit_mut(&mut self) -> &mut CircuitState {
+        &mut self.circuit
+    }
+}
+
+/// Close or open `component` if it is a switch, or set it to `volts` if it is a source.
+fn set_input(circuit: &mut CircuitState, component: ComponentId, closed: bool, volts: f) {
+    match circuit.component_mut(component) {
+        ComponentMut::Linear(v) => match &mut v.value {
+            LinearComponentValue::Switch { closed: c, .. } => *c = closed,
+            LinearComponentValue::Source(e) => *e = volts,
+            _ => {}
+        },
+        ComponentMut::NoiseSource(v) => v.value.offset = volts,
+        ComponentMut::MOSFET(_)
+        | ComponentMut::GateDriver(_)
+        | ComponentMut::Comparator(_)
+        | ComponentMut::Converter(_) => {}
+    }
+}
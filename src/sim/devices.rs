@@ -0,0 +1,339 @@
+//! A library of named parts, so circuits use a device's parameters by its name instead of
+//! retyping them: `CircuitBuilder::mosfet_part` and MOSFETs of netlists whose model isn't a
+//! `.model` of the netlist take them from a `DeviceLibrary`.
+//!
+//! Libraries are text, a section per part with a `key = value` line per parameter (values by
+//! `units::parse_si`, `#` starts a comment):
+//!
+//! ```text
+//! [generic_nfet_30v]
+//! kind = nmos
+//! beta = 40
+//! threshold_voltage = 2
+//! body_diode_saturation_current = 1p
+//! body_diode_ideality_factor = 1.2
+//! # optional: saturation_knee (8) and multiplicity (1)
+//! ```
+//!
+//! `kind` is `nmos` or `pmos`, with `threshold_voltage` the magnitude for either. `BUILTIN` is
+//! the library compiled in; `DeviceLibrary::load` reads others to `extend` it with.
+
+use std::{collections::BTreeMap, error::Error, fmt, fs, io, path::Path, sync::OnceLock};
+
+use super::{
+    components::{MOSFETComponentValue, MOSFETDopingType},
+    error::SimError,
+    f,
+    units::parse_si,
+    Scalar,
+};
+
+/// The library `DeviceLibrary::builtin` reads: rough stand-ins for common parts at 25 °C, good
+/// for the order of magnitude of conduction losses rather than for datasheet accuracy.
+pub const BUILTIN: &str = "\
+# logic-level 30 V N-channel power MOSFET, ~8 mohm at 4.5 V gate drive
+[generic_nfet_30v]
+kind = nmos
+beta = 46
+threshold_voltage = 1.8
+body_diode_saturation_current = 1p
+body_diode_ideality_factor = 1.2
+
+# 60 V N-channel power MOSFET, ~12 mohm at 10 V gate drive
+[generic_nfet_60v]
+kind = nmos
+beta = 12
+threshold_voltage = 3
+body_diode_saturation_current = 1p
+body_diode_ideality_factor = 1.2
+
+# 30 V P-channel power MOSFET, ~20 mohm at 10 V gate drive
+[generic_pfet_30v]
+kind = pmos
+beta = 6
+threshold_voltage = 2
+body_diode_saturation_current = 1p
+body_diode_ideality_factor = 1.2
+
+# after the IRLB8721: 30 V, ~9 mohm at 4.5 V gate drive
+[IRLB8721-ish]
+kind = nmos
+beta = 40
+threshold_voltage = 1.8
+body_diode_saturation_current = 10p
+body_diode_ideality_factor = 1.1
+
+# after the 2N7000: 60 V small-signal, ~2 ohm at 10 V gate drive
+[2N7000-ish]
+kind = nmos
+beta = 60m
+threshold_voltage = 2.1
+body_diode_saturation_current = 10f
+body_diode_ideality_factor = 1
+";
+
+/// The parameters of a part.
+#[derive(Debug, Clone, Copy)]
+pub enum Device {
+    MOSFET(MOSFETComponentValue),
+}
+
+#[derive(Debug)]
+pub enum DeviceError {
+    Io(io::Error),
+    /// A line that isn't a section header, a parameter or a comment, at this (1-based) line.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// The part doesn't give a parameter it needs.
+    Missing {
+        part: String,
+        field: &'static str,
+    },
+    NonPositive {
+        part: String,
+        field: &'static str,
+        value: f,
+    },
+}
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Syntax { line, message } => write!(f, "device library line {line}: {message}"),
+            Self::Missing { part, field } => write!(f, "part {part:?} has no {field}"),
+            Self::NonPositive { part, field, value } => {
+                write!(
+                    f,
+                    "part {part:?} has {field} {value:e}, expected a positive value"
+                )
+            }
+        }
+    }
+}
+impl Error for DeviceError {}
+impl From<io::Error> for DeviceError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Parts by name; names are looked up ignoring ASCII case.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceLibrary {
+    /// `(name as given, device)` by lowercase name.
+    parts: BTreeMap<String, (String, Device)>,
+}
+impl DeviceLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// `BUILTIN`, read once.
+    pub fn builtin() -> &'static Self {
+        static LIBRARY: OnceLock<DeviceLibrary> = OnceLock::new();
+        LIBRARY.get_or_init(|| Self::parse(BUILTIN).expect("the builtin device library is valid"))
+    }
+
+    /// Read a library in the format of the module documentation.
+    pub fn parse(text: &str) -> Result<Self, DeviceError> {
+        let mut library = Self::new();
+        let mut part: Option<PartFields> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let syntax = |message: String| DeviceError::Syntax {
+                line: line_no,
+                message,
+            };
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if let Some(part) = part.take() {
+                    library.insert_new(part)?;
+                }
+                part = Some(PartFields {
+                    name: name.trim().to_string(),
+                    header: line_no,
+                    kind: None,
+                    numbers: BTreeMap::new(),
+                });
+                continue;
+            }
+            let Some(part) = &mut part else {
+                return Err(syntax(format!("{line:?} before the first [part]")));
+            };
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(syntax(format!("expected `key = value`, got {line:?}")));
+            };
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+            let given_twice = if key == "kind" {
+                let kind = match value.to_ascii_lowercase().as_str() {
+                    "nmos" => MOSFETDopingType::NChannel,
+                    "pmos" => MOSFETDopingType::PChannel,
+                    _ => return Err(syntax(format!("unknown kind {value:?}"))),
+                };
+                part.kind.replace(kind).is_some()
+            } else {
+                let Some(&field) = NUMBER_FIELDS.iter().find(|&&field| field == key) else {
+                    return Err(syntax(format!("unknown parameter {key:?}")));
+                };
+                let value =
+                    parse_si(value).ok_or_else(|| syntax(format!("malformed number {value:?}")))?;
+                part.numbers.insert(field, value).is_some()
+            };
+            if given_twice {
+                return Err(syntax(format!("{key} given twice")));
+            }
+        }
+        if let Some(part) = part {
+            library.insert_new(part)?;
+        }
+        Ok(library)
+    }
+    /// `parse` the file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DeviceError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn insert_new(&mut self, part: PartFields) -> Result<(), DeviceError> {
+        if self.part(&part.name).is_some() {
+            return Err(DeviceError::Syntax {
+                line: part.header,
+                message: format!("part {:?} defined twice", part.name),
+            });
+        }
+        self.insert(&part.name, part.device()?);
+        Ok(())
+    }
+
+    /// Add the part `name`, replacing one of that name.
+    pub fn insert(&mut self, name: &str, device: Device) {
+        self.parts
+            .insert(name.to_ascii_lowercase(), (name.to_string(), device));
+    }
+    /// Add the parts of `other`, replacing those of the same names: the builtin library
+    /// extended by a user's file takes the user's parts where they disagree.
+    pub fn extend(&mut self, other: Self) {
+        self.parts.extend(other.parts);
+    }
+    /// The part names, in lowercase order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.parts.values().map(|(name, _)| name.as_str())
+    }
+    pub fn part(&self, name: &str) -> Option<&Device> {
+        self.parts
+            .get(&name.to_ascii_lowercase())
+            .map(|(_, device)| device)
+    }
+
+    /// The MOSFET `name`.
+    ///
+    /// Errors with the names closest to `name` if the library has no such part.
+    pub fn mosfet<S: Scalar>(&self, name: &str) -> Result<MOSFETComponentValue<S>, SimError> {
+        let Some(&Device::MOSFET(value)) = self.part(name) else {
+            return Err(SimError::UnknownPart {
+                name: name.to_string(),
+                close: self.close_matches(name),
+            });
+        };
+        Ok(MOSFETComponentValue {
+            ty: value.ty,
+            beta: S::from_f64(value.beta),
+            threshold_voltage: S::from_f64(value.threshold_voltage),
+            body_diode_saturation_current: S::from_f64(value.body_diode_saturation_current),
+            body_diode_ideality_facotor: S::from_f64(value.body_diode_ideality_facotor),
+            saturation_knee: S::from_f64(value.saturation_knee),
+            multiplicity: S::from_f64(value.multiplicity),
+            avalanche: None,
+        })
+    }
+
+    /// Up to three part names within a few edits of `name` or containing it, closest first.
+    pub fn close_matches(&self, name: &str) -> Vec<String> {
+        let name = name.to_ascii_lowercase();
+        let mut close = self
+            .parts
+            .iter()
+            .filter_map(|(key, (part, _))| {
+                let distance = edit_distance(&name, key);
+                let close = distance <= (name.len() / 3).max(2)
+                    || (!name.is_empty() && key.contains(&name));
+                close.then(|| (distance, part.clone()))
+            })
+            .collect::<Vec<_>>();
+        close.sort();
+        close.into_iter().take(3).map(|(_, part)| part).collect()
+    }
+}
+
+/// Parameters that take numbers, as `parse` reads them.
+const NUMBER_FIELDS: [&str; 6] = [
+    "beta",
+    "threshold_voltage",
+    "body_diode_saturation_current",
+    "body_diode_ideality_factor",
+    "saturation_knee",
+    "multiplicity",
+];
+
+/// A part as `DeviceLibrary::parse` reads it.
+struct PartFields {
+    name: String,
+    /// Line of the part's `[name]`.
+    header: usize,
+    kind: Option<MOSFETDopingType>,
+    numbers: BTreeMap<&'static str, f>,
+}
+impl PartFields {
+    fn device(&self) -> Result<Device, DeviceError> {
+        let missing = |field| DeviceError::Missing {
+            part: self.name.clone(),
+            field,
+        };
+        let field = |field: &'static str, default: Option<f>| {
+            let value = self
+                .numbers
+                .get(field)
+                .copied()
+                .or(default)
+                .ok_or_else(|| missing(field))?;
+            if value > 0.0 {
+                Ok(value)
+            } else {
+                Err(DeviceError::NonPositive {
+                    part: self.name.clone(),
+                    field,
+                    value,
+                })
+            }
+        };
+        Ok(Device::MOSFET(MOSFETComponentValue {
+            ty: self.kind.ok_or_else(|| missing("kind"))?,
+            beta: field("beta", None)?,
+            threshold_voltage: field("threshold_voltage", None)?,
+            body_diode_saturation_current: field("body_diode_saturation_current", None)?,
+            body_diode_ideality_facotor: field("body_diode_ideality_factor", None)?,
+            saturation_knee: field("saturation_knee", Some(8.0))?,
+            multiplicity: field("multiplicity", Some(1.0))?,
+            avalanche: None,
+        }))
+    }
+}
+
+/// Levenshtein distance between `a` and `b`, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            row[j + 1] = (previous[j] + usize::from(ca != cb))
+                .min(previous[j + 1] + 1)
+                .min(row[j] + 1);
+        }
+        previous = row;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,264 @@
+use std::fmt;
+
+use super::{
+    f, solver::ChargeAudit, CircuitState, ComponentId, ComponentState, NetId, NetStamps, Scalar,
+};
+
+#[derive(Debug, Clone)]
+pub struct ComponentDiagnostic<S: Scalar = f> {
+    pub component: ComponentId,
+    pub label: String,
+    /// `ComponentState::last_residual`.
+    pub residual: S,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetDiagnostic<S: Scalar = f> {
+    pub net: NetId,
+    /// `CircuitState::net_label` of the net.
+    pub label: String,
+    /// Labels of the components connected to the net.
+    pub components: Vec<String>,
+    /// Current still created or destroyed at the net after the last charge-state correction.
+    pub excess_current: S,
+    /// Spread (max - min) of the voltages the connected components would each set the net to.
+    pub voltage_disagreement: S,
+}
+
+/// The worst offenders of a (typically failed) solve, see `CircuitState::diagnose`.
+#[derive(Debug, Clone)]
+pub struct Diagnosis<S: Scalar = f> {
+    /// Components by largest last residual.
+    pub components: Vec<ComponentDiagnostic<S>>,
+    /// Nets by largest `|excess_current|`.
+    pub nets_by_excess_current: Vec<NetDiagnostic<S>>,
+    /// Nets by largest `voltage_disagreement`.
+    pub nets_by_voltage_disagreement: Vec<NetDiagnostic<S>>,
+}
+
+/// A net that gained or lost more than `ChargeAudit::tolerance` between two audits.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChargeImbalance<S: Scalar = f> {
+    pub net: NetId,
+    /// `CircuitState::net_label` of the net.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub label: String,
+    /// Labels of the components connected to the net.
+    pub components: Vec<String>,
+    /// Charge created (positive) or destroyed at the net since the previous audit, in coulombs.
+    pub charge: S,
+}
+
+/// Outcome of a charge conservation audit, see `CircuitState::last_charge_audit`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChargeAuditReport<S: Scalar = f> {
+    /// Simulation time of the audit.
+    pub time: S,
+    /// Ticks since the previous audit.
+    pub ticks: usize,
+    /// Nets over the tolerance, by largest `|charge|`.
+    pub imbalanced: Vec<ChargeImbalance<S>>,
+    /// Charge created (positive) or destroyed in the whole circuit since the previous audit, the
+    /// sum over all nets. Components that each move as much charge in as out keep this at zero
+    /// even if the nets don't balance.
+    pub drift: S,
+    /// `drift` summed over every audit so far.
+    pub total_drift: S,
+}
+
+/// Charge moved into each net since the last audit.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct ChargeAuditState<S: Scalar> {
+    charge: Vec<S>,
+    ticks: usize,
+    total_drift: S,
+    last: Option<ChargeAuditReport<S>>,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// The last charge conservation audit, if `SolverConfig::charge_audit` is set and `every`
+    /// ticks have passed since it was.
+    pub fn last_charge_audit(&self) -> Option<&ChargeAuditReport<S>> {
+        self.charge_audit.last.as_ref()
+    }
+
+    /// Add the charge moved in a tick of `dt` and audit it every `audit.every` ticks.
+    pub(super) fn audit_charge(&mut self, audit: ChargeAudit<S>, dt: S) {
+        let mut charge = std::mem::take(&mut self.charge_audit.charge);
+        charge.resize(self.nets.len(), S::from(0));
+        let mut terminals = Vec::new();
+        let components = self
+            .components()
+            .filter(|&(component_i, _)| !self.faults.is_bypassed(component_i))
+            .map(|(_, component)| component.as_dyn())
+            .chain(
+                self.faults
+                    .stand_ins()
+                    .map(|stand_in| stand_in as &dyn ComponentState<S>),
+            );
+        for component in components {
+            terminals.clear();
+            terminals.resize(component.nets().len(), S::from(0));
+            component.terminal_charge(dt, &mut terminals);
+            for (&net_i, &q) in component.nets().iter().zip(&terminals) {
+                charge[net_i] += q;
+            }
+        }
+        self.charge_audit.ticks += 1;
+        if self.charge_audit.ticks < audit.every.max(1) {
+            self.charge_audit.charge = charge;
+            return;
+        }
+
+        let drift = charge.iter().copied().sum::<S>();
+        let mut imbalanced = charge
+            .iter()
+            .enumerate()
+            .filter(|(_, q)| q.abs() > audit.tolerance)
+            .map(|(net_i, &charge)| ChargeImbalance {
+                net: net_i,
+                label: self.net_label(net_i),
+                components: self.nets[net_i]
+                    .components
+                    .iter()
+                    .map(|&(component_i, _)| self.component_label(component_i))
+                    .collect(),
+                charge,
+            })
+            .collect::<Vec<_>>();
+        imbalanced.sort_by(|a, b| (b.charge.abs().to_f64()).total_cmp(&a.charge.abs().to_f64()));
+        self.charge_audit.total_drift += drift;
+        self.charge_audit.last = Some(ChargeAuditReport {
+            time: self.time,
+            ticks: self.charge_audit.ticks,
+            imbalanced,
+            drift,
+            total_drift: self.charge_audit.total_drift,
+        });
+        self.charge_audit.ticks = 0;
+        charge.iter_mut().for_each(|q| *q = S::from(0));
+        self.charge_audit.charge = charge;
+    }
+
+    /// Rank components and nets by how far they were from settling in the last solver iteration,
+    /// keeping the top `n` of each ranking.
+    pub fn diagnose(&self, n: usize) -> Diagnosis<S> {
+        let mut components = self
+            .components()
+            .map(|(component_i, component)| ComponentDiagnostic {
+                component: component_i,
+                label: self.component_label(component_i),
+                residual: component.as_dyn().last_residual(),
+            })
+            .collect::<Vec<_>>();
+        components.sort_by(|a, b| b.residual.to_f64().total_cmp(&a.residual.to_f64()));
+        components.truncate(n);
+
+        // each component's full correction (`step = 2`) is the voltage it would set the net to.
+        let mut stamps = NetStamps::default();
+        for (_, component) in self.components() {
+            component
+                .as_dyn()
+                .impart_voltage_to_nets(&self.nets, S::from(2), &mut stamps);
+        }
+        let mut proposals = vec![None; self.nets.len()];
+        for &(net_i, voltage, _) in &stamps.voltages {
+            let (min, max) = proposals[net_i].get_or_insert((voltage, voltage));
+            if voltage < *min {
+                *min = voltage;
+            }
+            if voltage > *max {
+                *max = voltage;
+            }
+        }
+        let nets = self
+            .nets
+            .iter()
+            .enumerate()
+            .map(|(net_i, net)| NetDiagnostic {
+                net: net_i,
+                label: self.net_label(net_i),
+                components: net
+                    .components
+                    .iter()
+                    .map(|&(component_i, _)| self.component_label(component_i))
+                    .collect(),
+                excess_current: net.current[0],
+                voltage_disagreement: proposals[net_i].map_or(S::from(0), |(min, max)| max - min),
+            })
+            .collect::<Vec<_>>();
+
+        let mut nets_by_excess_current = nets.clone();
+        nets_by_excess_current.sort_by(|a, b| {
+            (b.excess_current.abs().to_f64()).total_cmp(&a.excess_current.abs().to_f64())
+        });
+        nets_by_excess_current.truncate(n);
+        let mut nets_by_voltage_disagreement = nets;
+        nets_by_voltage_disagreement.sort_by(|a, b| {
+            (b.voltage_disagreement.to_f64()).total_cmp(&a.voltage_disagreement.to_f64())
+        });
+        nets_by_voltage_disagreement.truncate(n);
+
+        Diagnosis {
+            components,
+            nets_by_excess_current,
+            nets_by_voltage_disagreement,
+        }
+    }
+}
+
+impl<S: Scalar> fmt::Display for ChargeAuditReport<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "charge audit at t = {:e} s over {} ticks: drift {:e} C (total {:e} C)",
+            self.time.to_f64(),
+            self.ticks,
+            self.drift.to_f64(),
+            self.total_drift.to_f64()
+        )?;
+        for v in &self.imbalanced {
+            writeln!(
+                f,
+                "  net {} [{}]: {:e} C",
+                v.label,
+                v.components.join(", "),
+                v.charge.to_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: Scalar> fmt::Display for Diagnosis<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "components by residual:")?;
+        for v in &self.components {
+            writeln!(f, "  {}: {:e}", v.label, v.residual.to_f64())?;
+        }
+        writeln!(f, "nets by excess current:")?;
+        for v in &self.nets_by_excess_current {
+            writeln!(
+                f,
+                "  net {} [{}]: {:e} A",
+                v.label,
+                v.components.join(", "),
+                v.excess_current.to_f64()
+            )?;
+        }
+        writeln!(f, "nets by voltage disagreement:")?;
+        for v in &self.nets_by_voltage_disagreement {
+            writeln!(
+                f,
+                "  net {} [{}]: {:e} V",
+                v.label,
+                v.components.join(", "),
+                v.voltage_disagreement.to_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+//! Graphviz export of a circuit's topology, for checking how a programmatically built circuit
+//! is wired: `dot -Tsvg circuit.dot > circuit.svg`.
+
+use std::fmt::Write;
+
+use super::{
+    builder::NameMap,
+    components::{LinearComponentValue, MOSFETDopingType},
+    CircuitState, ComponentRef, Scalar,
+};
+
+/// What `CircuitState::to_dot_with` puts on the graph.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DotOptions<'a> {
+    /// Names for the nets, which are otherwise labelled with their aliases, or `n<id>` for nets
+    /// without.
+    pub names: Option<&'a NameMap>,
+    /// Label nets with their voltage and components with their current (first terminal to
+    /// last) as last solved.
+    pub solution: bool,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// `to_dot_with` the default options.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::default())
+    }
+    /// The circuit as an undirected Graphviz graph: nets are nodes and two-terminal components
+    /// edges between them, labelled with their name and value; MOSFETs are box nodes joined to
+    /// their nets by edges labelled with the terminal.
+    pub fn to_dot_with(&self, options: &DotOptions<'_>) -> String {
+        let mut net_names = vec![None; self.nets.len()];
+        if let Some(names) = options.names {
+            for (name, &net) in &names.nets {
+                net_names[net] = Some(name.as_str());
+            }
+        }
+        let mut out = String::from("graph circuit {\n    node [shape=ellipse];\n");
+        for (net_i, net) in self.nets.iter().enumerate() {
+            let aliases = self.net_aliases(net_i);
+            let mut label = match net_names[net_i] {
+                Some(name) => name.to_string(),
+                None if !aliases.is_empty() => aliases.join(", "),
+                None => format!("n{net_i}"),
+            };
+            if options.solution {
+                write!(label, "\n{:.4e} V", net.voltage.to_f64()).unwrap();
+            }
+            writeln!(out, "    n{net_i} [label={}];", quote(&label)).unwrap();
+        }
+        for (component_i, component) in self.components() {
+            let mut label = format!(
+                "{}\n{}",
+                self.component_label(component_i),
+                describe(component)
+            );
+            if options.solution {
+                let current = match component {
+                    ComponentRef::Linear(v) => v.q[1],
+                    ComponentRef::MOSFET(v) => v.i[0],
+                    ComponentRef::NoiseSource(v) => v.i[0],
+                    ComponentRef::GateDriver(v) => v.i[0],
+                    ComponentRef::Comparator(v) => v.i[0],
+                    ComponentRef::Converter(v) => v.i[0],
+                };
+                write!(label, "\n{:.4e} A", current.to_f64()).unwrap();
+            }
+            let nets = component.as_dyn().nets();
+            match component {
+                ComponentRef::MOSFET(_)
+                | ComponentRef::GateDriver(_)
+                | ComponentRef::Comparator(_)
+                | ComponentRef::Converter(_) => {
+                    writeln!(
+                        out,
+                        "    c{component_i} [shape=box, label={}];",
+                        quote(&label)
+                    )
+                    .unwrap();
+                    for (terminal, net) in component.value().terminal_names().iter().zip(nets) {
+                        writeln!(
+                            out,
+                            "    c{component_i} -- n{net} [label={}];",
+                            quote(terminal)
+                        )
+                        .unwrap();
+                    }
+                }
+                ComponentRef::Linear(_) | ComponentRef::NoiseSource(_) => {
+                    writeln!(
+                        out,
+                        "    n{} -- n{} [label={}];",
+                        nets[0],
+                        nets[1],
+                        quote(&label)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// The value of `component`, in a line.
+fn describe<S: Scalar>(component: ComponentRef<'_, S>) -> String {
+    match component {
+        ComponentRef::Linear(v) => match v.value {
+            LinearComponentValue::Capacitive(c) => format!("{:e} F", c.to_f64()),
+            LinearComponentValue::LossyCapacitive {
+                capacitance, esr, ..
+            } => format!("{:e} F, ESR {:e} ohm", capacitance.to_f64(), esr.to_f64()),
+            LinearComponentValue::Resistive(r) => format!("{:e} ohm", r.to_f64()),
+            LinearComponentValue::Inductive(l) => format!("{:e} H", l.to_f64()),
+            LinearComponentValue::SaturatingInductive {
+                inductance,
+                saturation_current,
+                ..
+            } => format!(
+                "{:e} H, saturating at {:e} A",
+                inductance.to_f64(),
+                saturation_current.to_f64()
+            ),
+            LinearComponentValue::Source(v) => format!("{:e} V", v.to_f64()),
+            LinearComponentValue::Pwm(wave) => format!(
+                "PWM {:e} / {:e} V, {:e} s, duty {}",
+                wave.low.to_f64(),
+                wave.high.to_f64(),
+                wave.period.to_f64(),
+                wave.duty.to_f64()
+            ),
+            LinearComponentValue::Switch { closed: true, .. } => "closed".to_string(),
+            LinearComponentValue::Switch { closed: false, .. } => "open".to_string(),
+            LinearComponentValue::Jumper => "jumper".to_string(),
+        },
+        ComponentRef::MOSFET(v) => format!(
+            "{} beta {:e}, Vt {:e} V",
+            match v.value.ty {
+                MOSFETDopingType::NChannel => "N-channel",
+                MOSFETDopingType::PChannel => "P-channel",
+            },
+            v.value.beta.to_f64(),
+            v.value.threshold_voltage.to_f64()
+        ),
+        ComponentRef::NoiseSource(v) => format!(
+            "noise {:e} V +- {:e} V",
+            v.value.offset.to_f64(),
+            v.value.sigma.to_f64()
+        ),
+        ComponentRef::GateDriver(v) => format!(
+            "gate driver {:e} / {:e} ohm, {:e} s, UVLO {:e} V",
+            v.value.source_resistance.to_f64(),
+            v.value.sink_resistance.to_f64(),
+            v.value.propagation_delay.to_f64(),
+            v.value.uvlo_threshold.to_f64()
+        ),
+        ComponentRef::Comparator(v) => format!(
+            "comparator {:e} / {:e} V, {:e} ohm, {:e} V hyst, {:e} s",
+            v.value.output_low.to_f64(),
+            v.value.output_high.to_f64(),
+            v.value.output_resistance.to_f64(),
+            v.value.hysteresis.to_f64(),
+            v.value.propagation_delay.to_f64()
+        ),
+        ComponentRef::Converter(v) => format!(
+            "DC-DC converter {:e} V, {:e} A limit, {:e} efficiency, {:e} F, {:e} s soft start",
+            v.value.output_voltage.to_f64(),
+            v.value.current_limit.to_f64(),
+            v.value.efficiency.to_f64(),
+            v.value.output_capacitance.to_f64(),
+            v.value.soft_start.to_f64()
+        ),
+    }
+}
+
+/// `text` as a DOT string, with line breaks as `\n` escapes.
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
@@ -0,0 +1,175 @@
+//! Timing of recorded logic-like channels (gate drives, comparator outputs): the edges where a
+//! channel crosses a threshold, and the duty cycle, frequency and dead time they give.
+//!
+//! A channel is high once it rises above `threshold + hysteresis / 2` and low once it falls
+//! below `threshold - hysteresis / 2`; an edge is timed where the channel last crossed
+//! `threshold` itself on the way, interpolated between rows, so noise on a slow edge neither
+//! adds edges nor moves it.
+
+use super::{f, probe::Recording, stats::StatsError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeDirection {
+    Rising,
+    Falling,
+}
+
+/// A threshold crossing of a channel, see `Recording::edges`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edge {
+    pub time: f,
+    pub direction: EdgeDirection,
+}
+
+/// The gaps between the falling edges of one channel and the rising edges of its complement,
+/// see `Recording::dead_time`. Negative gaps are overlap (shoot-through in a half-bridge).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadTime {
+    pub min: f,
+    pub mean: f,
+    /// Number of gaps measured.
+    pub n: usize,
+}
+
+impl Recording {
+    /// The edges of the channel labelled `label`, in time order, rising and falling edges
+    /// alternating. The first row outside the hysteresis band only sets the starting level.
+    pub fn edges(&self, label: &str, threshold: f, hysteresis: f) -> Result<Vec<Edge>, StatsError> {
+        let samples = self
+            .channel(label)
+            .ok_or_else(|| StatsError::UnknownChannel(label.to_string()))?;
+        let (low, high) = (threshold - 0.5 * hysteresis, threshold + 0.5 * hysteresis);
+        let mut edges = Vec::new();
+        let mut level = None;
+        // the last crossing of `threshold` since the level changed.
+        let mut crossing = None;
+        let mut prev: Option<(f, f)> = None;
+        for (&time, &x) in self.time.iter().zip(samples) {
+            if !x.is_finite() {
+                return Err(StatsError::NonFinite {
+                    channel: label.to_string(),
+                    time,
+                });
+            }
+            if let Some((t0, x0)) = prev {
+                if (x0 < threshold) != (x < threshold) {
+                    crossing = Some(t0 + (time - t0) * (threshold - x0) / (x - x0));
+                }
+            }
+            prev = Some((time, x));
+            let direction = match level {
+                Some(EdgeDirection::Rising) | None if x < low => EdgeDirection::Falling,
+                Some(EdgeDirection::Falling) | None if x > high => EdgeDirection::Rising,
+                _ => continue,
+            };
+            if level.is_some() {
+                edges.push(Edge {
+                    time: crossing.unwrap_or(time),
+                    direction,
+                });
+            }
+            level = Some(direction);
+            crossing = None;
+        }
+        Ok(edges)
+    }
+
+    /// The fraction of time the channel labelled `label` is high, over the whole periods from its
+    /// first rising edge to its last.
+    ///
+    /// Errors with `StatsError::TooFewEdges` if it has fewer than two rising edges.
+    pub fn duty_cycle(&self, label: &str, threshold: f, hysteresis: f) -> Result<f, StatsError> {
+        let edges = self.edges(label, threshold, hysteresis)?;
+        let (first, last) = Self::period_span(label, &edges)?;
+        let high = edges
+            .windows(2)
+            .filter(|pair| pair[0].direction == EdgeDirection::Rising && pair[0].time < last)
+            .map(|pair| pair[1].time - pair[0].time)
+            .sum::<f>();
+        Ok(high / (last - first))
+    }
+    /// The mean switching frequency of the channel labelled `label`, in hertz: the whole
+    /// periods from its first rising edge to its last over the time they take.
+    ///
+    /// Errors with `StatsError::TooFewEdges` if it has fewer than two rising edges.
+    pub fn frequency(&self, label: &str, threshold: f, hysteresis: f) -> Result<f, StatsError> {
+        let edges = self.edges(label, threshold, hysteresis)?;
+        let (first, last) = Self::period_span(label, &edges)?;
+        let n_periods = edges
+            .iter()
+            .filter(|edge| edge.direction == EdgeDirection::Rising)
+            .count()
+            - 1;
+        Ok(n_periods as f / (last - first))
+    }
+    /// The first and last rising edge.
+    fn period_span(label: &str, edges: &[Edge]) -> Result<(f, f), StatsError> {
+        let mut rising = edges
+            .iter()
+            .filter(|edge| edge.direction == EdgeDirection::Rising);
+        match (rising.next(), rising.next_back()) {
+            (Some(first), Some(last)) => Ok((first.time, last.time)),
+            (first, _) => Err(StatsError::TooFewEdges {
+                channel: label.to_string(),
+                found: usize::from(first.is_some()),
+            }),
+        }
+    }
+
+    /// The dead time between the complementary channels labelled `high` and `low` (the gate
+    /// drives of a half-bridge): for every falling edge of either, the time to the rising edge
+    /// of the other nearest it, out of those between the rising edges of the first either side.
+    /// Edges the recording ends or starts before the complement of are left out.
+    ///
+    /// Errors with `StatsError::TooFewEdges` if neither channel has a falling edge with a rising
+    /// edge of the other to pair with.
+    pub fn dead_time(
+        &self,
+        high: &str,
+        low: &str,
+        threshold: f,
+        hysteresis: f,
+    ) -> Result<DeadTime, StatsError> {
+        let high_edges = self.edges(high, threshold, hysteresis)?;
+        let low_edges = self.edges(low, threshold, hysteresis)?;
+        let mut gaps = Vec::new();
+        for (falling, rising) in [(&high_edges, &low_edges), (&low_edges, &high_edges)] {
+            let rising = rising
+                .iter()
+                .filter(|edge| edge.direction == EdgeDirection::Rising)
+                .map(|edge| edge.time)
+                .collect::<Vec<_>>();
+            for (i, edge) in falling.iter().enumerate() {
+                if edge.direction != EdgeDirection::Falling {
+                    continue;
+                }
+                // edges alternate, so those either side are the rising ones.
+                let after = i
+                    .checked_sub(1)
+                    .map_or(f::NEG_INFINITY, |i| falling[i].time);
+                let before = falling.get(i + 1).map_or(f::INFINITY, |edge| edge.time);
+                let nearest = rising
+                    .iter()
+                    .filter(|&&time| after < time && time < before)
+                    .map(|&time| time - edge.time)
+                    .min_by(|a, b| {
+                        a.abs()
+                            .partial_cmp(&b.abs())
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    });
+                gaps.extend(nearest);
+            }
+        }
+        if gaps.is_empty() {
+            return Err(StatsError::TooFewEdges {
+                channel: format!("{high} and {low}"),
+                found: 0,
+            });
+        }
+        Ok(DeadTime {
+            min: gaps.iter().copied().fold(f::INFINITY, f::min),
+            mean: gaps.iter().sum::<f>() / gaps.len() as f,
+            n: gaps.len(),
+        })
+    }
+}
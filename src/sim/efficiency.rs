@@ -0,0 +1,220 @@
+//! Efficiency of a power stage over a steady-state window: the power its sources deliver, the
+//! power its load absorbs, and the losses between them by stage, each MOSFET's split by the
+//! region it dissipates in (see `MOSFETRegion`).
+//!
+//! The output is electrical, the power absorbed by the components given as the load; there is
+//! no motor model yet to take mechanical power (torque times speed) from.
+
+use std::fmt;
+
+use super::{components::MOSFETRegion, f, CircuitState, ComponentId, ComponentRef};
+
+/// Which components are which stage of the power stage measured, and over when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EfficiencyConfig {
+    /// Simulation times the window begins and ends at; ticks ending in `(start, end]` count.
+    pub window: (f, f),
+    /// The sources feeding the stage (the battery), their delivered power the input.
+    pub inputs: Vec<ComponentId>,
+    /// The components of the load, their absorbed power the output.
+    pub outputs: Vec<ComponentId>,
+    pub high_side: Vec<ComponentId>,
+    pub low_side: Vec<ComponentId>,
+    /// Capacitors of the DC bus, their dissipation (ESR and leakage) a stage of its own.
+    pub bus_capacitors: Vec<ComponentId>,
+}
+
+/// The average losses of the MOSFETs of one side of the bridge, in watts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SwitchLosses {
+    /// Dissipated in `MOSFETRegion::Triode` and `Off`.
+    pub conduction: f,
+    /// Dissipated in `MOSFETRegion::BodyDiode`, the dead time.
+    pub body_diode: f,
+    /// Dissipated in `MOSFETRegion::Saturation`, the V·I overlap of the transitions.
+    pub switching: f,
+}
+impl SwitchLosses {
+    pub fn total(&self) -> f {
+        self.conduction + self.body_diode + self.switching
+    }
+}
+
+/// Average powers over the window of an `EfficiencyMeter`, in watts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EfficiencyReport {
+    /// Length of the window covered.
+    pub duration: f,
+    pub input_power: f,
+    pub output_power: f,
+    pub high_side: SwitchLosses,
+    pub low_side: SwitchLosses,
+    pub bus_capacitor: f,
+    /// Dissipated in every other component (wiring, snubbers, the loss of reactive parts).
+    pub other: f,
+    /// Power going into stored energy, which is zero in a true steady state.
+    pub stored: f,
+}
+impl EfficiencyReport {
+    /// Every loss.
+    pub fn losses(&self) -> f {
+        self.high_side.total() + self.low_side.total() + self.bus_capacitor + self.other
+    }
+    /// `output_power / input_power`.
+    pub fn efficiency(&self) -> f {
+        self.output_power / self.input_power
+    }
+    /// `input - output - losses - stored`, the error of the energy balance: zero from exact
+    /// integration, growing with the step.
+    pub fn unaccounted(&self) -> f {
+        self.input_power - self.output_power - self.losses() - self.stored
+    }
+}
+impl fmt::Display for EfficiencyReport {
+    fn fmt(&self, fm: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let share = |p: f| 100.0 * p / self.input_power;
+        let mut row = |name: &str, p: f| writeln!(fm, "{name:<26} {p:>12.4} W {:>7.2} %", share(p));
+        row("input", self.input_power)?;
+        row("output", self.output_power)?;
+        for (side, losses) in [("high side", self.high_side), ("low side", self.low_side)] {
+            row(&format!("{side} conduction"), losses.conduction)?;
+            row(&format!("{side} body diode"), losses.body_diode)?;
+            row(&format!("{side} switching"), losses.switching)?;
+        }
+        row("bus capacitor", self.bus_capacitor)?;
+        row("other", self.other)?;
+        row("stored", self.stored)?;
+        row("unaccounted", self.unaccounted())?;
+        write!(
+            fm,
+            "efficiency {:.2} % over {:e} s",
+            100.0 * self.efficiency(),
+            self.duration
+        )
+    }
+}
+
+/// Integrates the powers of an `EfficiencyReport` over a run, like a `LossAccumulator`.
+#[derive(Debug, Clone)]
+pub struct EfficiencyMeter {
+    config: EfficiencyConfig,
+    /// Energies of the report fields, in joules.
+    energy: EfficiencyReport,
+    /// Total stored energy after the last `accumulate`, and at the start of the window.
+    stored: Option<f>,
+    stored_at_start: Option<f>,
+}
+impl EfficiencyMeter {
+    pub fn new(config: EfficiencyConfig) -> Self {
+        Self {
+            config,
+            energy: EfficiencyReport::default(),
+            stored: None,
+            stored_at_start: None,
+        }
+    }
+
+    /// Integrate the powers over a step of length `dt`, if it ends in the window.
+    ///
+    /// Call once after each `tick(dt)`.
+    pub fn accumulate(&mut self, circuit: &CircuitState, dt: f) {
+        let stored = (0..circuit.n_components())
+            .map(|component| circuit.stored_energy(component))
+            .sum::<f>();
+        let before = self.stored.replace(stored);
+        let (start, end) = self.config.window;
+        let t = circuit.now();
+        if t <= start || t > end {
+            return;
+        }
+        // the window starts with the state before the first tick in it.
+        self.stored_at_start.get_or_insert(before.unwrap_or(stored));
+
+        let config = &self.config;
+        let energy = &mut self.energy;
+        energy.duration += dt;
+        for (component, state) in circuit.components() {
+            let dissipated = circuit.dissipated_power(component) * dt;
+            let side = if config.inputs.contains(&component) {
+                energy.input_power -= circuit.instantaneous_power(component) * dt;
+                continue;
+            } else if config.outputs.contains(&component) {
+                energy.output_power += circuit.instantaneous_power(component) * dt;
+                continue;
+            } else if config.bus_capacitors.contains(&component) {
+                energy.bus_capacitor += dissipated;
+                continue;
+            } else if config.high_side.contains(&component) {
+                &mut energy.high_side
+            } else if config.low_side.contains(&component) {
+                &mut energy.low_side
+            } else {
+                energy.other += dissipated;
+                continue;
+            };
+            let region = match state {
+                ComponentRef::MOSFET(mosfet) => mosfet.region(&circuit.nets),
+                // a switch or resistor standing in for the FET.
+                _ => MOSFETRegion::Triode,
+            };
+            match region {
+                MOSFETRegion::BodyDiode => side.body_diode += dissipated,
+                MOSFETRegion::Off | MOSFETRegion::Triode => side.conduction += dissipated,
+                MOSFETRegion::Saturation => side.switching += dissipated,
+            }
+        }
+        // as `LossAccumulator`, the loss of charge sharing at the start of the tick.
+        if let Some(sharing) = circuit.last_charge_sharing() {
+            energy.other += sharing.dissipated;
+        }
+    }
+
+    /// The average powers over the part of the window accumulated so far.
+    pub fn report(&self) -> EfficiencyReport {
+        let energy = &self.energy;
+        let duration = energy.duration;
+        if duration <= 0.0 {
+            return EfficiencyReport::default();
+        }
+        let per_time = |losses: SwitchLosses| SwitchLosses {
+            conduction: losses.conduction / duration,
+            body_diode: losses.body_diode / duration,
+            switching: losses.switching / duration,
+        };
+        EfficiencyReport {
+            duration,
+            input_power: energy.input_power / duration,
+            output_power: energy.output_power / duration,
+            high_side: per_time(energy.high_side),
+            low_side: per_time(energy.low_side),
+            bus_capacitor: energy.bus_capacitor / duration,
+            other: energy.other / duration,
+            stored: match (self.stored_at_start, self.stored) {
+                (Some(start), Some(end)) => (end - start) / duration,
+                _ => 0.0,
+            },
+        }
+    }
+}
+
+/// Tick `circuit` by `dt` to the end of `config.window`, calling `before_tick` (to drive the
+/// gates) with the circuit before each tick, and report its efficiency over the window, with the
+/// number of ticks in it that didn't converge.
+pub fn efficiency_report(
+    circuit: &mut CircuitState,
+    config: EfficiencyConfig,
+    dt: f,
+    mut before_tick: impl FnMut(&mut CircuitState),
+) -> (EfficiencyReport, usize) {
+    let (start, end) = config.window;
+    let mut meter = EfficiencyMeter::new(config);
+    let mut unconverged = 0;
+    while circuit.now() + 0.5 * dt < end {
+        before_tick(circuit);
+        if !circuit.tick(dt) && circuit.now() > start {
+            unconverged += 1;
+        }
+        meter.accumulate(circuit, dt);
+    }
+    (meter.report(), unconverged)
+}
@@ -0,0 +1,83 @@
+//! Conditions the whole circuit is in, rather than any one component: for now the ambient
+//! temperature, which MOSFETs and resistors take at every tick unless their temperature is held
+//! (see `MOSFETComponentState::temperature_held`, as a `ThermalNetwork` does for those bound to
+//! it).
+
+use super::{components::NOMINAL_TEMPERATURE, f, CircuitState, Scalar};
+
+/// A quantity over simulation time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Profile<S: Scalar = f> {
+    Constant(S),
+    /// `(time, value)` points in ascending time, interpolated linearly between them and holding
+    /// the end values outside.
+    Piecewise(Vec<(S, S)>),
+}
+impl<S: Scalar> Profile<S> {
+    pub fn at(&self, t: S) -> S {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Piecewise(points) => {
+                let i = points.partition_point(|&(time, _)| time < t);
+                match (i.checked_sub(1).map(|i| points[i]), points.get(i)) {
+                    (Some((t0, v0)), Some(&(t1, v1))) if t1 > t0 => {
+                        v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+                    }
+                    (_, Some(&(_, v))) | (Some((_, v)), None) => v,
+                    (None, None) => S::from(0),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Environment<S: Scalar = f> {
+    /// Ambient temperature in kelvin, `NOMINAL_TEMPERATURE` by default.
+    pub ambient: Profile<S>,
+}
+impl<S: Scalar> Default for Environment<S> {
+    fn default() -> Self {
+        Self {
+            ambient: Profile::Constant(S::from_f64(NOMINAL_TEMPERATURE)),
+        }
+    }
+}
+impl<S: Scalar> Environment<S> {
+    /// A constant ambient temperature of `celsius` degrees Celsius.
+    pub fn ambient_celsius(celsius: S) -> Self {
+        Self {
+            ambient: Profile::Constant(celsius + S::from_f64(273.15)),
+        }
+    }
+}
+
+impl<S: Scalar> CircuitState<S> {
+    pub fn environment(&self) -> &Environment<S> {
+        &self.environment
+    }
+    /// Replace the environment. Components that follow the ambient temperature take it right away,
+    /// for the next `solve_state`, and again at every tick.
+    pub fn set_environment(&mut self, environment: Environment<S>) {
+        self.environment = environment;
+        self.apply_environment();
+    }
+
+    /// Set every MOSFET and linear component that follows the ambient temperature to it at the
+    /// present time.
+    pub(super) fn apply_environment(&mut self) {
+        let ambient = self.environment.ambient.at(self.time);
+        for mosfet in &mut self.pools.mosfet {
+            if !mosfet.temperature_held {
+                mosfet.temperature = ambient;
+            }
+        }
+        for linear in &mut self.pools.linear {
+            if !linear.temperature_held {
+                linear.set_temperature(ambient);
+            }
+        }
+    }
+}
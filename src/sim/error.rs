@@ -0,0 +1,132 @@
+use std::{error::Error, fmt};
+
+use super::{f, ComponentId, NetId};
+
+/// A net or component of a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Net(NetId),
+    Component(ComponentId),
+}
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Net(net) => write!(f, "net {net}"),
+            Self::Component(component) => write!(f, "component {component}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimError {
+    /// No component of the circuit has this id.
+    UnknownComponent(ComponentId),
+    /// The component is not of the kind the operation applies to.
+    WrongComponentKind {
+        component: ComponentId,
+        expected: &'static str,
+    },
+    /// No net of the circuit has this name.
+    UnknownNet(String),
+    /// No net of the circuit has this id.
+    UnknownNetId(NetId),
+    /// `net` already has this alias, see `CircuitState::add_net_alias`.
+    DuplicateNetAlias { alias: String, net: NetId },
+    /// Another component already has this name.
+    DuplicateName(String),
+    /// A component was given a different number of nets than it has terminals.
+    WrongNetCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A netlist could not be read, at this (1-based) line.
+    Netlist { line: usize, message: String },
+    /// No component was bound to a signal of this name (see `CircuitState::bind_signal`).
+    UnknownSignal(String),
+    /// A `SimulationSet` has no circuit of this name.
+    UnknownCircuit(String),
+    /// A `SimulationSet` already has a circuit of this name.
+    DuplicateCircuit(String),
+    /// A probe spec (see `Probe::parse`) is malformed or names something the circuit lacks.
+    InvalidProbe { spec: String, message: String },
+    /// A value is not a number with an optional SI prefix (see `units::parse_si`).
+    InvalidValue(String),
+    /// `SolverConfig::validate` found an error of this description in the circuit.
+    InvalidCircuit(String),
+    /// The device library has no part of this name; `close` are the names nearest to it.
+    UnknownPart { name: String, close: Vec<String> },
+    /// The solver produced a NaN or infinite `quantity` at this net or component, labelled as by
+    /// `CircuitState::location_label`.
+    NonFiniteValue {
+        net_or_component: Location,
+        label: String,
+        quantity: &'static str,
+    },
+    /// `SolverConfig::strict_dt` refused a tick of `dt` seconds, longer than the `max_dt` that
+    /// `component` (labelled as by `CircuitState::component_label`) allows.
+    TimeStepTooLarge {
+        component: ComponentId,
+        label: String,
+        dt: f,
+        max_dt: f,
+    },
+    /// The small-signal system of the circuit is singular at this frequency, in Hz.
+    SingularAcSystem(f),
+    /// A tick ending at this simulation time, in seconds, did not converge.
+    NotConverged(f),
+}
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownComponent(component) => write!(f, "no component with id {component}"),
+            Self::WrongComponentKind {
+                component,
+                expected,
+            } => write!(f, "component {component} is not {expected}"),
+            Self::UnknownNet(name) => write!(f, "no net named {name:?}"),
+            Self::UnknownNetId(net) => write!(f, "no net with id {net}"),
+            Self::DuplicateNetAlias { alias, net } => {
+                write!(f, "net {net} is already aliased {alias:?}")
+            }
+            Self::DuplicateName(name) => write!(f, "a component is already named {name:?}"),
+            Self::WrongNetCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "component {name:?} has {expected} terminals but was connected to {found} nets"
+            ),
+            Self::Netlist { line, message } => write!(f, "netlist line {line}: {message}"),
+            Self::UnknownSignal(name) => write!(f, "no signal named {name:?}"),
+            Self::UnknownCircuit(name) => write!(f, "no circuit named {name:?}"),
+            Self::DuplicateCircuit(name) => write!(f, "a circuit is already named {name:?}"),
+            Self::InvalidProbe { spec, message } => write!(f, "probe {spec:?}: {message}"),
+            Self::InvalidValue(value) => write!(f, "malformed value {value:?}"),
+            Self::InvalidCircuit(warning) => write!(f, "invalid circuit: {warning}"),
+            Self::UnknownPart { name, close } if close.is_empty() => {
+                write!(f, "no part named {name:?} in the device library")
+            }
+            Self::UnknownPart { name, close } => write!(
+                f,
+                "no part named {name:?} in the device library, did you mean {}?",
+                close.join(", ")
+            ),
+            Self::NonFiniteValue {
+                label, quantity, ..
+            } => write!(f, "non-finite {quantity} at {label}"),
+            Self::TimeStepTooLarge {
+                label, dt, max_dt, ..
+            } => write!(
+                f,
+                "time step {dt:e} s is longer than the {max_dt:e} s {label} allows"
+            ),
+            Self::SingularAcSystem(frequency) => {
+                write!(f, "the small-signal system is singular at {frequency:e} Hz")
+            }
+            Self::NotConverged(time) => write!(f, "the tick to {time:e} s did not converge"),
+        }
+    }
+}
+impl Error for SimError {}
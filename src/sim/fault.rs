@@ -0,0 +1,241 @@
+//! Faults injected into a running circuit: a component gone open or shorted, a degraded
+//! parameter or a stuck switch. The component's own value stays nominal and the solver consults
+//! the fault instead, so `CircuitState::clear_fault` restores nominal behaviour exactly.
+
+use super::{
+    components::{ComponentParameter, LinearComponentState, LinearComponentValue},
+    error::SimError,
+    f, CircuitState, ComponentId, ComponentMut, ComponentValue, Scalar,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fault<S: Scalar = f> {
+    /// The component no longer conducts or drives its nets.
+    Open,
+    /// `resistance` between the component's first and last terminals (drain and source of a
+    /// MOSFET) in place of the component.
+    Short { resistance: S },
+    /// `parameter` scaled by `factor`, e.g. `Beta` by 0.5 for a MOSFET lost half its channel.
+    ParameterScale {
+        parameter: ComponentParameter,
+        factor: S,
+    },
+    /// A switch held `closed` whatever it is switched to.
+    StuckSwitch { closed: bool },
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActiveFault<S: Scalar = f> {
+    pub component: ComponentId,
+    pub fault: Fault<S>,
+    /// Simulation time the fault was injected at.
+    pub since: S,
+    /// The resistor standing in for a shorted component.
+    stand_in: Option<LinearComponentState<S>>,
+    /// The value a `ParameterScale` or `StuckSwitch` replaced while it is applied.
+    nominal: Option<Nominal<S>>,
+}
+impl<S: Scalar> ActiveFault<S> {
+    /// Whether the solver leaves the component itself out.
+    fn bypasses(&self) -> bool {
+        matches!(self.fault, Fault::Open | Fault::Short { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Nominal<S: Scalar> {
+    Parameter(S),
+    Closed(bool),
+}
+
+/// The faults of a circuit, see `CircuitState::inject_fault`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct FaultOverlay<S: Scalar> {
+    faults: Vec<ActiveFault<S>>,
+    /// Per component in storage order, whether it is open or shorted; empty if none are.
+    pub(super) bypassed: Vec<bool>,
+    /// Whether the parameter and switch faults are swapped into the components.
+    applied: bool,
+}
+impl<S: Scalar> Default for FaultOverlay<S> {
+    fn default() -> Self {
+        Self {
+            faults: Vec::new(),
+            bypassed: Vec::new(),
+            applied: false,
+        }
+    }
+}
+impl<S: Scalar> FaultOverlay<S> {
+    pub(super) fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+    pub(super) fn is_bypassed(&self, component: ComponentId) -> bool {
+        self.faults
+            .iter()
+            .any(|fault| fault.component == component && fault.bypasses())
+    }
+    /// The resistors standing in for shorted components.
+    pub(super) fn stand_ins(&self) -> impl Iterator<Item = &LinearComponentState<S>> {
+        self.faults
+            .iter()
+            .filter_map(|fault| fault.stand_in.as_ref())
+    }
+    pub(super) fn stand_ins_mut(&mut self) -> impl Iterator<Item = &mut LinearComponentState<S>> {
+        self.faults
+            .iter_mut()
+            .filter_map(|fault| fault.stand_in.as_mut())
+    }
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Inject `fault` into `component` from the present simulation time on, replacing any fault
+    /// it already has. An open or shorted component keeps its state where it was until the fault
+    /// is cleared.
+    ///
+    /// Errors if `component` doesn't exist, lacks the scaled parameter or isn't a switch for
+    /// `StuckSwitch`.
+    pub fn inject_fault(
+        &mut self,
+        component: ComponentId,
+        fault: Fault<S>,
+    ) -> Result<(), SimError> {
+        if component >= self.slots.len() {
+            return Err(SimError::UnknownComponent(component));
+        }
+        let fits = match (fault, self.component_mut(component)) {
+            (Fault::Open | Fault::Short { .. }, _) => true,
+            (Fault::ParameterScale { parameter, .. }, c) => c.parameter_mut(parameter).is_some(),
+            (Fault::StuckSwitch { .. }, ComponentMut::Linear(c)) => {
+                matches!(c.value, LinearComponentValue::Switch { .. })
+            }
+            (Fault::StuckSwitch { .. }, _) => false,
+        };
+        if !fits {
+            return Err(SimError::WrongComponentKind {
+                component,
+                expected: match fault {
+                    Fault::StuckSwitch { .. } => "a switch",
+                    _ => "a component with that parameter",
+                },
+            });
+        }
+        let stand_in = match fault {
+            Fault::Short { resistance } => {
+                let nets = self.component(component).as_dyn().nets();
+                let terminals = [nets[0], nets[nets.len() - 1]];
+                Some(LinearComponentValue::Resistive(resistance).create(&terminals))
+            }
+            _ => None,
+        };
+        self.clear_fault(component);
+        self.faults.faults.push(ActiveFault {
+            component,
+            fault,
+            since: self.time,
+            stand_in,
+            nominal: None,
+        });
+        self.refresh_fault_bypass();
+        Ok(())
+    }
+
+    /// Remove the fault of `component`, returning it. A component that was open or shorted
+    /// resumes from the state it had when the fault was injected.
+    pub fn clear_fault(&mut self, component: ComponentId) -> Option<Fault<S>> {
+        let fault_i = self
+            .faults
+            .faults
+            .iter()
+            .position(|fault| fault.component == component)?;
+        let fault = self.faults.faults.remove(fault_i);
+        self.refresh_fault_bypass();
+        Some(fault.fault)
+    }
+
+    pub fn fault(&self, component: ComponentId) -> Option<&ActiveFault<S>> {
+        self.faults
+            .faults
+            .iter()
+            .find(|fault| fault.component == component)
+    }
+    /// The faults in the order they were injected.
+    pub fn faults(&self) -> &[ActiveFault<S>] {
+        &self.faults.faults
+    }
+
+    /// Rebuild `FaultOverlay::bypassed`, whenever faults change or components are added.
+    pub(super) fn refresh_fault_bypass(&mut self) {
+        let overlay = &mut self.faults;
+        overlay.bypassed.clear();
+        if !overlay.faults.iter().any(ActiveFault::bypasses) {
+            return;
+        }
+        overlay.bypassed.resize(self.pools.len(), false);
+        for fault in overlay.faults.iter().filter(|fault| fault.bypasses()) {
+            let (kind, i) = self.slots[fault.component];
+            overlay.bypassed[self.pools.storage_offset(kind) + i] = true;
+        }
+    }
+
+    /// Run `f` with the parameter and switch faults swapped into their components, unless they
+    /// already are, restoring the nominal values afterwards.
+    pub(super) fn with_fault_values<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        if self.faults.applied || self.faults.is_empty() {
+            return f(self);
+        }
+        let mut faults = std::mem::take(&mut self.faults.faults);
+        for fault in &mut faults {
+            fault.nominal = self.swap_fault_value(fault.component, fault.fault, None);
+        }
+        self.faults.faults = faults;
+        self.faults.applied = true;
+        let result = f(self);
+        self.faults.applied = false;
+        let mut faults = std::mem::take(&mut self.faults.faults);
+        for fault in &mut faults {
+            if let Some(nominal) = fault.nominal.take() {
+                self.swap_fault_value(fault.component, fault.fault, Some(nominal));
+            }
+        }
+        self.faults.faults = faults;
+        result
+    }
+    /// Apply `fault` to the value of `component`, returning what it replaced, or put `nominal`
+    /// back.
+    fn swap_fault_value(
+        &mut self,
+        component: ComponentId,
+        fault: Fault<S>,
+        nominal: Option<Nominal<S>>,
+    ) -> Option<Nominal<S>> {
+        match (fault, self.component_mut(component)) {
+            (Fault::ParameterScale { parameter, factor }, c) => {
+                // `dc_operating_point` may have swapped the value for one without the parameter.
+                let x = c.parameter_mut(parameter)?;
+                let previous = *x;
+                *x = match nominal {
+                    Some(Nominal::Parameter(x)) => x,
+                    _ => previous * factor,
+                };
+                Some(Nominal::Parameter(previous))
+            }
+            (Fault::StuckSwitch { closed: stuck }, ComponentMut::Linear(c)) => {
+                let LinearComponentValue::Switch { closed, .. } = &mut c.value else {
+                    return None;
+                };
+                let previous = *closed;
+                *closed = match nominal {
+                    Some(Nominal::Closed(closed)) => closed,
+                    _ => stuck,
+                };
+                Some(Nominal::Closed(previous))
+            }
+            _ => None,
+        }
+    }
+}
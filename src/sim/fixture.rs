@@ -0,0 +1,125 @@
+//! A single component on its own, each terminal on a net held at a voltage set by hand as if by an
+//! ideal source, with `purturb_from_nets` and the `impart_*` stamps called directly: a device
+//! model characterized point by point (its I-V curve, the voltages it proposes) without a circuit
+//! or the solver in the loop. Behind the `test-util` feature.
+
+use super::{
+    solver::{Limiter, PerturbationLimits},
+    ComponentState, ComponentValue, NetStamps, NetState, Scalar,
+};
+
+/// How many `purturb_from_nets` `ComponentFixture::settle` runs at most.
+pub const MAX_SETTLE_ITERATIONS: usize = 10_000;
+
+/// The component of value `V` with terminal `k` on net `k`, see the module docs. The nets carry
+/// no excess current, the ideal sources holding them taking whatever the component drives, so
+/// the component's currents relax to those of its model at the voltages set.
+#[derive(Debug)]
+pub struct ComponentFixture<S: Scalar, V: ComponentValue<S>> {
+    value: V,
+    state: V::State,
+    nets: Vec<NetState<S>>,
+    stamps: NetStamps<S>,
+    pub limits: PerturbationLimits<S>,
+}
+impl<S: Scalar, V: ComponentValue<S>> ComponentFixture<S, V> {
+    /// `value` with every terminal at 0 V.
+    pub fn new(value: V) -> Self {
+        let n = value.n_terminals();
+        Self {
+            value,
+            state: value.create(&(0..n).collect::<Vec<_>>()),
+            nets: (0..n).map(|_| NetState::new_empty()).collect(),
+            stamps: NetStamps::default(),
+            limits: PerturbationLimits::default(),
+        }
+    }
+
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+    pub fn state(&self) -> &V::State {
+        &self.state
+    }
+    pub fn state_mut(&mut self) -> &mut V::State {
+        &mut self.state
+    }
+    /// The nets of the terminals, in the order of `ComponentValue::terminal_names`.
+    pub fn nets(&self) -> &[NetState<S>] {
+        &self.nets
+    }
+
+    /// Hold the terminals at `voltages`, in the order of `ComponentValue::terminal_names`.
+    pub fn set_voltages(&mut self, voltages: &[S]) -> &mut Self {
+        assert_eq!(
+            voltages.len(),
+            self.nets.len(),
+            "need one voltage per terminal."
+        );
+        for (net, &voltage) in self.nets.iter_mut().zip(voltages) {
+            net.voltage = voltage;
+        }
+        self
+    }
+
+    /// One `purturb_from_nets` at the voltages set, whether it converged.
+    pub fn purturb(&mut self) -> bool {
+        let mut limiter = Limiter::new(self.limits);
+        self.state.purturb_from_nets(&self.nets, &mut limiter)
+    }
+    /// `purturb` until it converges, the number of iterations it took; `None` if it didn't
+    /// within `MAX_SETTLE_ITERATIONS`.
+    pub fn settle(&mut self) -> Option<usize> {
+        (1..=MAX_SETTLE_ITERATIONS).find(|_| self.purturb())
+    }
+    /// `tick` the state over `dt` from `t`.
+    pub fn tick(&mut self, t: S, dt: S) -> &mut Self {
+        self.state.tick(t, dt);
+        self
+    }
+
+    /// The current (`order` 0) or its derivative (`order` 1) the component drives out of each
+    /// terminal into its net, as `impart_currents_to_nets` stamps it.
+    pub fn terminal_currents(&mut self, order: usize) -> Vec<S> {
+        self.state.impart_currents_to_nets(&mut self.stamps);
+        let mut currents = vec![S::from(0); self.nets.len()];
+        for (net, stamped_order, current) in self.stamps.currents.drain(..) {
+            if stamped_order == order {
+                currents[net] += current;
+            }
+        }
+        self.stamps.voltages.clear();
+        currents
+    }
+    /// The voltage `impart_voltage_to_nets` proposes for each terminal at relaxation `step`, the
+    /// weighted average of its proposals; `None` for a terminal it doesn't drive.
+    pub fn proposed_voltages(&mut self, step: S) -> Vec<Option<S>> {
+        self.state
+            .impart_voltage_to_nets(&self.nets, step, &mut self.stamps);
+        let mut sums = vec![(S::from(0), S::from(0)); self.nets.len()];
+        for (net, voltage, weight) in self.stamps.voltages.drain(..) {
+            sums[net].0 += voltage * weight;
+            sums[net].1 += weight;
+        }
+        self.stamps.currents.clear();
+        sums.into_iter()
+            .map(|(sum, weight)| (weight != S::from(0)).then(|| sum / weight))
+            .collect()
+    }
+
+    /// `terminal_currents(0)` after settling at each set of `voltages` in turn, starting each
+    /// from where the last one settled; `None` for the points that didn't settle.
+    pub fn curve<'a>(
+        &mut self,
+        voltages: impl IntoIterator<Item = &'a [S]>,
+    ) -> Vec<Option<Vec<S>>> {
+        voltages
+            .into_iter()
+            .map(|voltages| {
+                self.set_voltages(voltages);
+                self.settle()?;
+                Some(self.terminal_currents(0))
+            })
+            .collect()
+    }
+}
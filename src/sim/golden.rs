@@ -0,0 +1,195 @@
+//! Golden-waveform regression checks: a recording is compared channel by channel against a CSV
+//! of an earlier run (as written by `Recording::write_csv`), or rewrites it when the environment
+//! has `UPDATE_GOLDEN=1`.
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    f,
+    probe::Recording,
+    waveform::{compare, Comparison, Tolerance},
+};
+
+/// The columns of a CSV written by `Recording::write_csv`.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub labels: Vec<String>,
+    pub time: Vec<f>,
+    /// One `Vec` per label.
+    pub channels: Vec<Vec<f>>,
+}
+impl Table {
+    pub fn read_csv(text: &str) -> Result<Self, GoldenError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let err = |line, message: String| GoldenError::Csv { line, message };
+        let (_, header) = lines.next().ok_or_else(|| err(1, "empty file".into()))?;
+        let mut columns = header.split(',');
+        if columns.next() != Some("time") {
+            return Err(err(1, "expected a `time` column first".into()));
+        }
+        let labels = columns.map(String::from).collect::<Vec<_>>();
+        let mut table = Self {
+            channels: vec![Vec::new(); labels.len()],
+            labels,
+            time: Vec::new(),
+        };
+        for (line_i, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let values = line
+                .split(',')
+                .map(|v| v.trim().parse::<f>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| err(line_i, e.to_string()))?;
+            if values.len() != table.labels.len() + 1 {
+                let message = format!(
+                    "{} values for {} columns",
+                    values.len(),
+                    1 + table.labels.len()
+                );
+                return Err(err(line_i, message));
+            }
+            table.time.push(values[0]);
+            for (channel, value) in table.channels.iter_mut().zip(&values[1..]) {
+                channel.push(*value);
+            }
+        }
+        Ok(table)
+    }
+
+    pub fn channel(&self, label: &str) -> Option<&[f]> {
+        let i = self.labels.iter().position(|l| l == label)?;
+        Some(&self.channels[i])
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(io::Error),
+    /// The golden file could not be read, at this (1-based) line.
+    Csv {
+        line: usize,
+        message: String,
+    },
+    /// There is no golden file at this path (run with `UPDATE_GOLDEN=1` to create it).
+    Missing(PathBuf),
+    /// The recording has a channel the golden file lacks, or the other way around.
+    Channels {
+        recorded: Vec<String>,
+        golden: Vec<String>,
+    },
+    /// At least one channel left its tolerance band; the one deviating the most.
+    Mismatch {
+        channel: String,
+        comparison: Comparison,
+    },
+}
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Csv { line, message } => write!(f, "golden file line {line}: {message}"),
+            Self::Missing(path) => write!(
+                f,
+                "no golden file {} (run with UPDATE_GOLDEN=1 to create it)",
+                path.display()
+            ),
+            Self::Channels { recorded, golden } => write!(
+                f,
+                "recorded channels [{}] but the golden file has [{}]",
+                recorded.join(", "),
+                golden.join(", ")
+            ),
+            Self::Mismatch {
+                channel,
+                comparison,
+            } => write!(
+                f,
+                "{channel} deviates from the golden waveform by up to {:.3e} at t = {:.3e} s",
+                comparison.max_deviation, comparison.at
+            ),
+        }
+    }
+}
+impl Error for GoldenError {}
+impl From<io::Error> for GoldenError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// What `check` did.
+#[derive(Debug, Clone)]
+pub enum GoldenOutcome {
+    /// Every channel was within its band, with the comparison of each (in recording order).
+    Matched(Vec<(String, Comparison)>),
+    /// `UPDATE_GOLDEN=1`: the golden file was (re)written from the recording.
+    Updated(PathBuf),
+}
+
+/// Compare every channel of `recording` against `golden`, interpolated onto the recording's times,
+/// within `tolerance(label)`.
+pub fn compare_recording(
+    golden: &Table,
+    recording: &Recording,
+    tolerance: impl Fn(&str) -> Tolerance,
+) -> Result<Vec<(String, Comparison)>, GoldenError> {
+    let recorded = recording.labels().map(String::from).collect::<Vec<_>>();
+    if recorded != golden.labels {
+        return Err(GoldenError::Channels {
+            recorded,
+            golden: golden.labels.clone(),
+        });
+    }
+    let comparisons = recorded
+        .into_iter()
+        .zip(&recording.channels)
+        .map(|(label, values)| {
+            let reference = golden.channel(&label).unwrap();
+            let c = compare(
+                &recording.time,
+                values,
+                &golden.time,
+                reference,
+                tolerance(&label),
+            );
+            (label, c)
+        })
+        .collect::<Vec<_>>();
+    let worst = comparisons
+        .iter()
+        .filter(|(_, c)| !c.within)
+        .max_by(|(_, a), (_, b)| a.max_deviation.total_cmp(&b.max_deviation));
+    if let Some((channel, comparison)) = worst {
+        return Err(GoldenError::Mismatch {
+            channel: channel.clone(),
+            comparison: *comparison,
+        });
+    }
+    Ok(comparisons)
+}
+
+/// Check `recording` against `dir/<name>.csv`, or write that file instead if `UPDATE_GOLDEN=1`.
+pub fn check(
+    dir: &Path,
+    name: &str,
+    recording: &Recording,
+    tolerance: impl Fn(&str) -> Tolerance,
+) -> Result<GoldenOutcome, GoldenError> {
+    let path = dir.join(format!("{name}.csv"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some_and(|v| v == "1") {
+        fs::create_dir_all(dir)?;
+        recording.write_csv(io::BufWriter::new(fs::File::create(&path)?))?;
+        return Ok(GoldenOutcome::Updated(path));
+    }
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(GoldenError::Missing(path))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    compare_recording(&Table::read_csv(&text)?, recording, tolerance).map(GoldenOutcome::Matched)
+}
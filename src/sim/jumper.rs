@@ -0,0 +1,173 @@
+//! Jumpers (`LinearComponentValue::Jumper`), the permanent ideal connections of a schematic kept
+//! readable (star points, sense taps, a net drawn as two), and `CircuitBuilder::build_collapsed`,
+//! which merges the nets each one joins before the circuit is solved so the solver doesn't spend
+//! a net and a stiff branch on every one. `CircuitState::stats` tells what it saved.
+
+use super::{
+    builder::{CircuitBuilder, NameMap},
+    components::LinearComponentValue,
+    error::SimError,
+    operating_limits::{LimitedQuantity, OperatingLimit},
+    CircuitState, ComponentId, ComponentRef, ComponentValueEnum, NetId, Scalar,
+};
+
+/// The size of a circuit as the solver sees it, see `CircuitState::stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub n_nets: usize,
+    pub n_components: usize,
+    /// Components of each pool.
+    pub n_linear: usize,
+    pub n_mosfets: usize,
+    pub n_noise_sources: usize,
+    pub n_gate_drivers: usize,
+    pub n_comparators: usize,
+    pub n_converters: usize,
+    /// The `Jumper`s among the linear components.
+    pub n_jumpers: usize,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    pub fn stats(&self) -> CircuitStats {
+        CircuitStats {
+            n_nets: self.n_nets(),
+            n_components: self.n_components(),
+            n_linear: self.pools.linear.len(),
+            n_mosfets: self.pools.mosfet.len(),
+            n_noise_sources: self.pools.noise_source.len(),
+            n_gate_drivers: self.pools.gate_driver.len(),
+            n_comparators: self.pools.comparator.len(),
+            n_converters: self.pools.converter.len(),
+            n_jumpers: self
+                .pools
+                .linear
+                .iter()
+                .filter(|state| matches!(state.value, LinearComponentValue::Jumper))
+                .count(),
+        }
+    }
+}
+
+impl<S: Scalar> CircuitBuilder<S> {
+    /// A jumper between `a` and `b`, see `LinearComponentValue::Jumper`.
+    pub fn jumper(&mut self, name: &str, a: &str, b: &str) -> Result<&mut Self, SimError> {
+        self.component(
+            name,
+            ComponentValueEnum::Linear(LinearComponentValue::Jumper),
+            &[a, b],
+        )
+    }
+
+    /// `build`, with the two nets of every jumper merged into one and the jumper left out, but
+    /// for the jumpers named in `keep`, whose current is wanted: those stay zero-volt branches.
+    /// A merged net keeps the lowest id of the nets it is made of (so net 0 stays net 0) and
+    /// all their names; the others are renumbered in order, and so are the components left.
+    ///
+    /// The circuit is made afresh from the values of the components, as an instance is (see
+    /// `instance`), with the solver configuration, strategy, environment, signal bindings and
+    /// operating limits of the one built (but for current limits on the jumpers merged). Names
+    /// in `keep` that aren't jumpers are ignored.
+    pub fn build_collapsed(&mut self, keep: &[&str]) -> (CircuitState<S>, NameMap) {
+        let (mut circuit, names) = self.build();
+        let collapsed = (0..circuit.n_components())
+            .map(|component| match circuit.component(component) {
+                ComponentRef::Linear(state) => {
+                    matches!(state.value, LinearComponentValue::Jumper)
+                        && circuit
+                            .component_name(component)
+                            .is_none_or(|name| !keep.contains(&name))
+                }
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+
+        // union-find over the nets, the root of a set its lowest net.
+        let mut parent = (0..circuit.n_nets()).collect::<Vec<NetId>>();
+        fn root(parent: &mut [NetId], mut net: NetId) -> NetId {
+            while parent[net] != net {
+                parent[net] = parent[parent[net]];
+                net = parent[net];
+            }
+            net
+        }
+        for (component, _) in collapsed.iter().enumerate().filter(|(_, &c)| c) {
+            let nets = circuit.component(component).as_dyn().nets();
+            let (a, b) = (root(&mut parent, nets[0]), root(&mut parent, nets[1]));
+            parent[a.max(b)] = a.min(b);
+        }
+        let mut net_map = vec![0; parent.len()];
+        let mut collapsed_circuit = CircuitState::new_empty();
+        for net in 0..parent.len() {
+            let root = root(&mut parent, net);
+            net_map[net] = if root == net {
+                collapsed_circuit.create_net()
+            } else {
+                net_map[root]
+            };
+            collapsed_circuit.copy_net_aliases(&circuit, net, net_map[net]);
+        }
+
+        let mut component_map: Vec<Option<ComponentId>> = vec![None; collapsed.len()];
+        for (component, state) in circuit.components() {
+            if collapsed[component] {
+                continue;
+            }
+            let connected = state
+                .as_dyn()
+                .nets()
+                .iter()
+                .map(|&net| net_map[net])
+                .collect::<Vec<_>>();
+            let value = state.value();
+            component_map[component] = Some(
+                match circuit.component_name(component) {
+                    Some(name) => collapsed_circuit.create_component_named(value, &connected, name),
+                    None => Ok(collapsed_circuit.create_component(value, &connected)),
+                }
+                .expect("the components were valid in the circuit built"),
+            );
+        }
+        for signal in circuit.signal_bus().signals() {
+            for binding in &signal.bindings {
+                if let Some(component) = component_map[binding.component] {
+                    collapsed_circuit
+                        .bind_signal(&signal.name, component, binding.gain, binding.offset)
+                        .expect("the bindings were valid in the circuit built");
+                }
+            }
+        }
+        for limit in circuit.operating_limits() {
+            let quantity = match limit.quantity {
+                LimitedQuantity::NetVoltage { net, reference } => LimitedQuantity::NetVoltage {
+                    net: net_map[net],
+                    reference: net_map[reference],
+                },
+                LimitedQuantity::ComponentCurrent(component) => match component_map[component] {
+                    Some(component) => LimitedQuantity::ComponentCurrent(component),
+                    None => continue,
+                },
+            };
+            collapsed_circuit
+                .add_operating_limit(OperatingLimit { quantity, ..*limit })
+                .expect("the limits were valid in the circuit built");
+        }
+        collapsed_circuit.set_abort_on_violation(circuit.abort_on_violation());
+        collapsed_circuit.solver = circuit.solver;
+        collapsed_circuit.environment = circuit.environment.clone();
+        std::mem::swap(&mut collapsed_circuit.strategy, &mut circuit.strategy);
+
+        let names = NameMap {
+            nets: names
+                .nets
+                .into_iter()
+                .map(|(name, net)| (name, net_map[net]))
+                .collect(),
+            components: names
+                .components
+                .into_iter()
+                .filter_map(|(name, component)| Some((name, component_map[component]?)))
+                .collect(),
+        };
+        (collapsed_circuit, names)
+    }
+}
@@ -0,0 +1,456 @@
+//! Everything needed to reproduce a run, to share with its results (feature `serde`): the
+//! circuit as it was before the run (its components, solver configuration, scheduled faults and
+//! signals, and the PRNG state of every noise source, as a checkpoint holds it), the run
+//! configuration and the version of the simulator, with a hash over all of them and a fingerprint
+//! of every waveform of the run. Written as JSON next to an exported recording (`results.csv`
+//! and `results.manifest.json`, see `RunManifest::path_beside`).
+//!
+//! `RunManifest::verify_against` re-runs a circuit and reports the inputs that differ from the
+//! manifest's, field by field, and the waveforms that don't match within a tolerance.
+//!
+//! The `SolveStrategy` of a circuit is not part of it, as for checkpoints.
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    error::SimError,
+    f,
+    probe::{Probe, Quantity, Recording},
+    run::{run_with_progress, RunConfig},
+    CircuitState, ComponentId, ComponentRef,
+};
+
+/// Version of the manifest format; `RunManifest::read` refuses any other.
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(io::Error),
+    /// The file is not a manifest, or the circuit can't be written (a value that isn't finite).
+    Format(String),
+    /// A manifest of another format version.
+    Version {
+        found: u32,
+        expected: u32,
+    },
+    Sim(SimError),
+}
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Format(message) => write!(f, "run manifest: {message}"),
+            Self::Version { found, expected } => write!(
+                f,
+                "manifest format version {found}, this simulator reads version {expected}"
+            ),
+            Self::Sim(err) => write!(f, "{err}"),
+        }
+    }
+}
+impl Error for ManifestError {}
+impl From<io::Error> for ManifestError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+impl From<SimError> for ManifestError {
+    fn from(err: SimError) -> Self {
+        Self::Sim(err)
+    }
+}
+impl From<serde_json::Error> for ManifestError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Format(err.to_string())
+    }
+}
+
+/// What a run recorded of one net voltage (`net:<net label>`, to net 0) or component current
+/// (`<component>.current`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaveformFingerprint {
+    pub label: String,
+    pub n_samples: usize,
+    /// FNV-1a over the bits of the samples, equal only for bit-identical waveforms.
+    pub hash: u64,
+    pub min: f,
+    pub max: f,
+    pub mean: f,
+    pub rms: f,
+}
+impl WaveformFingerprint {
+    fn new(label: &str, samples: &[f]) -> Self {
+        let n = samples.len() as f;
+        Self {
+            label: label.to_string(),
+            n_samples: samples.len(),
+            hash: samples
+                .iter()
+                .fold(Fnv::new(), |hash, sample| {
+                    hash.write(&sample.to_bits().to_le_bytes())
+                })
+                .0,
+            min: samples.iter().copied().fold(f::INFINITY, f::min),
+            max: samples.iter().copied().fold(f::NEG_INFINITY, f::max),
+            mean: samples.iter().sum::<f>() / n,
+            rms: (samples.iter().map(|x| x * x).sum::<f>() / n).sqrt(),
+        }
+    }
+    /// The statistic of `self` furthest from that of `other` relative to the largest magnitude
+    /// `self` reached, as `(name, self's, other's)`, if any is further than `tolerance`.
+    fn mismatch(&self, other: &Self, tolerance: f) -> Option<(&'static str, f, f)> {
+        let scale = self.min.abs().max(self.max.abs()).max(f::MIN_POSITIVE);
+        [
+            ("min", self.min, other.min),
+            ("max", self.max, other.max),
+            ("mean", self.mean, other.mean),
+            ("rms", self.rms, other.rms),
+        ]
+        .into_iter()
+        .map(|(name, a, b)| (name, a, b, (a - b).abs() / scale))
+        .filter(|&(.., deviation)| deviation > tolerance || deviation.is_nan())
+        .max_by(|a, b| a.3.total_cmp(&b.3))
+        .map(|(name, a, b, _)| (name, a, b))
+    }
+}
+
+/// See the module docs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub version: u32,
+    /// The version of this crate that captured the manifest.
+    pub crate_version: String,
+    pub circuit: CircuitState,
+    pub run: RunConfig,
+    /// FNV-1a over `crate_version`, `circuit` and `run`, as JSON with the keys sorted.
+    pub hash: u64,
+    /// Every net voltage then every component current, in id order.
+    pub waveforms: Vec<WaveformFingerprint>,
+}
+
+/// An input of a run that differs from the manifest's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// Where it is: `crate_version`, `run.<field>`, `circuit.<field>...` or
+    /// `component <label>.<field>...` (as `CircuitState` and the component states serialize).
+    pub path: String,
+    /// The value in the manifest and the one found, as JSON.
+    pub expected: String,
+    pub found: String,
+}
+
+/// A waveform of the re-run that doesn't match the manifest's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveformMismatch {
+    pub label: String,
+    /// The statistic furthest off (`min`, `max`, `mean` or `rms`), or `samples` if the number
+    /// of samples differs, or `missing` if the re-run has no such waveform.
+    pub statistic: &'static str,
+    pub expected: f,
+    pub found: f,
+}
+
+/// The result of `RunManifest::verify_against`.
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub changes: Vec<FieldChange>,
+    pub mismatches: Vec<WaveformMismatch>,
+    /// Whether every waveform is bit-identical to the manifest's.
+    pub exact: bool,
+}
+impl Verification {
+    /// Whether the inputs are the manifest's and every waveform matches.
+    pub fn passed(&self) -> bool {
+        self.changes.is_empty() && self.mismatches.is_empty()
+    }
+}
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passed() {
+            return write!(
+                f,
+                "reproduced{}",
+                if self.exact {
+                    " bit for bit"
+                } else {
+                    " within tolerance"
+                }
+            );
+        }
+        write!(f, "not reproduced")?;
+        for change in &self.changes {
+            write!(
+                f,
+                "\n  {}: {} in the manifest, {} now",
+                change.path, change.expected, change.found
+            )?;
+        }
+        for mismatch in &self.mismatches {
+            write!(
+                f,
+                "\n  waveform {}: {} {:e} in the manifest, {:e} now",
+                mismatch.label, mismatch.statistic, mismatch.expected, mismatch.found
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl RunManifest {
+    /// The manifest of running `circuit` as it is now with `config`, which this runs on a copy
+    /// of it (through `run::run_with_progress`) to fingerprint the waveforms.
+    pub fn capture(circuit: &CircuitState, config: &RunConfig) -> Result<Self, ManifestError> {
+        let crate_version = env!("CARGO_PKG_VERSION").to_string();
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            hash: inputs_hash(&crate_version, circuit, config)?,
+            crate_version,
+            circuit: circuit.clone(),
+            run: *config,
+            waveforms: fingerprint_run(circuit, config)?,
+        })
+    }
+
+    /// Re-run `circuit` with `config` and compare it with the manifest: every input that differs,
+    /// and every waveform whose min, max, mean or RMS differs by more than `tolerance` of the
+    /// largest magnitude the manifest's reached.
+    pub fn verify_against(
+        &self,
+        circuit: &CircuitState,
+        config: &RunConfig,
+        tolerance: f,
+    ) -> Result<Verification, ManifestError> {
+        let mut changes = Vec::new();
+        let crate_version = env!("CARGO_PKG_VERSION");
+        if self.crate_version != crate_version {
+            changes.push(FieldChange {
+                path: "crate_version".to_string(),
+                expected: self.crate_version.clone(),
+                found: crate_version.to_string(),
+            });
+        }
+        if inputs_hash(crate_version, circuit, config)? != self.hash {
+            diff_values(
+                "run",
+                &serde_json::to_value(self.run)?,
+                &serde_json::to_value(config)?,
+                &mut changes,
+            );
+            diff_circuits(&self.circuit, circuit, &mut changes)?;
+        }
+
+        let waveforms = fingerprint_run(circuit, config)?;
+        let mut mismatches = Vec::new();
+        let mut exact = waveforms.len() == self.waveforms.len();
+        for expected in &self.waveforms {
+            let Some(found) = waveforms.iter().find(|found| found.label == expected.label) else {
+                mismatches.push(WaveformMismatch {
+                    label: expected.label.clone(),
+                    statistic: "missing",
+                    expected: expected.n_samples as f,
+                    found: 0.0,
+                });
+                exact = false;
+                continue;
+            };
+            exact &= found.hash == expected.hash;
+            if found.n_samples != expected.n_samples {
+                mismatches.push(WaveformMismatch {
+                    label: expected.label.clone(),
+                    statistic: "samples",
+                    expected: expected.n_samples as f,
+                    found: found.n_samples as f,
+                });
+            } else if let Some((statistic, expected_value, found_value)) =
+                expected.mismatch(found, tolerance)
+            {
+                mismatches.push(WaveformMismatch {
+                    label: expected.label.clone(),
+                    statistic,
+                    expected: expected_value,
+                    found: found_value,
+                });
+            }
+        }
+        Ok(Verification {
+            changes,
+            mismatches,
+            exact,
+        })
+    }
+
+    /// `<export without its extension>.manifest.json`, the manifest of the recording exported to
+    /// `export`.
+    pub fn path_beside(export: impl AsRef<Path>) -> PathBuf {
+        export.as_ref().with_extension("manifest.json")
+    }
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), ManifestError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ManifestError> {
+        let json = fs::read_to_string(path)?;
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+        let header = serde_json::from_str::<Header>(&json)?;
+        if header.version != MANIFEST_VERSION {
+            return Err(ManifestError::Version {
+                found: header.version,
+                expected: MANIFEST_VERSION,
+            });
+        }
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// 64-bit FNV-1a, which is stable across platforms and releases unlike `std`'s hashers.
+struct Fnv(u64);
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+    fn write(self, bytes: &[u8]) -> Self {
+        Self(bytes.iter().fold(self.0, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        }))
+    }
+}
+
+fn inputs_hash(
+    crate_version: &str,
+    circuit: &CircuitState,
+    config: &RunConfig,
+) -> Result<u64, ManifestError> {
+    // through `Value`, whose maps are sorted, so the name map hashes the same in any order.
+    let inputs = serde_json::to_value((crate_version, circuit, config))?;
+    Ok(Fnv::new().write(inputs.to_string().as_bytes()).0)
+}
+
+/// Run a copy of `circuit` with `config`, recording every net voltage and component current.
+fn fingerprint_run(
+    circuit: &CircuitState,
+    config: &RunConfig,
+) -> Result<Vec<WaveformFingerprint>, SimError> {
+    let mut circuit = circuit.clone();
+    let probes = (0..circuit.n_nets())
+        .map(|net| (format!("net:{}", circuit.net_label(net)), Probe::Net(net)))
+        .chain((0..circuit.n_components()).map(|component| {
+            (
+                format!("{}.current", circuit.component_label(component)),
+                Probe::Component(component, Quantity::Current),
+            )
+        }))
+        .collect();
+    let config = RunConfig {
+        progress_every: 0,
+        ..*config
+    };
+    let outcome = run_with_progress(
+        &mut circuit,
+        Recording::new(probes),
+        config,
+        &AtomicBool::new(false),
+        |_| {},
+    )?;
+    let recording = outcome.recording;
+    Ok(recording
+        .labels()
+        .zip(&recording.channels)
+        .map(|(label, samples)| WaveformFingerprint::new(label, samples))
+        .collect())
+}
+
+/// The changes from `expected` to `found`: component by component, labelled by their names in
+/// `found`, then the rest of the circuit.
+fn diff_circuits(
+    expected: &CircuitState,
+    found: &CircuitState,
+    changes: &mut Vec<FieldChange>,
+) -> Result<(), ManifestError> {
+    let n_components = expected.n_components().min(found.n_components());
+    for component in 0..n_components {
+        diff_values(
+            &format!("component {}", found.component_label(component)),
+            &component_value(expected, component)?,
+            &component_value(found, component)?,
+            changes,
+        );
+    }
+    if expected.n_components() != found.n_components() {
+        changes.push(FieldChange {
+            path: "circuit.components".to_string(),
+            expected: expected.n_components().to_string(),
+            found: found.n_components().to_string(),
+        });
+    }
+    let [mut expected, mut found] = [expected, found].map(serde_json::to_value);
+    for value in [&mut expected, &mut found] {
+        if let Ok(Value::Object(fields)) = value {
+            fields.remove("pools");
+        }
+    }
+    diff_values("circuit", &expected?, &found?, changes);
+    Ok(())
+}
+
+fn component_value(
+    circuit: &CircuitState,
+    component: ComponentId,
+) -> Result<Value, serde_json::Error> {
+    match circuit.component(component) {
+        ComponentRef::Linear(v) => serde_json::to_value(v),
+        ComponentRef::MOSFET(v) => serde_json::to_value(v),
+        ComponentRef::NoiseSource(v) => serde_json::to_value(v),
+        ComponentRef::GateDriver(v) => serde_json::to_value(v),
+        ComponentRef::Comparator(v) => serde_json::to_value(v),
+        ComponentRef::Converter(v) => serde_json::to_value(v),
+    }
+}
+
+/// Push the leaves in which `found` differs from `expected` to `changes`, their paths under
+/// `path`.
+fn diff_values(path: &str, expected: &Value, found: &Value, changes: &mut Vec<FieldChange>) {
+    match (expected, found) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                let path = format!("{path}.{key}");
+                match b.get(key) {
+                    Some(other) => diff_values(&path, value, other, changes),
+                    None => changes.push(FieldChange {
+                        path,
+                        expected: value.to_string(),
+                        found: "nothing".to_string(),
+                    }),
+                }
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                changes.push(FieldChange {
+                    path: format!("{path}.{key}"),
+                    expected: "nothing".to_string(),
+                    found: value.to_string(),
+                });
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (value, other)) in a.iter().zip(b).enumerate() {
+                diff_values(&format!("{path}[{i}]"), value, other, changes);
+            }
+        }
+        _ if expected != found => changes.push(FieldChange {
+            path: path.to_string(),
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }),
+        _ => {}
+    }
+}
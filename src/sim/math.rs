@@ -0,0 +1,241 @@
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use super::{f, Scalar};
+use crate::linalg::{Field, RealField};
+
+/// Linear interpolation from `a` (at `t = 0`) to `b` (at `t = 1`), extrapolating outside
+/// `[0, 1]`.
+pub fn lerp<S: Scalar>(a: S, b: S, t: S) -> S {
+    a * (S::from(1) - t) + b * t
+}
+
+/// `x` limited to `[min, max]`; `min` wins if the bounds cross.
+pub fn clamp<S: Scalar>(x: S, min: S, max: S) -> S {
+    if x < min {
+        min
+    } else if x > max {
+        max
+    } else {
+        x
+    }
+}
+
+/// 0 up to `edge0`, 1 from `edge1` and the cubic `3 t^2 - 2 t^3` in `t = (x - edge0) / (edge1 -
+/// edge0)` between, with zero slope at both edges.
+pub fn smoothstep<S: Scalar>(edge0: S, edge1: S, x: S) -> S {
+    let t = clamp((x - edge0) / (edge1 - edge0), S::from(0), S::from(1));
+    t * t * (S::from(3) - S::from(2) * t)
+}
+
+/// `sign(x) sqrt(|x|)`, the inverse of `x |x|`.
+pub fn signed_sqrt<S: Scalar>(x: S) -> S {
+    if x < S::from(0) {
+        -(-x).sqrt()
+    } else {
+        x.sqrt()
+    }
+}
+
+/// `x / sqrt(1 + (x / limit)^2)`: close to `x` for `|x|` well below `limit`, approaching `±limit`
+/// smoothly (and monotonically) beyond it. `limit` must be positive.
+pub fn soft_limit<S: Scalar>(x: S, limit: S) -> S {
+    let r = x / limit;
+    x / (S::from(1) + r * r).sqrt()
+}
+
+/// `a b / (a^m + b^m)^(1/m)` for `a, b >= 0`: a soft minimum that is sharper for larger `m`,
+/// evaluated without overflowing for large `m`.
+pub fn soft_min<S: Scalar>(a: S, b: S, m: S) -> S {
+    let (lo, hi) = if a < b { (a, b) } else { (b, a) };
+    lo / (S::from(1) + (lo / hi).powf(m)).powf(S::from(1) / m)
+}
+
+/// A dual number `value + derivative ε` with `ε² = 0`: arithmetic on it carries the derivative
+/// of every result with respect to whatever the inputs were seeded by along with the value, so
+/// a circuit simulated in `Dual` gives exact derivatives instead of finite differences.
+/// Comparisons look at the value only, so a dual takes the branches its value would.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dual {
+    pub value: f,
+    pub derivative: f,
+}
+impl Dual {
+    pub fn new(value: f, derivative: f) -> Self {
+        Self { value, derivative }
+    }
+    /// `value` as the variable to differentiate by, of derivative 1.
+    pub fn variable(value: f) -> Self {
+        Self::new(value, 1.0)
+    }
+    pub fn constant(value: f) -> Self {
+        Self::new(value, 0.0)
+    }
+    /// `f(value)` with derivative `f'(value)` by the chain rule; a constant stays constant even
+    /// where `f'` is infinite, as `sqrt` at 0.
+    fn chain(self, value: f, slope: f) -> Self {
+        Self::new(value, self.derivative_term(slope))
+    }
+    /// `slope * derivative`, 0 for a constant.
+    fn derivative_term(self, slope: f) -> f {
+        if self.derivative == 0.0 {
+            0.0
+        } else {
+            slope * self.derivative
+        }
+    }
+}
+impl PartialEq for Dual {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+impl PartialOrd for Dual {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+impl From<i16> for Dual {
+    fn from(value: i16) -> Self {
+        Self::constant(value.into())
+    }
+}
+impl From<f32> for Dual {
+    fn from(value: f32) -> Self {
+        Self::constant(value.into())
+    }
+}
+impl Add for Dual {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.value + rhs.value, self.derivative + rhs.derivative)
+    }
+}
+impl Sub for Dual {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.value - rhs.value, self.derivative - rhs.derivative)
+    }
+}
+impl Mul for Dual {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.value * rhs.value,
+            self.derivative * rhs.value + self.value * rhs.derivative,
+        )
+    }
+}
+impl Div for Dual {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        Self::new(
+            self.value / rhs.value,
+            (self.derivative * rhs.value - self.value * rhs.derivative) / (rhs.value * rhs.value),
+        )
+    }
+}
+impl Neg for Dual {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.value, -self.derivative)
+    }
+}
+impl AddAssign for Dual {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl SubAssign for Dual {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl MulAssign for Dual {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+impl DivAssign for Dual {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+impl Sum for Dual {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |a, b| a + b)
+    }
+}
+impl Field for Dual {
+    fn magnitude(self) -> f64 {
+        self.value.abs()
+    }
+}
+impl RealField for Dual {
+    fn sqrt(self) -> Self {
+        let root = self.value.sqrt();
+        self.chain(root, 0.5 / root)
+    }
+    fn abs(self) -> Self {
+        self.chain(self.value.abs(), self.value.signum())
+    }
+    fn sin(self) -> Self {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+    fn cos(self) -> Self {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+    fn atan2(self, x: Self) -> Self {
+        let r_sq = self.value * self.value + x.value * x.value;
+        Self::new(
+            self.value.atan2(x.value),
+            (x.value * self.derivative - self.value * x.derivative) / r_sq,
+        )
+    }
+}
+impl Scalar for Dual {
+    const CONVERGENCE_EPSILON: Self = Self {
+        value: f64::CONVERGENCE_EPSILON,
+        derivative: 0.0,
+    };
+    fn from_f64(v: f64) -> Self {
+        Self::constant(v)
+    }
+    fn to_f64(self) -> f64 {
+        self.value
+    }
+    fn exp(self) -> Self {
+        let exp = self.value.exp();
+        self.chain(exp, exp)
+    }
+    fn ln(self) -> Self {
+        self.chain(self.value.ln(), 1.0 / self.value)
+    }
+    fn min(self, other: Self) -> Self {
+        if other.value < self.value {
+            other
+        } else {
+            self
+        }
+    }
+    fn max(self, other: Self) -> Self {
+        if other.value > self.value {
+            other
+        } else {
+            self
+        }
+    }
+    fn is_finite(self) -> bool {
+        self.value.is_finite() && self.derivative.is_finite()
+    }
+    fn powf(self, n: Self) -> Self {
+        let power = self.value.powf(n.value);
+        // `d(a^n) = n a^(n - 1) da + a^n ln(a) dn`, each term left out for a constant, so that a
+        // constant exponent takes bases of zero and below.
+        let base = self.derivative_term(n.value * self.value.powf(n.value - 1.0));
+        let exponent = n.derivative_term(power * self.value.ln());
+        Self::new(power, base + exponent)
+    }
+}
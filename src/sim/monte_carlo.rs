@@ -0,0 +1,163 @@
+use super::{
+    components::ComponentParameter,
+    f,
+    random::Rng,
+    simulation_set::SimulationSet,
+    sweep::{Sweep, SweepPoint},
+    CircuitState, ComponentId,
+};
+
+/// How a parameter deviates from its nominal value, relative to that value.
+#[derive(Debug, Clone, Copy)]
+pub enum ToleranceDistribution {
+    /// Uniform in `nominal * [1 - tolerance, 1 + tolerance]`.
+    Uniform { tolerance: f },
+    /// Normally distributed with standard deviation `nominal * sigma`.
+    Gaussian { sigma: f },
+}
+impl ToleranceDistribution {
+    fn sample_factor(&self, rng: &mut Rng) -> f {
+        match *self {
+            Self::Uniform { tolerance } => 1.0 + tolerance * (2.0 * rng.uniform() - 1.0),
+            Self::Gaussian { sigma } => 1.0 + sigma * rng.gaussian(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToleranceSpec {
+    pub component: ComponentId,
+    pub parameter: ComponentParameter,
+    pub distribution: ToleranceDistribution,
+}
+
+/// Runs a metric over many randomly perturbed copies of a circuit.
+#[derive(Debug, Clone)]
+pub struct MonteCarlo {
+    pub specs: Vec<ToleranceSpec>,
+    pub n_samples: usize,
+    pub seed: u64,
+    pub n_threads: usize,
+}
+impl MonteCarlo {
+    pub fn new(specs: Vec<ToleranceSpec>, n_samples: usize, seed: u64) -> Self {
+        Self {
+            specs,
+            n_samples,
+            seed,
+            n_threads: 1,
+        }
+    }
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads;
+        self
+    }
+
+    /// Sample the perturbation factors for every instance up front, so results only depend on
+    /// `seed` and not on how the work is split across threads.
+    fn sweep(&self) -> Sweep {
+        let mut rng = Rng::new(self.seed);
+        let points = (0..self.n_samples)
+            .map(|_| SweepPoint {
+                params: self
+                    .specs
+                    .iter()
+                    .enumerate()
+                    .map(|(spec_i, spec)| {
+                        (
+                            spec_i.to_string(),
+                            spec.distribution.sample_factor(&mut rng),
+                        )
+                    })
+                    .collect(),
+            })
+            .collect();
+        Sweep::from_points(points).with_threads(self.n_threads)
+    }
+
+    /// Evaluate `metric` on every perturbed copy of `base`: a circuit, or a `SimulationSet` whose
+    /// circuits all get the same factors (one sampled board against several loads).
+    pub fn run<T, E>(&self, base: &T, metric: E) -> MonteCarloResult
+    where
+        T: Perturb,
+        E: Fn(&mut T) -> f + Sync,
+    {
+        let result = self.sweep().run_mutating(
+            base,
+            |point, target| {
+                for (spec, (_, factor)) in self.specs.iter().zip(point.params.iter()) {
+                    target.scale_parameter(spec.component, spec.parameter, *factor);
+                }
+            },
+            |_, target| metric(target),
+        );
+        MonteCarloResult {
+            samples: result.metrics().copied().collect(),
+        }
+    }
+}
+
+/// What `MonteCarlo::run` perturbs.
+pub trait Perturb: Clone + Sync {
+    /// Multiply `parameter` of `component` by `factor`.
+    ///
+    /// Panics if the component doesn't have the parameter.
+    fn scale_parameter(&mut self, component: ComponentId, parameter: ComponentParameter, factor: f);
+}
+impl Perturb for CircuitState {
+    fn scale_parameter(
+        &mut self,
+        component: ComponentId,
+        parameter: ComponentParameter,
+        factor: f,
+    ) {
+        let value = self
+            .component_mut(component)
+            .parameter_mut(parameter)
+            .unwrap_or_else(|| panic!("component {component} has no parameter {parameter:?}"));
+        *value *= factor;
+    }
+}
+impl Perturb for SimulationSet {
+    /// In every circuit of the set.
+    fn scale_parameter(
+        &mut self,
+        component: ComponentId,
+        parameter: ComponentParameter,
+        factor: f,
+    ) {
+        for (_, circuit) in self.circuits_mut() {
+            circuit.scale_parameter(component, parameter, factor);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MonteCarloResult {
+    /// Metric value for each sampled instance, in sampling order.
+    pub samples: Vec<f>,
+}
+impl MonteCarloResult {
+    pub fn mean(&self) -> f {
+        self.samples.iter().sum::<f>() / self.samples.len() as f
+    }
+    /// Sample standard deviation.
+    pub fn std(&self) -> f {
+        let mean = self.mean();
+        let sum_sq = self
+            .samples
+            .iter()
+            .map(|v| (v - mean) * (v - mean))
+            .sum::<f>();
+        (sum_sq / (self.samples.len() as f - 1.0)).sqrt()
+    }
+    /// `p`-th percentile (`p` in `[0, 100]`), linearly interpolated between samples.
+    pub fn percentile(&self, p: f) -> f {
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let x = (p / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f;
+        let i = x.floor() as usize;
+        let j = (i + 1).min(sorted.len() - 1);
+        sorted[i] + (sorted[j] - sorted[i]) * (x - i as f)
+    }
+}
@@ -0,0 +1,120 @@
+//! Names of nets that stay with them as a circuit is edited, unlike their ids: any number of
+//! aliases per net (`vbus`, `C3.plus`), each naming one net of the circuit, saved with it and
+//! preferred over the id wherever a net is reported (`CircuitState::net_label`). `CircuitBuilder`
+//! gives every net it names its name as an alias.
+
+use std::collections::HashMap;
+
+use super::{
+    builder::NameMap,
+    error::{Location, SimError},
+    CircuitState, NetId, Scalar,
+};
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct NetAliases {
+    /// The aliases of each net in the order they were added; nets past the end have none.
+    by_net: Vec<Vec<String>>,
+    by_alias: HashMap<String, NetId>,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Give `net` the alias `alias`, which it keeps if it already has it.
+    ///
+    /// Errors if the net doesn't exist or another net has the alias.
+    pub fn add_net_alias(&mut self, net: NetId, alias: &str) -> Result<(), SimError> {
+        if net >= self.nets.len() {
+            return Err(SimError::UnknownNetId(net));
+        }
+        let aliases = &mut self.net_aliases;
+        match aliases.by_alias.get(alias) {
+            Some(&other) if other == net => return Ok(()),
+            Some(&other) => {
+                return Err(SimError::DuplicateNetAlias {
+                    alias: alias.to_string(),
+                    net: other,
+                })
+            }
+            None => {}
+        }
+        if aliases.by_net.len() <= net {
+            aliases.by_net.resize(net + 1, Vec::new());
+        }
+        aliases.by_net[net].push(alias.to_string());
+        aliases.by_alias.insert(alias.to_string(), net);
+        Ok(())
+    }
+    /// Take the alias `alias` off its net, returning the net.
+    pub fn remove_net_alias(&mut self, alias: &str) -> Option<NetId> {
+        let net = self.net_aliases.by_alias.remove(alias)?;
+        self.net_aliases.by_net[net].retain(|a| a != alias);
+        Some(net)
+    }
+    /// Call the net aliased `alias` `new_alias` instead, in the same place among its aliases.
+    ///
+    /// Errors if no net has the alias `alias` or another net has `new_alias`.
+    pub fn rename_net_alias(&mut self, alias: &str, new_alias: &str) -> Result<(), SimError> {
+        let Some(&net) = self.net_aliases.by_alias.get(alias) else {
+            return Err(SimError::UnknownNet(alias.to_string()));
+        };
+        if let Some(&other) = self.net_aliases.by_alias.get(new_alias) {
+            if other == net && alias == new_alias {
+                return Ok(());
+            }
+            return Err(SimError::DuplicateNetAlias {
+                alias: new_alias.to_string(),
+                net: other,
+            });
+        }
+        let aliases = &mut self.net_aliases;
+        aliases.by_alias.remove(alias);
+        aliases.by_alias.insert(new_alias.to_string(), net);
+        for a in aliases.by_net[net].iter_mut().filter(|a| *a == alias) {
+            *a = new_alias.to_string();
+        }
+        Ok(())
+    }
+
+    /// The aliases of `net`, the first of them its `net_label`.
+    pub fn net_aliases(&self, net: NetId) -> &[String] {
+        self.net_aliases.by_net.get(net).map_or(&[], Vec::as_slice)
+    }
+    pub fn net_by_alias(&self, alias: &str) -> Option<NetId> {
+        self.net_aliases.by_alias.get(alias).copied()
+    }
+    /// The first alias of `net`, or its id for nets without, for reports: `net <label>`.
+    pub fn net_label(&self, net: NetId) -> String {
+        match self.net_aliases(net).first() {
+            Some(alias) => alias.clone(),
+            None => net.to_string(),
+        }
+    }
+    /// `net <net_label>` or `component <component_label>`.
+    pub fn location_label(&self, location: Location) -> String {
+        match location {
+            Location::Net(net) => format!("net {}", self.net_label(net)),
+            Location::Component(component) => {
+                format!("component {}", self.component_label(component))
+            }
+        }
+    }
+
+    /// Every alias of every net and the name of every named component, to read probes and the
+    /// like against a circuit that wasn't just built (one read back from a checkpoint).
+    pub fn name_map(&self) -> NameMap {
+        NameMap {
+            nets: self.net_aliases.by_alias.clone(),
+            components: self.components_by_name.clone(),
+        }
+    }
+
+    /// Give the net `into` every alias of the net `from` too, for circuits rebuilt with nets
+    /// merged.
+    pub(super) fn copy_net_aliases(&mut self, from: &Self, from_net: NetId, into: NetId) {
+        for alias in from.net_aliases(from_net) {
+            self.add_net_alias(into, alias)
+                .expect("each alias names one net of the circuit copied from");
+        }
+    }
+}
@@ -0,0 +1,439 @@
+use std::{collections::HashMap, fmt::Write};
+
+use super::{
+    builder::{CircuitBuilder, NameMap},
+    components::{
+        saturating_inductance, LinearComponentValue, MOSFETComponentValue, MOSFETDopingType,
+        PwmWave,
+    },
+    devices::DeviceLibrary,
+    error::SimError,
+    f,
+    units::parse_si,
+    CircuitState, ComponentId, ComponentRef, ComponentState, NetId, Scalar,
+};
+
+impl<S: Scalar> CircuitState<S> {
+    /// The circuit as a SPICE netlist (net 0 is ground), with capacitor voltages and inductor
+    /// currents as initial conditions (`.tran ... uic`). Nets are nodes `n<id>` (`0` for net 0),
+    /// their aliases listed in a comment at the top.
+    ///
+    /// Elements are named by the component name, prefixed with their type letter unless it already
+    /// starts with it, or by the type letter and id for unnamed components. Closed switches become
+    /// 0 V sources and open ones their off-resistance (left out if they have none), PWM sources
+    /// `PULSE` sources, noise sources
+    /// DC sources at their present sample and converters a DC source and current sink at their
+    /// present output voltage and input current; `offset_emf` is not exported.
+    pub fn to_spice_netlist(&self) -> String {
+        let net = |net: NetId| {
+            if net == 0 {
+                "0".to_string()
+            } else {
+                format!("n{net}")
+            }
+        };
+        let mut out = String::from("* esc_sim_test circuit\n");
+        for net_i in 0..self.nets.len() {
+            let aliases = self.net_aliases(net_i);
+            if !aliases.is_empty() {
+                writeln!(out, "* {}: {}", net(net_i), aliases.join(", ")).unwrap();
+            }
+        }
+        for (component_i, component) in self.components() {
+            let name = |letter| self.spice_name(component_i, letter);
+            match component {
+                ComponentRef::Linear(v) => {
+                    let [a, b] = [0, 1].map(|i| net(v.nets()[i]));
+                    let line = match v.value {
+                        LinearComponentValue::LossyCapacitive {
+                            capacitance,
+                            esr,
+                            esl,
+                            leakage_resistance,
+                        } => {
+                            // `a - ESR - ESL - C - b`, leaving out the parts that are absent.
+                            let name = name('C');
+                            let mut lines = String::new();
+                            let mut from = a;
+                            if esr > S::from(0) {
+                                let to = format!("{name}_r");
+                                writeln!(lines, "R{name} {from} {to} {:e}", esr.to_f64()).unwrap();
+                                from = to;
+                            }
+                            if esl > S::from(0) {
+                                let to = format!("{name}_l");
+                                let l = esl.to_f64();
+                                let ic = v.q[1].to_f64();
+                                writeln!(lines, "L{name} {from} {to} {l:e} IC={ic:e}").unwrap();
+                                from = to;
+                            }
+                            let ic = (-v.q[0] / capacitance).to_f64();
+                            let c = capacitance.to_f64();
+                            write!(lines, "{name} {b} {from} {c:e} IC={ic:e}").unwrap();
+                            if let Some(r) = leakage_resistance {
+                                let r = r.to_f64();
+                                write!(lines, "\nR{name}_leak {b} {from} {r:e}").unwrap();
+                            }
+                            lines
+                        }
+                        LinearComponentValue::Resistive(r) => {
+                            format!("{} {a} {b} {:e}", name('R'), r.to_f64())
+                        }
+                        LinearComponentValue::Capacitive(c) => {
+                            // `V(nets[1]) - V(nets[0]) = -q[0] / C`
+                            let ic = (-v.q[0] / c).to_f64();
+                            format!("{} {b} {a} {:e} IC={ic:e}", name('C'), c.to_f64())
+                        }
+                        LinearComponentValue::Inductive(l) => {
+                            format!(
+                                "{} {a} {b} {:e} IC={:e}",
+                                name('L'),
+                                l.to_f64(),
+                                v.q[1].to_f64()
+                            )
+                        }
+                        LinearComponentValue::SaturatingInductive {
+                            inductance,
+                            saturation_current,
+                            series_resistance,
+                        } => {
+                            // no core model: the incremental inductance at the present current,
+                            // behind the winding resistance.
+                            let l = saturating_inductance(inductance, saturation_current, v.q[1]);
+                            let name = name('L');
+                            let (winding, a) = if series_resistance > S::from(0) {
+                                let w = format!("{name}_w");
+                                let r = series_resistance.to_f64();
+                                (format!("R{name} {a} {w} {r:e}\n"), w)
+                            } else {
+                                (String::new(), a)
+                            };
+                            format!(
+                                "{winding}{name} {a} {b} {:e} IC={:e}",
+                                l.to_f64(),
+                                v.q[1].to_f64()
+                            )
+                        }
+                        LinearComponentValue::Source(e) => {
+                            format!("{} {b} {a} DC {:e}", name('V'), e.to_f64())
+                        }
+                        LinearComponentValue::Pwm(wave) => {
+                            // `PULSE(V1 V2 TD TR TF PW PER)` with instant edges
+                            let period = wave.period.to_f64();
+                            format!(
+                                "{} {b} {a} PULSE({:e} {:e} {:e} 0 0 {:e} {period:e})",
+                                name('V'),
+                                wave.low.to_f64(),
+                                wave.high.to_f64(),
+                                wave.delay.to_f64(),
+                                wave.duty.to_f64() * period,
+                            )
+                        }
+                        LinearComponentValue::Switch { closed: true, .. }
+                        | LinearComponentValue::Jumper => {
+                            format!("{} {b} {a} DC 0", name('V'))
+                        }
+                        LinearComponentValue::Switch {
+                            closed: false,
+                            off_resistance: Some(r),
+                        } => {
+                            format!("{} {a} {b} {:e}", name('R'), r.to_f64())
+                        }
+                        LinearComponentValue::Switch {
+                            closed: false,
+                            off_resistance: None,
+                        } => {
+                            format!("* {}: open switch between {a} and {b}", name('S'))
+                        }
+                    };
+                    writeln!(out, "{line}").unwrap();
+                }
+                ComponentRef::MOSFET(v) => {
+                    let [source, gate, drain] = [0, 1, 2].map(|i| net(v.nets()[i]));
+                    let (ty, v_th) = match v.value.ty {
+                        MOSFETDopingType::NChannel => ("NMOS", v.value.threshold_voltage),
+                        MOSFETDopingType::PChannel => ("PMOS", -v.value.threshold_voltage),
+                    };
+                    // bulk tied to source, so the drain-bulk junction is the body diode.
+                    let name = name('M');
+                    write!(out, "{name} {drain} {gate} {source} {source} {name}_model").unwrap();
+                    if v.value.multiplicity != S::from(1) {
+                        write!(out, " M={:e}", v.value.multiplicity.to_f64()).unwrap();
+                    }
+                    writeln!(out).unwrap();
+                    writeln!(
+                        out,
+                        ".model {name}_model {ty} (LEVEL=1 KP={:e} VTO={:e} IS={:e} N={:e})",
+                        // at the present temperature, SPICE runs at its own nominal temperature.
+                        v.beta().to_f64(),
+                        v_th.to_f64(),
+                        v.body_diode_saturation_current().to_f64(),
+                        v.value.body_diode_ideality_facotor.to_f64(),
+                    )
+                    .unwrap();
+                }
+                ComponentRef::NoiseSource(v) => {
+                    // only the present sample, SPICE noise sources are simulator specific.
+                    let [a, b] = [0, 1].map(|i| net(v.nets()[i]));
+                    let name = name('V');
+                    writeln!(out, "{name} {b} {a} DC {:e}", v.voltage().to_f64()).unwrap();
+                }
+                ComponentRef::GateDriver(v) => {
+                    // only the output stage as it is, SPICE has no gate driver primitive.
+                    let [vss, vdd, _, out_net] = [0, 1, 2, 3].map(|i| net(v.nets()[i]));
+                    let (rail, r) = if v.output_high() {
+                        (vdd, v.value.source_resistance)
+                    } else {
+                        (vss, v.value.sink_resistance)
+                    };
+                    let name = name('R');
+                    writeln!(out, "{name} {rail} {out_net} {:e}", r.to_f64()).unwrap();
+                }
+                ComponentRef::Comparator(v) => {
+                    // only the output as it is, a source behind the output resistance.
+                    let [out_minus, _, _, out_plus] = [0, 1, 2, 3].map(|i| net(v.nets()[i]));
+                    let (source, resistor) = (name('V'), name('R'));
+                    let inner = format!("{source}_int");
+                    writeln!(
+                        out,
+                        "{source} {inner} {out_minus} DC {:e}",
+                        v.output_level().to_f64()
+                    )
+                    .unwrap();
+                    writeln!(
+                        out,
+                        "{resistor} {inner} {out_plus} {:e}",
+                        v.value.output_resistance.to_f64()
+                    )
+                    .unwrap();
+                }
+                ComponentRef::Converter(v) => {
+                    // the averaged converter as it is: the output a source at its present
+                    // voltage, the input a sink of its present current.
+                    let [out_minus, in_minus, in_plus, out_plus] =
+                        [0, 1, 2, 3].map(|i| net(v.nets()[i]));
+                    let name_v = name('V');
+                    let name_i = name('I');
+                    writeln!(
+                        out,
+                        "{name_v} {out_plus} {out_minus} DC {:e}",
+                        v.output_level().to_f64()
+                    )
+                    .unwrap();
+                    writeln!(
+                        out,
+                        "{name_i} {in_plus} {in_minus} DC {:e}",
+                        v.i_in.to_f64()
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        out.push_str(".end\n");
+        out
+    }
+
+    /// Element name for `component` of SPICE type `letter`, with characters SPICE would misparse
+    /// replaced.
+    fn spice_name(&self, component: ComponentId, letter: char) -> String {
+        let Some(name) = self.component_name(component) else {
+            return format!("{letter}{component}");
+        };
+        let name = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        if name.to_ascii_uppercase().starts_with(letter) {
+            name
+        } else {
+            format!("{letter}{name}")
+        }
+    }
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Read a circuit from a SPICE netlist, such as one written by `to_spice_netlist`.
+    ///
+    /// Supports resistors, capacitors and inductors (with `IC=`), DC voltage sources, `PULSE`
+    /// sources with instant edges (as PWM sources) and level-1
+    /// MOSFETs (`KP VTO IS N` of their `.model`, bulk tied to source, `M=` multiplicity and
+    /// `saturation_knee` 8, or a part of `DeviceLibrary::builtin` named in place of a model);
+    /// the first line is the title and other dot-commands are ignored. Node `0` is net 0 and
+    /// the elements are named after their SPICE names. Values are read by `units::parse_si`, so
+    /// unlike SPICE `M` is mega rather than milli.
+    pub fn from_spice_netlist(text: &str) -> Result<(Self, NameMap), SimError> {
+        Self::from_spice_netlist_with(text, DeviceLibrary::builtin())
+    }
+    /// `from_spice_netlist`, with the MOSFET parts of `devices`. A `.model` of the netlist takes
+    /// precedence over a part of the same name.
+    pub fn from_spice_netlist_with(
+        text: &str,
+        devices: &DeviceLibrary,
+    ) -> Result<(Self, NameMap), SimError> {
+        let lines = text
+            .lines()
+            .enumerate()
+            .skip(1)
+            .map(|(i, line)| (i + 1, line.split_whitespace().collect::<Vec<_>>()))
+            .filter(|(_, words)| !words.is_empty() && !words[0].starts_with('*'));
+        let error = |line, message: String| SimError::Netlist { line, message };
+        let number = |line, word: &str| {
+            parse_si(word)
+                .map(S::from_f64)
+                .ok_or_else(|| error(line, format!("malformed number {word:?}")))
+        };
+        // `key=value` parameters of an element or model line
+        let params = |line, words: &[&str]| -> Result<HashMap<String, S>, SimError> {
+            words
+                .iter()
+                .map(|word| word.trim_matches(|c| c == '(' || c == ')'))
+                .filter(|word| !word.is_empty())
+                .filter_map(|word| word.split_once('='))
+                .map(|(key, value)| Ok((key.to_ascii_uppercase(), number(line, value)?)))
+                .collect()
+        };
+
+        let mut models = HashMap::new();
+        for (line, words) in lines.clone() {
+            if words[0].eq_ignore_ascii_case(".model") {
+                let [_, name, ty, ..] = words[..] else {
+                    return Err(error(line, "expected `.model <name> <type> (...)`".into()));
+                };
+                let ty = match ty.to_ascii_uppercase().as_str() {
+                    "NMOS" => MOSFETDopingType::NChannel,
+                    "PMOS" => MOSFETDopingType::PChannel,
+                    ty => return Err(error(line, format!("unsupported model type {ty}"))),
+                };
+                models.insert(name.to_ascii_lowercase(), (ty, params(line, &words[3..])?));
+            }
+        }
+
+        let mut builder = CircuitBuilder::new();
+        builder.net("0");
+        let mut capacitor_voltages = Vec::new();
+        let mut inductor_currents = Vec::new();
+        for (line, words) in lines {
+            let name = words[0];
+            if name.starts_with('.') {
+                continue;
+            }
+            let nets = |n: usize| {
+                words
+                    .get(1..=n)
+                    .ok_or_else(|| error(line, format!("{name} needs {n} nodes")))
+            };
+            let value = |n: usize| {
+                let word = words
+                    .get(n + 1)
+                    .filter(|word| !word.contains('='))
+                    .ok_or_else(|| error(line, format!("{name} needs a value")))?;
+                number(line, word)
+            };
+            let letter = name.chars().next().unwrap().to_ascii_uppercase();
+            match letter {
+                'R' | 'C' | 'L' => {
+                    let &[p, n] = nets(2)? else { unreachable!() };
+                    let x = value(2)?;
+                    let ic = params(line, &words[4..])?.get("IC").copied();
+                    // all from node `p` (terminal 0) to node `n` (terminal 1), except that the
+                    // capacitor `IC` is `V(p) - V(n)` like a source.
+                    match letter {
+                        'R' => builder.resistor(name, p, n, x),
+                        'C' => {
+                            capacitor_voltages.extend(ic.map(|v| (name, v)));
+                            builder.capacitor(name, n, p, x)
+                        }
+                        _ => {
+                            inductor_currents.extend(ic.map(|i| (name, i)));
+                            builder.inductor(name, p, n, x)
+                        }
+                    }
+                }
+                'V' if words
+                    .get(3)
+                    .is_some_and(|word| word.to_ascii_uppercase().starts_with("PULSE")) =>
+                {
+                    let &[p, n] = nets(2)? else { unreachable!() };
+                    let args = words[3..].join(" ");
+                    let args = args[5..]
+                        .split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == ',')
+                        .filter(|word| !word.is_empty())
+                        .map(|word| number(line, word))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let &[low, high, delay, rise, fall, width, period] = &args[..] else {
+                        return Err(error(
+                            line,
+                            format!("{name}: expected PULSE(V1 V2 TD TR TF PW PER)"),
+                        ));
+                    };
+                    if rise != S::from(0) || fall != S::from(0) {
+                        return Err(error(
+                            line,
+                            format!("{name}: only instant edges (TR = TF = 0)"),
+                        ));
+                    }
+                    let wave = PwmWave::new(low, high, period, width / period, delay);
+                    builder.pwm_source(name, n, p, wave)
+                }
+                'V' => {
+                    let &[p, n] = nets(2)? else { unreachable!() };
+                    let v = match words.get(3) {
+                        Some(word) if word.eq_ignore_ascii_case("dc") => value(3)?,
+                        _ => value(2)?,
+                    };
+                    builder.source(name, n, p, v)
+                }
+                'M' => {
+                    let &[drain, gate, source, bulk] = nets(4)? else {
+                        unreachable!()
+                    };
+                    if bulk != source {
+                        return Err(error(line, format!("{name}: bulk must be tied to source")));
+                    }
+                    let model = words
+                        .get(5)
+                        .ok_or_else(|| error(line, format!("{name} needs a model")))?;
+                    let mut value = match models.get(&model.to_ascii_lowercase()) {
+                        Some((ty, model)) => {
+                            let param = |key, default: f| {
+                                model.get(key).copied().unwrap_or(S::from_f64(default))
+                            };
+                            let v_to = param("VTO", 0.0);
+                            MOSFETComponentValue {
+                                ty: *ty,
+                                beta: param("KP", 2e-5),
+                                threshold_voltage: match ty {
+                                    MOSFETDopingType::NChannel => v_to,
+                                    MOSFETDopingType::PChannel => -v_to,
+                                },
+                                body_diode_saturation_current: param("IS", 1e-14),
+                                body_diode_ideality_facotor: param("N", 1.0),
+                                saturation_knee: S::from_f64(8.0),
+                                multiplicity: S::from(1),
+                                avalanche: None,
+                            }
+                        }
+                        None => devices
+                            .mosfet(model)
+                            .map_err(|e| error(line, format!("unknown model {model}: {e}")))?,
+                    };
+                    if let Some(&m) = params(line, &words[6..])?.get("M") {
+                        value.multiplicity *= m;
+                    }
+                    builder.mosfet(name, value, source, gate, drain)
+                }
+                _ => return Err(error(line, format!("unsupported element {name}"))),
+            }
+            .map_err(|e| error(line, e.to_string()))?;
+        }
+
+        let (mut circuit, names) = builder.build();
+        for (name, volts) in capacitor_voltages {
+            circuit.set_initial_capacitor_voltage(names.component(name).unwrap(), volts)?;
+        }
+        for (name, amps) in inductor_currents {
+            circuit.set_initial_inductor_current(names.component(name).unwrap(), amps)?;
+        }
+        Ok((circuit, names))
+    }
+}
@@ -0,0 +1,194 @@
+//! Cross-checking transients against ngspice: run an exported netlist (see
+//! `CircuitState::to_spice_netlist`) in batch mode and read its ASCII rawfile, to compare with
+//! `waveform::compare`.
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::PathBuf,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{f, NetId};
+
+#[derive(Debug)]
+pub enum NgspiceError {
+    Io(io::Error),
+    /// ngspice exited unsuccessfully, with its output.
+    Failed {
+        output: String,
+    },
+    /// The rawfile could not be read, at this (1-based) line.
+    Rawfile {
+        line: usize,
+        message: String,
+    },
+}
+impl fmt::Display for NgspiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "running ngspice: {err}"),
+            Self::Failed { output } => write!(f, "ngspice failed:\n{output}"),
+            Self::Rawfile { line, message } => write!(f, "rawfile line {line}: {message}"),
+        }
+    }
+}
+impl Error for NgspiceError {}
+impl From<io::Error> for NgspiceError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The vectors of one rawfile plot, with the scale (`time` for transients) first.
+#[derive(Debug, Clone, Default)]
+pub struct Waveforms {
+    pub plot: String,
+    /// `(name, samples)` per vector, as named in the rawfile (`time`, `v(n1)`, `i(v1)`...).
+    pub vectors: Vec<(String, Vec<f>)>,
+}
+impl Waveforms {
+    /// The scale vector (time, for a transient).
+    pub fn scale(&self) -> &[f] {
+        &self.vectors[0].1
+    }
+    /// The vector named `name` (case-insensitive, `v(x)` also matches a bare `x`).
+    pub fn vector(&self, name: &str) -> Option<&[f]> {
+        let bare = name
+            .strip_prefix("v(")
+            .and_then(|name| name.strip_suffix(')'));
+        self.vectors
+            .iter()
+            .find(|(v, _)| {
+                v.eq_ignore_ascii_case(name) || bare.is_some_and(|b| v.eq_ignore_ascii_case(b))
+            })
+            .map(|(_, samples)| samples.as_slice())
+    }
+    /// The voltage of `net` relative to ground (net 0), as exported by `to_spice_netlist`.
+    pub fn net_voltage(&self, net: NetId) -> Option<Vec<f>> {
+        if net == 0 {
+            return Some(vec![0.0; self.scale().len()]);
+        }
+        self.vector(&format!("v(n{net})")).map(<[f]>::to_vec)
+    }
+}
+
+/// Read the last real plot of an ASCII rawfile (`SPICE_ASCIIRAWFILE=1`).
+pub fn parse_rawfile(text: &str) -> Result<Waveforms, NgspiceError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let err = |line, message: &str| NgspiceError::Rawfile {
+        line,
+        message: message.to_string(),
+    };
+    let mut last = None;
+    let mut plot = Waveforms::default();
+    let (mut n_variables, mut n_points, mut complex) = (0, 0, false);
+    while let Some((line_i, line)) = lines.next() {
+        let (key, value) = line.split_once(':').unwrap_or((line, ""));
+        match key.trim() {
+            "Plotname" => {
+                plot = Waveforms {
+                    plot: value.trim().to_string(),
+                    vectors: Vec::new(),
+                }
+            }
+            "Flags" => complex = value.contains("complex"),
+            "No. Variables" => {
+                n_variables = value.trim().parse().map_err(|_| err(line_i, "bad count"))?
+            }
+            "No. Points" => {
+                n_points = value.trim().parse().map_err(|_| err(line_i, "bad count"))?
+            }
+            "Variables" => {
+                for _ in 0..n_variables {
+                    let (line_i, line) = lines.next().ok_or_else(|| err(line_i, "truncated"))?;
+                    let name = line
+                        .split_whitespace()
+                        .nth(1)
+                        .ok_or_else(|| err(line_i, "expected `<index> <name> <type>`"))?;
+                    plot.vectors
+                        .push((name.to_string(), Vec::with_capacity(n_points)));
+                }
+            }
+            "Values" => {
+                // per point, `<point> <value of vector 0>` then one value per line for the others
+                for _ in 0..n_points {
+                    for (_, samples) in &mut plot.vectors {
+                        let (line_i, line) =
+                            lines.next().ok_or_else(|| err(line_i, "truncated"))?;
+                        let value = line
+                            .split_whitespace()
+                            .last()
+                            .and_then(|v| v.split(',').next())
+                            .and_then(|v| v.parse::<f>().ok())
+                            .ok_or_else(|| err(line_i, "expected a value"))?;
+                        samples.push(value);
+                    }
+                }
+                let plot = std::mem::take(&mut plot);
+                if !complex {
+                    last = Some(plot);
+                }
+            }
+            "Binary" => return Err(err(line_i, "binary rawfile, set SPICE_ASCIIRAWFILE=1")),
+            _ => {}
+        }
+    }
+    last.filter(|plot| !plot.vectors.is_empty())
+        .ok_or_else(|| err(0, "no plot"))
+}
+
+/// An ngspice binary.
+#[derive(Debug, Clone)]
+pub struct Ngspice {
+    pub binary: PathBuf,
+}
+impl Ngspice {
+    /// `$NGSPICE`, or `ngspice` on the `PATH`, if it runs.
+    pub fn find() -> Option<Self> {
+        let binary = std::env::var_os("NGSPICE").map_or_else(|| "ngspice".into(), PathBuf::from);
+        let runs = Command::new(&binary)
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        runs.then_some(Self { binary })
+    }
+
+    /// Run the transient `.tran step stop uic` of `netlist` (as from `to_spice_netlist`, whose
+    /// `.end` is replaced), limiting ngspice's time step to `step`.
+    pub fn transient(&self, netlist: &str, step: f, stop: f) -> Result<Waveforms, NgspiceError> {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let base = std::env::temp_dir().join(format!(
+            "esc_sim_ngspice_{}_{}",
+            std::process::id(),
+            RUNS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let (cir, raw) = (base.with_extension("cir"), base.with_extension("raw"));
+        let body = netlist.trim_end().strip_suffix(".end").unwrap_or(netlist);
+        fs::write(
+            &cir,
+            format!("{body}.tran {step:e} {stop:e} 0 {step:e} uic\n.end\n"),
+        )?;
+
+        let output = Command::new(&self.binary)
+            .env("SPICE_ASCIIRAWFILE", "1")
+            .arg("-b")
+            .arg("-r")
+            .arg(&raw)
+            .arg(&cir)
+            .output();
+        let result = output.map_err(NgspiceError::from).and_then(|output| {
+            if !output.status.success() {
+                return Err(NgspiceError::Failed {
+                    output: String::from_utf8_lossy(&output.stdout).into_owned()
+                        + &String::from_utf8_lossy(&output.stderr),
+                });
+            }
+            parse_rawfile(&fs::read_to_string(&raw)?)
+        });
+        let _ = fs::remove_file(&cir);
+        let _ = fs::remove_file(&raw);
+        result
+    }
+}
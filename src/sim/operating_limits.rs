@@ -0,0 +1,186 @@
+//! Operating limits checked after every tick: a bus or node voltage that mustn't exceed a
+//! maximum (relative to a reference net), a device current that mustn't, and each stretch of
+//! ticks spent beyond one reported as a `LimitViolation`. With `set_abort_on_violation` the run
+//! drivers stop at the first breach, returning what they recorded up to it.
+
+use super::{
+    builder::CircuitBuilder, error::SimError, f, CircuitState, ComponentId, NetId, Scalar,
+};
+
+/// What an `OperatingLimit` bounds the magnitude of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LimitedQuantity {
+    /// The voltage of `net` to `reference`.
+    NetVoltage { net: NetId, reference: NetId },
+    /// `CircuitState::branch_current` of the component.
+    ComponentCurrent(ComponentId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OperatingLimit<S: Scalar = f> {
+    pub quantity: LimitedQuantity,
+    /// Largest magnitude allowed, in volts or amperes.
+    pub max: S,
+}
+
+/// A stretch of consecutive ticks that ended with `limit` exceeded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LimitViolation<S: Scalar = f> {
+    /// Index of the limit in `CircuitState::operating_limits`.
+    pub limit: usize,
+    pub quantity: LimitedQuantity,
+    pub max: S,
+    /// The end of the first tick over the limit.
+    pub start: S,
+    /// The time spent over it, the `dt` of every tick of the stretch.
+    pub duration: S,
+    /// The value of the quantity at the tick it was furthest over the limit, signed.
+    pub peak: S,
+    /// Whether the stretch is still going on at the last tick.
+    pub ongoing: bool,
+}
+
+/// The limits of a circuit and what they have seen.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct LimitMonitor<S: Scalar> {
+    limits: Vec<OperatingLimit<S>>,
+    /// The violation each limit is in at the last tick.
+    open: Vec<Option<LimitViolation<S>>>,
+    closed: Vec<LimitViolation<S>>,
+    abort: bool,
+    /// Whether the last tick began a violation.
+    breached: bool,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Check `limit` after every tick from now on, returning its index.
+    ///
+    /// Errors if the component of a `ComponentCurrent` doesn't exist. Nets are taken as they
+    /// are by `net`.
+    pub fn add_operating_limit(&mut self, limit: OperatingLimit<S>) -> Result<usize, SimError> {
+        if let LimitedQuantity::ComponentCurrent(component) = limit.quantity {
+            if component >= self.slots.len() {
+                return Err(SimError::UnknownComponent(component));
+            }
+        }
+        self.operating_limits.limits.push(limit);
+        self.operating_limits.open.push(None);
+        Ok(self.operating_limits.limits.len() - 1)
+    }
+    pub fn operating_limits(&self) -> &[OperatingLimit<S>] {
+        &self.operating_limits.limits
+    }
+    /// Take every limit away, with the violations they saw.
+    pub fn clear_operating_limits(&mut self) {
+        self.operating_limits = LimitMonitor {
+            abort: self.operating_limits.abort,
+            ..LimitMonitor::default()
+        };
+    }
+
+    /// Every violation so far, by start, the ongoing ones included.
+    pub fn limit_violations(&self) -> Vec<LimitViolation<S>> {
+        let monitor = &self.operating_limits;
+        let mut violations = monitor
+            .closed
+            .iter()
+            .chain(monitor.open.iter().flatten())
+            .copied()
+            .collect::<Vec<_>>();
+        violations.sort_by(|a, b| a.start.to_f64().total_cmp(&b.start.to_f64()));
+        violations
+    }
+    /// Forget the violations so far; one ongoing starts again at the next tick over its limit.
+    pub fn clear_limit_violations(&mut self) {
+        let monitor = &mut self.operating_limits;
+        monitor.closed.clear();
+        monitor.open.iter_mut().for_each(|open| *open = None);
+        monitor.breached = false;
+    }
+
+    /// Make `run_simple`, `run_with_progress` and the runs built on them stop after the first
+    /// tick that exceeds an operating limit. Off by default.
+    pub fn set_abort_on_violation(&mut self, abort: bool) {
+        self.operating_limits.abort = abort;
+    }
+    pub fn abort_on_violation(&self) -> bool {
+        self.operating_limits.abort
+    }
+    /// Whether the last tick began a violation, and `set_abort_on_violation` is on.
+    pub fn should_abort(&self) -> bool {
+        self.operating_limits.abort && self.operating_limits.breached
+    }
+
+    /// Check the limits at the end of a tick of `dt`.
+    pub(super) fn check_operating_limits(&mut self, dt: S) {
+        let mut monitor = std::mem::take(&mut self.operating_limits);
+        monitor.breached = false;
+        for (limit_i, (limit, open)) in monitor.limits.iter().zip(&mut monitor.open).enumerate() {
+            let value = match limit.quantity {
+                LimitedQuantity::NetVoltage { net, reference } => {
+                    self.nets[net].voltage - self.nets[reference].voltage
+                }
+                LimitedQuantity::ComponentCurrent(component) => self.branch_current(component),
+            };
+            if value.abs() <= limit.max {
+                if let Some(mut violation) = open.take() {
+                    violation.ongoing = false;
+                    monitor.closed.push(violation);
+                }
+                continue;
+            }
+            match open {
+                Some(violation) => {
+                    violation.duration += dt;
+                    if value.abs() > violation.peak.abs() {
+                        violation.peak = value;
+                    }
+                }
+                None => {
+                    trace_event!(
+                        warn,
+                        limit = limit_i,
+                        value = value.to_f64(),
+                        max = limit.max.to_f64(),
+                        "operating limit exceeded"
+                    );
+                    monitor.breached = true;
+                    *open = Some(LimitViolation {
+                        limit: limit_i,
+                        quantity: limit.quantity,
+                        max: limit.max,
+                        start: self.time,
+                        duration: dt,
+                        peak: value,
+                        ongoing: true,
+                    });
+                }
+            }
+        }
+        self.operating_limits = monitor;
+    }
+}
+
+impl<S: Scalar> CircuitBuilder<S> {
+    /// Limit the voltage of the net `net` to `reference` to `max` in magnitude, see
+    /// `CircuitState::add_operating_limit`. The nets are created if they don't exist yet.
+    pub fn voltage_limit(
+        &mut self,
+        net: &str,
+        reference: &str,
+        max: S,
+    ) -> Result<&mut Self, SimError> {
+        let quantity = LimitedQuantity::NetVoltage {
+            net: self.net(net),
+            reference: self.net(reference),
+        };
+        self.circuit_mut()
+            .add_operating_limit(OperatingLimit { quantity, max })?;
+        Ok(self)
+    }
+}
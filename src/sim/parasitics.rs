@@ -0,0 +1,95 @@
+use super::{
+    components::LinearComponentValue, f, CircuitState, ComponentId, ComponentMut,
+    ComponentValueEnum, NetId, Scalar,
+};
+
+/// Series resistance and inductance of a trace or wire.
+#[derive(Debug, Clone, Copy)]
+pub struct ParasiticValue<S: Scalar = f> {
+    pub resistance: S,
+    pub inductance: S,
+}
+
+/// The components `connect_with_parasitics` inserted between two nets:
+/// `a - resistor - net - inductor - b`.
+#[derive(Debug, Clone, Copy)]
+pub struct Parasitic<S: Scalar = f> {
+    pub value: ParasiticValue<S>,
+    /// The internal net between the resistance and the inductance.
+    pub net: NetId,
+    pub resistor: ComponentId,
+    pub inductor: ComponentId,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Join nets `a` and `b` through `resistance` ohms and `inductance` henries in series, via a
+    /// new internal net. A zero resistance or inductance becomes a closed switch.
+    pub fn connect_with_parasitics(
+        &mut self,
+        a: NetId,
+        b: NetId,
+        resistance: S,
+        inductance: S,
+    ) -> Parasitic<S> {
+        let value = ParasiticValue {
+            resistance,
+            inductance,
+        };
+        let [r, l] = parasitic_components(value, true);
+        let net = self.create_net();
+        let resistor = self.create_component(ComponentValueEnum::Linear(r), &[a, net]);
+        let inductor = self.create_component(ComponentValueEnum::Linear(l), &[net, b]);
+        Parasitic {
+            value,
+            net,
+            resistor,
+            inductor,
+        }
+    }
+    /// `connect_with_parasitics` for each `(a, b, value)`, in order.
+    pub fn apply_parasitics(
+        &mut self,
+        connections: &[(NetId, NetId, ParasiticValue<S>)],
+    ) -> Vec<Parasitic<S>> {
+        connections
+            .iter()
+            .map(|&(a, b, value)| {
+                self.connect_with_parasitics(a, b, value.resistance, value.inductance)
+            })
+            .collect()
+    }
+    /// Short out a parasitic (`enabled = false`) or restore its values, for comparing a circuit
+    /// with and without it. The inductor keeps its current across the change.
+    pub fn set_parasitic_enabled(&mut self, parasitic: &Parasitic<S>, enabled: bool) {
+        let values = parasitic_components(parasitic.value, enabled);
+        for (component, value) in [parasitic.resistor, parasitic.inductor]
+            .into_iter()
+            .zip(values)
+        {
+            let ComponentMut::Linear(v) = self.component_mut(component) else {
+                unreachable!("parasitics are linear components")
+            };
+            v.value = value;
+        }
+    }
+}
+
+/// The resistor and inductor values of `value`, or closed switches where it is zero or disabled.
+fn parasitic_components<S: Scalar>(
+    value: ParasiticValue<S>,
+    enabled: bool,
+) -> [LinearComponentValue<S>; 2] {
+    let short = LinearComponentValue::switch(true);
+    [
+        if enabled && value.resistance > S::from(0) {
+            LinearComponentValue::Resistive(value.resistance)
+        } else {
+            short
+        },
+        if enabled && value.inductance > S::from(0) {
+            LinearComponentValue::Inductive(value.inductance)
+        } else {
+            short
+        },
+    ]
+}
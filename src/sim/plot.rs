@@ -0,0 +1,190 @@
+//! Live plotting during a run: `run::run_with_plot` hands a `PlotSink` the rows recorded since
+//! the last batch at a fixed interval of simulation time. `SparklineSink` draws them in a
+//! terminal and `GnuplotSink` (feature `gnuplot`) in a gnuplot window; other front-ends
+//! implement the trait themselves.
+
+use std::io;
+
+use super::f;
+
+/// The rows recorded since the previous batch.
+#[derive(Debug, Clone, Copy)]
+pub struct PlotBatch<'a> {
+    pub time: &'a [f],
+    /// One slice per column of the recording, in the order of `PlotSink::begin`'s labels.
+    pub channels: &'a [&'a [f]],
+}
+
+/// Receiver of the samples of a run as it goes; see `run::run_with_plot`.
+pub trait PlotSink {
+    /// Called once before the first batch, with the labels of the recording's columns.
+    fn begin(&mut self, _labels: &[String]) {}
+    fn batch(&mut self, batch: PlotBatch<'_>);
+    /// Called once after the last batch.
+    fn end(&mut self) {}
+}
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Prints every batch as one line of unicode block sparklines per channel, scaled to the
+/// batch's range and squeezed to `width` characters (by their mean).
+#[derive(Debug)]
+pub struct SparklineSink<W: io::Write> {
+    out: W,
+    width: usize,
+    labels: Vec<String>,
+}
+impl SparklineSink<io::Stderr> {
+    pub fn stderr(width: usize) -> Self {
+        Self::new(io::stderr(), width)
+    }
+}
+impl<W: io::Write> SparklineSink<W> {
+    pub fn new(out: W, width: usize) -> Self {
+        Self {
+            out,
+            width: width.max(1),
+            labels: Vec::new(),
+        }
+    }
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// `samples` as at most `width` sparks.
+    pub fn sparkline(samples: &[f], width: usize) -> String {
+        let n = samples.len().min(width.max(1));
+        let columns = (0..n)
+            .map(|i| {
+                let bucket = &samples[i * samples.len() / n..(i + 1) * samples.len() / n];
+                bucket.iter().sum::<f>() / bucket.len() as f
+            })
+            .collect::<Vec<_>>();
+        let (min, max) = columns
+            .iter()
+            .fold((f::INFINITY, f::NEG_INFINITY), |(lo, hi), &x| {
+                (lo.min(x), hi.max(x))
+            });
+        // lines flat to rounding (or not finite) sit at the bottom.
+        let flat = max - min <= 1e-9 * max.abs().max(min.abs());
+        columns
+            .iter()
+            .map(|&x| match (x - min) / (max - min) {
+                level if level.is_finite() && !flat => {
+                    SPARKS[((level * SPARKS.len() as f) as usize).min(SPARKS.len() - 1)]
+                }
+                _ => SPARKS[0],
+            })
+            .collect()
+    }
+}
+impl<W: io::Write> PlotSink for SparklineSink<W> {
+    fn begin(&mut self, labels: &[String]) {
+        self.labels = labels.to_vec();
+    }
+    fn batch(&mut self, batch: PlotBatch<'_>) {
+        let (Some(first), Some(last)) = (batch.time.first(), batch.time.last()) else {
+            return;
+        };
+        let label_width = self.labels.iter().map(String::len).max().unwrap_or(0);
+        // a closed terminal is no reason to stop the run.
+        let _ = writeln!(self.out, "t = {first:.3e} .. {last:.3e} s");
+        for (label, samples) in self.labels.iter().zip(batch.channels) {
+            let (min, max) = samples
+                .iter()
+                .fold((f::INFINITY, f::NEG_INFINITY), |(lo, hi), &x| {
+                    (lo.min(x), hi.max(x))
+                });
+            let _ = writeln!(
+                self.out,
+                "  {label:label_width$} {} [{min:.3e}, {max:.3e}]",
+                Self::sparkline(samples, self.width)
+            );
+        }
+    }
+}
+
+/// Plots the last `window` rows in a gnuplot window, redrawn every batch. Needs `gnuplot` on
+/// the `PATH` (or at `$GNUPLOT`); `find` returns `None` without it.
+#[cfg(feature = "gnuplot")]
+#[derive(Debug)]
+pub struct GnuplotSink {
+    child: std::process::Child,
+    window: usize,
+    labels: Vec<String>,
+    time: std::collections::VecDeque<f>,
+    channels: Vec<std::collections::VecDeque<f>>,
+}
+#[cfg(feature = "gnuplot")]
+impl GnuplotSink {
+    pub fn find(window: usize) -> Option<Self> {
+        use std::process::{Command, Stdio};
+        let binary = std::env::var_os("GNUPLOT").unwrap_or_else(|| "gnuplot".into());
+        let runs = Command::new(&binary)
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !runs {
+            return None;
+        }
+        let child = Command::new(&binary)
+            .arg("-persist")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .ok()?;
+        Some(Self {
+            child,
+            window: window.max(2),
+            labels: Vec::new(),
+            time: Default::default(),
+            channels: Vec::new(),
+        })
+    }
+
+    fn redraw(&mut self) -> io::Result<()> {
+        use std::io::Write;
+        let stdin = self.child.stdin.as_mut().ok_or(io::ErrorKind::BrokenPipe)?;
+        let mut script = String::from("plot ");
+        for (i, label) in self.labels.iter().enumerate() {
+            let separator = if i > 0 { ", " } else { "" };
+            script += &format!("{separator}'-' using 1:2 with lines title '{label}'");
+        }
+        script.push('\n');
+        for channel in &self.channels {
+            for (t, x) in self.time.iter().zip(channel) {
+                script += &format!("{t:e} {x:e}\n");
+            }
+            script += "e\n";
+        }
+        stdin.write_all(script.as_bytes())?;
+        stdin.flush()
+    }
+}
+#[cfg(feature = "gnuplot")]
+impl PlotSink for GnuplotSink {
+    fn begin(&mut self, labels: &[String]) {
+        self.labels = labels.iter().map(|l| l.replace('\'', "")).collect();
+        self.channels = vec![Default::default(); labels.len()];
+    }
+    fn batch(&mut self, batch: PlotBatch<'_>) {
+        self.time.extend(batch.time);
+        for (kept, samples) in self.channels.iter_mut().zip(batch.channels) {
+            kept.extend(samples.iter());
+        }
+        let excess = self.time.len().saturating_sub(self.window);
+        self.time.drain(..excess);
+        for kept in &mut self.channels {
+            kept.drain(..excess);
+        }
+        if !self.labels.is_empty() {
+            // a closed gnuplot window is no reason to stop the run.
+            let _ = self.redraw();
+        }
+    }
+    fn end(&mut self) {
+        // let gnuplot exit once it has drawn the last batch; `-persist` keeps the window.
+        drop(self.child.stdin.take());
+        let _ = self.child.wait();
+    }
+}
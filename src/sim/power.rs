@@ -0,0 +1,218 @@
+use super::{
+    f,
+    rating::{RatingMonitor, RatingViolation},
+    CircuitState, ComponentId, ComponentRef,
+};
+
+/// How the energy absorbed by a component is accounted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerKind {
+    /// Absorbed energy leaves the circuit as heat (resistors, switches, MOSFETs).
+    Dissipative,
+    /// Absorbed energy is held in a field and may be returned later (capacitors, inductors).
+    Reactive,
+    /// Delivers energy into the circuit.
+    Source,
+}
+
+/// Integrates the energy absorbed by every component of a circuit over a run.
+#[derive(Debug, Clone)]
+pub struct LossAccumulator {
+    elapsed: f,
+    /// Absorbed energy per component, indexed by `ComponentId`.
+    energy: Vec<f>,
+    /// The dissipated part of `energy`.
+    dissipated: Vec<f>,
+    /// See `with_rating_window`.
+    rating_window: f,
+    /// The window of each resistor with a power rating, indexed by `ComponentId`.
+    ratings: Vec<RatingMonitor>,
+    /// The violations of ratings that have ended.
+    violations: Vec<RatingViolation>,
+}
+impl LossAccumulator {
+    pub fn new() -> Self {
+        Self {
+            elapsed: 0.0,
+            energy: Vec::new(),
+            dissipated: Vec::new(),
+            rating_window: f::INFINITY,
+            ratings: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+    /// Check the dissipation of resistors with a `ResistorRating::power` averaged over
+    /// consecutive windows of `window` seconds, as long as the part takes to heat up, rather
+    /// than over the whole run (the default).
+    pub fn with_rating_window(mut self, window: f) -> Self {
+        self.rating_window = window;
+        self
+    }
+
+    /// Integrate the instantaneous power of every component over a step of length `dt`, and
+    /// add the loss of any charge sharing (`CircuitState::last_charge_sharing`) to the switches
+    /// that caused it. Resistors dissipating above their power rating over a window start a
+    /// `RatingViolation` (and a warning event, with the `tracing` feature). Components
+    /// `SolverConfig::sub_steps` sub-stepped over the tick count the energy it integrated.
+    ///
+    /// Call once after each `tick(dt)`.
+    pub fn accumulate(&mut self, circuit: &CircuitState, dt: f) {
+        self.energy.resize(circuit.n_components(), 0.0);
+        self.dissipated.resize(circuit.n_components(), 0.0);
+        self.ratings
+            .resize_with(circuit.n_components(), RatingMonitor::default);
+        for (component_i, component) in circuit.components() {
+            let rating = match component {
+                ComponentRef::Linear(state) => state.rating().and_then(|rating| rating.power),
+                _ => None,
+            };
+            let component = component.as_dyn();
+            let dissipated = component.dissipated_power(&circuit.nets);
+            match component.sub_stepped_energy() {
+                // all of it heat: only dissipative components sub-step.
+                Some(energy) => {
+                    self.energy[component_i] += energy;
+                    self.dissipated[component_i] += energy;
+                }
+                None => {
+                    self.energy[component_i] += component.instantaneous_power(&circuit.nets) * dt;
+                    self.dissipated[component_i] += dissipated * dt;
+                }
+            }
+            if let Some(rating) = rating {
+                let ended = self.ratings[component_i].add(
+                    component_i,
+                    rating,
+                    dissipated,
+                    circuit.now(),
+                    dt,
+                    self.rating_window,
+                );
+                self.violations.extend(ended);
+            }
+        }
+        // the charge sharing at the start of the tick, split between the switches that closed.
+        if let Some(sharing) = circuit.last_charge_sharing() {
+            let share = sharing.dissipated / sharing.switches.len() as f;
+            for &switch in &sharing.switches {
+                self.energy[switch] += share;
+                self.dissipated[switch] += share;
+            }
+        }
+        self.elapsed += dt;
+    }
+
+    pub fn elapsed(&self) -> f {
+        self.elapsed
+    }
+    /// Energy absorbed by `component` so far, in joules (negative for sources delivering power).
+    pub fn energy(&self, component: ComponentId) -> f {
+        self.energy.get(component).copied().unwrap_or(0.0)
+    }
+    /// Energy `component` has turned into heat so far, in joules.
+    pub fn dissipated(&self, component: ComponentId) -> f {
+        self.dissipated.get(component).copied().unwrap_or(0.0)
+    }
+
+    /// List all components, the largest dissipators first, followed by reactive components and
+    /// sources, and every `RatingViolation` so far, counting the window under way.
+    pub fn report(&self, circuit: &CircuitState) -> LossReport {
+        let mut components = circuit
+            .components()
+            .map(|(component_i, component)| {
+                let energy = self.energy(component_i);
+                ComponentLoss {
+                    component: component_i,
+                    kind: component.as_dyn().power_kind(),
+                    energy,
+                    dissipated: self.dissipated(component_i),
+                    average_power: if self.elapsed > 0.0 {
+                        energy / self.elapsed
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        components.sort_by(|a, b| {
+            let rank = |kind| match kind {
+                PowerKind::Dissipative => 0,
+                PowerKind::Reactive => 1,
+                PowerKind::Source => 2,
+            };
+            rank(a.kind)
+                .cmp(&rank(b.kind))
+                .then(b.energy.abs().total_cmp(&a.energy.abs()))
+        });
+        let mut violations = self.violations.clone();
+        for (component_i, component) in circuit.components() {
+            let ComponentRef::Linear(state) = component else {
+                continue;
+            };
+            if let (Some(rating), Some(monitor)) = (
+                state.rating().and_then(|rating| rating.power),
+                self.ratings.get(component_i),
+            ) {
+                violations.extend(monitor.finish(component_i, rating, circuit.now()));
+            }
+        }
+        violations.sort_by(|a, b| a.start.total_cmp(&b.start));
+        LossReport {
+            elapsed: self.elapsed,
+            components,
+            violations,
+        }
+    }
+}
+impl Default for LossAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentLoss {
+    pub component: ComponentId,
+    pub kind: PowerKind,
+    /// Energy absorbed over the run, in joules. For reactive components this is the net energy
+    /// moved into storage rather than a loss.
+    pub energy: f,
+    /// The part of `energy` turned into heat, e.g. in the ESR of a capacitor.
+    pub dissipated: f,
+    pub average_power: f,
+}
+
+#[derive(Debug, Clone)]
+pub struct LossReport {
+    pub elapsed: f,
+    /// Sorted by dissipation, see `LossAccumulator::report`.
+    pub components: Vec<ComponentLoss>,
+    /// Resistors over their power rating, in the order the violations began.
+    pub violations: Vec<RatingViolation>,
+}
+impl LossReport {
+    fn total(&self, kind: PowerKind) -> f {
+        self.components
+            .iter()
+            .filter(|v| v.kind == kind)
+            .map(|v| v.energy)
+            .sum()
+    }
+    /// Energy converted to heat (conduction losses), including that in the parasitic resistance
+    /// of reactive components.
+    pub fn dissipated_energy(&self) -> f {
+        self.components.iter().map(|v| v.dissipated).sum()
+    }
+    /// Net energy moved into capacitors and inductors.
+    pub fn reactive_energy(&self) -> f {
+        self.components
+            .iter()
+            .filter(|v| v.kind == PowerKind::Reactive)
+            .map(|v| v.energy - v.dissipated)
+            .sum()
+    }
+    /// Energy delivered into the circuit by sources.
+    pub fn source_energy(&self) -> f {
+        -self.total(PowerKind::Source)
+    }
+}
@@ -0,0 +1,497 @@
+use std::{f64::consts::TAU, io};
+
+use super::{builder::NameMap, error::SimError, f, CircuitState, ComponentId, NetId};
+
+/// What a `Probe::Component` measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Quantity {
+    /// Current from the first terminal to the last through the component (the channel current
+    /// from source to drain for MOSFETs).
+    Current,
+    /// Voltage of the last terminal relative to the first (`V_ds` for MOSFETs).
+    Voltage,
+    /// `CircuitState::instantaneous_power`.
+    Power,
+}
+
+/// A signal of a circuit that can be sampled after each tick.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Probe {
+    /// Voltage of a net relative to net 0.
+    Net(NetId),
+    Component(ComponentId, Quantity),
+    /// A math channel over the other probes of a `Recording`, evaluated on each sample.
+    Derived(DerivedExpr),
+}
+
+/// The expression of a `Probe::Derived`, like a scope's math channel.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DerivedExpr {
+    /// The sample of the probe at this index of the recording, which has to come before the
+    /// derived one.
+    Channel(usize),
+    Constant(f),
+    Sum(Box<DerivedExpr>, Box<DerivedExpr>),
+    Difference(Box<DerivedExpr>, Box<DerivedExpr>),
+    Product(Box<DerivedExpr>, Box<DerivedExpr>),
+    /// `gain * expr + offset`.
+    Scaled {
+        expr: Box<DerivedExpr>,
+        gain: f,
+        offset: f,
+    },
+    /// `expr` through a first-order low-pass with its -3 dB point at `cutoff` Hz, as a scope's
+    /// bandwidth limit. It starts at the first sample and takes each later one as its input
+    /// since the sample before.
+    LowPass {
+        expr: Box<DerivedExpr>,
+        cutoff: f,
+    },
+}
+impl DerivedExpr {
+    pub fn plus(self, other: Self) -> Self {
+        Self::Sum(Box::new(self), Box::new(other))
+    }
+    pub fn minus(self, other: Self) -> Self {
+        Self::Difference(Box::new(self), Box::new(other))
+    }
+    pub fn times(self, other: Self) -> Self {
+        Self::Product(Box::new(self), Box::new(other))
+    }
+    pub fn scaled(self, gain: f, offset: f) -> Self {
+        Self::Scaled {
+            expr: Box::new(self),
+            gain,
+            offset,
+        }
+    }
+    pub fn low_pass(self, cutoff: f) -> Self {
+        Self::LowPass {
+            expr: Box::new(self),
+            cutoff,
+        }
+    }
+
+    /// The greatest `Channel` index of the expression.
+    fn max_channel(&self) -> Option<usize> {
+        match self {
+            Self::Channel(i) => Some(*i),
+            Self::Constant(_) => None,
+            Self::Sum(a, b) | Self::Difference(a, b) | Self::Product(a, b) => {
+                a.max_channel().max(b.max_channel())
+            }
+            Self::Scaled { expr, .. } | Self::LowPass { expr, .. } => expr.max_channel(),
+        }
+    }
+    /// `LowPass` nodes of the expression, each with a `LowPassState` in the recording.
+    fn n_filters(&self) -> usize {
+        match self {
+            Self::Channel(_) | Self::Constant(_) => 0,
+            Self::Sum(a, b) | Self::Difference(a, b) | Self::Product(a, b) => {
+                a.n_filters() + b.n_filters()
+            }
+            Self::Scaled { expr, .. } => expr.n_filters(),
+            Self::LowPass { expr, .. } => 1 + expr.n_filters(),
+        }
+    }
+    /// The value at `time` with `samples` the present samples of the probes before it, taking
+    /// the states of its filters from `filters` in the order of `n_filters`.
+    fn eval<'a>(
+        &self,
+        samples: &[f],
+        filters: &mut impl Iterator<Item = &'a mut LowPassState>,
+        time: f,
+    ) -> f {
+        match self {
+            Self::Channel(i) => samples[*i],
+            Self::Constant(x) => *x,
+            Self::Sum(a, b) => a.eval(samples, filters, time) + b.eval(samples, filters, time),
+            Self::Difference(a, b) => {
+                a.eval(samples, filters, time) - b.eval(samples, filters, time)
+            }
+            Self::Product(a, b) => a.eval(samples, filters, time) * b.eval(samples, filters, time),
+            Self::Scaled { expr, gain, offset } => {
+                gain * expr.eval(samples, filters, time) + offset
+            }
+            Self::LowPass { expr, cutoff } => {
+                let state = filters.next().unwrap();
+                let x = expr.eval(samples, filters, time);
+                let y = match state.last {
+                    // exact for an input constant over the step.
+                    Some((y, t)) => y + (x - y) * (1.0 - (-(time - t) * TAU * cutoff).exp()),
+                    None => x,
+                };
+                state.last = Some((y, time));
+                y
+            }
+        }
+    }
+}
+
+/// The state of a `DerivedExpr::LowPass` of a recording.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct LowPassState {
+    /// The output and time of the last sample.
+    last: Option<(f, f)>,
+}
+impl Probe {
+    /// Read `net:<net>` or `comp:<component>.<current|voltage|power>`, with the names of `names`.
+    pub fn parse(spec: &str, names: &NameMap) -> Result<Self, SimError> {
+        let invalid = |message: String| SimError::InvalidProbe {
+            spec: spec.to_string(),
+            message,
+        };
+        if let Some(net) = spec.strip_prefix("net:") {
+            return names
+                .net(net)
+                .map(Self::Net)
+                .ok_or_else(|| invalid(format!("no net named {net:?}")));
+        }
+        let (component, quantity) = spec
+            .strip_prefix("comp:")
+            .and_then(|spec| spec.rsplit_once('.'))
+            .ok_or_else(|| invalid("expected net:<net> or comp:<component>.<quantity>".into()))?;
+        let component = names
+            .component(component)
+            .ok_or_else(|| invalid(format!("no component named {component:?}")))?;
+        let quantity = match quantity {
+            "current" => Quantity::Current,
+            "voltage" => Quantity::Voltage,
+            "power" => Quantity::Power,
+            _ => return Err(invalid(format!("unknown quantity {quantity:?}"))),
+        };
+        Ok(Self::Component(component, quantity))
+    }
+
+    /// The value of the probe; NaN for a `Derived` one, which only a `Recording` evaluates.
+    pub fn sample(&self, circuit: &CircuitState) -> f {
+        match *self {
+            Self::Net(net) => circuit.net_voltage(net) - circuit.net_voltage(0),
+            Self::Component(component, Quantity::Current) => circuit.branch_current(component),
+            Self::Component(component, Quantity::Voltage) => circuit.branch_voltage(component),
+            Self::Component(component, Quantity::Power) => circuit.instantaneous_power(component),
+            Self::Derived(_) => f::NAN,
+        }
+    }
+}
+
+/// How a channel of a decimated recording reduces the samples of each bucket of ticks to a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Policy {
+    /// The last sample of the bucket: plain take-every-Nth.
+    #[default]
+    Sample,
+    /// The mean of the bucket, a boxcar filter against aliasing.
+    Mean,
+    /// The least and greatest sample of the bucket, as two columns `<label>.min` and
+    /// `<label>.max`, so spikes shorter than a bucket still show when plotted.
+    Envelope,
+}
+
+/// Samples of a set of probes over a run, one row per `record` call or per bucket of `every`
+/// calls for `Recording::decimated`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recording {
+    probes: Vec<(Probe, Policy)>,
+    /// Label of each column of `channels`.
+    labels: Vec<String>,
+    pub time: Vec<f>,
+    /// One `Vec` per column: one per probe, in the order given to `new`, except two for an
+    /// `Envelope`.
+    #[cfg_attr(feature = "serde", serde(with = "channels"))]
+    pub channels: Vec<Vec<f>>,
+    every: usize,
+    bucket: Bucket,
+    /// One per `DerivedExpr::LowPass`, probe by probe.
+    #[cfg_attr(feature = "serde", serde(default))]
+    filters: Vec<LowPassState>,
+}
+/// The samples of the row being recorded, per probe.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Bucket {
+    n: usize,
+    time: f,
+    #[cfg_attr(feature = "serde", serde(with = "samples"))]
+    last: Vec<f>,
+    #[cfg_attr(feature = "serde", serde(with = "samples"))]
+    sum: Vec<f>,
+    #[cfg_attr(feature = "serde", serde(with = "samples"))]
+    min: Vec<f>,
+    #[cfg_attr(feature = "serde", serde(with = "samples"))]
+    max: Vec<f>,
+}
+impl Recording {
+    /// Record `probes`, labelled for `write_csv`.
+    pub fn new(probes: Vec<(String, Probe)>) -> Self {
+        Self::decimated(
+            probes
+                .into_iter()
+                .map(|(label, probe)| (label, probe, Policy::Sample))
+                .collect(),
+            1,
+        )
+    }
+    /// Record one row per `every` calls of `record`, each probe reduced by its policy. Rows
+    /// carry the time of the last sample in them. Derived probes are evaluated on the samples of
+    /// each `record` and reduced like the others.
+    ///
+    /// Panics if a `Probe::Derived` reads a channel that isn't before it.
+    pub fn decimated(probes: Vec<(String, Probe, Policy)>, every: usize) -> Self {
+        let mut labels = Vec::new();
+        let mut n_filters = 0;
+        for (i, (label, probe, policy)) in probes.iter().enumerate() {
+            if let Probe::Derived(expr) = probe {
+                if let Some(channel) = expr.max_channel() {
+                    assert!(
+                        channel < i,
+                        "derived probe {label:?} reads channel {channel}, which isn't before it"
+                    );
+                }
+                n_filters += expr.n_filters();
+            }
+            match policy {
+                Policy::Sample | Policy::Mean => labels.push(label.clone()),
+                Policy::Envelope => {
+                    labels.push(format!("{label}.min"));
+                    labels.push(format!("{label}.max"));
+                }
+            }
+        }
+        let n = probes.len();
+        Self {
+            channels: vec![Vec::new(); labels.len()],
+            labels,
+            probes: probes
+                .into_iter()
+                .map(|(_, probe, policy)| (probe, policy))
+                .collect(),
+            time: Vec::new(),
+            every: every.max(1),
+            bucket: Bucket {
+                last: vec![0.0; n],
+                sum: vec![0.0; n],
+                min: vec![f::INFINITY; n],
+                max: vec![f::NEG_INFINITY; n],
+                ..Bucket::default()
+            },
+            filters: vec![LowPassState::default(); n_filters],
+        }
+    }
+
+    /// Sample every probe at simulation time `time`.
+    pub fn record(&mut self, circuit: &CircuitState, time: f) {
+        self.record_with(time, |_, probe| probe.sample(circuit));
+    }
+    /// `record` with the probe at each index sampled by `sample`, for probes on several circuits.
+    pub(super) fn record_with(&mut self, time: f, mut sample: impl FnMut(usize, &Probe) -> f) {
+        let bucket = &mut self.bucket;
+        let mut filters = self.filters.iter_mut();
+        for (i, (probe, _)) in self.probes.iter().enumerate() {
+            let x = match probe {
+                Probe::Derived(expr) => expr.eval(&bucket.last[..i], &mut filters, time),
+                _ => sample(i, probe),
+            };
+            bucket.last[i] = x;
+            bucket.sum[i] += x;
+            bucket.min[i] = bucket.min[i].min(x);
+            bucket.max[i] = bucket.max[i].max(x);
+        }
+        bucket.n += 1;
+        bucket.time = time;
+        if bucket.n == self.every {
+            self.flush();
+        }
+    }
+    /// End the row being recorded with the samples so far, if there are any; for the end of a
+    /// decimated run.
+    pub fn flush(&mut self) {
+        let bucket = &mut self.bucket;
+        if bucket.n == 0 {
+            return;
+        }
+        self.time.push(bucket.time);
+        let mut columns = self.channels.iter_mut();
+        for (i, &(_, policy)) in self.probes.iter().enumerate() {
+            let mut push = |x| columns.next().unwrap().push(x);
+            match policy {
+                Policy::Sample => push(bucket.last[i]),
+                Policy::Mean => push(bucket.sum[i] / bucket.n as f),
+                Policy::Envelope => {
+                    push(bucket.min[i]);
+                    push(bucket.max[i]);
+                }
+            }
+            bucket.sum[i] = 0.0;
+            bucket.min[i] = f::INFINITY;
+            bucket.max[i] = f::NEG_INFINITY;
+        }
+        bucket.n = 0;
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.labels.iter().map(String::as_str)
+    }
+    /// The samples of the column labelled `label`.
+    pub fn channel(&self, label: &str) -> Option<&[f]> {
+        let i = self.labels.iter().position(|l| l == label)?;
+        Some(&self.channels[i])
+    }
+
+    /// Write a CSV with a `time` column followed by one column per probe (two for an
+    /// `Envelope`).
+    pub fn write_csv(&self, mut out: impl io::Write) -> io::Result<()> {
+        self.write_csv_header(&mut out)?;
+        self.write_csv_rows(&mut out)
+    }
+    fn write_csv_header(&self, out: &mut impl io::Write) -> io::Result<()> {
+        write!(out, "time")?;
+        for label in self.labels() {
+            write!(out, ",{label}")?;
+        }
+        writeln!(out)
+    }
+    fn write_csv_rows(&self, out: &mut impl io::Write) -> io::Result<()> {
+        for (row, time) in self.time.iter().enumerate() {
+            write!(out, "{time:e}")?;
+            for channel in &self.channels {
+                write!(out, ",{:e}", channel[row])?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `Recording` written to `sink` as CSV (in the format of `Recording::write_csv`) every
+/// `chunk_rows` rows, so a long run keeps at most that many rows in memory.
+#[derive(Debug)]
+pub struct StreamingRecording<W: io::Write> {
+    recording: Recording,
+    sink: W,
+    chunk_rows: usize,
+    header_written: bool,
+}
+impl<W: io::Write> StreamingRecording<W> {
+    /// Stream the rows of `recording` (which should have none yet).
+    pub fn new(recording: Recording, sink: W, chunk_rows: usize) -> Self {
+        Self {
+            recording,
+            sink,
+            chunk_rows: chunk_rows.max(1),
+            header_written: false,
+        }
+    }
+    /// `Recording::record`, writing out the rows if a chunk is full.
+    pub fn record(&mut self, circuit: &CircuitState, time: f) -> io::Result<()> {
+        self.recording.record(circuit, time);
+        if self.recording.time.len() >= self.chunk_rows {
+            self.write_chunk()?;
+        }
+        Ok(())
+    }
+    /// Flush the last row and write out what is left, returning the sink.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.recording.flush();
+        self.write_chunk()?;
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if !self.header_written {
+            self.recording.write_csv_header(&mut self.sink)?;
+            self.header_written = true;
+        }
+        self.recording.write_csv_rows(&mut self.sink)?;
+        self.recording.time.clear();
+        for channel in &mut self.recording.channels {
+            channel.clear();
+        }
+        Ok(())
+    }
+}
+
+/// `serde(with)` for samples, which unlike circuit state are often not finite (a bucket's
+/// running extremes start at the infinities): those go as the strings `NaN`, `inf` and `-inf`.
+#[cfg(feature = "serde")]
+mod samples {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::f;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Sample {
+        Number(f),
+        NonFinite(String),
+    }
+
+    pub(super) fn serialize<Ser: Serializer>(
+        samples: &[f],
+        serializer: Ser,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(samples.iter().map(|&x| NonFinite(x)))
+    }
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<f>, D::Error> {
+        Vec::<Sample>::deserialize(deserializer)?
+            .into_iter()
+            .map(|sample| match sample {
+                Sample::Number(x) => Ok(x),
+                Sample::NonFinite(text) => text.parse().map_err(serde::de::Error::custom),
+            })
+            .collect()
+    }
+
+    struct NonFinite(f);
+    impl Serialize for NonFinite {
+        fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+            match self.0 {
+                x if x.is_finite() => serializer.serialize_f64(x),
+                x if x.is_nan() => serializer.serialize_str("NaN"),
+                x if x > 0.0 => serializer.serialize_str("inf"),
+                _ => serializer.serialize_str("-inf"),
+            }
+        }
+    }
+}
+
+/// `samples` for a `Vec` of channels.
+#[cfg(feature = "serde")]
+mod channels {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::f;
+
+    pub(super) fn serialize<Ser: Serializer>(
+        channels: &[Vec<f>],
+        serializer: Ser,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(channels.iter().map(|channel| Channel(channel)))
+    }
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<f>>, D::Error> {
+        Ok(Vec::<OwnedChannel>::deserialize(deserializer)?
+            .into_iter()
+            .map(|channel| channel.0)
+            .collect())
+    }
+
+    struct Channel<'a>(&'a [f]);
+    impl Serialize for Channel<'_> {
+        fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+            super::samples::serialize(self.0, serializer)
+        }
+    }
+    #[derive(Deserialize)]
+    struct OwnedChannel(#[serde(with = "super::samples")] Vec<f>);
+}
@@ -0,0 +1,80 @@
+//! Hardware protection of a co-simulated controller: a comparator on a probe (bus or FET
+//! current, bus voltage) that latches the gates off when it trips, as ESC gate drivers do
+//! within microseconds and without the firmware. See `CoSim::add_protection`.
+
+use super::{cosim::ControlId, f, probe::Probe};
+
+pub type ProtectionId = usize;
+
+/// A latching comparator trip on `probe`, checked after every step of a `CoSim`.
+#[derive(Debug, Clone)]
+pub struct ProtectionBlock {
+    pub probe: Probe,
+    /// Trips when the probe goes above this, or its magnitude does for `bipolar`.
+    pub threshold: f,
+    pub bipolar: bool,
+    /// Time after an edge of any of `gates` for which the comparator is ignored, so the
+    /// recovery spike of a switching edge doesn't trip it.
+    pub blanking: f,
+    /// Time from the step the comparator first trips at to the gates going low.
+    pub response_time: f,
+    /// The controls forced low while tripped: sources to 0 V, switches open and PWMs off.
+    pub gates: Vec<ControlId>,
+    /// When the comparator tripped, while latched.
+    tripped_at: Option<f>,
+}
+impl ProtectionBlock {
+    pub fn new(probe: Probe, threshold: f, gates: Vec<ControlId>) -> Self {
+        Self {
+            probe,
+            threshold,
+            bipolar: false,
+            blanking: 0.0,
+            response_time: 0.0,
+            gates,
+            tripped_at: None,
+        }
+    }
+    pub fn bipolar(mut self) -> Self {
+        self.bipolar = true;
+        self
+    }
+    pub fn with_blanking(mut self, blanking: f) -> Self {
+        self.blanking = blanking;
+        self
+    }
+    pub fn with_response_time(mut self, response_time: f) -> Self {
+        self.response_time = response_time;
+        self
+    }
+
+    /// Whether the latch is set. The gates go low `response_time` after it tripped.
+    pub fn tripped(&self) -> bool {
+        self.tripped_at.is_some()
+    }
+    /// The simulation time the comparator tripped at, while latched.
+    pub fn tripped_at(&self) -> Option<f> {
+        self.tripped_at
+    }
+    /// Release the latch; the gates follow their controls again from the next step.
+    pub fn reset(&mut self) {
+        self.tripped_at = None;
+    }
+
+    /// Check the comparator on `sample` at `time`, with the last edge of its gates at
+    /// `last_edge`.
+    pub(super) fn check(&mut self, sample: f, time: f, last_edge: Option<f>) {
+        if self.tripped() || last_edge.is_some_and(|edge| time - edge < self.blanking) {
+            return;
+        }
+        let level = if self.bipolar { sample.abs() } else { sample };
+        if level > self.threshold {
+            self.tripped_at = Some(time);
+        }
+    }
+    /// Whether the gates are forced low for the step starting at `time`, `dt` long.
+    pub(super) fn forces_low(&self, time: f, dt: f) -> bool {
+        self.tripped_at
+            .is_some_and(|at| time + 0.5 * dt >= at + self.response_time)
+    }
+}
@@ -0,0 +1,31 @@
+use super::f;
+
+/// Small seedable PRNG (SplitMix64), so analyses are reproducible without external crates.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rng {
+    state: u64,
+}
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    /// Uniform in `[0, 1)`.
+    pub fn uniform(&mut self) -> f {
+        (self.next_u64() >> 11) as f / (1u64 << 53) as f
+    }
+    /// Standard normal sample (Box-Muller).
+    pub fn gaussian(&mut self) -> f {
+        let u0 = 1.0 - self.uniform(); // in (0, 1], keeps ln finite
+        let u1 = self.uniform();
+        (-2.0 * u0.ln()).sqrt() * (std::f64::consts::TAU * u1).cos()
+    }
+}
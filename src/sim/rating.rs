@@ -0,0 +1,154 @@
+//! Ratings of resistors (see `ResistorRating`): a temperature coefficient their resistance
+//! follows the ambient or thermal-network temperature by, and a power rating a
+//! `LossAccumulator` checks their average dissipation against, reporting each stretch of a run
+//! spent above it as a `RatingViolation`. An undersized gate or bleed resistor is the usual find.
+
+use super::{
+    components::{LinearComponentValue, ResistorRating},
+    error::SimError,
+    f, CircuitState, ComponentId, ComponentMut, Scalar,
+};
+
+impl<S: Scalar> CircuitState<S> {
+    /// Give the resistor `component` `rating`, or take its ratings away for `None`. Its
+    /// resistance is taken as at `NOMINAL_TEMPERATURE` and moves with its temperature from now
+    /// on by `rating.tempco`.
+    ///
+    /// Errors if `component` doesn't exist, or with `SimError::WrongComponentKind` if it isn't
+    /// a `LinearComponentValue::Resistive`.
+    pub fn set_resistor_rating(
+        &mut self,
+        component: ComponentId,
+        rating: Option<ResistorRating<S>>,
+    ) -> Result<(), SimError> {
+        if component >= self.slots.len() {
+            return Err(SimError::UnknownComponent(component));
+        }
+        match self.component_mut(component) {
+            ComponentMut::Linear(state)
+                if matches!(state.value, LinearComponentValue::Resistive(_)) =>
+            {
+                state.set_rating(rating);
+                Ok(())
+            }
+            _ => Err(SimError::WrongComponentKind {
+                component,
+                expected: "a resistor",
+            }),
+        }
+    }
+}
+
+/// A stretch of a run over which a resistor dissipated more than its `ResistorRating::power`
+/// on average, see `LossAccumulator::with_rating_window`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatingViolation {
+    pub component: ComponentId,
+    /// Simulation times the stretch begins and ends at, whole averaging windows.
+    pub start: f,
+    pub end: f,
+    pub rating: f,
+    /// The mean dissipation over the stretch, in watts.
+    pub average: f,
+    /// The highest dissipation of any tick in the stretch, in watts.
+    pub peak: f,
+}
+
+/// The averaging window of one rated resistor in a `LossAccumulator`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct RatingMonitor {
+    /// The window being averaged: its start, the energy dissipated in it so far and over how
+    /// long, and its highest dissipation.
+    start: f,
+    energy: f,
+    duration: f,
+    peak: f,
+    /// The violation the windows up to this one are the end of.
+    open: Option<RatingViolation>,
+}
+impl RatingMonitor {
+    /// Add a tick of length `dt` ending at `time`, dissipating `power`, closing the window if
+    /// it is `window` long. Returns the violation the window ends, if it does.
+    pub(super) fn add(
+        &mut self,
+        component: ComponentId,
+        rating: f,
+        power: f,
+        time: f,
+        dt: f,
+        window: f,
+    ) -> Option<RatingViolation> {
+        if self.duration == 0.0 {
+            self.start = time - dt;
+        }
+        self.energy += power * dt;
+        self.duration += dt;
+        self.peak = self.peak.max(power);
+        if self.duration < window - 0.5 * dt {
+            return None;
+        }
+        self.close_window(component, rating, time)
+    }
+
+    /// The violation this resistor is in, counting the window under way.
+    pub(super) fn finish(
+        &self,
+        component: ComponentId,
+        rating: f,
+        time: f,
+    ) -> Option<RatingViolation> {
+        let mut monitor = *self;
+        if monitor.duration > 0.0 {
+            if let Some(violation) = monitor.close_window(component, rating, time) {
+                return Some(violation);
+            }
+        }
+        monitor.open
+    }
+
+    /// End the window at `time`, extending the open violation by it if its average is over
+    /// `rating`, or returning the one it ends.
+    fn close_window(
+        &mut self,
+        component: ComponentId,
+        rating: f,
+        time: f,
+    ) -> Option<RatingViolation> {
+        let open = self.open.take();
+        let window = std::mem::take(self);
+        let average = window.energy / window.duration;
+        if average <= rating {
+            return open;
+        }
+        self.open = Some(match open {
+            Some(violation) => {
+                let duration = violation.end - violation.start;
+                RatingViolation {
+                    end: time,
+                    average: (violation.average * duration + window.energy)
+                        / (duration + window.duration),
+                    peak: violation.peak.max(window.peak),
+                    ..violation
+                }
+            }
+            None => {
+                trace_event!(
+                    warn,
+                    component,
+                    average,
+                    rating,
+                    "resistor dissipating above its power rating"
+                );
+                RatingViolation {
+                    component,
+                    start: window.start,
+                    end: time,
+                    rating,
+                    average,
+                    peak: window.peak,
+                }
+            }
+        });
+        None
+    }
+}
@@ -0,0 +1,118 @@
+//! Replacing the value of a component between ticks (a throttle-dependent source, a
+//! potentiometer swept, a resistance scaled with temperature) without touching its state by
+//! hand: `CircuitState::set_component_value` keeps the kind of the component, whose state means
+//! something different for every kind, and carries a capacitor's state across by a `ChargePolicy`.
+
+use super::{
+    components::LinearComponentValue, error::SimError, CircuitState, ComponentId, ComponentMut,
+    ComponentValueEnum, Scalar,
+};
+
+/// What a capacitor whose capacitance changes keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ChargePolicy {
+    /// The voltage across it, its charge rescaled by the ratio of the capacitances, as for a
+    /// part swapped in already charged to it.
+    #[default]
+    PreserveVoltage,
+    /// Its charge, so the voltage moves inversely to the capacitance, as for plates pulled apart.
+    PreserveCharge,
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Replace the value of `component` with `value`, from the next `solve_state` on.
+    ///
+    /// The value has to be of the same kind as the one it replaces (down to the variant of
+    /// `LinearComponentValue`: a `Capacitive` stays `Capacitive`). A capacitor's charge is carried
+    /// across by `policy`; inductors keep their current and every other component its state
+    /// as it is. A resistance is taken as at `NOMINAL_TEMPERATURE`, as by the `ResistorRating`
+    /// of the resistor.
+    ///
+    /// Errors if `component` doesn't exist, or with `SimError::WrongComponentKind` naming the
+    /// kind of `value` if the component isn't of it.
+    pub fn set_component_value(
+        &mut self,
+        component: ComponentId,
+        value: ComponentValueEnum<S>,
+        policy: ChargePolicy,
+    ) -> Result<(), SimError> {
+        if component >= self.slots.len() {
+            return Err(SimError::UnknownComponent(component));
+        }
+        let wrong_kind = SimError::WrongComponentKind {
+            component,
+            expected: kind_name(&value),
+        };
+        if self.component(component).as_dyn().nets().len() != value.n_terminals() {
+            return Err(wrong_kind);
+        }
+        match (self.component_mut(component), value) {
+            (ComponentMut::Linear(state), ComponentValueEnum::Linear(value)) => {
+                if std::mem::discriminant(&state.value) != std::mem::discriminant(&value) {
+                    return Err(wrong_kind);
+                }
+                let capacitances = match (state.value, value) {
+                    (
+                        LinearComponentValue::Capacitive(old),
+                        LinearComponentValue::Capacitive(new),
+                    )
+                    | (
+                        LinearComponentValue::LossyCapacitive {
+                            capacitance: old, ..
+                        },
+                        LinearComponentValue::LossyCapacitive {
+                            capacitance: new, ..
+                        },
+                    ) => Some((old, new)),
+                    _ => None,
+                };
+                if let (Some((old, new)), ChargePolicy::PreserveVoltage) = (capacitances, policy) {
+                    state.q[0] = state.q[0] * new / old;
+                }
+                state.value = value;
+                state.scale_to_temperature();
+            }
+            (ComponentMut::MOSFET(state), ComponentValueEnum::MOSFET(value)) => {
+                state.value = value;
+            }
+            (ComponentMut::NoiseSource(state), ComponentValueEnum::NoiseSource(value)) => {
+                state.value = value;
+            }
+            (ComponentMut::GateDriver(state), ComponentValueEnum::GateDriver(value)) => {
+                state.value = value;
+            }
+            (ComponentMut::Comparator(state), ComponentValueEnum::Comparator(value)) => {
+                state.value = value;
+            }
+            (ComponentMut::Converter(state), ComponentValueEnum::Converter(value)) => {
+                state.value = value;
+            }
+            _ => return Err(wrong_kind),
+        }
+        Ok(())
+    }
+}
+
+/// The kind of `value` for `SimError::WrongComponentKind`.
+fn kind_name<S: Scalar>(value: &ComponentValueEnum<S>) -> &'static str {
+    match value {
+        ComponentValueEnum::Linear(value) => match value {
+            LinearComponentValue::Capacitive(_) => "a capacitor",
+            LinearComponentValue::LossyCapacitive { .. } => "a lossy capacitor",
+            LinearComponentValue::Resistive(_) => "a resistor",
+            LinearComponentValue::Inductive(_) => "an inductor",
+            LinearComponentValue::SaturatingInductive { .. } => "a saturating inductor",
+            LinearComponentValue::Source(_) => "a source",
+            LinearComponentValue::Pwm(_) => "a PWM source",
+            LinearComponentValue::Switch { .. } => "a switch",
+            LinearComponentValue::Jumper => "a jumper",
+        },
+        ComponentValueEnum::MOSFET(_) => "a MOSFET",
+        ComponentValueEnum::NoiseSource(_) => "a noise source",
+        ComponentValueEnum::GateDriver(_) => "a gate driver",
+        ComponentValueEnum::Comparator(_) => "a comparator",
+        ComponentValueEnum::Converter(_) => "a DC-DC converter",
+    }
+}
@@ -0,0 +1,155 @@
+//! Reverse battery connection, the classic way to destroy an ESC: flipping a source between two
+//! ticks (`CircuitState::reverse_source`, or `Action::ReverseSource` in a scenario), fuses that
+//! blow on the current that follows, and the protection placed between battery and bus to
+//! prevent it (`CircuitBuilder::reverse_protection`).
+
+use super::{
+    builder::{CircuitBuilder, PATH_SEPARATOR},
+    components::{Fuse, LinearComponentValue, MOSFETComponentValue},
+    error::SimError,
+    f, CircuitState, ComponentId, ComponentMut, ComponentRef, Scalar,
+};
+
+impl<S: Scalar> CircuitState<S> {
+    /// Negate the voltage of the source `component` (both levels of a `Pwm`) from the next tick
+    /// on. A source bound to a signal is set again by the next `set_signal`, with its old sign.
+    ///
+    /// Errors if `component` doesn't exist, or with `SimError::WrongComponentKind` if it isn't
+    /// a `Source` or `Pwm`.
+    pub fn reverse_source(&mut self, component: ComponentId) -> Result<(), SimError> {
+        if component >= self.slots.len() {
+            return Err(SimError::UnknownComponent(component));
+        }
+        match self.component_mut(component) {
+            ComponentMut::Linear(state) => match &mut state.value {
+                LinearComponentValue::Source(v) => *v = -*v,
+                LinearComponentValue::Pwm(wave) => {
+                    wave.low = -wave.low;
+                    wave.high = -wave.high;
+                }
+                _ => return Err(wrong_kind(component, "a source")),
+            },
+            _ => return Err(wrong_kind(component, "a source")),
+        }
+        Ok(())
+    }
+
+    /// Make the switch `component` a fuse of melting integral `i2t` (in A^2 s), intact and
+    /// closed; `None` makes it a plain switch again, left as it is. See `Fuse`.
+    ///
+    /// Errors if `component` doesn't exist, or with `SimError::WrongComponentKind` if it isn't
+    /// a `Switch`.
+    pub fn set_fuse(&mut self, component: ComponentId, i2t: Option<S>) -> Result<(), SimError> {
+        if component >= self.slots.len() {
+            return Err(SimError::UnknownComponent(component));
+        }
+        match self.component_mut(component) {
+            ComponentMut::Linear(state) => match &mut state.value {
+                LinearComponentValue::Switch { closed, .. } => {
+                    if i2t.is_some() {
+                        *closed = true;
+                    }
+                    state.set_fuse(i2t.map(Fuse::new));
+                }
+                _ => return Err(wrong_kind(component, "a switch")),
+            },
+            _ => return Err(wrong_kind(component, "a switch")),
+        }
+        Ok(())
+    }
+    /// Whether the fuse `component` has blown; `None` if it isn't a fuse.
+    pub fn fuse_blown(&self, component: ComponentId) -> Option<bool> {
+        match self.component(component) {
+            ComponentRef::Linear(state) => state.fuse().map(|fuse| fuse.blown()),
+            _ => None,
+        }
+    }
+}
+
+fn wrong_kind(component: ComponentId, expected: &'static str) -> SimError {
+    SimError::WrongComponentKind {
+        component,
+        expected,
+    }
+}
+
+/// How `CircuitBuilder::reverse_protection` keeps a reversed battery off the bus.
+#[derive(Debug, Clone, Copy)]
+pub enum ReverseProtection<S: Scalar = f> {
+    /// A P-FET in the positive lead, drain to the battery and source to the bus, its gate pulled
+    /// to ground through `gate_resistance` and clamped to its source by `clamp_resistance`, so
+    /// `V_gs` is the bus voltage divided down by the two. Reversed, the gate sits at the source
+    /// and the body diode blocks.
+    ///
+    /// The right way round a real part's channel carries the current source-wards, but the
+    /// MOSFET model has no reverse channel conduction (see `MOSFETRegion::BodyDiode`), so the
+    /// body diode carries it here, with its drop, as for a `Diode`.
+    PFet {
+        mosfet: MOSFETComponentValue<S>,
+        gate_resistance: S,
+        clamp_resistance: S,
+    },
+    /// A series diode, the body diode of `mosfet` (a P-FET) with its gate tied to its source:
+    /// the bus sits a diode drop below the battery.
+    Diode { mosfet: MOSFETComponentValue<S> },
+}
+
+/// The components `CircuitBuilder::reverse_protection` added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReverseProtectionHandles {
+    pub mosfet: ComponentId,
+    /// The gate resistor to ground and the gate-source clamp of a `ReverseProtection::PFet`.
+    pub gate_resistor: Option<ComponentId>,
+    pub clamp_resistor: Option<ComponentId>,
+}
+
+impl<S: Scalar> CircuitBuilder<S> {
+    /// A switch between `a` and `b` that blows open once `i2t` (in A^2 s) has been let
+    /// through, see `CircuitState::set_fuse`.
+    pub fn fuse(&mut self, name: &str, a: &str, b: &str, i2t: S) -> Result<&mut Self, SimError> {
+        self.switch(name, a, b, true)?;
+        let component = self.names().component(name).unwrap();
+        self.circuit_mut().set_fuse(component, Some(i2t))?;
+        Ok(self)
+    }
+
+    /// Add `protection` between the positive terminal of the battery, `battery`, and `bus`,
+    /// with `ground` its negative terminal. Its components are named `<name>/Q`, `<name>/RG`
+    /// and `<name>/RGS`, as in an instance (see `CircuitBuilder::instance`), and so is the gate
+    /// net `<name>/gate`.
+    pub fn reverse_protection(
+        &mut self,
+        name: &str,
+        protection: ReverseProtection<S>,
+        battery: &str,
+        bus: &str,
+        ground: &str,
+    ) -> Result<ReverseProtectionHandles, SimError> {
+        let path = |inner: &str| format!("{name}{PATH_SEPARATOR}{inner}");
+        let (mosfet, gate, resistors) = match protection {
+            ReverseProtection::PFet {
+                mosfet,
+                gate_resistance,
+                clamp_resistance,
+            } => (
+                mosfet,
+                path("gate"),
+                Some((gate_resistance, clamp_resistance)),
+            ),
+            ReverseProtection::Diode { mosfet } => (mosfet, bus.to_string(), None),
+        };
+        self.mosfet(&path("Q"), mosfet, bus, &gate, battery)?;
+        let mut handles = ReverseProtectionHandles {
+            mosfet: self.names().component(&path("Q")).unwrap(),
+            gate_resistor: None,
+            clamp_resistor: None,
+        };
+        if let Some((gate_resistance, clamp_resistance)) = resistors {
+            self.resistor(&path("RG"), &gate, ground, gate_resistance)?
+                .resistor(&path("RGS"), bus, &gate, clamp_resistance)?;
+            handles.gate_resistor = self.names().component(&path("RG"));
+            handles.clamp_resistor = self.names().component(&path("RGS"));
+        }
+        Ok(handles)
+    }
+}
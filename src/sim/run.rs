@@ -0,0 +1,335 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use super::{
+    error::SimError,
+    f,
+    operating_limits::LimitViolation,
+    plot::{PlotBatch, PlotSink},
+    probe::{Probe, Recording},
+    solver::SolveReport,
+    CircuitState, ComponentId,
+};
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunConfig {
+    pub dt: f,
+    pub n_steps: usize,
+    /// Steps between calls of the progress callback.
+    pub progress_every: usize,
+    /// Steps a resumed run already did (see `checkpoint::Checkpoint::run_config`), 0 for a new
+    /// one. The run goes on from there to `n_steps` and doesn't record the initial row again.
+    pub start_step: usize,
+}
+
+/// Passed to the progress callback of `run_with_progress`.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub sim_time: f,
+    pub wall_time: Duration,
+    pub steps_done: usize,
+    pub last_solve: SolveReport,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunOutcome {
+    /// Samples up to the last completed step.
+    pub recording: Recording,
+    pub steps_done: usize,
+    /// Whether the run stopped early on `cancel`.
+    pub cancelled: bool,
+    /// Steps whose `solve_state` did not converge.
+    pub unconverged: usize,
+    /// `CircuitState::limit_violations` at the end of the run.
+    pub limit_violations: Vec<LimitViolation>,
+    /// Whether the run stopped at the first of `limit_violations`, see
+    /// `CircuitState::set_abort_on_violation`.
+    pub aborted: bool,
+}
+
+/// What `CircuitState::run_simple` saw of a run: everything it recorded, summaries of each
+/// channel and how the solver did.
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    /// Samples up to the last completed step, the first at time 0.
+    pub recording: Recording,
+    /// One per column of `recording`, in its order.
+    pub channels: Vec<ChannelSummary>,
+    pub steps_done: usize,
+    /// Outer iterations of every step's `solve_state`.
+    pub total_iterations: usize,
+    /// The time the step taking the most iterations ended at, and how many it took.
+    pub worst_step: Option<(f, usize)>,
+    /// End times of the steps that did not converge.
+    pub failures: Vec<f>,
+    /// Whether the run stopped at the first of `failures` or `limit_violations`, before
+    /// `n_steps`.
+    pub stopped_early: bool,
+    /// `CircuitState::avalanche_overloads` at the end of the run: the MOSFETs that avalanched
+    /// past their `E_AS` rating, with the energy.
+    pub avalanche_overloads: Vec<(ComponentId, f)>,
+    /// `CircuitState::limit_violations` at the end of the run; with
+    /// `CircuitState::set_abort_on_violation` the run stops after the step the first began at.
+    pub limit_violations: Vec<LimitViolation>,
+}
+impl RunSummary {
+    pub fn converged(&self) -> bool {
+        self.failures.is_empty()
+    }
+    /// The summary of the channel labelled `label`.
+    pub fn channel(&self, label: &str) -> Option<&ChannelSummary> {
+        self.channels.iter().find(|channel| channel.label == label)
+    }
+}
+
+/// Statistics of one column of a `RunSummary::recording` over the whole run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelSummary {
+    pub label: String,
+    pub min: f,
+    pub max: f,
+    /// Of the samples, as evenly spaced in time.
+    pub mean: f,
+    /// The sample after the last step.
+    pub last: f,
+}
+
+impl CircuitState {
+    /// Tick `n_steps` times by `dt`, recording `probes` (labelled as for `Recording::new`) before
+    /// the first step and after each, and summarize the run. Stops after the first step that
+    /// doesn't converge, returning what it has; see `run_simple_through` to go on.
+    ///
+    /// For experiments and checks: `run_with_progress` is the run to build tools on.
+    pub fn run_simple(
+        &mut self,
+        dt: f,
+        n_steps: usize,
+        probes: Vec<(String, Probe)>,
+    ) -> RunSummary {
+        self.simple_run(dt, n_steps, probes, true)
+    }
+    /// `run_simple`, carrying on past steps that don't converge.
+    pub fn run_simple_through(
+        &mut self,
+        dt: f,
+        n_steps: usize,
+        probes: Vec<(String, Probe)>,
+    ) -> RunSummary {
+        self.simple_run(dt, n_steps, probes, false)
+    }
+    fn simple_run(
+        &mut self,
+        dt: f,
+        n_steps: usize,
+        probes: Vec<(String, Probe)>,
+        stop_on_failure: bool,
+    ) -> RunSummary {
+        let mut recording = Recording::new(probes);
+        recording.record(self, 0.0);
+        let mut steps_done = 0;
+        let mut total_iterations = 0;
+        let mut worst_step = None::<(f, usize)>;
+        let mut failures = Vec::new();
+        for step in 1..=n_steps {
+            let converged = self.tick(dt);
+            let time = step as f * dt;
+            recording.record(self, time);
+            steps_done = step;
+            let iterations = self.last_iterations();
+            total_iterations += iterations;
+            if worst_step.is_none_or(|(_, worst)| iterations > worst) {
+                worst_step = Some((time, iterations));
+            }
+            if !converged {
+                failures.push(time);
+                if stop_on_failure {
+                    break;
+                }
+            }
+            if self.should_abort() {
+                break;
+            }
+        }
+        let channels = recording
+            .labels()
+            .zip(&recording.channels)
+            .map(|(label, samples)| ChannelSummary {
+                label: label.to_string(),
+                min: samples.iter().copied().fold(f::INFINITY, f::min),
+                max: samples.iter().copied().fold(f::NEG_INFINITY, f::max),
+                mean: samples.iter().sum::<f>() / samples.len() as f,
+                last: samples.last().copied().unwrap_or(f::NAN),
+            })
+            .collect();
+        RunSummary {
+            recording,
+            channels,
+            steps_done,
+            total_iterations,
+            worst_step,
+            failures,
+            stopped_early: steps_done < n_steps,
+            avalanche_overloads: self.avalanche_overloads(),
+            limit_violations: self.limit_violations(),
+        }
+    }
+}
+
+/// Where `run_with_plot` sends the samples of a run.
+pub struct PlotConfig<'a> {
+    pub sink: &'a mut dyn PlotSink,
+    /// Simulation time between batches.
+    pub every: f,
+}
+
+/// Tick `circuit` `config.n_steps` times by `config.dt`, recording it after every step (and once
+/// before the first, at time 0).
+///
+/// `on_progress` is called every `config.progress_every` steps; `cancel` is checked before each
+/// step, and setting it (from another thread or a signal handler) ends the run with the
+/// recording so far (the last row of a decimated recording flushed).
+///
+/// With `CircuitState::set_abort_on_violation` the run also ends after the step an operating
+/// limit is first exceeded at, see `RunOutcome::aborted`.
+pub fn run_with_progress(
+    circuit: &mut CircuitState,
+    recording: Recording,
+    config: RunConfig,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(&Progress),
+) -> Result<RunOutcome, SimError> {
+    run(
+        circuit,
+        recording,
+        config,
+        cancel,
+        on_progress,
+        None,
+        |_, _, _| Ok(()),
+    )
+}
+
+/// `run_with_progress`, handing `plot.sink` the rows recorded in each `plot.every` of
+/// simulation time as the run passes it, and the rest when it ends.
+pub fn run_with_plot(
+    circuit: &mut CircuitState,
+    recording: Recording,
+    config: RunConfig,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(&Progress),
+    plot: PlotConfig<'_>,
+) -> Result<RunOutcome, SimError> {
+    run(
+        circuit,
+        recording,
+        config,
+        cancel,
+        on_progress,
+        Some(plot),
+        |_, _, _| Ok(()),
+    )
+}
+
+/// The run of `run_with_progress`, calling `after_step` with the circuit, the recording and the
+/// step number after each step is recorded.
+pub(super) fn run<E: From<SimError>>(
+    circuit: &mut CircuitState,
+    mut recording: Recording,
+    config: RunConfig,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(&Progress),
+    mut plot: Option<PlotConfig<'_>>,
+    mut after_step: impl FnMut(&CircuitState, &Recording, usize) -> Result<(), E>,
+) -> Result<RunOutcome, E> {
+    let start = Instant::now();
+    let mut unconverged = 0;
+    // rows already handed to the plot sink, and when the next batch is due.
+    let mut plotted = 0;
+    let mut next_batch = 0.0;
+    let resumed_at = config.start_step as f * config.dt;
+    if let Some(plot) = &mut plot {
+        plot.sink
+            .begin(&recording.labels().map(String::from).collect::<Vec<_>>());
+        next_batch = plot.every;
+        if config.start_step > 0 {
+            // the rows before the resumption were plotted by the run that recorded them.
+            plotted = recording.time.len();
+            while next_batch <= resumed_at + config.dt / 2.0 {
+                next_batch += plot.every;
+            }
+        }
+    }
+    if config.start_step == 0 {
+        recording.record(circuit, 0.0);
+    }
+    let mut steps_done = config.n_steps.max(config.start_step);
+    let mut cancelled = false;
+    let mut aborted = false;
+    for step in config.start_step + 1..=config.n_steps {
+        if cancel.load(Ordering::Relaxed) {
+            steps_done = step - 1;
+            cancelled = true;
+            break;
+        }
+        if !circuit.try_tick(config.dt)? {
+            unconverged += 1;
+        }
+        let sim_time = step as f * config.dt;
+        recording.record(circuit, sim_time);
+        after_step(circuit, &recording, step)?;
+        if let Some(plot) = &mut plot {
+            if sim_time >= next_batch - config.dt / 2.0 {
+                plot_batch(plot.sink, &recording, &mut plotted);
+                while next_batch <= sim_time + config.dt / 2.0 {
+                    next_batch += plot.every;
+                }
+            }
+        }
+        if config.progress_every > 0 && step % config.progress_every == 0 {
+            on_progress(&Progress {
+                sim_time,
+                wall_time: start.elapsed(),
+                steps_done: step,
+                last_solve: circuit.last_solve_report(),
+            });
+        }
+        if circuit.should_abort() {
+            steps_done = step;
+            aborted = true;
+            break;
+        }
+    }
+    recording.flush();
+    if let Some(plot) = plot {
+        plot_batch(plot.sink, &recording, &mut plotted);
+        plot.sink.end();
+    }
+    Ok(RunOutcome {
+        recording,
+        steps_done,
+        cancelled,
+        unconverged,
+        limit_violations: circuit.limit_violations(),
+        aborted,
+    })
+}
+
+/// Hand `sink` the rows of `recording` from `plotted` on, if there are any.
+fn plot_batch(sink: &mut dyn PlotSink, recording: &Recording, plotted: &mut usize) {
+    if recording.time.len() <= *plotted {
+        return;
+    }
+    let channels = recording
+        .channels
+        .iter()
+        .map(|channel| &channel[*plotted..])
+        .collect::<Vec<_>>();
+    sink.batch(PlotBatch {
+        time: &recording.time[*plotted..],
+        channels: &channels,
+    });
+    *plotted = recording.time.len();
+}
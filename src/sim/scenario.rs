@@ -0,0 +1,525 @@
+//! Scripted validation scenarios: timed actions (signal ramps, switch toggles, parameter changes
+//! such as a resistance dropping to 1 mohm to emulate a short) applied to a circuit while
+//! assertions on its probes are checked after every tick, as in "ramp the throttle over 200 ms,
+//! at 300 ms short phase B to ground for 1 ms, the supply current stays below 40 A".
+//!
+//! With the "serde" feature a scenario loads from JSON through `Scenario::from_json`, e.g.
+//!
+//! ```text
+//! {
+//!     "name": "throttle_ramp",
+//!     "netlist": "...",
+//!     "signals": [{ "signal": "throttle", "component": "Vthrottle", "gain": 5.0 }],
+//!     "dt": 1e-5,
+//!     "duration": 0.01,
+//!     "actions": [{ "at": 0.0, "kind": "ramp_signal", "signal": "throttle", "from": 0.0, "to": 1.0, "duration": 0.002 }],
+//!     "assertions": [{ "kind": "within", "probe": "net:out", "min": -0.01, "max": 5.01 }]
+//! }
+//! ```
+
+use std::{error::Error, fmt};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+use super::{
+    builder::NameMap,
+    components::{ComponentParameter, LinearComponentState, LinearComponentValue},
+    error::SimError,
+    f,
+    probe::Probe,
+    retune::ChargePolicy,
+    CircuitState, ComponentId, ComponentMut, ComponentValueEnum,
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct Scenario {
+    pub name: String,
+    /// The circuit, as a SPICE netlist (see `CircuitState::from_spice_netlist`), if the scenario
+    /// brings its own; otherwise it is run on one passed to `run`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub netlist: Option<String>,
+    /// Sources to bind to signals when the circuit is built from `netlist`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub signals: Vec<SignalSpec>,
+    pub dt: f,
+    pub duration: f,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub actions: Vec<TimedAction>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub assertions: Vec<Assertion>,
+}
+
+/// `CircuitState::bind_signal` of the source named `component`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct SignalSpec {
+    pub signal: String,
+    pub component: String,
+    #[cfg_attr(feature = "serde", serde(default = "one"))]
+    pub gain: f,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: f,
+}
+#[cfg(feature = "serde")]
+fn one() -> f {
+    1.0
+}
+
+/// An action taking effect from the first tick starting at or after `at` (seconds from the start
+/// of the run, rounded to whole ticks).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct TimedAction {
+    pub at: f,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub action: Action,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+pub enum Action {
+    /// `CircuitState::set_signal`.
+    SetSignal { signal: String, value: f },
+    /// Move a signal linearly from `from` to `to` over `duration`, set at the end of each tick.
+    RampSignal {
+        signal: String,
+        from: f,
+        to: f,
+        duration: f,
+    },
+    /// Close or open a switch.
+    Switch { component: String, closed: bool },
+    /// Set a parameter of a component's value.
+    SetParameter {
+        component: String,
+        #[cfg_attr(feature = "serde", serde(default = "value_parameter"))]
+        parameter: ComponentParameter,
+        value: f,
+    },
+    /// Replace a component's value, see `CircuitState::set_component_value`.
+    SetValue {
+        component: String,
+        value: ComponentValueEnum,
+        #[cfg_attr(feature = "serde", serde(default))]
+        policy: ChargePolicy,
+    },
+    /// Flip a source, see `CircuitState::reverse_source`.
+    ReverseSource { component: String },
+}
+#[cfg(feature = "serde")]
+fn value_parameter() -> ComponentParameter {
+    ComponentParameter::Value
+}
+
+/// A condition on a probe (a `Probe::parse` spec), checked after every tick.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize),
+    serde(tag = "kind", rename_all = "snake_case")
+)]
+pub enum Assertion {
+    /// The probe stays within `[min, max]` from `from` to `until` (the whole run by default).
+    Within {
+        probe: String,
+        min: f,
+        max: f,
+        #[cfg_attr(feature = "serde", serde(default))]
+        from: f,
+        #[cfg_attr(feature = "serde", serde(default))]
+        until: Option<f>,
+    },
+    /// The probe is within `[min, max]` after some tick from `from` (the start by default) to
+    /// `by`.
+    Reaches {
+        probe: String,
+        min: f,
+        max: f,
+        #[cfg_attr(feature = "serde", serde(default))]
+        from: f,
+        by: f,
+    },
+}
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Within {
+                probe,
+                min,
+                max,
+                from,
+                until,
+            } => {
+                write!(f, "{probe} within [{min}, {max}]")?;
+                match until {
+                    Some(until) => write!(f, " from {from:e} s to {until:e} s"),
+                    None if *from > 0.0 => write!(f, " from {from:e} s"),
+                    None => Ok(()),
+                }
+            }
+            Self::Reaches {
+                probe,
+                min,
+                max,
+                from,
+                by,
+            } => {
+                write!(f, "{probe} reaches [{min}, {max}]")?;
+                if *from > 0.0 {
+                    write!(f, " from {from:e} s")?;
+                }
+                write!(f, " by {by:e} s")
+            }
+        }
+    }
+}
+impl Assertion {
+    fn probe(&self) -> &str {
+        match self {
+            Self::Within { probe, .. } | Self::Reaches { probe, .. } => probe,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    /// The description could not be read.
+    Parse(String),
+    /// `Scenario::build` of a scenario without a netlist.
+    NoNetlist,
+    /// An action or signal names a component the circuit lacks.
+    UnknownComponent(String),
+    Sim(SimError),
+}
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(message) => write!(f, "reading the scenario: {message}"),
+            Self::NoNetlist => write!(f, "the scenario has no netlist"),
+            Self::UnknownComponent(name) => write!(f, "no component named {name:?}"),
+            Self::Sim(err) => write!(f, "{err}"),
+        }
+    }
+}
+impl Error for ScenarioError {}
+impl From<SimError> for ScenarioError {
+    fn from(err: SimError) -> Self {
+        Self::Sim(err)
+    }
+}
+
+/// The first assertion a run violated.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    /// Index into `Scenario::assertions`.
+    pub assertion: usize,
+    pub description: String,
+    /// Seconds from the start of the run.
+    pub time: f,
+    /// The probe's value then, or the closest it came for `Assertion::Reaches`.
+    pub value: f,
+}
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "assertion {} ({}) violated at t = {:.3e} s with {:.6e}",
+            self.assertion, self.description, self.time, self.value
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScenarioReport {
+    pub name: String,
+    /// `None` if every assertion held; the run stops at the first violation.
+    pub violation: Option<Violation>,
+    pub steps: usize,
+    /// Ticks whose `solve_state` did not converge.
+    pub unconverged: usize,
+}
+impl ScenarioReport {
+    pub fn passed(&self) -> bool {
+        self.violation.is_none()
+    }
+}
+impl fmt::Display for ScenarioReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.violation {
+            None => write!(f, "pass {}: {} steps", self.name, self.steps)?,
+            Some(violation) => write!(f, "FAIL {}: {violation}", self.name)?,
+        }
+        if self.unconverged > 0 {
+            write!(f, " ({} unconverged)", self.unconverged)?;
+        }
+        Ok(())
+    }
+}
+
+/// A resolved `Action`, with signals by index into `Scenario::signal_names`.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Signal(usize, f),
+    Ramp(Ramp),
+    Switch(ComponentId, bool),
+    Parameter(ComponentId, ComponentParameter, f),
+    Value(ComponentId, ComponentValueEnum, ChargePolicy),
+    Reverse(ComponentId),
+}
+#[derive(Debug, Clone, Copy)]
+struct Ramp {
+    signal: usize,
+    from: f,
+    to: f,
+    /// Seconds from the start of the run.
+    start: f,
+    duration: f,
+}
+
+impl Scenario {
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Self, ScenarioError> {
+        serde_json::from_str(text).map_err(|e| ScenarioError::Parse(e.to_string()))
+    }
+
+    /// The circuit of `netlist`, with `signals` bound.
+    pub fn build(&self) -> Result<(CircuitState, NameMap), ScenarioError> {
+        let netlist = self.netlist.as_ref().ok_or(ScenarioError::NoNetlist)?;
+        let (mut circuit, names) = CircuitState::from_spice_netlist(netlist)?;
+        for spec in &self.signals {
+            let component = component(&names, &spec.component)?;
+            circuit.bind_signal(&spec.signal, component, spec.gain, spec.offset)?;
+        }
+        Ok((circuit, names))
+    }
+
+    /// Tick `circuit` by `dt` for `duration` from its present state, applying the actions as
+    /// they come due and checking the assertions after every tick.
+    pub fn run(
+        &self,
+        circuit: &mut CircuitState,
+        names: &NameMap,
+    ) -> Result<ScenarioReport, ScenarioError> {
+        let signals = self.signal_names();
+        // the actions by time, stable for those at the same time.
+        let mut queue = self
+            .actions
+            .iter()
+            .map(|action| Ok((action.at, resolve(&action.action, &signals, names)?)))
+            .collect::<Result<Vec<_>, ScenarioError>>()?;
+        queue.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        let mut queue = queue.into_iter().peekable();
+        let probes = self
+            .assertions
+            .iter()
+            .map(|assertion| Probe::parse(assertion.probe(), names))
+            .collect::<Result<Vec<_>, _>>()?;
+        // closest distance to its band so far and the value then, per `Reaches`
+        let mut closest = vec![(f::INFINITY, f::NAN); self.assertions.len()];
+        let mut reached = vec![false; self.assertions.len()];
+        let mut ramps = Vec::<Ramp>::new();
+
+        let start = circuit.now();
+        let mut report = ScenarioReport {
+            name: self.name.clone(),
+            violation: None,
+            steps: 0,
+            unconverged: 0,
+        };
+        for _ in 0..(self.duration / self.dt).round() as usize {
+            let t = circuit.now() - start;
+            while let Some((_, step)) = queue.next_if(|&(at, _)| at <= t + 0.5 * self.dt) {
+                match step {
+                    Step::Signal(signal, value) => {
+                        ramps.retain(|ramp| ramp.signal != signal);
+                        circuit.set_signal(&signals[signal], value)?;
+                    }
+                    Step::Ramp(ramp) => {
+                        ramps.retain(|r| r.signal != ramp.signal);
+                        ramps.push(Ramp { start: t, ..ramp });
+                    }
+                    step => apply(circuit, step)?,
+                }
+            }
+            for ramp in &ramps {
+                let x = ((t + self.dt - ramp.start) / ramp.duration).clamp(0.0, 1.0);
+                circuit.set_signal(&signals[ramp.signal], ramp.from + (ramp.to - ramp.from) * x)?;
+            }
+            ramps.retain(|ramp| t + self.dt - ramp.start < ramp.duration);
+
+            if !circuit.try_tick(self.dt)? {
+                report.unconverged += 1;
+            }
+            report.steps += 1;
+            let t = circuit.now() - start;
+            report.violation = self.check(circuit, &probes, t, &mut closest, &mut reached);
+            if report.violation.is_some() {
+                return Ok(report);
+            }
+        }
+        // `Reaches` due after the end of the run
+        report.violation = self.unreached(&closest, &reached, f::INFINITY, circuit.now() - start);
+        Ok(report)
+    }
+
+    fn check(
+        &self,
+        circuit: &CircuitState,
+        probes: &[Probe],
+        t: f,
+        closest: &mut [(f, f)],
+        reached: &mut [bool],
+    ) -> Option<Violation> {
+        // within half a tick, for times given in whole ticks.
+        let slack = 0.5 * self.dt;
+        for (i, (assertion, probe)) in self.assertions.iter().zip(probes).enumerate() {
+            let value = probe.sample(circuit);
+            match *assertion {
+                Assertion::Within {
+                    min,
+                    max,
+                    from,
+                    until,
+                    ..
+                } => {
+                    let active = t + slack >= from && until.is_none_or(|until| t - slack <= until);
+                    if active && !(min..=max).contains(&value) {
+                        return Some(Violation {
+                            assertion: i,
+                            description: assertion.to_string(),
+                            time: t,
+                            value,
+                        });
+                    }
+                }
+                Assertion::Reaches { min, max, from, .. } if t + slack >= from => {
+                    if (min..=max).contains(&value) {
+                        reached[i] = true;
+                    }
+                    let distance = (min - value).max(value - max);
+                    if distance < closest[i].0 {
+                        closest[i] = (distance, value);
+                    }
+                }
+                Assertion::Reaches { .. } => {}
+            }
+        }
+        self.unreached(closest, reached, t + slack, t)
+    }
+
+    /// The first `Reaches` due by `due` that hasn't been reached, as violated at `t` (or at its
+    /// `by` if that is earlier).
+    fn unreached(&self, closest: &[(f, f)], reached: &[bool], due: f, t: f) -> Option<Violation> {
+        self.assertions
+            .iter()
+            .enumerate()
+            .find_map(|(i, assertion)| match *assertion {
+                Assertion::Reaches { by, .. } if !reached[i] && by <= due => Some(Violation {
+                    assertion: i,
+                    description: assertion.to_string(),
+                    time: by.min(t),
+                    value: closest[i].1,
+                }),
+                _ => None,
+            })
+    }
+
+    /// The signals actions refer to, in order of first mention.
+    fn signal_names(&self) -> Vec<String> {
+        let mut signals = Vec::<String>::new();
+        for action in &self.actions {
+            if let Action::SetSignal { signal, .. } | Action::RampSignal { signal, .. } =
+                &action.action
+            {
+                if !signals.contains(signal) {
+                    signals.push(signal.clone());
+                }
+            }
+        }
+        signals
+    }
+}
+
+fn resolve(action: &Action, signals: &[String], names: &NameMap) -> Result<Step, ScenarioError> {
+    let signal = |name: &String| signals.iter().position(|s| s == name).unwrap();
+    Ok(match action {
+        Action::SetSignal {
+            signal: name,
+            value,
+        } => Step::Signal(signal(name), *value),
+        &Action::RampSignal {
+            signal: ref name,
+            from,
+            to,
+            duration,
+        } => Step::Ramp(Ramp {
+            signal: signal(name),
+            from,
+            to,
+            start: 0.0,
+            duration,
+        }),
+        Action::Switch {
+            component: name,
+            closed,
+        } => Step::Switch(component(names, name)?, *closed),
+        Action::SetParameter {
+            component: name,
+            parameter,
+            value,
+        } => Step::Parameter(component(names, name)?, *parameter, *value),
+        Action::SetValue {
+            component: name,
+            value,
+            policy,
+        } => Step::Value(component(names, name)?, *value, *policy),
+        Action::ReverseSource { component: name } => Step::Reverse(component(names, name)?),
+    })
+}
+
+fn component(names: &NameMap, name: &str) -> Result<ComponentId, ScenarioError> {
+    names
+        .component(name)
+        .ok_or_else(|| ScenarioError::UnknownComponent(name.to_string()))
+}
+
+/// Apply a `Step::Switch`, `Step::Parameter`, `Step::Value` or `Step::Reverse`.
+fn apply(circuit: &mut CircuitState, step: Step) -> Result<(), SimError> {
+    match step {
+        Step::Switch(component, closed) => match circuit.component_mut(component) {
+            ComponentMut::Linear(LinearComponentState {
+                value: LinearComponentValue::Switch { closed: c, .. },
+                ..
+            }) => *c = closed,
+            _ => {
+                return Err(SimError::WrongComponentKind {
+                    component,
+                    expected: "a switch",
+                })
+            }
+        },
+        Step::Parameter(component, parameter, value) => {
+            match circuit.component_mut(component).parameter_mut(parameter) {
+                Some(v) => *v = value,
+                None => {
+                    return Err(SimError::WrongComponentKind {
+                        component,
+                        expected: "a component with this parameter",
+                    })
+                }
+            }
+        }
+        Step::Value(component, value, policy) => {
+            circuit.set_component_value(component, value, policy)?
+        }
+        Step::Reverse(component) => circuit.reverse_source(component)?,
+        Step::Signal(..) | Step::Ramp(_) => unreachable!("signals are set by `Scenario::run`"),
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! The net voltages the first `solve_state` of a circuit starts from. Every net begins at 0 V,
+//! and relaxation drags nets held by sources up to their voltage a fraction per sweep, so
+//! seeding sets them directly: source voltages are propagated breadth-first through
+//! zero-impedance paths (sources and closed switches), each group of nets so joined keeping the
+//! voltage of its lowest net (ground, for circuits that name it first).
+
+use std::collections::VecDeque;
+
+use super::{components::LinearComponentValue, CircuitState, ComponentState, NetId, Scalar};
+
+impl<S: Scalar> CircuitState<S> {
+    /// Set the voltage of every net that a source or closed switch joins to a lower net to the
+    /// voltage that path imposes, as `solve_state` does on its own (see `SolverConfig::seed`)
+    /// before the first solve of a new circuit and after nets or components are added. Where
+    /// paths disagree (sources in parallel), the one with the fewest steps from the group's lowest
+    /// net wins. Nets no such path reaches are left as they are.
+    ///
+    /// Returns the number of nets set.
+    pub fn seed_voltages(&mut self) -> usize {
+        self.seed_pending = false;
+        // `(other net, V(other) - V(net))` of every zero-impedance path out of each net.
+        let mut paths = vec![Vec::new(); self.nets.len()];
+        for (_, component) in self.linear_components() {
+            let difference = match component.value {
+                LinearComponentValue::Source(v) => v,
+                LinearComponentValue::Pwm(wave) => component.pwm_voltage(&wave),
+                LinearComponentValue::Switch { closed: true, .. }
+                | LinearComponentValue::Jumper => S::from(0),
+                _ => continue,
+            };
+            let &[net0, net1] = component.nets() else {
+                unreachable!()
+            };
+            paths[net0].push((net1, difference));
+            paths[net1].push((net0, -difference));
+        }
+
+        let mut seeded = vec![false; self.nets.len()];
+        let mut queue = VecDeque::<NetId>::new();
+        let mut n_set = 0;
+        for root in 0..self.nets.len() {
+            if seeded[root] || paths[root].is_empty() {
+                continue;
+            }
+            seeded[root] = true;
+            queue.push_back(root);
+            while let Some(net_i) = queue.pop_front() {
+                for &(other, difference) in &paths[net_i] {
+                    if !seeded[other] {
+                        seeded[other] = true;
+                        self.nets[other].voltage = self.nets[net_i].voltage + difference;
+                        n_set += 1;
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+        n_set
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fmt, sync::atomic::AtomicBool};
+
+use super::{
+    components::ComponentParameter,
+    error::SimError,
+    f,
+    probe::Recording,
+    run::{run_with_progress, RunConfig},
+    sweep::{Sweep, SweepPoint},
+    CircuitState, ComponentId,
+};
+
+/// A component parameter a `Sensitivity` perturbs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensitivityTarget {
+    pub component: ComponentId,
+    pub parameter: ComponentParameter,
+}
+
+/// Derivatives of a metric of a run with respect to component parameters, by central
+/// differences: the circuit is run once nominally and twice per target, with the parameter
+/// scaled by `1 + relative_step` and `1 - relative_step`.
+#[derive(Debug, Clone)]
+pub struct Sensitivity {
+    pub targets: Vec<SensitivityTarget>,
+    pub relative_step: f,
+    pub n_threads: usize,
+}
+impl Sensitivity {
+    /// Sensitivities to `targets` with a relative step of 1%.
+    pub fn new(targets: Vec<SensitivityTarget>) -> Self {
+        Self {
+            targets,
+            relative_step: 0.01,
+            n_threads: 1,
+        }
+    }
+    pub fn with_relative_step(mut self, relative_step: f) -> Self {
+        self.relative_step = relative_step;
+        self
+    }
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads;
+        self
+    }
+
+    /// Run a clone of `base` for `config` recording into a clone of `recording` at the nominal
+    /// and each perturbed value, and evaluate `metric` on each recording. Only the parameter
+    /// values are perturbed; the state `base` starts from (capacitor charges, inductor
+    /// currents) stays as it is.
+    ///
+    /// Errors if a target names a component that doesn't exist or lacks the parameter, or if a
+    /// run fails.
+    pub fn run<E>(
+        &self,
+        base: &CircuitState,
+        recording: &Recording,
+        config: RunConfig,
+        metric: E,
+    ) -> Result<SensitivityResult, SimError>
+    where
+        E: Fn(&Recording) -> f + Sync,
+    {
+        let mut scratch = base.clone();
+        let nominal_values = self
+            .targets
+            .iter()
+            .map(|target| {
+                if target.component >= scratch.n_components() {
+                    return Err(SimError::UnknownComponent(target.component));
+                }
+                scratch
+                    .component_mut(target.component)
+                    .parameter_mut(target.parameter)
+                    .map(|value| *value)
+                    .ok_or(SimError::WrongComponentKind {
+                        component: target.component,
+                        expected: "a component with this parameter",
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the nominal run, then the upper and lower run of each target.
+        let mut points = vec![SweepPoint { params: Vec::new() }];
+        for target_i in 0..self.targets.len() {
+            for factor in [1.0 + self.relative_step, 1.0 - self.relative_step] {
+                points.push(SweepPoint {
+                    params: vec![(target_i.to_string(), factor)],
+                });
+            }
+        }
+        let never = AtomicBool::new(false);
+        let result = Sweep::from_points(points)
+            .with_threads(self.n_threads)
+            .run_mutating(
+                base,
+                |point, circuit| {
+                    for (target_i, factor) in &point.params {
+                        let target = self.targets[target_i.parse::<usize>().unwrap()];
+                        *circuit
+                            .component_mut(target.component)
+                            .parameter_mut(target.parameter)
+                            .unwrap() *= factor;
+                    }
+                },
+                |_, circuit| {
+                    run_with_progress(circuit, recording.clone(), config, &never, |_| {})
+                        .map(|outcome| metric(&outcome.recording))
+                },
+            );
+        let metrics = result.rows.into_iter().map(|(_, metric)| metric);
+        let metrics = metrics.collect::<Result<Vec<_>, _>>()?;
+
+        let nominal = metrics[0];
+        let rows = self
+            .targets
+            .iter()
+            .zip(nominal_values)
+            .zip(metrics[1..].chunks(2))
+            .map(|((&target, value), perturbed)| {
+                let change = (perturbed[0] - perturbed[1]) / (2.0 * self.relative_step);
+                SensitivityRow {
+                    target,
+                    value,
+                    derivative: change / value,
+                    elasticity: change / nominal,
+                }
+            })
+            .collect();
+        Ok(SensitivityResult { nominal, rows })
+    }
+}
+
+/// The sensitivity of the metric to one target.
+#[derive(Debug, Clone, Copy)]
+pub struct SensitivityRow {
+    pub target: SensitivityTarget,
+    /// Nominal value of the parameter.
+    pub value: f,
+    /// `d metric / d value`, in units of the metric per unit of the parameter.
+    pub derivative: f,
+    /// `(d metric / metric) / (d value / value)`: percent change of the metric per percent
+    /// change of the parameter.
+    pub elasticity: f,
+}
+
+#[derive(Debug, Clone)]
+pub struct SensitivityResult {
+    /// The metric of the nominal run.
+    pub nominal: f,
+    /// One row per target, in the order of `Sensitivity::targets`.
+    pub rows: Vec<SensitivityRow>,
+}
+impl fmt::Display for SensitivityResult {
+    /// A table of the rows, one line each.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "nominal metric {:e}", self.nominal)?;
+        writeln!(
+            f,
+            "{:>9} {:>16} {:>12} {:>12} {:>10}",
+            "component", "parameter", "value", "derivative", "%/%"
+        )?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:>9} {:>16} {:>12.4e} {:>12.4e} {:>10.4}",
+                row.target.component,
+                format!("{:?}", row.target.parameter),
+                row.value,
+                row.derivative,
+                row.elasticity
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Named signals driving sources, so that several sources fed from one controller output (the
+//! gate drives of a bridge) are set together: each binding holds its source at
+//! `gain * signal + offset`, and `CircuitState::set_signal` updates every source bound to the
+//! signal before the next solve.
+
+use super::{
+    components::{ComponentParameter, LinearComponentValue},
+    error::SimError,
+    f, CircuitState, ComponentId, ComponentRef, Scalar,
+};
+
+/// A source following a signal as `gain * signal + offset`; a gain of -1 with an offset of the
+/// drive voltage gives the complementary gate of a half-bridge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalBinding<S: Scalar = f> {
+    pub component: ComponentId,
+    pub gain: S,
+    pub offset: S,
+}
+impl<S: Scalar> SignalBinding<S> {
+    pub fn value(&self, signal: S) -> S {
+        self.gain * signal + self.offset
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Signal<S: Scalar = f> {
+    pub name: String,
+    /// The last value set, 0 until the first `set_signal`.
+    pub value: S,
+    pub bindings: Vec<SignalBinding<S>>,
+}
+
+/// The signals of a circuit, see `CircuitState::bind_signal`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalBus<S: Scalar = f> {
+    signals: Vec<Signal<S>>,
+}
+impl<S: Scalar> SignalBus<S> {
+    pub fn signal(&self, name: &str) -> Option<&Signal<S>> {
+        self.signals.iter().find(|signal| signal.name == name)
+    }
+    pub fn signals(&self) -> &[Signal<S>] {
+        &self.signals
+    }
+    fn signal_mut(&mut self, name: &str) -> Option<&mut Signal<S>> {
+        self.signals.iter_mut().find(|signal| signal.name == name)
+    }
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Bind the source (or the offset of a noise source) `component` to the signal `name`,
+    /// created (at 0) if it doesn't exist yet. The source takes the signal's present value right
+    /// away.
+    pub fn bind_signal(
+        &mut self,
+        name: &str,
+        component: ComponentId,
+        gain: S,
+        offset: S,
+    ) -> Result<(), SimError> {
+        if component >= self.slots.len() {
+            return Err(SimError::UnknownComponent(component));
+        }
+        let is_source = match self.component(component) {
+            ComponentRef::Linear(v) => matches!(v.value, LinearComponentValue::Source(_)),
+            ComponentRef::NoiseSource(_) => true,
+            ComponentRef::MOSFET(_)
+            | ComponentRef::GateDriver(_)
+            | ComponentRef::Comparator(_)
+            | ComponentRef::Converter(_) => false,
+        };
+        if !is_source {
+            return Err(SimError::WrongComponentKind {
+                component,
+                expected: "a source",
+            });
+        }
+        let binding = SignalBinding {
+            component,
+            gain,
+            offset,
+        };
+        let signal = match self.signals.signal_mut(name) {
+            Some(signal) => signal,
+            None => {
+                self.signals.signals.push(Signal {
+                    name: name.to_string(),
+                    value: S::from(0),
+                    bindings: Vec::new(),
+                });
+                self.signals.signals.last_mut().unwrap()
+            }
+        };
+        signal.bindings.push(binding);
+        let value = signal.value;
+        self.drive(binding, value);
+        Ok(())
+    }
+
+    /// Set the signal `name`, and with it every source bound to it.
+    pub fn set_signal(&mut self, name: &str, value: S) -> Result<(), SimError> {
+        let signal = self
+            .signals
+            .signal_mut(name)
+            .ok_or_else(|| SimError::UnknownSignal(name.to_string()))?;
+        signal.value = value;
+        for binding in signal.bindings.clone() {
+            self.drive(binding, value);
+        }
+        Ok(())
+    }
+
+    /// The present value of the signal `name`.
+    pub fn signal(&self, name: &str) -> Option<S> {
+        self.signals.signal(name).map(|signal| signal.value)
+    }
+    pub fn signal_bus(&self) -> &SignalBus<S> {
+        &self.signals
+    }
+
+    fn drive(&mut self, binding: SignalBinding<S>, signal: S) {
+        if let Some(v) = self
+            .component_mut(binding.component)
+            .parameter_mut(ComponentParameter::Value)
+        {
+            *v = binding.value(signal);
+        }
+    }
+}
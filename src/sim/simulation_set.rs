@@ -0,0 +1,95 @@
+//! Several circuits stepped in lockstep, such as one ESC design against different motors, with
+//! their probes in one recording under the name of their circuit.
+
+use super::{error::SimError, f, probe::Probe, probe::Recording, CircuitState, HasConverged};
+
+/// Named circuits ticked together, see `SimulationSet::tick_all`.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationSet {
+    circuits: Vec<(String, CircuitState)>,
+}
+
+/// A `Recording` of probes on the circuits of a `SimulationSet`, see `SimulationSet::recording`.
+#[derive(Debug, Clone)]
+pub struct SetRecording {
+    /// The index in the set of the circuit of each probe.
+    circuits: Vec<usize>,
+    pub recording: Recording,
+}
+
+impl SimulationSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `circuit` as `name`.
+    ///
+    /// Errors with `SimError::DuplicateCircuit` if the set already has a circuit of that name.
+    pub fn add(&mut self, name: &str, circuit: CircuitState) -> Result<&mut Self, SimError> {
+        if self.circuit(name).is_some() {
+            return Err(SimError::DuplicateCircuit(name.to_string()));
+        }
+        self.circuits.push((name.to_string(), circuit));
+        Ok(self)
+    }
+    pub fn circuit(&self, name: &str) -> Option<&CircuitState> {
+        self.index(name).map(|i| &self.circuits[i].1)
+    }
+    pub fn circuit_mut(&mut self, name: &str) -> Option<&mut CircuitState> {
+        self.index(name).map(|i| &mut self.circuits[i].1)
+    }
+    /// The circuits in the order they were added.
+    pub fn circuits(&self) -> impl Iterator<Item = (&str, &CircuitState)> {
+        self.circuits
+            .iter()
+            .map(|(name, circuit)| (name.as_str(), circuit))
+    }
+    pub fn circuits_mut(&mut self) -> impl Iterator<Item = (&str, &mut CircuitState)> {
+        self.circuits
+            .iter_mut()
+            .map(|(name, circuit)| (name.as_str(), circuit))
+    }
+    fn index(&self, name: &str) -> Option<usize> {
+        self.circuits.iter().position(|(n, _)| n == name)
+    }
+
+    /// Tick every circuit by `dt`; whether all of them converged.
+    pub fn tick_all(&mut self, dt: f) -> HasConverged {
+        let mut converged = true;
+        for (_, circuit) in &mut self.circuits {
+            converged &= circuit.tick(dt);
+        }
+        converged
+    }
+
+    /// A recording of `probes`, each `(circuit, label, probe)`, labelled `<circuit>.<label>`. The
+    /// channels of `Probe::Derived` ones are indices into `probes`, across circuits.
+    ///
+    /// Errors with `SimError::UnknownCircuit` if a probe names a circuit the set doesn't have.
+    pub fn recording(&self, probes: Vec<(&str, String, Probe)>) -> Result<SetRecording, SimError> {
+        let mut circuits = Vec::with_capacity(probes.len());
+        let mut labelled = Vec::with_capacity(probes.len());
+        for (circuit, label, probe) in probes {
+            circuits.push(
+                self.index(circuit)
+                    .ok_or_else(|| SimError::UnknownCircuit(circuit.to_string()))?,
+            );
+            labelled.push((format!("{circuit}.{label}"), probe));
+        }
+        Ok(SetRecording {
+            circuits,
+            recording: Recording::new(labelled),
+        })
+    }
+    /// Sample every probe of `recording` on its circuit, at the time of the first circuit.
+    pub fn record(&self, recording: &mut SetRecording) {
+        let time = self
+            .circuits
+            .first()
+            .map_or(0.0, |(_, circuit)| circuit.now());
+        let circuits = &recording.circuits;
+        recording
+            .recording
+            .record_with(time, |i, probe| probe.sample(&self.circuits[circuits[i]].1));
+    }
+}
@@ -0,0 +1,163 @@
+//! The trajectory of a single `solve_state`, for tuning the relaxation offline: the net voltages
+//! and component currents after every outer iteration, written out as CSV to plot, and which nets
+//! oscillate rather than settle. See `CircuitState::solve_state_traced`.
+
+use std::io;
+
+use super::{error::SimError, f, CircuitState, HasConverged, NetId, Scalar};
+
+/// The state of the circuit after one outer iteration of a traced `solve_state`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveSnapshot<S: Scalar = f> {
+    pub iteration: usize,
+    /// Relaxation factor the iteration ran with.
+    pub omega: S,
+    /// Largest current imbalance at any net after it, see `SolveReport::residual`.
+    pub residual: S,
+    /// Indexed by `NetId`.
+    pub voltages: Vec<S>,
+    /// `CircuitState::branch_current` of each component, indexed by `ComponentId`.
+    pub currents: Vec<S>,
+}
+
+/// Snapshots of the outer iterations of one `solve_state_traced` call, up to `max_iterations` of
+/// them so a solve that runs to its iteration cap doesn't fill memory with them.
+#[derive(Debug, Clone)]
+pub struct SolveTrace<S: Scalar = f> {
+    pub max_iterations: usize,
+    snapshots: Vec<SolveSnapshot<S>>,
+    /// Outer iterations past `max_iterations`, not recorded.
+    dropped: usize,
+}
+impl<S: Scalar> SolveTrace<S> {
+    pub fn new(max_iterations: usize) -> Self {
+        Self {
+            max_iterations,
+            snapshots: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn snapshots(&self) -> &[SolveSnapshot<S>] {
+        &self.snapshots
+    }
+    /// Outer iterations the solve ran past `max_iterations`.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+    /// The residual after each recorded iteration.
+    pub fn residuals(&self) -> Vec<S> {
+        self.snapshots
+            .iter()
+            .map(|snapshot| snapshot.residual)
+            .collect()
+    }
+    /// The voltage of `net` after each recorded iteration.
+    pub fn net_voltages(&self, net: NetId) -> Vec<S> {
+        self.snapshots
+            .iter()
+            .map(|snapshot| snapshot.voltages[net])
+            .collect()
+    }
+
+    /// The longest run of consecutive changes of the voltage of `net` between iterations that
+    /// alternate in sign, the mark of an over-relaxed net bouncing about its solution. Changes
+    /// within `Scalar::CONVERGENCE_EPSILON` end a run.
+    pub fn sign_alternations(&self, net: NetId) -> usize {
+        let voltages = self.net_voltages(net);
+        let zero = S::from(0);
+        let mut longest = 0;
+        let mut run = 0;
+        let mut prev = zero;
+        for pair in voltages.windows(2) {
+            let delta = pair[1] - pair[0];
+            if delta.abs() <= S::CONVERGENCE_EPSILON {
+                run = 0;
+                prev = zero;
+                continue;
+            }
+            run = if (delta > zero) != (prev > zero) && prev != zero {
+                run + 1
+            } else {
+                0
+            };
+            longest = longest.max(run);
+            prev = delta;
+        }
+        longest
+    }
+    /// The nets whose `sign_alternations` reach `min_alternations`.
+    pub fn oscillating_nets(&self, min_alternations: usize) -> Vec<NetId> {
+        let n_nets = self.snapshots.first().map_or(0, |s| s.voltages.len());
+        (0..n_nets)
+            .filter(|&net| self.sign_alternations(net) >= min_alternations)
+            .collect()
+    }
+
+    /// Write a CSV with one row per recorded iteration: `iteration`, `omega` and `residual`,
+    /// then a `v<net>` column per net and an `i<component>` column per component.
+    pub fn write_csv(&self, mut out: impl io::Write) -> io::Result<()> {
+        write!(out, "iteration,omega,residual")?;
+        if let Some(first) = self.snapshots.first() {
+            for net in 0..first.voltages.len() {
+                write!(out, ",v{net}")?;
+            }
+            for component in 0..first.currents.len() {
+                write!(out, ",i{component}")?;
+            }
+        }
+        writeln!(out)?;
+        for snapshot in &self.snapshots {
+            write!(
+                out,
+                "{},{:e},{:e}",
+                snapshot.iteration,
+                snapshot.omega.to_f64(),
+                snapshot.residual.to_f64()
+            )?;
+            for value in snapshot.voltages.iter().chain(&snapshot.currents) {
+                write!(out, ",{:e}", value.to_f64())?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Record the circuit after outer iteration `iteration`, run with `omega`.
+    pub(super) fn record(&mut self, circuit: &CircuitState<S>, iteration: usize, omega: S) {
+        if self.snapshots.len() >= self.max_iterations {
+            self.dropped += 1;
+            return;
+        }
+        self.snapshots.push(SolveSnapshot {
+            iteration,
+            omega,
+            residual: circuit.residual,
+            voltages: circuit.nets.iter().map(|net| net.voltage).collect(),
+            currents: (0..circuit.n_components())
+                .map(|component| circuit.branch_current(component))
+                .collect(),
+        });
+    }
+    fn clear(&mut self) {
+        self.snapshots.clear();
+        self.dropped = 0;
+    }
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// `solve_state`, recording the circuit after every outer iteration into `trace` (replacing
+    /// what it held).
+    pub fn solve_state_traced(&mut self, trace: &mut SolveTrace<S>) -> HasConverged {
+        self.try_solve_state_traced(trace).unwrap_or(false)
+    }
+    /// `try_solve_state`, recording into `trace` as `solve_state_traced`; the iterations up to
+    /// an error are kept.
+    pub fn try_solve_state_traced(
+        &mut self,
+        trace: &mut SolveTrace<S>,
+    ) -> Result<HasConverged, SimError> {
+        trace.clear();
+        self.with_fault_values(|circuit| circuit.run_solve(Some(trace)))
+    }
+}
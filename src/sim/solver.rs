@@ -0,0 +1,404 @@
+use super::{f, math::clamp, Scalar};
+use crate::linalg::sparse::IterativeConfig;
+
+/// How `CircuitState::solve_state` picks the relaxation factor `omega` of each outer iteration.
+///
+/// Each voltage correction moves a net `omega / 2` of the way towards the voltage its components
+/// propose, so values above 1 over-relax.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Relaxation<S: Scalar = f> {
+    Fixed {
+        omega: S,
+    },
+    /// Successive over-relaxation driven by the residual (the largest current imbalance at any
+    /// net): `omega` grows by `increase` while the residual shrinks and is cut by `decrease` as
+    /// soon as it grows (oscillation), staying within `[min, max]`.
+    Adaptive {
+        initial: S,
+        min: S,
+        max: S,
+        increase: S,
+        decrease: S,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolverConfig<S: Scalar = f> {
+    pub relaxation: Relaxation<S>,
+    /// Stop with `SimError::NonFiniteValue` as soon as a net voltage or component state becomes
+    /// NaN or infinite. On by default in debug builds.
+    pub check_finite: bool,
+    /// Check every net for charge created or destroyed while ticking, see
+    /// `CircuitState::last_charge_audit`. Off by default.
+    pub charge_audit: Option<ChargeAudit<S>>,
+    /// Bounds on the values `purturb_from_nets` acts on, all off by default.
+    pub limits: PerturbationLimits<S>,
+    /// How `tick` guesses the net voltages `solve_state` starts from.
+    pub predictor: Predictor,
+    /// Skip `purturb_from_nets` of components whose residual stayed within
+    /// `Scalar::CONVERGENCE_EPSILON` for this many consecutive iterations, until the voltage of
+    /// one of their nets moves by more than that from where they settled. Off by default, and
+    /// only used by the serial sweeps.
+    pub settle_after: Option<usize>,
+    /// Run `CircuitState::validate` at the start of every `solve_state` (each tick's included),
+    /// failing with `SimError::InvalidCircuit` on the first warning of `Severity::Error` and
+    /// logging the others with the `tracing` feature. Off by default.
+    pub validate: bool,
+    /// Leave components that are `ComponentState::is_currently_inert` at the start of a
+    /// `solve_state` out of its sweeps. The results are identical either way; on by default, and
+    /// only used by the serial sweeps without `settle_after` or faults.
+    pub skip_inert: bool,
+    /// Fail a `tick` longer than the tightest `ComponentState::max_dt_hint` with
+    /// `SimError::TimeStepTooLarge` (which `tick` takes as not converging, `try_tick` reports)
+    /// instead of taking it. A step that doesn't land on the next edge of a PWM source counts as
+    /// too long, so fixed steps have to divide its timing. Off by default.
+    pub strict_dt: bool,
+    /// Start the first `solve_state` of a new circuit, and the first after nets or components
+    /// are added, from `CircuitState::seed_voltages`. On by default.
+    pub seed: bool,
+    /// Settle capacitors that a switch closed onto since the last tick to the voltages charge
+    /// conservation gives, at the start of the tick, instead of leaving the impulse current to
+    /// the solver; see `CircuitState::last_charge_sharing`. On by default.
+    pub share_charge: bool,
+    /// How a voltage correction combines the voltages the components of a net propose.
+    pub voltage_averaging: VoltageAveraging<S>,
+    /// After each tick, integrate the energy of the components that are
+    /// `ComponentState::is_stiff` over it in this many sub-steps (see
+    /// `ComponentState::sub_step`), which `LossAccumulator::accumulate` then counts instead of
+    /// their power at the end of the tick. Off by default.
+    ///
+    /// The circuit is still solved once per tick: a MOSFET switching within one follows its
+    /// gate voltage interpolated across it, against the load line through the two solutions.
+    /// That is exact for a resistive load and a gate that moves linearly over the tick; the error
+    /// grows with the curvature of the gate voltage and with reactance in the drain circuit,
+    /// and disappears as `dt` shrinks anyway. The waveforms are the same either way.
+    pub sub_steps: Option<usize>,
+    /// How the small-signal analyses (`CircuitState::ac_analysis`, `measure_impedance` and the
+    /// analyses built on them) solve their complex MNA system. Direct by default.
+    pub linear_solver: LinearSolver,
+}
+
+/// See `SolverConfig::linear_solver`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LinearSolver {
+    /// Dense LU, `Mat::solve`.
+    Direct,
+    /// BiCGSTAB on the sparse system, `CsrMat::solve_bicgstab`, for circuits large enough that
+    /// the dense LU (cubic in the nets and branches) is the cost; the direct solve where it
+    /// doesn't converge within `max_iterations`.
+    Iterative(IterativeConfig),
+}
+impl<S: Scalar> Default for SolverConfig<S> {
+    fn default() -> Self {
+        Self {
+            relaxation: Relaxation::Adaptive {
+                initial: S::from(1),
+                min: S::from_f64(0.2),
+                max: S::from_f64(1.5),
+                increase: S::from_f64(1.05),
+                decrease: S::from_f64(0.7),
+            },
+            check_finite: cfg!(debug_assertions),
+            charge_audit: None,
+            limits: PerturbationLimits::default(),
+            predictor: Predictor::Off,
+            settle_after: None,
+            validate: false,
+            skip_inert: true,
+            strict_dt: false,
+            seed: true,
+            share_charge: true,
+            voltage_averaging: VoltageAveraging::Plain,
+            sub_steps: None,
+            linear_solver: LinearSolver::Direct,
+        }
+    }
+}
+
+/// How a voltage correction moves each net from the proposals of its components, see
+/// `NetStamps::weighted_voltage`.
+///
+/// Every component proposes the net voltage moved by its own share of the correction, so on a net
+/// with many of them the few that disagree are outvoted by the rest proposing the voltage as it
+/// is: the plain average under-corrects a DC bus with dozens of connections compared with a net
+/// between two components, and it takes more sweeps to settle. The schemes all stop at the same
+/// fixed point, where every proposal is the voltage of the net.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum VoltageAveraging<S: Scalar = f> {
+    /// The weighted average of the proposals.
+    #[default]
+    Plain,
+    /// The move of `Plain` scaled by `(degree / 2)^exponent`, where the degree is the total
+    /// weight of the proposals (the number of terminals on the net, for unit weights), so nets
+    /// of degree 2 or less move as with `Plain`. Too large an exponent overshoots on the nets of
+    /// highest degree; around 0.25 is safe for a bus of 50 (see the `bus_solve_state` bench).
+    DegreeCompensated { exponent: S },
+}
+impl<S: Scalar> VoltageAveraging<S> {
+    /// The voltage a net at `voltage` moves to, from proposals summing to `accumulator` with a
+    /// total `weight` (not zero).
+    pub(super) fn next_voltage(self, voltage: S, accumulator: S, weight: S) -> S {
+        let average = accumulator / weight;
+        match self {
+            Self::Plain => average,
+            Self::DegreeCompensated { exponent } => {
+                let degree = weight / S::from(2);
+                if degree <= S::from(1) {
+                    average
+                } else {
+                    voltage + (average - voltage) * degree.powf(exponent)
+                }
+            }
+        }
+    }
+}
+
+/// How often and how strictly `CircuitState::tick` audits charge conservation: the charge every
+/// component moves into each net is summed over `every` ticks, and nets that gained or lost more
+/// than `tolerance` coulombs in that time are reported.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChargeAudit<S: Scalar = f> {
+    pub every: usize,
+    pub tolerance: S,
+}
+
+/// The starting point of the `solve_state` in each `tick`. Component currents always start from
+/// their own `tick` (`I + dI/dt dt`); the predictor also extrapolates the net voltages and the
+/// solved part of the component states (`dI/dt`, see `ComponentState::solved_state_mut`) from the
+/// solutions of the previous ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Predictor {
+    /// Start from the voltages and `dI/dt` of the previous tick.
+    #[default]
+    Off,
+    /// Extrapolate the line through the previous two solutions.
+    Linear,
+    /// Extrapolate the parabola through the previous three solutions.
+    Quadratic,
+}
+impl Predictor {
+    /// Solutions needed to extrapolate.
+    pub(super) fn order(self) -> usize {
+        match self {
+            Self::Off => 0,
+            Self::Linear => 2,
+            Self::Quadratic => 3,
+        }
+    }
+}
+
+/// The last few ticks' solutions (net voltages, then the solved states of the components), as
+/// `(time, solution)` with the latest last.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct SolutionHistory<S: Scalar> {
+    solutions: Vec<(S, Vec<S>)>,
+}
+impl<S: Scalar> SolutionHistory<S> {
+    /// Add the solution at `time`, keeping the last `n`. Solutions of another length (the circuit
+    /// changed) or from a time that isn't earlier are dropped.
+    pub(super) fn push(&mut self, time: S, solution: Vec<S>, n: usize) {
+        self.solutions
+            .retain(|(t, v)| *t < time && v.len() == solution.len());
+        self.solutions.push((time, solution));
+        let excess = self.solutions.len().saturating_sub(n);
+        self.solutions.drain(..excess);
+    }
+    /// The solution at `time`, extrapolated by the Lagrange polynomial through the last `order`
+    /// solutions, if there are that many.
+    pub(super) fn extrapolate(&self, order: usize, time: S) -> Option<Vec<S>> {
+        let solutions = self
+            .solutions
+            .get(self.solutions.len().checked_sub(order)?..)?;
+        let (_, latest) = solutions.last()?;
+        let mut prediction = vec![S::from(0); latest.len()];
+        for (j, (t_j, v_j)) in solutions.iter().enumerate() {
+            let weight = solutions
+                .iter()
+                .enumerate()
+                .filter(|&(k, _)| k != j)
+                .map(|(_, &(t_k, _))| (time - t_k) / (*t_j - t_k))
+                .fold(S::from(1), |a, b| a * b);
+            for (p, &v) in prediction.iter_mut().zip(v_j) {
+                *p += v * weight;
+            }
+        }
+        Some(prediction)
+    }
+}
+
+/// Bookkeeping of `SolverConfig::settle_after` within one `solve_state`, with components in
+/// storage order (pool by pool).
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct SettleMask<S: Scalar> {
+    /// `SolverConfig::settle_after` while a `solve_state` with serial sweeps uses it.
+    pub(super) settle_after: Option<usize>,
+    /// Storage index of each `ComponentId`.
+    pub(super) storage: Vec<usize>,
+    /// Consecutive iterations each component stayed within tolerance.
+    pub(super) counts: Vec<usize>,
+    /// Voltage of each net when its components last had their counts reset.
+    pub(super) reference: Vec<S>,
+    /// `purturb_from_nets` calls skipped.
+    pub(super) skipped: usize,
+}
+impl<S: Scalar> SettleMask<S> {
+    pub(super) fn reset(
+        &mut self,
+        settle_after: Option<usize>,
+        storage: impl Iterator<Item = usize>,
+        voltages: impl Iterator<Item = S>,
+    ) {
+        self.settle_after = settle_after;
+        self.skipped = 0;
+        if settle_after.is_none() {
+            return;
+        }
+        self.storage.clear();
+        self.storage.extend(storage);
+        self.counts.clear();
+        self.counts.resize(self.storage.len(), 0);
+        self.reference.clear();
+        self.reference.extend(voltages);
+    }
+}
+
+/// Storage indices (pool by pool) of the components a `solve_state` with
+/// `SolverConfig::skip_inert` sweeps over.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct ActiveSet {
+    /// Whether the sweeps of the present `solve_state` only visit `storage`, set when at least
+    /// one component is inert.
+    pub(super) skipping: bool,
+    /// In increasing order.
+    pub(super) storage: Vec<usize>,
+}
+
+/// Bounds on what a single `purturb_from_nets` may act on, so that exponential devices don't
+/// take a wild intermediate voltage (a kilovolt gate swing) or current and spend hundreds of
+/// iterations recovering from it.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerturbationLimits<S: Scalar = f> {
+    /// Largest change per iteration of a controlling voltage of a nonlinear component (`v_gs`
+    /// and `v_ds` of a MOSFET) from the value it last acted on.
+    pub max_voltage_step: Option<S>,
+    /// Largest `|I|` any component may carry.
+    pub max_current: Option<S>,
+}
+
+/// `PerturbationLimits` as applied in one `purturb_from_nets`, counting how often they cut a
+/// value.
+#[derive(Debug, Clone, Copy)]
+pub struct Limiter<S: Scalar = f> {
+    limits: PerturbationLimits<S>,
+    engaged: usize,
+}
+impl<S: Scalar> Limiter<S> {
+    pub fn new(limits: PerturbationLimits<S>) -> Self {
+        Self { limits, engaged: 0 }
+    }
+    /// How often a value was cut.
+    pub fn engaged(&self) -> usize {
+        self.engaged
+    }
+    /// `next`, at most `max_voltage_step` away from `prev`.
+    pub fn voltage_step(&mut self, prev: S, next: S) -> S {
+        match self.limits.max_voltage_step {
+            Some(max) => prev + self.clamp(next - prev, max),
+            None => next,
+        }
+    }
+    /// `current` within `max_current`.
+    pub fn current(&mut self, current: S) -> S {
+        match self.limits.max_current {
+            Some(max) => self.clamp(current, max),
+            None => current,
+        }
+    }
+    fn clamp(&mut self, v: S, max: S) -> S {
+        if v.abs() <= max {
+            return v;
+        }
+        self.engaged += 1;
+        clamp(v, -max, max)
+    }
+}
+
+/// Outcome of a `solve_state` call, see `CircuitState::last_solve_report`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolveReport<S: Scalar = f> {
+    pub converged: bool,
+    /// Outer iterations used.
+    pub iterations: usize,
+    /// Largest current imbalance at any net after the last iteration.
+    pub residual: S,
+    /// How often `SolverConfig::limits` cut a value, over all iterations.
+    pub limited: usize,
+    /// Largest difference between a net voltage `SolverConfig::predictor` predicted and the one
+    /// solved for, if this was the solve of a `tick` that predicted them.
+    pub prediction_error: Option<S>,
+    /// `purturb_from_nets` calls skipped for settled components, see
+    /// `SolverConfig::settle_after`.
+    pub skipped: usize,
+    /// `SolveStrategy::name` of the strategy that ran.
+    pub strategy: &'static str,
+    /// Whether the net voltages were seeded before the first iteration, see
+    /// `SolverConfig::seed`.
+    pub seeded: bool,
+}
+
+/// The `omega` sequence of one `solve_state` call.
+#[derive(Debug, Clone)]
+pub(super) struct RelaxationSchedule<S: Scalar> {
+    relaxation: Relaxation<S>,
+    omega: S,
+    prev_residual: Option<S>,
+}
+impl<S: Scalar> RelaxationSchedule<S> {
+    pub(super) fn new(relaxation: Relaxation<S>) -> Self {
+        let omega = match relaxation {
+            Relaxation::Fixed { omega } => omega,
+            Relaxation::Adaptive { initial, .. } => initial,
+        };
+        Self {
+            relaxation,
+            omega,
+            prev_residual: None,
+        }
+    }
+    pub(super) fn omega(&self) -> S {
+        self.omega
+    }
+    /// Adapt `omega` to the residual of the sweep that just ran with it.
+    pub(super) fn update(&mut self, residual: S) {
+        if let Relaxation::Adaptive {
+            min,
+            max,
+            increase,
+            decrease,
+            ..
+        } = self.relaxation
+        {
+            if let Some(prev_residual) = self.prev_residual {
+                let omega = if residual < prev_residual {
+                    self.omega * increase
+                } else {
+                    self.omega * decrease
+                };
+                self.omega = clamp(omega, min, max);
+            }
+        }
+        self.prev_residual = Some(residual);
+    }
+}
@@ -0,0 +1,195 @@
+//! Spectra of recorded channels, for switching ripple and harmonic distortion.
+
+use std::f64::consts::TAU;
+
+use super::{ac::Cf, f, probe::Recording};
+
+/// Window applied to the samples before the transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Window {
+    /// No window; exact for tones with a whole number of periods in `n` sample intervals for
+    /// `n` rows.
+    #[default]
+    Rect,
+    /// Raised cosine, trading a wider peak for far less leakage from off-bin tones.
+    Hann,
+}
+impl Window {
+    fn weight(self, i: usize, n: usize) -> f {
+        match self {
+            Self::Rect => 1.0,
+            Self::Hann if n < 2 => 1.0,
+            Self::Hann => 0.5 - 0.5 * (TAU * i as f / (n - 1) as f).cos(),
+        }
+    }
+    /// Bins either side of a tone's own that the window spreads it into.
+    fn half_lobe(self) -> usize {
+        match self {
+            Self::Rect => 0,
+            Self::Hann => 1,
+        }
+    }
+}
+
+/// Single-sided spectrum of a channel.
+#[derive(Debug, Clone)]
+pub struct Spectrum {
+    /// Bin frequencies in Hz, from 0 to the Nyquist frequency.
+    pub frequencies: Vec<f>,
+    /// Complex amplitude per bin, scaled so a sinusoid of amplitude `a` on a bin has magnitude
+    /// `a` (and a DC level its value). Tones between bins read low, by up to 36% with
+    /// `Window::Rect` and 15% with `Window::Hann`.
+    pub amplitudes: Vec<Cf>,
+    pub window: Window,
+}
+impl Spectrum {
+    /// Spacing of the bins in Hz.
+    pub fn resolution(&self) -> f {
+        self.frequencies.get(1).copied().unwrap_or(0.0)
+    }
+    /// The bin with the largest magnitude above DC and the bins the window spreads it into.
+    pub fn peak(&self) -> Option<usize> {
+        (1 + self.window.half_lobe()..self.amplitudes.len()).max_by(|&a, &b| {
+            self.amplitudes[a]
+                .abs()
+                .total_cmp(&self.amplitudes[b].abs())
+        })
+    }
+    /// Magnitude of a tone at `frequency`: the largest within a bin of it, so spectral leakage
+    /// of a tone between bins or spread by a window isn't missed.
+    pub fn magnitude_at(&self, frequency: f) -> f {
+        let bin = (frequency / self.resolution()).round() as usize;
+        let bins = bin.saturating_sub(1)..(bin + 2).min(self.amplitudes.len());
+        bins.map(|i| self.amplitudes[i].abs()).fold(0.0, f::max)
+    }
+}
+
+/// In-place radix-2 FFT, `X_k = sum_n x_n e^(-2 pi i k n / N)`.
+///
+/// Panics unless `data.len()` is a power of two.
+pub fn fft(data: &mut [Cf]) {
+    let n = data.len();
+    assert!(n.is_power_of_two(), "FFT length {n} isn't a power of two");
+    // bit-reversal permutation, then butterflies of doubling size.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -TAU / len as f;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let w = Cf::new((angle * k as f).cos(), (angle * k as f).sin());
+                let even = data[start + k];
+                let odd = data[start + k + len / 2] * w;
+                data[start + k] = even + odd;
+                data[start + k + len / 2] = even - odd;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Discrete Fourier transform of any length: `fft` for powers of two, otherwise Bluestein's
+/// algorithm, which rewrites the transform as a convolution computed with `fft`.
+pub fn dft(data: &[Cf]) -> Vec<Cf> {
+    let n = data.len();
+    if n.is_power_of_two() || n == 0 {
+        let mut data = data.to_vec();
+        fft(&mut data);
+        return data;
+    }
+    // chirp `e^(-pi i k^2 / n)`, with `k^2` reduced mod `2 n` to keep the angle accurate.
+    let chirp = (0..n)
+        .map(|k| {
+            let angle = -TAU / 2.0 * ((k * k) % (2 * n)) as f / n as f;
+            Cf::new(angle.cos(), angle.sin())
+        })
+        .collect::<Vec<_>>();
+    let m = (2 * n - 1).next_power_of_two();
+    let mut a = vec![Cf::from(0); m];
+    let mut b = vec![Cf::from(0); m];
+    for k in 0..n {
+        a[k] = data[k] * chirp[k];
+        b[k] = chirp[k].conj();
+        if k > 0 {
+            b[m - k] = chirp[k].conj();
+        }
+    }
+    fft(&mut a);
+    fft(&mut b);
+    // inverse transform of the product through the conjugate.
+    let mut c = a
+        .iter()
+        .zip(&b)
+        .map(|(&a, &b)| (a * b).conj())
+        .collect::<Vec<_>>();
+    fft(&mut c);
+    (0..n)
+        .map(|k| c[k].conj() * chirp[k] * Cf::new(1.0 / m as f, 0.0))
+        .collect()
+}
+
+impl Recording {
+    /// Spectrum of the channel labelled `label`, taking the rows as evenly spaced over the
+    /// recording. Bins are `1 / n` of the sample rate apart for `n` rows, whatever `n` is.
+    ///
+    /// Returns `None` if there's no such channel or fewer than two rows.
+    pub fn fft(&self, label: &str, window: Window) -> Option<Spectrum> {
+        let samples = self.channel(label)?;
+        let n = samples.len();
+        if n < 2 {
+            return None;
+        }
+        let sample_rate = (n - 1) as f / (self.time[n - 1] - self.time[0]);
+        let weights = (0..n).map(|i| window.weight(i, n)).collect::<Vec<_>>();
+        let gain = weights.iter().sum::<f>();
+        let windowed = samples
+            .iter()
+            .zip(&weights)
+            .map(|(&sample, &weight)| Cf::new(sample * weight, 0.0))
+            .collect::<Vec<_>>();
+        let amplitudes = dft(&windowed)[..=n / 2]
+            .iter()
+            .enumerate()
+            .map(|(k, &x)| {
+                // fold the negative frequencies onto the positive ones.
+                let scale = if k == 0 || 2 * k == n { 1.0 } else { 2.0 };
+                x * Cf::new(scale / gain, 0.0)
+            })
+            .collect();
+        Some(Spectrum {
+            frequencies: (0..=n / 2).map(|k| k as f * sample_rate / n as f).collect(),
+            amplitudes,
+            window,
+        })
+    }
+
+    /// Total harmonic distortion of the channel labelled `label` about a `fundamental` in Hz:
+    /// the RMS sum of the harmonics up to the Nyquist frequency relative to the fundamental.
+    ///
+    /// Returns `None` without such a channel or if the fundamental is above the Nyquist
+    /// frequency.
+    pub fn thd(&self, label: &str, fundamental: f, window: Window) -> Option<f> {
+        let spectrum = self.fft(label, window)?;
+        let nyquist = *spectrum.frequencies.last()?;
+        if fundamental <= 0.0 || fundamental > nyquist {
+            return None;
+        }
+        let harmonics = (2..)
+            .map(|h| h as f * fundamental)
+            .take_while(|&frequency| frequency <= nyquist)
+            .map(|frequency| spectrum.magnitude_at(frequency).powi(2))
+            .sum::<f>();
+        Some(harmonics.sqrt() / spectrum.magnitude_at(fundamental))
+    }
+}
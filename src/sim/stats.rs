@@ -0,0 +1,184 @@
+//! Summary statistics of recorded channels over a time range or cycle by cycle, for ripple and
+//! conduction-loss checks. Rows are taken as evenly spaced samples, as `Recording::record`
+//! after every tick gives.
+
+use std::{error::Error, fmt, ops::RangeBounds};
+
+use super::{f, probe::Recording};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsError {
+    UnknownChannel(String),
+    /// No rows fall in the range.
+    Empty,
+    /// The channel has a NaN or infinite sample at this time.
+    NonFinite {
+        channel: String,
+        time: f,
+    },
+    /// A `cycle_stats` period that isn't positive and finite.
+    InvalidPeriod(f),
+    /// A timing measurement (see `Recording::edges`) that needs more edges than the channel
+    /// has: two rising edges for a period, a falling edge and a complementary rising edge for a
+    /// dead time.
+    TooFewEdges {
+        channel: String,
+        found: usize,
+    },
+}
+impl fmt::Display for StatsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownChannel(label) => write!(f, "no channel labelled {label:?}"),
+            Self::Empty => write!(f, "no samples in the range"),
+            Self::NonFinite { channel, time } => {
+                write!(f, "{channel} is not finite at t = {time:.3e} s")
+            }
+            Self::InvalidPeriod(period) => write!(f, "invalid cycle period {period:e}"),
+            Self::TooFewEdges { channel, found } => {
+                write!(f, "{channel} has too few edges to measure ({found})")
+            }
+        }
+    }
+}
+impl Error for StatsError {}
+
+/// Statistics of one cycle of a channel, see `Recording::cycle_stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CycleStats {
+    /// Time the cycle starts at.
+    pub start: f,
+    pub rms: f,
+    pub mean: f,
+    pub min: f,
+    pub max: f,
+}
+
+/// Running sums over the samples of a range.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    n: usize,
+    sum: f,
+    sum_sq: f,
+    min: f,
+    max: f,
+}
+impl Accumulator {
+    fn new() -> Self {
+        Self {
+            n: 0,
+            sum: 0.0,
+            sum_sq: 0.0,
+            min: f::INFINITY,
+            max: f::NEG_INFINITY,
+        }
+    }
+    fn add(&mut self, x: f) {
+        self.n += 1;
+        self.sum += x;
+        self.sum_sq += x * x;
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+    }
+    fn mean(&self) -> f {
+        self.sum / self.n as f
+    }
+    fn rms(&self) -> f {
+        (self.sum_sq / self.n as f).sqrt()
+    }
+}
+
+impl Recording {
+    /// Root mean square of the channel labelled `label` over the rows with times in `range`.
+    pub fn rms(&self, label: &str, range: impl RangeBounds<f>) -> Result<f, StatsError> {
+        Ok(self.accumulate(label, range)?.rms())
+    }
+    pub fn mean(&self, label: &str, range: impl RangeBounds<f>) -> Result<f, StatsError> {
+        Ok(self.accumulate(label, range)?.mean())
+    }
+    pub fn peak_to_peak(&self, label: &str, range: impl RangeBounds<f>) -> Result<f, StatsError> {
+        let acc = self.accumulate(label, range)?;
+        Ok(acc.max - acc.min)
+    }
+
+    /// Statistics of the channel labelled `label` over consecutive cycles of `period` seconds
+    /// from the first row, each row belonging to the cycle its time falls in. A final cycle the
+    /// recording ends partway through is left out.
+    pub fn cycle_stats(&self, label: &str, period: f) -> Result<Vec<CycleStats>, StatsError> {
+        if !(period > 0.0 && period.is_finite()) {
+            return Err(StatsError::InvalidPeriod(period));
+        }
+        let samples = self.samples(label)?;
+        let (Some(&first), Some(&last)) = (self.time.first(), self.time.last()) else {
+            return Ok(Vec::new());
+        };
+        // each row stands for the interval up to the next.
+        let interval = match self.time.len() {
+            1 => 0.0,
+            n => (last - first) / (n - 1) as f,
+        };
+        let mut cycles = Vec::new();
+        let mut acc = Accumulator::new();
+        let mut cycle_i = 0;
+        let cycle_of = |time: f| ((time - first) / period + 1e-9).floor() as usize;
+        for (&time, &x) in self.time.iter().zip(samples) {
+            if !x.is_finite() {
+                return Err(StatsError::NonFinite {
+                    channel: label.to_string(),
+                    time,
+                });
+            }
+            let row_cycle = cycle_of(time);
+            if row_cycle != cycle_i {
+                if acc.n > 0 {
+                    cycles.push(Self::cycle(first, period, cycle_i, acc));
+                }
+                acc = Accumulator::new();
+                cycle_i = row_cycle;
+            }
+            acc.add(x);
+        }
+        let end = first + (cycle_i + 1) as f * period;
+        if acc.n > 0 && last + interval >= end - 1e-9 * period {
+            cycles.push(Self::cycle(first, period, cycle_i, acc));
+        }
+        Ok(cycles)
+    }
+    fn cycle(first: f, period: f, cycle_i: usize, acc: Accumulator) -> CycleStats {
+        CycleStats {
+            start: first + cycle_i as f * period,
+            rms: acc.rms(),
+            mean: acc.mean(),
+            min: acc.min,
+            max: acc.max,
+        }
+    }
+
+    fn samples(&self, label: &str) -> Result<&[f], StatsError> {
+        self.channel(label)
+            .ok_or_else(|| StatsError::UnknownChannel(label.to_string()))
+    }
+    fn accumulate(
+        &self,
+        label: &str,
+        range: impl RangeBounds<f>,
+    ) -> Result<Accumulator, StatsError> {
+        let mut acc = Accumulator::new();
+        for (&time, &x) in self.time.iter().zip(self.samples(label)?) {
+            if !range.contains(&time) {
+                continue;
+            }
+            if !x.is_finite() {
+                return Err(StatsError::NonFinite {
+                    channel: label.to_string(),
+                    time,
+                });
+            }
+            acc.add(x);
+        }
+        if acc.n == 0 {
+            return Err(StatsError::Empty);
+        }
+        Ok(acc)
+    }
+}
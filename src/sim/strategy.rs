@@ -0,0 +1,129 @@
+//! The iteration structure of `CircuitState::solve_state`. Each outer iteration alternates
+//! voltage corrections (every net moved towards the voltages its components propose) with
+//! charge-state corrections (every component's currents moved to balance its nets); a
+//! `SolveStrategy` decides how many of each, in which order, and when to give up.
+
+use std::fmt::Debug;
+
+use super::{error::SimError, f, CircuitState, HasConverged, Scalar};
+
+/// How `solve_state` iterates, see `CircuitState::set_strategy`.
+pub trait SolveStrategy<S: Scalar = f>: Debug + Send + Sync {
+    /// The name `SolveReport::strategy` records.
+    fn name(&self) -> &'static str;
+    /// Outer iterations before `solve_state` gives up.
+    fn max_outer(&self) -> usize;
+    /// One outer iteration with relaxation factor `step` (from `SolverConfig::relaxation`),
+    /// whether the circuit converged in it.
+    fn outer_iteration(
+        &self,
+        corrections: &mut Corrections<'_, S>,
+        step: S,
+    ) -> Result<HasConverged, SimError>;
+    fn clone_box(&self) -> Box<dyn SolveStrategy<S>>;
+}
+impl<S: Scalar> Clone for Box<dyn SolveStrategy<S>> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+impl<S: Scalar> Default for Box<dyn SolveStrategy<S>> {
+    fn default() -> Self {
+        Box::new(RelaxationStrategy::default())
+    }
+}
+
+/// The corrections an outer iteration is made of, on the circuit being solved.
+#[derive(Debug)]
+pub struct Corrections<'a, S: Scalar = f> {
+    pub(super) circuit: &'a mut CircuitState<S>,
+}
+impl<S: Scalar> Corrections<'_, S> {
+    /// Move every net towards the voltages its components propose, by `step`; whether no net
+    /// moved by more than `Scalar::CONVERGENCE_EPSILON`.
+    pub fn voltages(&mut self, step: S) -> Result<HasConverged, SimError> {
+        self.circuit.correct_voltages(step)
+    }
+    /// Move every component's currents towards balancing its nets; whether the components and
+    /// the net currents settled.
+    pub fn charge_states(&mut self) -> Result<HasConverged, SimError> {
+        self.circuit.correct_charge_states()
+    }
+}
+
+/// Up to `inner_voltage_sweeps` voltage corrections, stopping early once the voltages settle,
+/// then a charge-state correction: the default.
+#[derive(Debug, Clone, Copy)]
+pub struct RelaxationStrategy {
+    pub inner_voltage_sweeps: usize,
+    pub max_outer: usize,
+}
+impl Default for RelaxationStrategy {
+    fn default() -> Self {
+        Self {
+            inner_voltage_sweeps: 10,
+            max_outer: 10000,
+        }
+    }
+}
+impl<S: Scalar> SolveStrategy<S> for RelaxationStrategy {
+    fn name(&self) -> &'static str {
+        "relaxation"
+    }
+    fn max_outer(&self) -> usize {
+        self.max_outer
+    }
+    fn outer_iteration(
+        &self,
+        corrections: &mut Corrections<'_, S>,
+        step: S,
+    ) -> Result<HasConverged, SimError> {
+        let mut converged = true;
+        for _ in 0..self.inner_voltage_sweeps {
+            if !corrections.voltages(step)? {
+                converged = false;
+            } else {
+                break;
+            }
+        }
+        if !corrections.charge_states()? {
+            converged = false;
+        }
+        Ok(converged)
+    }
+    fn clone_box(&self) -> Box<dyn SolveStrategy<S>> {
+        Box::new(*self)
+    }
+}
+
+/// One voltage correction and one charge-state correction per outer iteration, so currents
+/// catch up with every voltage step.
+#[derive(Debug, Clone, Copy)]
+pub struct InterleavedStrategy {
+    pub max_outer: usize,
+}
+impl Default for InterleavedStrategy {
+    fn default() -> Self {
+        Self { max_outer: 100000 }
+    }
+}
+impl<S: Scalar> SolveStrategy<S> for InterleavedStrategy {
+    fn name(&self) -> &'static str {
+        "interleaved"
+    }
+    fn max_outer(&self) -> usize {
+        self.max_outer
+    }
+    fn outer_iteration(
+        &self,
+        corrections: &mut Corrections<'_, S>,
+        step: S,
+    ) -> Result<HasConverged, SimError> {
+        let voltages = corrections.voltages(step)?;
+        let charge_states = corrections.charge_states()?;
+        Ok(voltages && charge_states)
+    }
+    fn clone_box(&self) -> Box<dyn SolveStrategy<S>> {
+        Box::new(*self)
+    }
+}
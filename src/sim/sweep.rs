@@ -0,0 +1,129 @@
+use std::thread;
+
+use super::f;
+
+/// One combination of parameter values in a sweep.
+#[derive(Debug, Clone)]
+pub struct SweepPoint {
+    pub params: Vec<(String, f)>,
+}
+impl SweepPoint {
+    /// Value of the parameter `name` at this point.
+    pub fn get(&self, name: &str) -> f {
+        self.params
+            .iter()
+            .find(|(param_name, _)| param_name == name)
+            .unwrap_or_else(|| panic!("sweep has no parameter named {name:?}"))
+            .1
+    }
+}
+
+/// A set of parameter points to run a circuit at.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    points: Vec<SweepPoint>,
+    n_threads: usize,
+}
+impl Sweep {
+    /// Sweep a single parameter over a list of values.
+    pub fn list(name: &str, values: &[f]) -> Self {
+        Self::from_points(
+            values
+                .iter()
+                .map(|v| SweepPoint {
+                    params: vec![(name.to_string(), *v)],
+                })
+                .collect(),
+        )
+    }
+    /// Sweep over every combination of the given parameter values (the last parameter varies fastest).
+    pub fn grid(params: &[(&str, &[f])]) -> Self {
+        let mut points = vec![SweepPoint { params: Vec::new() }];
+        for (name, values) in params {
+            points = points
+                .into_iter()
+                .flat_map(|point| {
+                    values.iter().map(move |v| {
+                        let mut point = point.clone();
+                        point.params.push((name.to_string(), *v));
+                        point
+                    })
+                })
+                .collect();
+        }
+        Self::from_points(points)
+    }
+    pub fn from_points(points: Vec<SweepPoint>) -> Self {
+        Self {
+            points,
+            n_threads: 1,
+        }
+    }
+    /// Run the points on up to `n_threads` threads. Results are returned in point order regardless.
+    pub fn with_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads.max(1);
+        self
+    }
+
+    pub fn points(&self) -> &[SweepPoint] {
+        &self.points
+    }
+
+    /// Build a circuit (or `SimulationSet`) for each point with `build` and evaluate it with
+    /// `metric`, which is free to run it however it likes.
+    pub fn run<M, T, B, E>(&self, build: B, metric: E) -> SweepResult<M>
+    where
+        M: Send,
+        B: Fn(&SweepPoint) -> T + Sync,
+        E: Fn(&SweepPoint, &mut T) -> M + Sync,
+    {
+        let eval = |point: &SweepPoint| metric(point, &mut build(point));
+        let metrics = if self.n_threads == 1 {
+            self.points.iter().map(eval).collect::<Vec<_>>()
+        } else {
+            let chunk_size = self.points.len().div_ceil(self.n_threads).max(1);
+            thread::scope(|scope| {
+                let handles = self
+                    .points
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(|| chunk.iter().map(eval).collect::<Vec<_>>()))
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().expect("sweep thread panicked"))
+                    .collect()
+            })
+        };
+        SweepResult {
+            rows: self.points.iter().cloned().zip(metrics).collect(),
+        }
+    }
+    /// Like `run`, but each point starts from a clone of `base` modified by `mutate`.
+    pub fn run_mutating<M, T, U, E>(&self, base: &T, mutate: U, metric: E) -> SweepResult<M>
+    where
+        M: Send,
+        T: Clone + Sync,
+        U: Fn(&SweepPoint, &mut T) + Sync,
+        E: Fn(&SweepPoint, &mut T) -> M + Sync,
+    {
+        self.run(
+            |point| {
+                let mut circuit = base.clone();
+                mutate(point, &mut circuit);
+                circuit
+            },
+            metric,
+        )
+    }
+}
+
+/// Table of `(params, metric)` rows, in sweep point order.
+#[derive(Debug, Clone)]
+pub struct SweepResult<M> {
+    pub rows: Vec<(SweepPoint, M)>,
+}
+impl<M> SweepResult<M> {
+    pub fn metrics(&self) -> impl Iterator<Item = &M> {
+        self.rows.iter().map(|(_, metric)| metric)
+    }
+}
@@ -0,0 +1,180 @@
+use super::{f, CircuitState, ComponentId, ComponentMut, ComponentRef};
+
+pub type ThermalNodeId = usize;
+
+/// Lumped thermal model (junctions, cases, heatsinks) driven by the heat components dissipate.
+///
+/// Nodes hold a temperature in kelvin and are joined by thermal resistances (K/W); each node
+/// either has a heat capacity (J/K) or is held at a fixed temperature (ambient). Components are
+/// bound to a node to heat it with their `dissipated_power`, and MOSFETs and resistors (see
+/// `ResistorRating::tempco`) bound to a node take its temperature. The node of a MOSFET with a
+/// `multiplicity` is the junction of one of its devices, heated by its share of the power, so it
+/// takes the thermal resistances of a single device; a heatsink they share then only sees that
+/// share, and stands in for theirs with `multiplicity` times its resistance to ambient and a
+/// `multiplicity`th of its heat capacity.
+///
+/// Thermal time constants are many electrical ticks long, so the network can be stepped only
+/// every few ticks (see `set_decimation`), with the heat of the ticks in between summed up.
+#[derive(Debug, Clone)]
+pub struct ThermalNetwork {
+    temperatures: Vec<f>,
+    /// Heat capacity of each node, `None` for nodes at a fixed temperature.
+    capacitances: Vec<Option<f>>,
+    resistances: Vec<(ThermalNodeId, ThermalNodeId, f)>,
+    bindings: Vec<(ComponentId, ThermalNodeId)>,
+    every: usize,
+    /// Heat (J) into each node over the ticks since the last step.
+    pending_heat: Vec<f>,
+    pending_dt: f,
+    pending_ticks: usize,
+}
+impl Default for ThermalNetwork {
+    fn default() -> Self {
+        Self {
+            temperatures: Vec::new(),
+            capacitances: Vec::new(),
+            resistances: Vec::new(),
+            bindings: Vec::new(),
+            every: 1,
+            pending_heat: Vec::new(),
+            pending_dt: 0.0,
+            pending_ticks: 0,
+        }
+    }
+}
+impl ThermalNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Step the temperatures only every `every` calls of `tick`, over the time of all of them
+    /// and with the mean heating power over it, so no heat is lost in between. The MOSFET
+    /// temperatures lag by up to `every` ticks. 1 (the default) steps every tick.
+    pub fn set_decimation(&mut self, every: usize) {
+        self.every = every.max(1);
+    }
+
+    /// A node with heat capacity `capacitance`, starting at `temperature`.
+    pub fn add_node(&mut self, capacitance: f, temperature: f) -> ThermalNodeId {
+        self.temperatures.push(temperature);
+        self.capacitances.push(Some(capacitance));
+        self.temperatures.len() - 1
+    }
+    /// A node held at `temperature`, such as the ambient air or a cold plate.
+    pub fn add_fixed_node(&mut self, temperature: f) -> ThermalNodeId {
+        self.temperatures.push(temperature);
+        self.capacitances.push(None);
+        self.temperatures.len() - 1
+    }
+    /// Join `a` and `b` through `resistance` K/W.
+    pub fn add_resistance(&mut self, a: ThermalNodeId, b: ThermalNodeId, resistance: f) {
+        assert!(
+            a < self.temperatures.len() && b < self.temperatures.len(),
+            "thermal node id invalid"
+        );
+        self.resistances.push((a, b, resistance));
+    }
+    /// Heat `node` with the power `component` dissipates (and set its temperature, for MOSFETs
+    /// and resistors).
+    pub fn bind(&mut self, component: ComponentId, node: ThermalNodeId) {
+        assert!(node < self.temperatures.len(), "thermal node id invalid");
+        self.bindings.push((component, node));
+    }
+
+    pub fn temperature(&self, node: ThermalNodeId) -> f {
+        self.temperatures[node]
+    }
+    pub fn set_temperature(&mut self, node: ThermalNodeId, temperature: f) {
+        self.temperatures[node] = temperature;
+    }
+
+    /// Add the heat the bound components dissipate over `dt` at their present power and, every
+    /// `set_decimation` calls, integrate the node temperatures over the time since the last
+    /// step and pass the new temperatures on to the bound MOSFETs and resistors.
+    ///
+    /// Call once after each `tick(dt)` of `circuit`. Steps are split so the explicit integration
+    /// stays stable however small the heat capacities are.
+    pub fn tick(&mut self, circuit: &mut CircuitState, dt: f) {
+        self.pending_heat.resize(self.temperatures.len(), 0.0);
+        for &(component, node) in &self.bindings {
+            let devices = match circuit.component(component) {
+                ComponentRef::MOSFET(v) => v.value.multiplicity,
+                _ => 1.0,
+            };
+            self.pending_heat[node] += circuit.dissipated_power(component) / devices * dt;
+        }
+        self.pending_dt += dt;
+        self.pending_ticks += 1;
+        if self.pending_ticks >= self.every {
+            self.flush(circuit);
+        }
+    }
+
+    /// Integrate the heat of the ticks since the last step now, as at the end of a run that
+    /// isn't a multiple of `set_decimation` ticks long.
+    pub fn flush(&mut self, circuit: &mut CircuitState) {
+        let dt = std::mem::take(&mut self.pending_dt);
+        self.pending_ticks = 0;
+        if dt > 0.0 {
+            let mut heat = std::mem::take(&mut self.pending_heat);
+            heat.iter_mut().for_each(|energy| *energy /= dt);
+            self.step(&heat, dt);
+        }
+        self.pending_heat.clear();
+
+        for &(component, node) in &self.bindings {
+            match circuit.component_mut(component) {
+                ComponentMut::MOSFET(v) => {
+                    v.temperature = self.temperatures[node];
+                    v.temperature_held = true;
+                }
+                ComponentMut::Linear(v) => {
+                    v.set_temperature(self.temperatures[node]);
+                    v.temperature_held = true;
+                }
+                ComponentMut::NoiseSource(_)
+                | ComponentMut::GateDriver(_)
+                | ComponentMut::Comparator(_)
+                | ComponentMut::Converter(_) => {}
+            }
+        }
+    }
+
+    /// Integrate the node temperatures over `dt` with `heat` watts into each node.
+    fn step(&mut self, heat: &[f], dt: f) {
+        // a node relaxes towards its neighbours with time constant `C / sum(1 / R)`.
+        let mut conductance = vec![0.0; self.temperatures.len()];
+        for &(a, b, r) in &self.resistances {
+            conductance[a] += 1.0 / r;
+            conductance[b] += 1.0 / r;
+        }
+        let time_constant = self
+            .capacitances
+            .iter()
+            .zip(&conductance)
+            .filter_map(|(c, g)| Some(c.as_ref()? / g))
+            .fold(f::INFINITY, f::min);
+        let n_steps = (2.0 * dt / time_constant).ceil().max(1.0) as usize;
+        let dt = dt / n_steps as f;
+
+        let mut flow = vec![0.0; self.temperatures.len()];
+        for _ in 0..n_steps {
+            flow.copy_from_slice(heat);
+            for &(a, b, r) in &self.resistances {
+                let q = (self.temperatures[a] - self.temperatures[b]) / r;
+                flow[a] -= q;
+                flow[b] += q;
+            }
+            for ((temperature, capacitance), flow) in self
+                .temperatures
+                .iter_mut()
+                .zip(&self.capacitances)
+                .zip(&flow)
+            {
+                if let Some(c) = capacitance {
+                    *temperature += flow / c * dt;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Derived channels of a three-phase output (an inverter's, a motor's terminals): phase and
+//! line-to-line voltages, per-phase and total power, and the Clarke (alpha-beta) components,
+//! added to a `Recording` as `Probe::Derived` math channels over the phase nets.
+
+use std::ops::RangeBounds;
+
+use super::{
+    f,
+    probe::{DerivedExpr, Probe, Quantity, Recording},
+    stats::StatsError,
+    ComponentId, NetId,
+};
+
+/// Three phase nets and the channels derived from them, see `ThreePhaseProbe::append_to`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThreePhaseProbe {
+    /// Prefix of the labels of the channels, `<label>.<channel>`.
+    pub label: String,
+    /// The nets of phases a, b and c.
+    pub phases: [NetId; 3],
+    /// The star point the phase voltages are taken from; `None` for the mean of the three phase
+    /// voltages, the star point of a balanced load.
+    pub neutral: Option<NetId>,
+    /// Components carrying the current of each phase, oriented so `Quantity::Current` is
+    /// positive out of the phase net into the load; for the power channels.
+    pub currents: Option<[ComponentId; 3]>,
+}
+
+const PHASES: [&str; 3] = ["a", "b", "c"];
+
+impl ThreePhaseProbe {
+    pub fn new(label: &str, phases: [NetId; 3]) -> Self {
+        Self {
+            label: label.to_string(),
+            phases,
+            neutral: None,
+            currents: None,
+        }
+    }
+    pub fn with_neutral(mut self, neutral: NetId) -> Self {
+        self.neutral = Some(neutral);
+        self
+    }
+    pub fn with_currents(mut self, currents: [ComponentId; 3]) -> Self {
+        self.currents = Some(currents);
+        self
+    }
+
+    /// Add the channels to `probes`, for `Recording::new`:
+    /// - `a`, `b`, `c` (and `n`): the net voltages.
+    /// - `van`, `vbn`, `vcn`: the phase voltages.
+    /// - `vab`, `vbc`, `vca`: the line-to-line voltages.
+    /// - `alpha`, `beta`: the amplitude-invariant Clarke transform of the phase voltages.
+    /// - with `currents`: `ia`, `ib`, `ic`, the phase powers `pa`, `pb`, `pc` and their sum `p`.
+    pub fn append_to(&self, probes: &mut Vec<(String, Probe)>) {
+        let mut push = |name: &str, probe: Probe| {
+            probes.push((format!("{}.{name}", self.label), probe));
+            DerivedExpr::Channel(probes.len() - 1)
+        };
+        let nets = [0, 1, 2].map(|i| push(PHASES[i], Probe::Net(self.phases[i])));
+        let neutral = match self.neutral {
+            Some(neutral) => push("n", Probe::Net(neutral)),
+            None => {
+                let [a, b, c] = nets.clone();
+                push(
+                    "n",
+                    Probe::Derived(a.plus(b).plus(c).scaled(1.0 / 3.0, 0.0)),
+                )
+            }
+        };
+        let [van, vbn, vcn] = [0, 1, 2].map(|i| {
+            let phase = nets[i].clone().minus(neutral.clone());
+            push(&format!("v{}n", PHASES[i]), Probe::Derived(phase))
+        });
+        for i in 0..3 {
+            let line = nets[i].clone().minus(nets[(i + 1) % 3].clone());
+            push(
+                &format!("v{}{}", PHASES[i], PHASES[(i + 1) % 3]),
+                Probe::Derived(line),
+            );
+        }
+        // alpha = (2 va - vb - vc) / 3, beta = (vb - vc) / sqrt(3).
+        let alpha = van
+            .clone()
+            .scaled(2.0, 0.0)
+            .minus(vbn.clone())
+            .minus(vcn.clone())
+            .scaled(1.0 / 3.0, 0.0);
+        push("alpha", Probe::Derived(alpha));
+        let beta = vbn
+            .clone()
+            .minus(vcn.clone())
+            .scaled(1.0 / 3f64.sqrt(), 0.0);
+        push("beta", Probe::Derived(beta));
+
+        let Some(currents) = self.currents else {
+            return;
+        };
+        let currents = [0, 1, 2].map(|i| {
+            push(
+                &format!("i{}", PHASES[i]),
+                Probe::Component(currents[i], Quantity::Current),
+            )
+        });
+        let powers = [van, vbn, vcn]
+            .into_iter()
+            .zip(currents)
+            .enumerate()
+            .map(|(i, (v, i_phase))| {
+                push(&format!("p{}", PHASES[i]), Probe::Derived(v.times(i_phase)))
+            })
+            .collect::<Vec<_>>();
+        let [pa, pb, pc] = <[DerivedExpr; 3]>::try_from(powers).unwrap();
+        push("p", Probe::Derived(pa.plus(pb).plus(pc)));
+    }
+
+    /// The RMS of each phase voltage over `range` of a recording of the channels.
+    pub fn phase_rms(
+        &self,
+        recording: &Recording,
+        range: impl RangeBounds<f> + Clone,
+    ) -> Result<[f; 3], StatsError> {
+        let mut rms = [0.0; 3];
+        for (i, rms) in rms.iter_mut().enumerate() {
+            *rms = recording.rms(&format!("{}.v{}n", self.label, PHASES[i]), range.clone())?;
+        }
+        Ok(rms)
+    }
+    /// The RMS of each line-to-line voltage, `vab`, `vbc` and `vca`, over `range`.
+    pub fn line_rms(
+        &self,
+        recording: &Recording,
+        range: impl RangeBounds<f> + Clone,
+    ) -> Result<[f; 3], StatsError> {
+        let mut rms = [0.0; 3];
+        for (i, rms) in rms.iter_mut().enumerate() {
+            let label = format!("{}.v{}{}", self.label, PHASES[i], PHASES[(i + 1) % 3]);
+            *rms = recording.rms(&label, range.clone())?;
+        }
+        Ok(rms)
+    }
+}
@@ -0,0 +1,261 @@
+//! Small-signal transfer functions from a source to a probe (a filter's response, the loop gain
+//! of a regulator), and first- and second-order models fitted to them for their pole and zero
+//! frequencies and Q.
+
+use super::{
+    ac::{AcPoint, Cf},
+    components::LinearComponentValue,
+    error::SimError,
+    f,
+    probe::{Probe, Quantity},
+    CircuitState, ComponentId, ComponentRef,
+};
+use crate::linalg::Mat;
+
+/// The small-signal response measured by `CircuitState::measure_transfer_function`.
+#[derive(Debug, Clone)]
+pub struct TransferFunction {
+    pub points: Vec<AcPoint>,
+}
+
+/// A rational model `N(s) / D(s)` of a transfer function, with `N` and `D` polynomials of up to
+/// its order and `D(0) = 1`, fitted by `TransferFunction::fit`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RationalFit {
+    pub order: usize,
+    /// Angular frequency `s` is normalized by in the coefficients, the geometric mean of the fitted
+    /// points'.
+    omega: f,
+    /// Coefficients of `N` and `D` in rising powers of `s / omega`.
+    numerator: [f; 3],
+    denominator: [f; 3],
+}
+
+/// Relative size below which a leading coefficient of a fitted polynomial is taken as zero.
+const NEGLIGIBLE: f = 1e-6;
+/// Reweighting passes of the fit after the first.
+const REFINEMENTS: usize = 4;
+
+impl CircuitState {
+    /// Small-signal transfer from the `input` source (driven with a 1 V excitation) to `output`
+    /// at each of `frequencies`, linearized about the present operating point as by
+    /// `ac_analysis`.
+    ///
+    /// `output` has to be a voltage: a `Probe::Net` or a `Probe::Component` of
+    /// `Quantity::Voltage`.
+    pub fn measure_transfer_function(
+        &self,
+        input: ComponentId,
+        output: &Probe,
+        frequencies: &[f],
+    ) -> Result<TransferFunction, SimError> {
+        if input >= self.n_components() {
+            return Err(SimError::UnknownComponent(input));
+        }
+        match self.component(input) {
+            ComponentRef::Linear(state)
+                if matches!(
+                    state.value,
+                    LinearComponentValue::Source(_) | LinearComponentValue::Pwm(_)
+                ) => {}
+            ComponentRef::NoiseSource(_) => {}
+            _ => {
+                return Err(SimError::WrongComponentKind {
+                    component: input,
+                    expected: "a source",
+                })
+            }
+        }
+        let nets = match *output {
+            Probe::Net(net) if net < self.nets.len() => [0, net],
+            Probe::Component(component, Quantity::Voltage) if component < self.n_components() => {
+                let nets = self.component(component).as_dyn().nets();
+                [nets[0], nets[nets.len() - 1]]
+            }
+            _ => {
+                return Err(SimError::InvalidProbe {
+                    spec: format!("{output:?}"),
+                    message: "not a voltage of the circuit".to_string(),
+                })
+            }
+        };
+        let mut points = Vec::with_capacity(frequencies.len());
+        for &frequency in frequencies {
+            let points_at = self
+                .ac_analysis(input, nets, &[frequency])
+                .ok_or(SimError::SingularAcSystem(frequency))?;
+            points.extend(points_at);
+        }
+        Ok(TransferFunction { points })
+    }
+}
+
+impl TransferFunction {
+    pub fn frequencies(&self) -> Vec<f> {
+        self.points.iter().map(|point| point.frequency).collect()
+    }
+    pub fn gains_db(&self) -> Vec<f> {
+        self.points.iter().map(AcPoint::magnitude_db).collect()
+    }
+    /// Degrees.
+    pub fn phases_deg(&self) -> Vec<f> {
+        self.points
+            .iter()
+            .map(|point| point.phase.to_degrees())
+            .collect()
+    }
+
+    /// Fit an `order` 1 or 2 `RationalFit` to the response, by least squares on the error of
+    /// each point relative to its magnitude (as for a fit of log-magnitude and phase), from
+    /// Levy's linearization `N(s) - H(s) (D(s) - 1) = H(s)` refined by reweighting with the last
+    /// `1 / |D(s)|` (Sanathanan-Koerner).
+    ///
+    /// Returns `None` for too few points to determine the model, or a response of zeros.
+    ///
+    /// Panics unless `order` is 1 or 2.
+    pub fn fit(&self, order: usize) -> Option<RationalFit> {
+        assert!(
+            order == 1 || order == 2,
+            "Only first- and second-order models are fitted."
+        );
+        let points = self
+            .points
+            .iter()
+            .filter(|point| point.frequency > 0.0 && point.magnitude > 0.0)
+            .collect::<Vec<_>>();
+        if points.is_empty() {
+            return None;
+        }
+        let omega = (points
+            .iter()
+            .map(|point| (std::f64::consts::TAU * point.frequency).ln())
+            .sum::<f>()
+            / points.len() as f)
+            .exp();
+        let n_unknowns = 2 * order + 1;
+        let mut fit = RationalFit {
+            order,
+            omega,
+            numerator: [0.0; 3],
+            denominator: [1.0, 0.0, 0.0],
+        };
+        for _ in 0..=REFINEMENTS {
+            // unknowns: numerator[0..=order], then denominator[1..=order].
+            let mut a = Mat::<f>::zeros(2 * points.len(), n_unknowns);
+            let mut b = Mat::<f>::zeros(2 * points.len(), 1);
+            for (point_i, point) in points.iter().enumerate() {
+                let p = fit.normalized(point.frequency);
+                let weight = 1.0 / (point.magnitude * fit.denominator_at(p).abs());
+                let h = point.response;
+                let mut p_k = Cf::from(1);
+                for k in 0..=order {
+                    let mut set = |column: usize, term: Cf| {
+                        a[[2 * point_i, column]] = weight * term.re;
+                        a[[2 * point_i + 1, column]] = weight * term.im;
+                    };
+                    set(k, p_k);
+                    if k > 0 {
+                        set(order + k, Cf::from(0) - h * p_k);
+                    }
+                    p_k *= p;
+                }
+                b[[2 * point_i, 0]] = weight * h.re;
+                b[[2 * point_i + 1, 0]] = weight * h.im;
+            }
+            let x = a.lstsq(&b)?;
+            for k in 0..=order {
+                fit.numerator[k] = x[[k, 0]];
+            }
+            for k in 1..=order {
+                fit.denominator[k] = x[[order + k, 0]];
+            }
+        }
+        Some(fit)
+    }
+}
+
+impl RationalFit {
+    fn normalized(&self, frequency: f) -> Cf {
+        Cf::new(0.0, std::f64::consts::TAU * frequency / self.omega)
+    }
+    fn denominator_at(&self, p: Cf) -> Cf {
+        polynomial_at(&self.denominator, p)
+    }
+
+    /// The model's response at `frequency`, in Hz.
+    pub fn response(&self, frequency: f) -> Cf {
+        let p = self.normalized(frequency);
+        polynomial_at(&self.numerator, p) / self.denominator_at(p)
+    }
+    pub fn dc_gain(&self) -> f {
+        self.numerator[0]
+    }
+    /// Coefficients of the numerator in rising powers of `s` (in rad/s).
+    pub fn numerator(&self) -> Vec<f> {
+        self.in_s(&self.numerator)
+    }
+    /// Coefficients of the denominator in rising powers of `s`, the first 1.
+    pub fn denominator(&self) -> Vec<f> {
+        self.in_s(&self.denominator)
+    }
+    fn in_s(&self, coefficients: &[f; 3]) -> Vec<f> {
+        (0..=self.order)
+            .map(|k| coefficients[k] / self.omega.powi(k as i32))
+            .collect()
+    }
+
+    /// Poles of the model in the `s` plane, in rad/s.
+    pub fn poles(&self) -> Vec<Cf> {
+        self.roots(&self.denominator)
+    }
+    /// Finite zeros of the model in the `s` plane, in rad/s.
+    pub fn zeros(&self) -> Vec<Cf> {
+        self.roots(&self.numerator)
+    }
+    fn roots(&self, coefficients: &[f; 3]) -> Vec<Cf> {
+        let largest = coefficients.iter().fold(0.0, |max: f, c| max.max(c.abs()));
+        let degree = (1..=self.order)
+            .rev()
+            .find(|&k| coefficients[k].abs() > NEGLIGIBLE * largest)
+            .unwrap_or(0);
+        let scale = |root: Cf| Cf::new(root.re * self.omega, root.im * self.omega);
+        match degree {
+            1 => vec![scale(Cf::new(-coefficients[0] / coefficients[1], 0.0))],
+            2 => {
+                let [c, b, a] = *coefficients;
+                let discriminant = b * b - 4.0 * a * c;
+                let (re, im) = if discriminant >= 0.0 {
+                    (discriminant.sqrt(), 0.0)
+                } else {
+                    (0.0, (-discriminant).sqrt())
+                };
+                [1.0, -1.0]
+                    .map(|sign| scale(Cf::new((-b + sign * re) / (2.0 * a), sign * im / (2.0 * a))))
+                    .to_vec()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The frequency of the poles in Hz: the pole of a first-order model, the natural
+    /// (undamped) frequency of a second-order one.
+    pub fn natural_frequency(&self) -> f {
+        let omega = match self.order {
+            1 => self.omega / self.denominator[1],
+            _ => self.omega / self.denominator[2].sqrt(),
+        };
+        omega / std::f64::consts::TAU
+    }
+    /// Quality factor of the poles of a second-order model, `None` for a first-order one.
+    pub fn q(&self) -> Option<f> {
+        (self.order == 2).then(|| self.denominator[2].sqrt() / self.denominator[1])
+    }
+}
+
+/// `sum_k coefficients[k] p^k`.
+fn polynomial_at(coefficients: &[f; 3], p: Cf) -> Cf {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Cf::from(0), |accum, &c| accum * p + Cf::new(c, 0.0))
+}
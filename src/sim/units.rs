@@ -0,0 +1,83 @@
+//! Values written with SI prefixes, as the netlist importer, the CLI and `circuit!` take them.
+
+use super::{error::SimError, f};
+
+/// Units `parse_si` accepts after the prefix, in any case.
+const UNITS: [&str; 10] = ["V", "A", "F", "H", "s", "Hz", "ohm", "ohms", "Ω", "W"];
+
+/// A number with an optional SI prefix and an optional unit (one of `UNITS`), e.g. `4.7k`,
+/// `100nF`, `10ms`, `2.2µ`, `1e-7`.
+///
+/// The prefixes are `f p n u µ m k meg M g t`. They are case-insensitive as in SPICE, except
+/// that `m` is milli and `M` (like `meg` in any case) is mega. A unit without a prefix reads as
+/// one (`5V`), so one starting with a prefix letter needs the prefix spelled out: `10F` is ten
+/// femto, not ten farads.
+///
+/// Returns `None` for anything else, including a missing number, an unknown unit and values
+/// too large to represent.
+pub fn parse_si(s: &str) -> Option<f> {
+    let split = s
+        .char_indices()
+        .find(|&(i, c)| {
+            // an exponent `e` is part of the number only if followed by a digit or sign.
+            c.is_alphabetic()
+                && !(matches!(c, 'e' | 'E')
+                    && s[i + 1..].starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+'))
+        })
+        .map_or(s.len(), |(i, _)| i);
+    let (number, suffix) = s.split_at(split);
+    let (exponent, unit) = prefix(suffix);
+    if !(unit.is_empty() || UNITS.iter().any(|known| unit.eq_ignore_ascii_case(known))) {
+        return None;
+    }
+    let mantissa = number.parse::<f>().ok()?;
+    let value = if number.contains(['e', 'E']) {
+        mantissa * (10.0 as f).powi(exponent)
+    } else {
+        // through the exponent rather than a multiplication, so `100n` is exactly `100e-9`.
+        format!("{number}e{exponent}").parse::<f>().ok()?
+    };
+    value.is_finite().then_some(value)
+}
+
+/// The power of ten of the prefix `suffix` starts with, and the rest of it.
+fn prefix(suffix: &str) -> (i32, &str) {
+    if let Some(rest) = suffix
+        .get(..3)
+        .filter(|meg| meg.eq_ignore_ascii_case("meg"))
+        .map(|_| &suffix[3..])
+    {
+        return (6, rest);
+    }
+    let mut chars = suffix.chars();
+    let exponent = match chars.next() {
+        Some('f' | 'F') => -15,
+        Some('p' | 'P') => -12,
+        Some('n' | 'N') => -9,
+        // the micro sign and the Greek letter mu
+        Some('u' | 'U' | 'µ' | 'μ') => -6,
+        Some('m') => -3,
+        Some('k' | 'K') => 3,
+        Some('M') => 6,
+        Some('g' | 'G') => 9,
+        Some('t' | 'T') => 12,
+        // no prefix, or a bare unit
+        _ => return (0, suffix),
+    };
+    (exponent, chars.as_str())
+}
+
+/// A value given either as a number or as text for `parse_si`, see `circuit!`.
+pub trait SiValue {
+    fn si_value(self) -> Result<f, SimError>;
+}
+impl SiValue for f {
+    fn si_value(self) -> Result<f, SimError> {
+        Ok(self)
+    }
+}
+impl SiValue for &str {
+    fn si_value(self) -> Result<f, SimError> {
+        parse_si(self).ok_or_else(|| SimError::InvalidValue(self.to_string()))
+    }
+}
@@ -0,0 +1,493 @@
+//! Sanity checks of a circuit's values and topology before simulating it, see
+//! `CircuitState::validate`.
+
+use std::{collections::HashMap, fmt};
+
+use super::{
+    builder::NameMap, components::LinearComponentValue, f, CircuitState, ComponentId, ComponentRef,
+    ComponentState, NetId, Scalar,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Legitimate in some circuits, but often a mistake.
+    Info,
+    /// Solvable, but likely to give arbitrary voltages or slow the solver down.
+    Warning,
+    /// The circuit has no finite solution.
+    Error,
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A problem `CircuitState::validate` found. Net 0 is taken as ground.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationWarning<S: Scalar = f> {
+    /// A capacitance, resistance, inductance or MOSFET parameter that isn't positive, or a
+    /// series resistance or noise level that is negative.
+    NonPositiveValue {
+        component: ComponentId,
+        quantity: &'static str,
+        value: S,
+    },
+    /// Nets that no DC path connects to ground, so nothing but the initial charge of the
+    /// `capacitors` on them (if any) sets their voltage.
+    NoDcPath {
+        nets: Vec<NetId>,
+        capacitors: Vec<ComponentId>,
+    },
+    /// A loop of inductors, sources and closed switches without resistance, around which a
+    /// current can grow without bound.
+    InductorLoop { components: Vec<ComponentId> },
+    /// A loop of sources and closed switches, whose voltages either contradict each other or
+    /// leave the loop current undetermined.
+    SourceLoop { components: Vec<ComponentId> },
+    /// Two components of the same kind connected to the same nets.
+    Duplicate {
+        components: [ComponentId; 2],
+        nets: Vec<NetId>,
+    },
+    /// A MOSFET whose gate net has no DC path to ground, so nothing sets its gate voltage.
+    UndrivenGate { component: ComponentId, net: NetId },
+    /// A net with fewer than two terminals connected, the one being of `component`.
+    DanglingNet {
+        net: NetId,
+        component: Option<ComponentId>,
+    },
+}
+impl<S: Scalar> ValidationWarning<S> {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::NonPositiveValue { .. } | Self::InductorLoop { .. } | Self::SourceLoop { .. } => {
+                Severity::Error
+            }
+            Self::NoDcPath { .. } | Self::UndrivenGate { .. } | Self::DanglingNet { .. } => {
+                Severity::Warning
+            }
+            Self::Duplicate { .. } => Severity::Info,
+        }
+    }
+
+    /// The warning with nets named after `names` (or else by `CircuitState::net_label`) and
+    /// components after their names in `circuit`, rather than by id as `Display` gives them.
+    pub fn describe(&self, circuit: &CircuitState<S>, names: Option<&NameMap>) -> String {
+        let mut net_names = HashMap::new();
+        if let Some(names) = names {
+            net_names.extend(names.nets.iter().map(|(name, &net)| (net, name.as_str())));
+        }
+        let mut out = String::new();
+        self.write(
+            &mut out,
+            |net| match net_names.get(&net) {
+                Some(name) => format!("net {name}"),
+                None => format!("net {}", circuit.net_label(net)),
+            },
+            |component| circuit.component_label(component),
+        )
+        .unwrap();
+        out
+    }
+
+    fn write(
+        &self,
+        out: &mut impl fmt::Write,
+        net: impl Fn(NetId) -> String,
+        component: impl Fn(ComponentId) -> String,
+    ) -> fmt::Result {
+        let list = |ids: &[usize], name: &dyn Fn(usize) -> String| {
+            ids.iter()
+                .map(|&id| name(id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Self::NonPositiveValue {
+                component: c,
+                quantity,
+                value,
+            } => write!(out, "{} has {quantity} {:e}", component(*c), value.to_f64()),
+            Self::NoDcPath { nets, capacitors } if capacitors.is_empty() => {
+                write!(out, "{} not connected to ground", list(nets, &net))
+            }
+            Self::NoDcPath { nets, capacitors } => write!(
+                out,
+                "{} connected to ground only through {}",
+                list(nets, &net),
+                list(capacitors, &component)
+            ),
+            Self::InductorLoop { components } => write!(
+                out,
+                "loop without resistance through {}",
+                list(components, &component)
+            ),
+            Self::SourceLoop { components } => {
+                write!(out, "loop of sources {}", list(components, &component))
+            }
+            Self::Duplicate {
+                components: [a, b],
+                nets,
+            } => write!(
+                out,
+                "{} and {} are both between {}",
+                component(*a),
+                component(*b),
+                list(nets, &net)
+            ),
+            Self::UndrivenGate {
+                component: c,
+                net: n,
+            } => write!(
+                out,
+                "nothing drives the gate of {} ({})",
+                component(*c),
+                net(*n)
+            ),
+            Self::DanglingNet {
+                net: n,
+                component: Some(c),
+            } => write!(out, "{} is connected only to {}", net(*n), component(*c)),
+            Self::DanglingNet {
+                net: n,
+                component: None,
+            } => write!(out, "{} is not connected to anything", net(*n)),
+        }
+    }
+}
+impl<S: Scalar> fmt::Display for ValidationWarning<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(
+            f,
+            |net| format!("net {net}"),
+            |component| format!("component {component}"),
+        )
+    }
+}
+
+/// What a component is for `ValidationWarning::Duplicate`.
+fn kind_name<S: Scalar>(component: ComponentRef<'_, S>) -> &'static str {
+    match component {
+        ComponentRef::Linear(v) => match v.value {
+            LinearComponentValue::Capacitive(_) | LinearComponentValue::LossyCapacitive { .. } => {
+                "capacitor"
+            }
+            LinearComponentValue::Resistive(_) => "resistor",
+            LinearComponentValue::Inductive(_)
+            | LinearComponentValue::SaturatingInductive { .. } => "inductor",
+            LinearComponentValue::Source(_) | LinearComponentValue::Pwm(_) => "source",
+            LinearComponentValue::Switch { .. } => "switch",
+            LinearComponentValue::Jumper => "jumper",
+        },
+        ComponentRef::MOSFET(_) => "MOSFET",
+        ComponentRef::NoiseSource(_) => "noise source",
+        ComponentRef::GateDriver(_) => "gate driver",
+        ComponentRef::Comparator(_) => "comparator",
+        ComponentRef::Converter(_) => "DC-DC converter",
+    }
+}
+
+/// Branches of a component between two of its nets, by how they conduct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conduction {
+    /// A capacitance with nothing across it.
+    Blocking,
+    Resistive,
+    /// An ideal source, closed switch or jumper.
+    Stiff,
+    /// An inductance without series resistance.
+    Inductive,
+}
+fn conduction<S: Scalar>(component: ComponentRef<'_, S>) -> Conduction {
+    let zero = S::from(0);
+    match component {
+        ComponentRef::Linear(v) => match v.value {
+            LinearComponentValue::Capacitive(_)
+            | LinearComponentValue::LossyCapacitive {
+                leakage_resistance: None,
+                ..
+            } => Conduction::Blocking,
+            LinearComponentValue::Resistive(_)
+            | LinearComponentValue::LossyCapacitive { .. }
+            | LinearComponentValue::Switch { closed: false, .. } => Conduction::Resistive,
+            LinearComponentValue::SaturatingInductive {
+                series_resistance, ..
+            } if series_resistance > zero => Conduction::Resistive,
+            LinearComponentValue::Inductive(_)
+            | LinearComponentValue::SaturatingInductive { .. } => Conduction::Inductive,
+            LinearComponentValue::Source(_)
+            | LinearComponentValue::Pwm(_)
+            | LinearComponentValue::Switch { closed: true, .. }
+            | LinearComponentValue::Jumper => Conduction::Stiff,
+        },
+        // drain to source; the gate is handled separately.
+        ComponentRef::MOSFET(_) => Conduction::Resistive,
+        ComponentRef::NoiseSource(_) => Conduction::Stiff,
+        // `vss` to `out` through the drive resistance; the input and supply are only sensed.
+        ComponentRef::GateDriver(_) => Conduction::Resistive,
+        // `out-` to `out+` through the output resistance; the inputs are only sensed.
+        ComponentRef::Comparator(_) => Conduction::Resistive,
+        // `out-` to `out+` held at the output voltage; the input only draws a current.
+        ComponentRef::Converter(_) => Conduction::Stiff,
+    }
+}
+
+impl<S: Scalar> CircuitState<S> {
+    /// Check the circuit for values and topologies that are likely mistakes or that the solver
+    /// can't handle, in order of the checks: non-positive values, loops of sources and inductors,
+    /// nets without a DC path to ground (net 0), floating MOSFET gates, duplicated components
+    /// and dangling nets. An empty result doesn't mean the circuit will converge.
+    pub fn validate(&self) -> Vec<ValidationWarning<S>> {
+        let mut warnings = Vec::new();
+        self.validate_values(&mut warnings);
+        self.validate_loops(&mut warnings);
+        self.validate_dc_paths(&mut warnings);
+        self.validate_duplicates(&mut warnings);
+        for (net_i, net) in self.nets.iter().enumerate() {
+            if net.components.len() < 2 {
+                warnings.push(ValidationWarning::DanglingNet {
+                    net: net_i,
+                    component: net.components.first().map(|&(component, _)| component),
+                });
+            }
+        }
+        warnings
+    }
+
+    fn validate_values(&self, warnings: &mut Vec<ValidationWarning<S>>) {
+        let zero = S::from(0);
+        for (component, c) in self.components() {
+            // (quantity, value, whether zero is allowed)
+            let mut checked = Vec::new();
+            match c {
+                ComponentRef::Linear(v) => match v.value {
+                    LinearComponentValue::Capacitive(c) => checked.push(("capacitance", c, false)),
+                    LinearComponentValue::LossyCapacitive {
+                        capacitance,
+                        esr,
+                        esl,
+                        leakage_resistance,
+                    } => {
+                        checked.push(("capacitance", capacitance, false));
+                        checked.push(("ESR", esr, true));
+                        checked.push(("ESL", esl, true));
+                        if let Some(r) = leakage_resistance {
+                            checked.push(("leakage resistance", r, false));
+                        }
+                    }
+                    LinearComponentValue::Resistive(r) => checked.push(("resistance", r, false)),
+                    LinearComponentValue::Inductive(l) => checked.push(("inductance", l, false)),
+                    LinearComponentValue::SaturatingInductive {
+                        inductance,
+                        saturation_current,
+                        series_resistance,
+                    } => {
+                        checked.push(("inductance", inductance, false));
+                        checked.push(("saturation current", saturation_current, false));
+                        checked.push(("series resistance", series_resistance, true));
+                    }
+                    LinearComponentValue::Switch {
+                        off_resistance: Some(r),
+                        ..
+                    } => checked.push(("off resistance", r, false)),
+                    LinearComponentValue::Pwm(wave) => checked.push(("period", wave.period, false)),
+                    LinearComponentValue::Source(_)
+                    | LinearComponentValue::Switch { .. }
+                    | LinearComponentValue::Jumper => {}
+                },
+                ComponentRef::MOSFET(v) => {
+                    checked.push(("beta", v.value.beta, false));
+                    checked.push(("multiplicity", v.value.multiplicity, false));
+                }
+                ComponentRef::NoiseSource(v) => checked.push(("sigma", v.value.sigma, true)),
+                ComponentRef::GateDriver(v) => {
+                    checked.push(("source resistance", v.value.source_resistance, false));
+                    checked.push(("sink resistance", v.value.sink_resistance, false));
+                    checked.push(("propagation delay", v.value.propagation_delay, true));
+                }
+                ComponentRef::Comparator(v) => {
+                    checked.push(("output resistance", v.value.output_resistance, false));
+                    checked.push(("hysteresis", v.value.hysteresis, true));
+                    checked.push(("propagation delay", v.value.propagation_delay, true));
+                }
+                ComponentRef::Converter(v) => {
+                    checked.push(("efficiency", v.value.efficiency, false));
+                    checked.push(("current limit", v.value.current_limit, false));
+                    checked.push(("output capacitance", v.value.output_capacitance, false));
+                    checked.push(("soft start", v.value.soft_start, true));
+                }
+            }
+            for (quantity, value, zero_allowed) in checked {
+                if value < zero || (value == zero && !zero_allowed) || !value.is_finite() {
+                    warnings.push(ValidationWarning::NonPositiveValue {
+                        component,
+                        quantity,
+                        value,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Loops of branches without resistance, found as each closes a path of the ones before it.
+    fn validate_loops(&self, warnings: &mut Vec<ValidationWarning<S>>) {
+        // per net, the (component, other net) of the stiff and inductive branches so far
+        let mut branches = vec![Vec::<(ComponentId, NetId)>::new(); self.nets.len()];
+        for (component, c) in self.components() {
+            if !matches!(conduction(c), Conduction::Stiff | Conduction::Inductive) {
+                continue;
+            }
+            let nets = c.as_dyn().nets();
+            let (a, b) = (nets[0], nets[nets.len() - 1]);
+            let Some(mut path) = Self::find_path(&branches, a, b) else {
+                branches[a].push((component, b));
+                branches[b].push((component, a));
+                continue;
+            };
+            path.push(component);
+            let is_source = |&component: &ComponentId| match self.component(component) {
+                ComponentRef::Linear(v) => matches!(
+                    v.value,
+                    LinearComponentValue::Source(_) | LinearComponentValue::Pwm(_)
+                ),
+                ComponentRef::NoiseSource(_) | ComponentRef::Converter(_) => true,
+                ComponentRef::MOSFET(_) | ComponentRef::GateDriver(_) => false,
+                // a source behind its output resistance.
+                ComponentRef::Comparator(_) => false,
+            };
+            let is_inductor = |&component: &ComponentId| {
+                conduction(self.component(component)) == Conduction::Inductive
+            };
+            if path.iter().any(is_inductor) {
+                warnings.push(ValidationWarning::InductorLoop { components: path });
+            } else if path.iter().any(is_source) {
+                warnings.push(ValidationWarning::SourceLoop { components: path });
+            }
+        }
+    }
+    /// The components along a path from `from` to `to` through `branches`, by breadth-first
+    /// search; empty if they are the same net.
+    fn find_path(
+        branches: &[Vec<(ComponentId, NetId)>],
+        from: NetId,
+        to: NetId,
+    ) -> Option<Vec<ComponentId>> {
+        let mut reached_by = vec![None; branches.len()];
+        let mut queue = std::collections::VecDeque::from([from]);
+        let mut seen = vec![false; branches.len()];
+        seen[from] = true;
+        while let Some(net) = queue.pop_front() {
+            if net == to {
+                let mut path = Vec::new();
+                let mut net = to;
+                while let Some((component, previous)) = reached_by[net] {
+                    path.push(component);
+                    net = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &(component, next) in &branches[net] {
+                if !seen[next] {
+                    seen[next] = true;
+                    reached_by[next] = Some((component, net));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Groups of nets joined by DC paths that don't include net 0, and MOSFET gates in them.
+    fn validate_dc_paths(&self, warnings: &mut Vec<ValidationWarning<S>>) {
+        if self.nets.is_empty() {
+            return;
+        }
+        let mut group = (0..self.nets.len()).collect::<Vec<_>>();
+        fn find(group: &mut [usize], i: usize) -> usize {
+            if group[i] != i {
+                group[i] = find(group, group[i]);
+            }
+            group[i]
+        }
+        for (_, c) in self.components() {
+            if conduction(c) == Conduction::Blocking {
+                continue;
+            }
+            let nets = c.as_dyn().nets();
+            let (a, b) = (
+                find(&mut group, nets[0]),
+                find(&mut group, nets[nets.len() - 1]),
+            );
+            group[a.max(b)] = a.min(b);
+        }
+        let ground = find(&mut group, 0);
+        // (group, its nets, capacitors on them, whether all their terminals are MOSFET gates)
+        let mut floating = Vec::<(usize, Vec<NetId>, Vec<ComponentId>, bool)>::new();
+        for net_i in 0..self.nets.len() {
+            let root = find(&mut group, net_i);
+            if root == ground {
+                continue;
+            }
+            let group_i = floating
+                .iter()
+                .position(|&(r, ..)| r == root)
+                .unwrap_or_else(|| {
+                    floating.push((root, Vec::new(), Vec::new(), true));
+                    floating.len() - 1
+                });
+            let (_, nets, capacitors, only_gates) = &mut floating[group_i];
+            nets.push(net_i);
+            for &(component, terminal) in &self.nets[net_i].components {
+                let c = self.component(component);
+                *only_gates &= matches!(c, ComponentRef::MOSFET(_)) && terminal == 1;
+                if conduction(c) == Conduction::Blocking && !capacitors.contains(&component) {
+                    capacitors.push(component);
+                }
+            }
+        }
+        // nets that only gate MOSFETs are reported as undriven gates alone.
+        warnings.extend(
+            floating
+                .into_iter()
+                .filter(|&(.., only_gates)| !only_gates)
+                .map(|(_, nets, capacitors, _)| ValidationWarning::NoDcPath { nets, capacitors }),
+        );
+        for (component, mosfet) in self.mosfets() {
+            let gate = mosfet.nets()[1];
+            if find(&mut group, gate) != ground {
+                warnings.push(ValidationWarning::UndrivenGate {
+                    component,
+                    net: gate,
+                });
+            }
+        }
+    }
+
+    fn validate_duplicates(&self, warnings: &mut Vec<ValidationWarning<S>>) {
+        let mut seen = HashMap::<(&'static str, Vec<NetId>), ComponentId>::new();
+        for (component, c) in self.components() {
+            let mut nets = c.as_dyn().nets().to_vec();
+            // the terminals of a MOSFET aren't interchangeable.
+            if !matches!(c, ComponentRef::MOSFET(_)) {
+                nets.sort_unstable();
+            }
+            match seen.entry((kind_name(c), nets)) {
+                std::collections::hash_map::Entry::Occupied(first) => {
+                    warnings.push(ValidationWarning::Duplicate {
+                        components: [*first.get(), component],
+                        nets: first.key().1.clone(),
+                    })
+                }
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(component);
+                }
+            }
+        }
+    }
+}
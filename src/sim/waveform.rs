@@ -0,0 +1,64 @@
+//! Comparison of sampled waveforms against a reference, which may be sampled at other times.
+
+use super::f;
+
+/// Linear interpolation of the samples `values` at `times` (ascending) at `t`, holding the end
+/// values outside.
+pub fn interpolate(times: &[f], values: &[f], t: f) -> f {
+    let i = times.partition_point(|&time| time < t);
+    if i == 0 {
+        return values[0];
+    }
+    if i == times.len() {
+        return values[times.len() - 1];
+    }
+    let (t0, t1) = (times[i - 1], times[i]);
+    if t1 == t0 {
+        return values[i];
+    }
+    values[i - 1] + (values[i] - values[i - 1]) * (t - t0) / (t1 - t0)
+}
+
+/// Allowed deviation from a reference sample `r`: `absolute + relative |r|`.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    pub absolute: f,
+    pub relative: f,
+}
+
+/// Worst deviation of a waveform from a reference, see `compare`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    /// Largest `|value - reference|`.
+    pub max_deviation: f,
+    /// Time of `max_deviation`.
+    pub at: f,
+    /// Whether every sample was within the tolerance band.
+    pub within: bool,
+}
+
+/// Compare `values` at `times` against `reference` at `reference_times`, interpolated onto
+/// `times` (so the two runs may use unrelated, even adaptive, time steps), within `tolerance`.
+pub fn compare(
+    times: &[f],
+    values: &[f],
+    reference_times: &[f],
+    reference: &[f],
+    tolerance: Tolerance,
+) -> Comparison {
+    let mut comparison = Comparison {
+        max_deviation: 0.0,
+        at: 0.0,
+        within: true,
+    };
+    for (&t, &value) in times.iter().zip(values) {
+        let r = interpolate(reference_times, reference, t);
+        let deviation = (value - r).abs();
+        if deviation > comparison.max_deviation {
+            comparison.max_deviation = deviation;
+            comparison.at = t;
+        }
+        comparison.within &= deviation <= tolerance.absolute + tolerance.relative * r.abs();
+    }
+    comparison
+}
@@ -0,0 +1,290 @@
+//! JavaScript bindings to the simulator, see `examples/wasm`.
+//!
+//! Only construction, stepping, probes and input mutation are exposed: nothing here touches
+//! `std::time` (`sim::run` measures wall time, which panics on `wasm32-unknown-unknown`) or the
+//! file system, so the browser drives the stepping loop itself.
+
+use js_sys::Float64Array;
+use wasm_bindgen::prelude::*;
+
+use crate::sim::{
+    components::{LinearComponentValue, MOSFETComponentValue, MOSFETDopingType},
+    error::SimError,
+    probe::{Probe, Quantity},
+    CircuitState, ComponentId, ComponentMut, ComponentValueEnum, NetId,
+};
+
+/// A circuit with its probes. Nets and components are numbered from 0 in creation order;
+/// voltages are absolute, so take differences to a reference net.
+#[wasm_bindgen]
+pub struct Circuit {
+    circuit: CircuitState,
+    probes: Vec<Probe>,
+    /// Filled by `voltagesPtr`/`netVoltages`, kept so its address stays put between calls.
+    voltages: Vec<f64>,
+    /// Filled by `probeValuesPtr`/`probeValues`.
+    probe_values: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Circuit {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            circuit: CircuitState::new_empty(),
+            probes: Vec::new(),
+            voltages: Vec::new(),
+            probe_values: Vec::new(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addNet)]
+    pub fn add_net(&mut self) -> NetId {
+        self.circuit.create_net()
+    }
+    #[wasm_bindgen(js_name = addResistor)]
+    pub fn add_resistor(&mut self, a: NetId, b: NetId, ohms: f64) -> Result<ComponentId, JsError> {
+        self.add_linear(a, b, LinearComponentValue::Resistive(ohms))
+    }
+    #[wasm_bindgen(js_name = addCapacitor)]
+    pub fn add_capacitor(
+        &mut self,
+        a: NetId,
+        b: NetId,
+        farads: f64,
+    ) -> Result<ComponentId, JsError> {
+        self.add_linear(a, b, LinearComponentValue::Capacitive(farads))
+    }
+    #[wasm_bindgen(js_name = addInductor)]
+    pub fn add_inductor(
+        &mut self,
+        a: NetId,
+        b: NetId,
+        henries: f64,
+    ) -> Result<ComponentId, JsError> {
+        self.add_linear(a, b, LinearComponentValue::Inductive(henries))
+    }
+    /// A source holding `V(b) - V(a) = volts`.
+    #[wasm_bindgen(js_name = addSource)]
+    pub fn add_source(&mut self, a: NetId, b: NetId, volts: f64) -> Result<ComponentId, JsError> {
+        self.add_linear(a, b, LinearComponentValue::Source(volts))
+    }
+    /// A switch with the default off-resistance, see `LinearComponentValue::switch`.
+    #[wasm_bindgen(js_name = addSwitch)]
+    pub fn add_switch(&mut self, a: NetId, b: NetId, closed: bool) -> Result<ComponentId, JsError> {
+        self.add_linear(a, b, LinearComponentValue::switch(closed))
+    }
+    #[wasm_bindgen(js_name = addMosfet)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_mosfet(
+        &mut self,
+        source: NetId,
+        gate: NetId,
+        drain: NetId,
+        p_channel: bool,
+        beta: f64,
+        threshold_voltage: f64,
+        body_diode_saturation_current: f64,
+        body_diode_ideality_factor: f64,
+    ) -> Result<ComponentId, JsError> {
+        let value = ComponentValueEnum::MOSFET(MOSFETComponentValue {
+            ty: if p_channel {
+                MOSFETDopingType::PChannel
+            } else {
+                MOSFETDopingType::NChannel
+            },
+            beta,
+            threshold_voltage,
+            body_diode_saturation_current,
+            body_diode_ideality_facotor: body_diode_ideality_factor,
+            saturation_knee: 8.0,
+            multiplicity: 1.0,
+            avalanche: None,
+        });
+        self.add_component(value, &[source, gate, drain])
+    }
+
+    #[wasm_bindgen(js_name = setCapacitorVoltage)]
+    pub fn set_capacitor_voltage(
+        &mut self,
+        component: ComponentId,
+        volts: f64,
+    ) -> Result<(), JsError> {
+        self.check_component(component)?;
+        Ok(self
+            .circuit
+            .set_initial_capacitor_voltage(component, volts)?)
+    }
+    #[wasm_bindgen(js_name = setSource)]
+    pub fn set_source(&mut self, component: ComponentId, volts: f64) -> Result<(), JsError> {
+        self.check_component(component)?;
+        match self.circuit.component_mut(component) {
+            ComponentMut::Linear(v) => match &mut v.value {
+                LinearComponentValue::Source(e) => *e = volts,
+                _ => return Err(wrong_kind(component, "a source")),
+            },
+            _ => return Err(wrong_kind(component, "a source")),
+        }
+        Ok(())
+    }
+    #[wasm_bindgen(js_name = setSwitch)]
+    pub fn set_switch(&mut self, component: ComponentId, closed: bool) -> Result<(), JsError> {
+        self.check_component(component)?;
+        match self.circuit.component_mut(component) {
+            ComponentMut::Linear(v) => match &mut v.value {
+                LinearComponentValue::Switch { closed: c, .. } => *c = closed,
+                _ => return Err(wrong_kind(component, "a switch")),
+            },
+            _ => return Err(wrong_kind(component, "a switch")),
+        }
+        Ok(())
+    }
+
+    /// Solve the present state without advancing time, as before the first `tick` of a circuit
+    /// with initial conditions. Returns whether the solve converged.
+    #[wasm_bindgen(js_name = solveState)]
+    pub fn solve_state(&mut self) -> Result<bool, JsError> {
+        Ok(self.circuit.try_solve_state()?)
+    }
+    /// Advance by `dt` seconds. Returns whether the solve converged.
+    pub fn tick(&mut self, dt: f64) -> Result<bool, JsError> {
+        Ok(self.circuit.try_tick(dt)?)
+    }
+
+    #[wasm_bindgen(js_name = nNets)]
+    pub fn n_nets(&self) -> usize {
+        self.circuit.n_nets()
+    }
+    #[wasm_bindgen(js_name = netVoltage)]
+    pub fn net_voltage(&self, net: NetId) -> Result<f64, JsError> {
+        self.check_net(net)?;
+        Ok(self.circuit.net_voltage(net))
+    }
+    /// Copy of every net voltage, indexed by net.
+    #[wasm_bindgen(js_name = netVoltages)]
+    pub fn net_voltages(&mut self) -> Float64Array {
+        self.fill_voltages();
+        Float64Array::from(&self.voltages[..])
+    }
+    /// Address in wasm memory of every net voltage, indexed by net, for a copy-free
+    /// `new Float64Array(memory.buffer, ptr, circuit.nNets())`. The view only reflects the
+    /// voltages as of this call, and is detached if adding nets, components or probes grows the
+    /// memory, so take a new one after each tick.
+    #[wasm_bindgen(js_name = voltagesPtr)]
+    pub fn voltages_ptr(&mut self) -> *const f64 {
+        self.fill_voltages();
+        self.voltages.as_ptr()
+    }
+
+    /// Probe the voltage of `net` (absolute, like `netVoltage`).
+    #[wasm_bindgen(js_name = addNetProbe)]
+    pub fn add_net_probe(&mut self, net: NetId) -> Result<usize, JsError> {
+        self.check_net(net)?;
+        Ok(self.add_probe(Probe::Net(net)))
+    }
+    /// Probe the `"current"`, `"voltage"` or `"power"` of `component`.
+    #[wasm_bindgen(js_name = addComponentProbe)]
+    pub fn add_component_probe(
+        &mut self,
+        component: ComponentId,
+        quantity: &str,
+    ) -> Result<usize, JsError> {
+        self.check_component(component)?;
+        let quantity = match quantity {
+            "current" => Quantity::Current,
+            "voltage" => Quantity::Voltage,
+            "power" => Quantity::Power,
+            _ => return Err(JsError::new(&format!("unknown quantity {quantity:?}"))),
+        };
+        Ok(self.add_probe(Probe::Component(component, quantity)))
+    }
+    #[wasm_bindgen(js_name = nProbes)]
+    pub fn n_probes(&self) -> usize {
+        self.probes.len()
+    }
+    #[wasm_bindgen(js_name = readProbe)]
+    pub fn read_probe(&self, probe: usize) -> Result<f64, JsError> {
+        let probe = self
+            .probes
+            .get(probe)
+            .ok_or_else(|| JsError::new(&format!("no probe with id {probe}")))?;
+        Ok(probe.sample(&self.circuit))
+    }
+    /// Copy of every probe's value, indexed by probe.
+    #[wasm_bindgen(js_name = probeValues)]
+    pub fn probe_values(&mut self) -> Float64Array {
+        self.fill_probe_values();
+        Float64Array::from(&self.probe_values[..])
+    }
+    /// Like `voltagesPtr`, for `new Float64Array(memory.buffer, ptr, circuit.nProbes())`.
+    #[wasm_bindgen(js_name = probeValuesPtr)]
+    pub fn probe_values_ptr(&mut self) -> *const f64 {
+        self.fill_probe_values();
+        self.probe_values.as_ptr()
+    }
+}
+
+impl Default for Circuit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Circuit {
+    fn add_linear(
+        &mut self,
+        a: NetId,
+        b: NetId,
+        value: LinearComponentValue,
+    ) -> Result<ComponentId, JsError> {
+        self.add_component(ComponentValueEnum::Linear(value), &[a, b])
+    }
+    fn add_component(
+        &mut self,
+        value: ComponentValueEnum,
+        nets: &[NetId],
+    ) -> Result<ComponentId, JsError> {
+        for &net in nets {
+            self.check_net(net)?;
+        }
+        Ok(self.circuit.create_component(value, nets))
+    }
+    fn add_probe(&mut self, probe: Probe) -> usize {
+        self.probes.push(probe);
+        self.probes.len() - 1
+    }
+
+    fn check_net(&self, net: NetId) -> Result<(), JsError> {
+        if net < self.circuit.n_nets() {
+            Ok(())
+        } else {
+            Err(JsError::new(&format!("no net with id {net}")))
+        }
+    }
+    fn check_component(&self, component: ComponentId) -> Result<(), JsError> {
+        if component < self.circuit.n_components() {
+            Ok(())
+        } else {
+            Err(SimError::UnknownComponent(component).into())
+        }
+    }
+
+    fn fill_voltages(&mut self) {
+        self.voltages.clear();
+        self.voltages
+            .extend(self.circuit.nets().map(|(_, net)| net.voltage()));
+    }
+    fn fill_probe_values(&mut self) {
+        self.probe_values.clear();
+        self.probe_values
+            .extend(self.probes.iter().map(|probe| probe.sample(&self.circuit)));
+    }
+}
+
+fn wrong_kind(component: ComponentId, expected: &'static str) -> JsError {
+    SimError::WrongComponentKind {
+        component,
+        expected,
+    }
+    .into()
+}
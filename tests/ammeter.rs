@@ -0,0 +1,66 @@
+//! Ammeters read the current of the branch they are put in without moving the solution.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    probe::Probe,
+    CircuitState, ComponentRef,
+};
+
+const DT: f64 = 1e-6;
+
+/// 10 V into 1 kohm to `out`, 2 kohm and 100 nF from `out` to ground, with an ammeter `AM1` between
+/// `R1` and `out` if `ammeter`.
+fn rc_divider(ammeter: bool) -> (CircuitState, NameMap) {
+    let mut builder = CircuitBuilder::new();
+    builder
+        .source("V1", "gnd", "in", 10.0)
+        .and_then(|b| b.resistor("R2", "out", "gnd", 2e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 100e-9))
+        .unwrap();
+    if ammeter {
+        builder
+            .resistor("R1", "in", "sense", 1e3)
+            .and_then(|b| b.ammeter("AM1", "sense", "out"))
+            .unwrap();
+    } else {
+        builder.resistor("R1", "in", "out", 1e3).unwrap();
+    }
+    let (mut circuit, names) = builder.build();
+    circuit.solve_state();
+    (circuit, names)
+}
+
+/// The current of `R1` from `in` towards `out`, its `q[1]`.
+fn r1_current(circuit: &CircuitState, names: &NameMap) -> f64 {
+    match circuit.component(names.component("R1").unwrap()) {
+        ComponentRef::Linear(r1) => r1.q[1],
+        _ => unreachable!(),
+    }
+}
+
+/// Over 10 RC of the capacitor charging, the ammeter reads the current of `R1` and the circuit
+/// with it has the net voltages of the one without.
+#[test]
+fn series_ammeter() {
+    let (mut plain, plain_names) = rc_divider(false);
+    let (mut metered, metered_names) = rc_divider(true);
+    let current = Probe::parse("comp:AM1.current", &metered_names).unwrap();
+    let voltages =
+        |names: &NameMap| ["net:in", "net:out"].map(|spec| Probe::parse(spec, names).unwrap());
+    let (plain_voltages, metered_voltages) = (voltages(&plain_names), voltages(&metered_names));
+    let mut deviation = (0.0, 0.0_f64);
+    for _ in 0..700 {
+        assert!(plain.tick(DT) && metered.tick(DT));
+        for (plain_v, metered_v) in plain_voltages.iter().zip(&metered_voltages) {
+            let dv = (plain_v.sample(&plain) - metered_v.sample(&metered)).abs();
+            deviation.0 = f64::max(deviation.0, dv);
+        }
+        let di = (current.sample(&metered) - r1_current(&metered, &metered_names)).abs();
+        deviation.1 = deviation.1.max(di);
+    }
+    let (dv, di) = deviation;
+    assert!(dv < 1e-9, "net voltages moved by {dv:e} V");
+    assert!(di < 1e-12, "ammeter off the current of R1 by {di:e} A");
+    // the capacitor charged, the current is that of the divider.
+    assert!((current.sample(&metered) - 10.0 / 3e3).abs() < 1e-6);
+}
@@ -0,0 +1,83 @@
+//! MOSFETs clamping an unclamped inductive turn-off in avalanche, and the energy that costs them.
+
+use esc_sim_test::sim::{
+    components::{Avalanche, ComponentParameter},
+    mosfet_rl_test_circuit,
+    probe::Probe,
+    ComponentMut,
+};
+
+const DT: f64 = 1e-7;
+const L: f64 = 1e-3;
+const R: f64 = 10.0;
+const V_SUPPLY: f64 = 12.0;
+const AVALANCHE: Avalanche = Avalanche {
+    breakdown_voltage: 100.0,
+    conductance: 10.0,
+    rated_energy: Some(100e-6),
+};
+
+/// `mosfet_rl_test_circuit` with `M1` breaking down at 100 V, on with 20 V at its gate for
+/// 0.5 ms in 1 us ticks, then off in 0.1 us ticks with nothing across the inductor: `v_ds`
+/// clamps at the breakdown voltage (plus the current over the clamp's conductance), and the
+/// avalanche energy is `V_BR` times the charge of the current decaying from `I_0` towards
+/// `-I_a = -(V_BR - V_supply) / R` with `tau = L / R` until it reaches zero at `t_0`:
+/// `V_BR ((I_0 + I_a) tau (1 - e^(-t_0 / tau)) - I_a t_0)`. With the supply feeding the
+/// avalanche too, that is a little over `1/2 L I_0^2`, and over the 100 uJ rating.
+#[test]
+fn unclamped_turn_off() {
+    let (mut circuit, names) = mosfet_rl_test_circuit();
+    let [m1, vg] = ["M1", "Vg"].map(|name| names.component(name).unwrap());
+    let ComponentMut::MOSFET(m) = circuit.component_mut(m1) else {
+        unreachable!()
+    };
+    m.value.avalanche = Some(AVALANCHE);
+    let [i_l, v_ds] =
+        ["comp:L1.current", "net:drain"].map(|spec| Probe::parse(spec, &names).unwrap());
+    *circuit
+        .component_mut(vg)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = 20.0;
+    assert!(circuit.solve_state());
+    for _ in 0..500 {
+        assert!(
+            circuit.tick(10.0 * DT),
+            "no convergence at {:e} s",
+            circuit.now()
+        );
+    }
+    let i_0 = i_l.sample(&circuit).abs();
+    assert_eq!(circuit.avalanche_energy(m1), Some(0.0));
+
+    *circuit
+        .component_mut(vg)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = 0.0;
+    let mut v_max: f64 = 0.0;
+    for _ in 0..300 {
+        assert!(circuit.tick(DT), "no convergence at {:e} s", circuit.now());
+        v_max = v_max.max(v_ds.sample(&circuit));
+    }
+    let clamp = AVALANCHE.breakdown_voltage + i_0 / AVALANCHE.conductance;
+    assert!(
+        v_max > AVALANCHE.breakdown_voltage && v_max <= clamp + 1e-3,
+        "v_ds peaked at {v_max} V"
+    );
+
+    let tau = L / R;
+    let i_a = (AVALANCHE.breakdown_voltage - V_SUPPLY) / R;
+    let t_0 = tau * (1.0 + i_0 / i_a).ln();
+    let expected =
+        AVALANCHE.breakdown_voltage * ((i_0 + i_a) * tau * (1.0 - (-t_0 / tau).exp()) - i_a * t_0);
+    let energy = circuit.avalanche_energy(m1).unwrap();
+    assert!(
+        (energy - expected).abs() < 2e-2 * expected,
+        "{energy:e} J in avalanche, expected {expected:e} J"
+    );
+    let stored = 0.5 * L * i_0 * i_0;
+    assert!(
+        (stored..1.1 * stored).contains(&energy),
+        "{energy:e} J in avalanche, 1/2 L I^2 = {stored:e} J"
+    );
+    assert_eq!(circuit.avalanche_overloads(), [(m1, energy)]);
+}
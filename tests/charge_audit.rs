@@ -0,0 +1,64 @@
+//! `SolverConfig::charge_audit` on an RC load with an inductor carrying 1 mA, wired right and
+//! with its far end on a net nothing else connects to.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    diagnostics::ChargeAuditReport,
+    solver::{ChargeAudit, SolverConfig},
+};
+
+/// 5 V behind 1 kohm onto 1 uF, with 1 mH from the capacitor to `inductor_to`, audited every 2
+/// ticks of 1 us over 20 ticks. Returns the 10 audits.
+fn audits(inductor_to: &str) -> Vec<ChargeAuditReport> {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 5.0)
+        .and_then(|b| b.resistor("R1", "in", "a", 1e3))
+        .and_then(|b| b.capacitor("C1", "a", "gnd", 1e-6))
+        .and_then(|b| b.inductor("L1", "a", inductor_to, 1e-3))
+        .unwrap()
+        .build();
+    circuit.set_solver_config(SolverConfig {
+        charge_audit: Some(ChargeAudit {
+            every: 2,
+            tolerance: 1e-12,
+        }),
+        ..*circuit.solver_config()
+    });
+    let l1 = names.component("L1").unwrap();
+    circuit.set_initial_inductor_current(l1, 1e-3).unwrap();
+    assert!(circuit.solve_state());
+    let mut audits = Vec::new();
+    for k in 0..20 {
+        assert!(circuit.tick(1e-6), "tick {k}");
+        let audit = circuit.last_charge_audit();
+        assert_eq!(audit.is_some(), k > 0, "tick {k}");
+        if k % 2 == 1 {
+            audits.extend(audit.cloned());
+        }
+    }
+    audits
+}
+
+/// Returned to ground, the inductor's current leaves every net as it came in.
+#[test]
+fn wired() {
+    for audit in audits("gnd") {
+        assert_eq!(audit.ticks, 2);
+        assert!(audit.imbalanced.is_empty(), "{audit:?}");
+        assert!(audit.total_drift.abs() < 1e-15, "{audit:?}");
+    }
+}
+
+/// Left on a net of its own, the inductor pushes charge into it that no other component takes
+/// out: every audit flags that net first, with the inductor as its only component, while the
+/// charge in the whole circuit still sums to zero.
+#[test]
+fn dangling_inductor() {
+    for audit in audits("nc") {
+        let worst = &audit.imbalanced[0];
+        assert_eq!(worst.label, "nc", "{audit:?}");
+        assert_eq!(worst.components, ["L1"]);
+        assert!(worst.charge.abs() > 1e-11, "{audit:?}");
+        assert!(audit.drift.abs() < 1e-15, "{audit:?}");
+    }
+}
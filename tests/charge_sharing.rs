@@ -0,0 +1,57 @@
+//! Capacitors joined by a closing switch share their charge at the start of the tick, with the
+//! energy that loses put down to the switch.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{LinearComponentState, LinearComponentValue},
+    power::LossAccumulator,
+    CircuitState, ComponentMut, ComponentValueEnum,
+};
+
+/// Two 10 uF capacitors at 10 V and 0 V, joined by an ideal switch (no off resistance): after
+/// the tick it closes at, both hold exactly 5 V, and the switch is charged with the
+/// `C V^2 / 4` = 0.25 mJ the pair lost, out of 0.5 mJ.
+#[test]
+fn equal_capacitors() {
+    const C: f64 = 10e-6;
+    let open = LinearComponentValue::Switch {
+        closed: false,
+        off_resistance: None,
+    };
+    let (mut circuit, names) = CircuitBuilder::new()
+        .capacitor("C1", "a", "gnd", C)
+        .and_then(|b| b.capacitor("C2", "b", "gnd", C))
+        .and_then(|b| b.component("S1", ComponentValueEnum::Linear(open), &["a", "b"]))
+        .unwrap()
+        .build();
+    let [c1, c2, s1] = ["C1", "C2", "S1"].map(|name| names.component(name).unwrap());
+    circuit.set_initial_capacitor_voltage(c1, 10.0).unwrap();
+    assert!(circuit.solve_state());
+    let stored = |circuit: &CircuitState| [c1, c2].map(|c| circuit.stored_energy(c));
+    assert_eq!(stored(&circuit), [0.5e-3, 0.0]);
+
+    let mut losses = LossAccumulator::new();
+    assert!(circuit.tick(1e-6));
+    losses.accumulate(&circuit, 1e-6);
+    assert!(circuit.last_charge_sharing().is_none());
+    match circuit.component_mut(s1) {
+        ComponentMut::Linear(LinearComponentState {
+            value: LinearComponentValue::Switch { closed, .. },
+            ..
+        }) => *closed = true,
+        _ => unreachable!(),
+    }
+    assert!(circuit.tick(1e-6));
+    losses.accumulate(&circuit, 1e-6);
+
+    for c in [c1, c2] {
+        let v = circuit.branch_voltage(c).abs();
+        assert!((v - 5.0).abs() < 1e-12, "{v} V across {c}");
+    }
+    let sharing = circuit.last_charge_sharing().unwrap();
+    assert_eq!(sharing.switches, [s1]);
+    assert!((sharing.dissipated - 0.25e-3).abs() < 1e-15, "{sharing:?}");
+    let [e1, e2] = stored(&circuit);
+    assert!((0.5e-3 - e1 - e2 - sharing.dissipated).abs() < 1e-15);
+    assert_eq!(losses.dissipated(s1), sharing.dissipated);
+}
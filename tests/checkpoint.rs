@@ -0,0 +1,126 @@
+//! A run checkpointed halfway and resumed from the file against the same run done straight
+//! through, which it has to match bit for bit.
+#![cfg(feature = "serde")]
+
+use std::sync::atomic::AtomicBool;
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    checkpoint::{resume_from_checkpoint, run_with_checkpoints, CheckpointConfig},
+    components::{NoiseDistribution, NoiseSourceComponentValue},
+    probe::{Policy, Probe, Quantity, Recording},
+    run::{run_with_progress, RunConfig, RunOutcome},
+    CircuitState, ComponentValueEnum,
+};
+
+const N_STEPS: usize = 10_000;
+const DT: f64 = 1e-4;
+
+/// Band-limited noise into 1 kohm and 1 uF, so the resumed run also has to carry on each
+/// source's PRNG and filter state, recorded in buckets of 3 ticks so the checkpoint at 5000 cuts
+/// one in half.
+fn noisy_rc() -> (CircuitState, Recording) {
+    let noise = NoiseSourceComponentValue {
+        distribution: NoiseDistribution::Gaussian,
+        sigma: 0.1,
+        offset: 1.0,
+        seed: 7,
+        bandwidth: Some(200.0),
+    };
+    let (mut circuit, names) = CircuitBuilder::new()
+        .component("Vn", ComponentValueEnum::NoiseSource(noise), &["gnd", "in"])
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 10e-6))
+        .unwrap()
+        .build();
+    circuit.solve_state();
+    let probe = |c: &str, quantity| Probe::Component(names.component(c).unwrap(), quantity);
+    let recording = Recording::decimated(
+        vec![
+            ("v_in".into(), probe("Vn", Quantity::Voltage), Policy::Mean),
+            (
+                "v_out".into(),
+                probe("C1", Quantity::Voltage),
+                Policy::Sample,
+            ),
+            ("i".into(), probe("R1", Quantity::Current), Policy::Envelope),
+        ],
+        3,
+    );
+    (circuit, recording)
+}
+
+fn config(n_steps: usize) -> RunConfig {
+    RunConfig {
+        dt: DT,
+        n_steps,
+        progress_every: 0,
+        start_step: 0,
+    }
+}
+
+fn bits(values: &[f64]) -> Vec<u64> {
+    values.iter().map(|x| x.to_bits()).collect()
+}
+
+#[test]
+fn resumed_run_matches_straight_run() {
+    let cancel = AtomicBool::new(false);
+    let (mut straight, recording) = noisy_rc();
+    let straight_outcome =
+        run_with_progress(&mut straight, recording, config(N_STEPS), &cancel, |_| {}).unwrap();
+
+    let path = std::env::temp_dir().join(format!("esc_sim_checkpoint_{}.json", std::process::id()));
+    let (mut first_half, recording) = noisy_rc();
+    run_with_checkpoints(
+        &mut first_half,
+        recording,
+        config(N_STEPS / 2),
+        &cancel,
+        |_| {},
+        None,
+        CheckpointConfig {
+            path: &path,
+            every: N_STEPS / 2,
+        },
+    )
+    .unwrap();
+    drop(first_half);
+
+    // everything the second half runs on comes from the file
+    let checkpoint = resume_from_checkpoint(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(checkpoint.steps_done, N_STEPS / 2);
+    let config = checkpoint.run_config(N_STEPS, 0);
+    let mut resumed = checkpoint.circuit;
+    let resumed_outcome =
+        run_with_progress(&mut resumed, checkpoint.recording, config, &cancel, |_| {}).unwrap();
+
+    assert_eq!(resumed_outcome.steps_done, N_STEPS);
+    assert_eq!(resumed.now().to_bits(), straight.now().to_bits());
+    assert_eq!(
+        bits(&resumed.state_vector()),
+        bits(&straight.state_vector())
+    );
+    let voltages = |circuit: &CircuitState| {
+        circuit
+            .nets()
+            .map(|(_, net)| net.voltage().to_bits())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(voltages(&resumed), voltages(&straight));
+    assert_same_recording(&resumed_outcome, &straight_outcome);
+}
+
+fn assert_same_recording(resumed: &RunOutcome, straight: &RunOutcome) {
+    let [resumed, straight] = [resumed, straight].map(|outcome| &outcome.recording);
+    assert_eq!(resumed.time.len(), N_STEPS / 3 + 1);
+    assert_eq!(bits(&resumed.time), bits(&straight.time));
+    assert_eq!(resumed.channels.len(), straight.channels.len());
+    for (label, (a, b)) in resumed
+        .labels()
+        .zip(resumed.channels.iter().zip(&straight.channels))
+    {
+        assert_eq!(bits(a), bits(b), "channel {label}");
+    }
+}
@@ -0,0 +1,14 @@
+//! Fixtures shared by the integration tests.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    error::SimError,
+    CircuitState,
+};
+
+/// Build the circuit of `builder` and solve its DC operating point, failing unless it converges.
+pub fn build(builder: Result<&mut CircuitBuilder, SimError>) -> (CircuitState, NameMap) {
+    let (mut circuit, names) = builder.unwrap().build();
+    assert!(circuit.solve_state(), "no DC operating point");
+    (circuit, names)
+}
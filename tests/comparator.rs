@@ -0,0 +1,57 @@
+//! The comparator component switching its output on its own decisions.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder, components::ComparatorComponentValue, probe::Probe,
+};
+
+const DT: f64 = 1e-6;
+const V_REF: f64 = 5.0;
+const COMPARATOR: ComparatorComponentValue = ComparatorComponentValue {
+    output_high: 10.0,
+    output_low: 0.0,
+    hysteresis: 0.2,
+    propagation_delay: 2e-6,
+    output_resistance: 10.0,
+};
+
+/// A hysteretic regulator: `U1` senses the output `out` against a 5 V reference and its output
+/// stage, 0 or 10 V behind 10 ohm, is the switch, charging 10 uF at `out` through 100 ohm into a
+/// 1 kohm load. Over the last 1.5 ms of 3, `out` ripples between the 4.9 and 5.1 V the
+/// hysteresis sets, overshooting them by no more than the latching at tick boundaries and the
+/// delay let through, and `U1` switches about every 50 us.
+#[test]
+fn hysteretic_regulator() {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("VREF", "gnd", "ref", V_REF)
+        .and_then(|b| b.comparator("U1", COMPARATOR, ["out", "ref"], ["gnd", "drive"]))
+        .and_then(|b| b.resistor("R1", "drive", "out", 100.0))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 10e-6))
+        .and_then(|b| b.resistor("RL", "out", "gnd", 1e3))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let [out, drive] = ["net:out", "net:drive"].map(|spec| Probe::parse(spec, &names).unwrap());
+
+    let (mut low, mut high) = (f64::INFINITY, f64::NEG_INFINITY);
+    let mut switchings = 0;
+    let mut driving = drive.sample(&circuit) > 5.0;
+    for k in 0..3000 {
+        assert!(circuit.tick(DT), "no convergence at {:e} s", circuit.now());
+        if k < 1500 {
+            continue;
+        }
+        let v = out.sample(&circuit);
+        (low, high) = (low.min(v), high.max(v));
+        if (drive.sample(&circuit) > 5.0) != driving {
+            driving = !driving;
+            switchings += 1;
+        }
+    }
+    let band = COMPARATOR.hysteresis / 2.0;
+    assert!(
+        (V_REF - band - 0.05..=V_REF - band).contains(&low)
+            && (V_REF + band..=V_REF + band + 0.05).contains(&high),
+        "out rippled between {low} and {high} V"
+    );
+    assert!((20..40).contains(&switchings), "{switchings} switchings");
+}
@@ -0,0 +1,125 @@
+//! The averaged DC-DC converter under load steps: regulation up to its current limit, the
+//! output collapsing onto the load beyond it, and the input current the power balance asks for.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::{ComponentParameter, ConverterComponentState, ConverterComponentValue},
+    probe::Probe,
+    CircuitState, ComponentId, ComponentRef,
+};
+
+const DT: f64 = 10e-6;
+const V_IN: f64 = 12.0;
+
+const CONVERTER: ConverterComponentValue = ConverterComponentValue {
+    output_voltage: 5.0,
+    efficiency: 0.85,
+    current_limit: 1.0,
+    output_capacitance: 100e-6,
+    soft_start: 1e-3,
+};
+
+/// `CONVERTER` from a 12 V source into a `load` ohm resistor, past its soft-start.
+fn buck(load: f64) -> (CircuitState, NameMap) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", V_IN)
+        .and_then(|b| b.converter("U1", CONVERTER, ["gnd", "in"], ["gnd", "out"]))
+        .and_then(|b| b.resistor("R1", "out", "gnd", load))
+        .unwrap()
+        .build();
+    circuit.solve_state();
+    run(&mut circuit, 200);
+    (circuit, names)
+}
+
+fn run(circuit: &mut CircuitState, n: usize) {
+    for _ in 0..n {
+        assert!(
+            circuit.tick(DT),
+            "tick to {:e} s did not converge",
+            circuit.now()
+        );
+    }
+}
+
+fn set_load(circuit: &mut CircuitState, names: &NameMap, load: f64) {
+    let r1 = names.component("R1").unwrap();
+    *circuit
+        .component_mut(r1)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = load;
+}
+
+fn converter(circuit: &CircuitState, u1: ComponentId) -> &ConverterComponentState {
+    match circuit.component(u1) {
+        ComponentRef::Converter(converter) => converter,
+        _ => unreachable!(),
+    }
+}
+
+/// `efficiency V_in I_in` against the `V_out I_out` the load takes, relative to the latter.
+fn power_balance(circuit: &CircuitState, names: &NameMap) -> f64 {
+    let sample = |spec| Probe::parse(spec, names).unwrap().sample(circuit);
+    let (i_in, v_out, i_out) = (
+        sample("comp:V1.current").abs(),
+        sample("net:out"),
+        sample("comp:R1.current").abs(),
+    );
+    (CONVERTER.efficiency * V_IN * i_in - v_out * i_out).abs() / (v_out * i_out)
+}
+
+/// From 0.25 A to 0.5 A, within the 1 A limit: the output holds 5 V through the step and the
+/// input current doubles with the output power.
+#[test]
+fn load_step_within_limit() {
+    let (mut circuit, names) = buck(20.0);
+    let out = Probe::parse("net:out", &names).unwrap();
+    let u1 = names.component("U1").unwrap();
+    assert!((out.sample(&circuit) - 5.0).abs() < 1e-6);
+    assert!(power_balance(&circuit, &names) < 0.01);
+
+    set_load(&mut circuit, &names, 10.0);
+    for _ in 0..100 {
+        run(&mut circuit, 1);
+        let v_out = out.sample(&circuit);
+        assert!(
+            (v_out - 5.0).abs() < 1e-6,
+            "output at {v_out} V after the step"
+        );
+        assert!(!converter(&circuit, u1).current_limited());
+    }
+    assert!(power_balance(&circuit, &names) < 0.01);
+    let i_in = Probe::parse("comp:V1.current", &names)
+        .unwrap()
+        .sample(&circuit);
+    assert!((i_in.abs() - 5.0 * 0.5 / (0.85 * V_IN)).abs() < 1e-6);
+}
+
+/// From 0.25 A to the 2 A a 2.5 ohm load would take at 5 V: the converter holds its 1 A limit
+/// and the output falls to the 2.5 V that puts across the load, the input current following
+/// the power actually delivered.
+#[test]
+fn load_step_beyond_limit() {
+    let (mut circuit, names) = buck(20.0);
+    let out = Probe::parse("net:out", &names).unwrap();
+    let u1 = names.component("U1").unwrap();
+
+    set_load(&mut circuit, &names, 2.5);
+    // the first tick regulates on the load current of the solve before the step.
+    run(&mut circuit, 1);
+    let mut v_prev = out.sample(&circuit);
+    for _ in 0..500 {
+        run(&mut circuit, 1);
+        let converter = converter(&circuit, u1);
+        assert!(converter.current_limited());
+        assert!(converter.converted_current() <= CONVERTER.current_limit + 1e-9);
+        let v_out = out.sample(&circuit);
+        assert!(
+            v_out <= v_prev + 1e-9,
+            "output rose from {v_prev} V to {v_out} V"
+        );
+        v_prev = v_out;
+    }
+    assert!((v_prev - 2.5).abs() < 1e-3, "output settled at {v_prev} V");
+    assert!(power_balance(&circuit, &names) < 0.01);
+}
@@ -0,0 +1,232 @@
+//! Closed loops and controls through `sim::cosim`, driven the way firmware would drive them.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::{ComponentParameter, LinearComponentValue},
+    cosim::{CoSim, Control},
+    probe::{Probe, Quantity},
+    protection::ProtectionBlock,
+    random::Rng,
+    CircuitState, ComponentRef,
+};
+
+const PWM_PERIOD: f64 = 50e-6;
+
+/// A PI current controller closing the loop around a synchronous buck stage (12 V into 1 mH and
+/// 1 ohm) through `CoSim` alone: it samples the inductor current once per PWM period and writes
+/// the duty cycle of the half-bridge, ticking 50 times a period. Over the last 2.5 ms of 10 ms
+/// the current holds its 3 A setpoint within 1 %.
+#[test]
+fn current_controller_settles_at_setpoint() {
+    const SETPOINT: f64 = 3.0;
+    let (circuit, names) = CircuitBuilder::new()
+        .source("Vbus", "gnd", "vbus", 12.0)
+        .and_then(|b| b.switch("S_high", "vbus", "phase", false))
+        .and_then(|b| b.switch("S_low", "phase", "gnd", true))
+        .and_then(|b| b.inductor("L1", "phase", "load", 1e-3))
+        .and_then(|b| b.resistor("R1", "load", "gnd", 1.0))
+        .unwrap()
+        .build();
+    let component = |name| names.component(name).unwrap();
+
+    let mut sim = CoSim::new(circuit, PWM_PERIOD / 50.0);
+    let current = sim.add_probe(Probe::Component(component("L1"), Quantity::Current));
+    let pwm = |name, inverted| Control::Pwm {
+        component: component(name),
+        period: PWM_PERIOD,
+        low: 0.0,
+        high: 0.0,
+        inverted,
+    };
+    let duty_high = sim.add_control(pwm("S_high", false), 0.0);
+    let duty_low = sim.add_control(pwm("S_low", true), 0.0);
+
+    let (kp, ki) = (0.05, 200.0);
+    let mut integral = 0.0;
+    let mut settled = Vec::new();
+    for period in 1..=200 {
+        // ADC sample at the end of the previous period, new duty from the next one on
+        let sample = sim.read_probe(current);
+        if period > 150 {
+            settled.push(sample);
+        }
+        let error = SETPOINT - sample;
+        integral += error * PWM_PERIOD;
+        let duty = (kp * error + ki * integral).clamp(0.0, 1.0);
+        sim.write_control(duty_high, duty);
+        sim.write_control(duty_low, duty);
+        sim.step_until(period as f64 * PWM_PERIOD).unwrap();
+    }
+    assert_eq!(sim.unconverged(), 0);
+    for sample in settled {
+        assert!(
+            (sample - SETPOINT).abs() < 0.01 * SETPOINT,
+            "{sample} A against the {SETPOINT} A setpoint"
+        );
+    }
+}
+
+/// 12 V switched by `S1` into 10 ohm and 10 uF, with a source `VC` for a control voltage.
+fn switched_load() -> (CircuitState, NameMap) {
+    CircuitBuilder::new()
+        .source("V1", "gnd", "bus", 12.0)
+        .and_then(|b| b.switch("S1", "bus", "out", false))
+        .and_then(|b| b.resistor("R1", "out", "gnd", 10.0))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 10e-6))
+        .and_then(|b| b.source("VC", "gnd", "ctl", 0.0))
+        .unwrap()
+        .build()
+}
+
+fn closed(sim: &CoSim, switch: usize) -> bool {
+    match sim.circuit().component(switch) {
+        ComponentRef::Linear(v) => {
+            matches!(v.value, LinearComponentValue::Switch { closed: true, .. })
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// The switch closing on a control voltage that sits on its 0.5 V threshold with 10 mV of noise,
+/// read back every 1 us step: the request flips about every other step, but with a 20 us
+/// dwell the switch toggles at most once per dwell over 1 ms, and every step converges.
+#[test]
+fn switch_dwell_at_threshold() {
+    const DT: f64 = 1e-6;
+    const DWELL: f64 = 20e-6;
+    let (circuit, names) = switched_load();
+    let component = |name| names.component(name).unwrap();
+    let mut sim = CoSim::new(circuit, DT);
+    let ctl = sim.add_probe(Probe::parse("net:ctl", &names).unwrap());
+    let vc = sim.add_control(Control::Source(component("VC")), 0.5);
+    let s1 = sim.add_control(Control::Switch(component("S1")), 0.0);
+    sim.set_min_dwell(s1, DWELL);
+
+    let mut rng = Rng::new(397);
+    let (mut requests, mut toggles) = (0, 0);
+    let mut state = (false, false);
+    for step in 1..=1000 {
+        sim.write_control(vc, 0.5 + 0.01 * (2.0 * rng.uniform() - 1.0));
+        let request = sim.read_probe(ctl) > 0.5;
+        sim.write_control(s1, sim.read_probe(ctl));
+        sim.step_until(step as f64 * DT).unwrap();
+        let now = closed(&sim, component("S1"));
+        requests += usize::from(request != state.0);
+        toggles += usize::from(now != state.1);
+        state = (request, now);
+    }
+    assert_eq!(sim.unconverged(), 0);
+    assert!(requests > 250, "only {requests} requested toggles");
+    assert!(toggles > 10, "only {toggles} toggles");
+    assert!(
+        toggles as f64 <= 1e-3 / DWELL + 1.0,
+        "{toggles} toggles in 1 ms with a {DWELL:e} s dwell"
+    );
+}
+
+/// The switch, asked closed throughout with a 50 us dwell, forced open by an overcurrent trip
+/// after its first step: released 20 us later, it stays open until 50 us after it was forced
+/// open, not 50 us after it closed.
+#[test]
+fn switch_dwell_after_trip() {
+    const DT: f64 = 1e-6;
+    const DWELL: f64 = 50e-6;
+    let (circuit, names) = switched_load();
+    let component = |name| names.component(name).unwrap();
+    let mut sim = CoSim::new(circuit, DT);
+    let s1 = sim.add_control(Control::Switch(component("S1")), 1.0);
+    sim.set_min_dwell(s1, DWELL);
+    let trip = sim.add_protection(ProtectionBlock::new(
+        Probe::Component(component("R1"), Quantity::Current),
+        0.1,
+        vec![s1],
+    ));
+
+    sim.step_until(DT).unwrap();
+    let tripped_at = sim.protection(trip).tripped_at().unwrap();
+    sim.step_until(2.0 * DT).unwrap();
+    assert!(!closed(&sim, component("S1")));
+    sim.step_until(20e-6).unwrap();
+    sim.protection_mut(trip).reset();
+    while !closed(&sim, component("S1")) {
+        sim.step_until(sim.time() + DT).unwrap();
+        assert!(sim.time() < 1e-3, "never closed again");
+    }
+    // the step that closed it started at least the dwell after the one that opened it.
+    let reclosed = sim.time() - DT;
+    assert!(
+        reclosed >= tripped_at + DWELL - 0.5 * DT,
+        "closed again at {reclosed:e} s, forced open at {tripped_at:e} s"
+    );
+    assert_eq!(sim.unconverged(), 0);
+}
+
+/// A high-side switch held on into 10 ohm through a 1 ohm sense resistor, with a 5 A trip of
+/// 5 us response on the sense current. The load shorted down to 0.5 ohm at 1 ms (as the
+/// `phase_short` scenario does) trips it on the step after, the switch opens within the response
+/// time and stays open, though the control still asks for it and the short has cleared, until
+/// the trip is reset.
+#[test]
+fn overcurrent_trip() {
+    const DT: f64 = 1e-6;
+    const RESPONSE: f64 = 5e-6;
+    let (circuit, names) = CircuitBuilder::new()
+        .source("Vbus", "gnd", "bus", 12.0)
+        .and_then(|b| b.resistor("Rsense", "bus", "sense", 1.0))
+        .and_then(|b| b.switch("S_high", "sense", "phase", false))
+        .and_then(|b| b.resistor("Rload", "phase", "gnd", 10.0))
+        .unwrap()
+        .build();
+    let component = |name| names.component(name).unwrap();
+    let mut sim = CoSim::new(circuit, DT);
+    let gate = sim.add_control(Control::Switch(component("S_high")), 1.0);
+    let trip = sim.add_protection(
+        ProtectionBlock::new(
+            Probe::Component(component("Rsense"), Quantity::Current),
+            5.0,
+            vec![gate],
+        )
+        .with_response_time(RESPONSE),
+    );
+    let set_load = |sim: &mut CoSim, ohms| {
+        *sim.circuit_mut()
+            .component_mut(component("Rload"))
+            .parameter_mut(ComponentParameter::Value)
+            .unwrap() = ohms;
+    };
+
+    sim.step_until(1e-3).unwrap();
+    assert!(!sim.protection(trip).tripped());
+    assert!(closed(&sim, component("S_high")));
+    set_load(&mut sim, 0.5);
+    sim.step_until(1e-3 + DT).unwrap();
+    let tripped_at = sim.protection(trip).tripped_at().unwrap();
+    assert!(
+        tripped_at <= 1e-3 + DT + 1e-12,
+        "tripped at {tripped_at:e} s"
+    );
+    // the gate opens once the response time is up, not before.
+    while closed(&sim, component("S_high")) {
+        sim.step_until(sim.time() + DT).unwrap();
+    }
+    let opened = sim.time() - DT;
+    assert!(
+        (opened - (tripped_at + RESPONSE)).abs() < 0.5 * DT,
+        "opened at {opened:e} s, tripped at {tripped_at:e} s"
+    );
+
+    set_load(&mut sim, 10.0);
+    for step in 1..=1000 {
+        sim.step_until(opened + step as f64 * DT).unwrap();
+        assert!(
+            !closed(&sim, component("S_high")),
+            "closed at {:e} s",
+            sim.time()
+        );
+    }
+    sim.protection_mut(trip).reset();
+    sim.step_until(sim.time() + DT).unwrap();
+    assert!(closed(&sim, component("S_high")));
+    assert!(!sim.protection(trip).tripped());
+    assert_eq!(sim.unconverged(), 0);
+}
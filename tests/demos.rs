@@ -0,0 +1,51 @@
+//! The example circuits of `sim` (`make_rc_test`, `make_mosfet_test`, `make_mosfet_rl_test`):
+//! the first two assert on their own runs, the last returns a waveform checked here.
+
+use esc_sim_test::sim::{make_mosfet_rl_test, make_mosfet_test, make_rc_test};
+
+#[test]
+fn rc_test() {
+    make_rc_test();
+}
+
+#[test]
+fn mosfet_test() {
+    make_mosfet_test();
+}
+
+/// The inductor current of `make_mosfet_rl_test` against a first-order RL response on each edge,
+/// with the switch as its 10 ohm resistor in parallel with the channel linearized to
+/// `1 / (beta (V_gs - V_th))`. On, the current rises toward `V / (R + R_sw)` with
+/// `tau = L / (R + R_sw)`; off, it decays toward `V / (R + 10 ohm)` with `tau = L / (R + 10 ohm)`.
+#[test]
+fn mosfet_rl_test() {
+    let (v, r, l, r_parallel) = (12.0, 10.0, 1e-3, 10.0);
+    let r_on = 1.0 / (0.02 * (20.0 - 2.0));
+    let r_switch = r_on * r_parallel / (r_on + r_parallel);
+    let t_off = 0.5e-3;
+
+    let recording = make_mosfet_rl_test().unwrap();
+    let i_l = recording.channel("i_L").unwrap();
+    let i_open = v / (r + r_parallel);
+    assert!((i_l[0] - i_open).abs() < 1e-9, "{} A at rest", i_l[0]);
+
+    let i_switch_off = i_l[recording.time.iter().position(|&t| t >= t_off).unwrap()];
+    let first_order = |t: f64, from: f64, to: f64, tau: f64| to + (from - to) * (-t / tau).exp();
+    let mut deviation: f64 = 0.0;
+    for (&t, &i) in recording.time.iter().zip(i_l) {
+        let expected = if t <= t_off {
+            first_order(t, i_open, v / (r + r_switch), l / (r + r_switch))
+        } else {
+            first_order(t - t_off, i_switch_off, i_open, l / (r + r_parallel))
+        };
+        deviation = deviation.max((i - expected).abs());
+    }
+    assert!(deviation < 0.02, "max deviation {deviation:.3e} A");
+    // both edges settle within 5 time constants
+    let i_end = i_l[i_l.len() - 1];
+    assert!(
+        i_switch_off > 0.97 * v / (r + r_switch),
+        "{i_switch_off} A on"
+    );
+    assert!((i_end - i_open).abs() < 0.01, "{i_end} A off");
+}
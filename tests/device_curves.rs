@@ -0,0 +1,298 @@
+//! Device models against their closed-form I-V curves, point by point through `sim::fixture`
+//! with no circuit around them: the MOSFET square law in saturation, the slope of the triode
+//! region and the exponential of the body diode; and `MOSFETComponentValue::i_ds` and
+//! `v_ds_for_current` against each other and against the points the fixture settles on, in every
+//! region. Needs the `test-util` feature:
+//!
+//!     cargo test --features test-util --test device_curves
+
+use esc_sim_test::sim::{
+    components::{Avalanche, MOSFETComponentValue, MOSFETDopingType, NOMINAL_TEMPERATURE},
+    fixture::ComponentFixture,
+};
+
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.02,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+/// `MOSFET` with an avalanche clamp at 30 V.
+const CLAMPED_MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    avalanche: Some(Avalanche {
+        breakdown_voltage: 30.0,
+        conductance: 0.1,
+        rated_energy: None,
+    }),
+    ..MOSFET
+};
+
+/// `MOSFET` as a P-channel device.
+const P_MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    ty: MOSFETDopingType::PChannel,
+    ..MOSFET
+};
+
+/// Fail unless `deviation` (the largest relative deviation from the analytic curve) is within
+/// `tolerance`.
+fn assert_within(name: &str, deviation: f64, tolerance: f64) {
+    assert!(
+        deviation <= tolerance,
+        "{name}: max deviation {deviation:.3e} relative (tolerance {tolerance:.1e})"
+    );
+}
+
+/// The drain current of `value` at each `(v_gs, v_ds)`, the source at 0 V, positive into the
+/// drain. Panics if a point doesn't settle.
+fn drain_current_curve(value: MOSFETComponentValue, points: &[(f64, f64)]) -> Vec<f64> {
+    let mut fixture = ComponentFixture::new(value);
+    let voltages = points
+        .iter()
+        .map(|&(v_gs, v_ds)| [0.0, v_gs, v_ds])
+        .collect::<Vec<_>>();
+    fixture
+        .curve(voltages.iter().map(|v| &v[..]))
+        .into_iter()
+        .zip(points)
+        .map(|(currents, point)| {
+            // the drain terminal takes what the component drives out of it
+            -currents.unwrap_or_else(|| panic!("{point:?} didn't settle"))[2]
+        })
+        .collect()
+}
+
+/// `drain_current_curve` of `MOSFET`.
+fn drain_currents(points: &[(f64, f64)]) -> Vec<f64> {
+    drain_current_curve(MOSFET, points)
+}
+
+fn relative(value: f64, expected: f64) -> f64 {
+    (value - expected).abs() / expected.abs()
+}
+
+/// `I_d = beta / 2 (V_gs - V_th)^2` for `V_gs` from 3 V to 10 V, held in saturation at
+/// `V_ds` = 20 V.
+#[test]
+fn square_law() {
+    let points = (3..=10)
+        .map(|v_gs| (f64::from(v_gs), 20.0))
+        .collect::<Vec<_>>();
+    let deviation = drain_currents(&points)
+        .into_iter()
+        .zip(&points)
+        .map(|(i_d, &(v_gs, _))| {
+            relative(
+                i_d,
+                MOSFET.beta / 2.0 * (v_gs - MOSFET.threshold_voltage).powi(2),
+            )
+        })
+        .fold(0.0, f64::max);
+    assert_within("MOSFET square law", deviation, 1e-6);
+}
+
+/// `dI_d / dV_ds = beta (V_gs - V_th - V_ds)` deep in the triode region, `V_gs` = 10 V and
+/// `V_ds` from 0.1 V to 1 V, by central differences of 1 mV.
+#[test]
+fn triode_slope() {
+    let (v_gs, h) = (10.0, 1e-3);
+    let v_ctrl = v_gs - MOSFET.threshold_voltage;
+    let v_ds = (1..=10).map(|k| f64::from(k) * 0.1).collect::<Vec<_>>();
+    let points = v_ds
+        .iter()
+        .flat_map(|&v_ds| [(v_gs, v_ds - h), (v_gs, v_ds + h)])
+        .collect::<Vec<_>>();
+    let deviation = drain_currents(&points)
+        .chunks(2)
+        .zip(&v_ds)
+        .map(|(pair, &v_ds)| {
+            relative(
+                (pair[1] - pair[0]) / (2.0 * h),
+                MOSFET.beta * (v_ctrl - v_ds),
+            )
+        })
+        .fold(0.0, f64::max);
+    assert_within("MOSFET triode slope", deviation, 1e-6);
+}
+
+/// `I_d = -I_s (exp(-V_ds / n V_t) - 1)` with the gate off, from `V_ds` = -0.3 V to -0.8 V, at
+/// `NOMINAL_TEMPERATURE` where `I_s` is as given.
+#[test]
+fn body_diode() {
+    let n_vt = MOSFET.body_diode_ideality_facotor * NOMINAL_TEMPERATURE * 8.617333262e-5;
+    let points = (3..=8)
+        .map(|k| (0.0, -0.1 * f64::from(k)))
+        .collect::<Vec<_>>();
+    let deviation = drain_currents(&points)
+        .into_iter()
+        .zip(&points)
+        .map(|(i_d, &(_, v_ds))| {
+            relative(
+                i_d,
+                -MOSFET.body_diode_saturation_current * ((-v_ds / n_vt).exp() - 1.0),
+            )
+        })
+        .fold(0.0, f64::max);
+    // a point settles once the current changes by less than `Scalar::CONVERGENCE_EPSILON` an
+    // iteration, which leaves the 130 nA at -0.3 V a few parts per million short.
+    assert_within("body diode exponential", deviation, 1e-5);
+}
+
+/// Points `(v_gs, v_ds)` of every region, of the sign of the doping type: the body diode, off,
+/// triode, saturation and, for `CLAMPED_MOSFET`, avalanche with the gate off and on.
+fn region_points(value: &MOSFETComponentValue) -> Vec<(f64, f64)> {
+    let mut points = vec![
+        (0.0, -0.6),
+        (5.0, -0.4),
+        (0.0, 10.0),
+        (1.9, 0.5),
+        (10.0, 0.2),
+        (6.0, 2.0),
+        (4.0, 8.0),
+        (10.0, 20.0),
+    ];
+    if value.avalanche.is_some() {
+        points.extend([(0.0, 32.0), (0.0, 40.0), (3.0, 35.0)]);
+    }
+    points
+}
+
+/// `points` as terminal voltages of `value`, negated for a P-channel device.
+fn terminal_voltages(value: &MOSFETComponentValue, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let sign = match value.ty {
+        MOSFETDopingType::PChannel => -1.0,
+        MOSFETDopingType::NChannel => 1.0,
+    };
+    points
+        .iter()
+        .map(|&(v_gs, v_ds)| (v_gs * sign, v_ds * sign))
+        .collect()
+}
+
+/// `MOSFETComponentValue::i_ds` against the drain current `ComponentFixture` settles on at each
+/// of `region_points`, relative to the current or 1 nA, for the N-channel, clamped and
+/// P-channel devices.
+#[test]
+fn i_ds_against_fixture() {
+    let deviation = [MOSFET, CLAMPED_MOSFET, P_MOSFET]
+        .into_iter()
+        .flat_map(|value| {
+            let points = terminal_voltages(&value, &region_points(&value));
+            drain_current_curve(value, &points)
+                .into_iter()
+                .zip(points)
+                .map(move |(i_d, (v_gs, v_ds))| {
+                    let expected = value.i_ds(v_gs, v_ds, NOMINAL_TEMPERATURE);
+                    (i_d - expected).abs() / expected.abs().max(1e-9)
+                })
+        })
+        .fold(0.0, f64::max);
+    // settling to `Scalar::CONVERGENCE_EPSILON`, as for the body diode.
+    assert_within("i_ds against the fixture", deviation, 1e-5);
+}
+
+/// `MOSFETComponentValue::v_ds_for_current` of the drain current `ComponentFixture` settles on
+/// at each of `region_points` where the current sets the voltage (the body diode, triode and
+/// avalanche; off it doesn't, and in saturation `v_ds` follows too weakly from the current to
+/// be found from it), against the `v_ds` held.
+#[test]
+fn v_ds_for_current_against_fixture() {
+    let deviation = [MOSFET, CLAMPED_MOSFET, P_MOSFET]
+        .into_iter()
+        .flat_map(|value| {
+            let breakdown = value
+                .avalanche
+                .map_or(f64::INFINITY, |avalanche| avalanche.breakdown_voltage);
+            let points = region_points(&value)
+                .into_iter()
+                .filter(|&(v_gs, v_ds)| {
+                    let v_ctrl = v_gs - value.threshold_voltage;
+                    v_ds < 0.0 || v_ds > breakdown || (0.0 < v_ds && v_ds < v_ctrl)
+                })
+                .collect::<Vec<_>>();
+            let points = terminal_voltages(&value, &points);
+            drain_current_curve(value, &points)
+                .into_iter()
+                .zip(points)
+                .map(move |(i_d, (v_gs, v_ds))| {
+                    value
+                        .v_ds_for_current(i_d, v_gs, NOMINAL_TEMPERATURE)
+                        .map_or(f64::INFINITY, |found| relative(found, v_ds))
+                })
+        })
+        .fold(0.0, f64::max);
+    assert_within("v_ds_for_current against the fixture", deviation, 1e-5);
+}
+
+/// The largest relative deviation of `v_ds_for_current(i_ds(v_ds))` from `v_ds` at `points`, for
+/// `value` and its P-channel mirror; infinite if a current isn't inverted.
+fn round_trip(value: MOSFETComponentValue, points: &[(f64, f64)]) -> f64 {
+    let mirror = MOSFETComponentValue {
+        ty: MOSFETDopingType::PChannel,
+        ..value
+    };
+    [value, mirror]
+        .into_iter()
+        .flat_map(|value| {
+            terminal_voltages(&value, points)
+                .into_iter()
+                .map(move |(v_gs, v_ds)| {
+                    let i_ds = value.i_ds(v_gs, v_ds, NOMINAL_TEMPERATURE);
+                    value
+                        .v_ds_for_current(i_ds, v_gs, NOMINAL_TEMPERATURE)
+                        .map_or(f64::INFINITY, |found| relative(found, v_ds))
+                })
+        })
+        .fold(0.0, f64::max)
+}
+
+/// The body diode, with the gate off and on.
+#[test]
+fn round_trip_body_diode() {
+    let points = [
+        (0.0, -0.3),
+        (0.0, -0.6),
+        (0.0, -0.8),
+        (5.0, -0.4),
+        (10.0, -0.7),
+    ];
+    assert_within("body diode round trip", round_trip(MOSFET, &points), 1e-12);
+}
+
+/// Cutoff carries no current below breakdown, so there's nothing to invert; past it the clamp
+/// conducts and the voltage is found again, with the gate off and just below threshold.
+#[test]
+fn round_trip_cutoff() {
+    for value in [MOSFET, P_MOSFET] {
+        let [(v_gs, v_ds)] = terminal_voltages(&value, &[(0.0, 10.0)])[..] else {
+            unreachable!()
+        };
+        assert_eq!(value.i_ds(v_gs, v_ds, NOMINAL_TEMPERATURE), 0.0);
+        assert_eq!(value.v_ds_for_current(0.0, v_gs, NOMINAL_TEMPERATURE), None);
+    }
+    let points = [(0.0, 32.0), (0.0, 40.0), (1.9, 35.0)];
+    assert_within(
+        "cutoff round trip",
+        round_trip(CLAMPED_MOSFET, &points),
+        1e-12,
+    );
+}
+
+/// Below `v_gs - V_th` at gate drives from just over threshold to 10 V.
+#[test]
+fn round_trip_triode() {
+    let points = [(2.5, 0.2), (4.0, 1.0), (6.0, 2.0), (10.0, 0.2), (10.0, 5.0)];
+    assert_within("triode round trip", round_trip(MOSFET, &points), 1e-12);
+}
+
+/// Past `v_gs - V_th`, where `i_ds` flattens and the inverse loses precision the deeper it goes:
+/// at up to twice the knee it still holds to a few parts per billion.
+#[test]
+fn round_trip_saturation() {
+    let points = [(4.0, 3.0), (6.0, 4.4), (6.0, 5.0), (6.0, 8.0), (10.0, 16.0)];
+    assert_within("saturation round trip", round_trip(MOSFET, &points), 1e-8);
+}
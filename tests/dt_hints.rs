@@ -0,0 +1,87 @@
+//! Time steps bounded by `ComponentState::max_dt_hint`: `tick_adaptive` keeping to them and
+//! `SolverConfig::strict_dt` refusing longer ones.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{LinearComponentValue, PwmWave},
+    error::SimError,
+    solver::SolverConfig,
+    ComponentValueEnum,
+};
+
+/// A 10 us, 30 % PWM into 1 kohm, stepped by `tick_adaptive` with at most 4 us: the steps
+/// shorten to end on every rising and falling edge.
+#[test]
+fn pwm_edges() {
+    const PERIOD: f64 = 10e-6;
+    let wave = PwmWave {
+        low: 0.0,
+        high: 1.0,
+        period: PERIOD,
+        duty: 0.3,
+        delay: 0.0,
+        timing: Default::default(),
+    };
+    let (mut circuit, _) = CircuitBuilder::new()
+        .pwm_source("V1", "gnd", "in", wave)
+        .and_then(|b| b.resistor("R1", "in", "gnd", 1e3))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let mut times = Vec::new();
+    while circuit.now() < 5.0 * PERIOD {
+        let (dt, converged) = circuit.tick_adaptive(4e-6).unwrap();
+        assert!(converged);
+        assert!(dt <= 4e-6);
+        times.push(circuit.now());
+    }
+    for k in 0..5 {
+        for edge in [k as f64 * PERIOD, (k as f64 + 0.3) * PERIOD] {
+            if edge == 0.0 {
+                continue;
+            }
+            assert!(
+                times.iter().any(|t| (t - edge).abs() < 1e-9 * PERIOD),
+                "no step ends on the edge at {edge:e} s: {times:?}"
+            );
+        }
+    }
+}
+
+/// 1 V into 1 ohm and a 1 uH inductor with 1 ohm of winding resistance, a time constant of its
+/// own of 1 us: with `strict_dt` a 10 us tick fails naming the inductor, a 0.5 us one is taken.
+#[test]
+fn strict_rl() {
+    let inductor = LinearComponentValue::SaturatingInductive {
+        inductance: 1e-6,
+        saturation_current: 10.0,
+        series_resistance: 1.0,
+    };
+    let mut builder = CircuitBuilder::new();
+    builder
+        .source("V1", "gnd", "in", 1.0)
+        .and_then(|b| b.resistor("R1", "in", "mid", 1.0))
+        .and_then(|b| b.component("L1", ComponentValueEnum::Linear(inductor), &["mid", "gnd"]))
+        .unwrap();
+    let (mut circuit, names) = builder.build();
+    circuit.set_solver_config(SolverConfig {
+        strict_dt: true,
+        ..Default::default()
+    });
+    assert!(circuit.solve_state());
+
+    let l1 = names.component("L1").unwrap();
+    let error = circuit.try_tick(10e-6).unwrap_err();
+    match &error {
+        SimError::TimeStepTooLarge {
+            component, max_dt, ..
+        } => {
+            assert_eq!(*component, l1);
+            assert!((max_dt - 1e-6).abs() < 1e-12, "max dt {max_dt:e} s");
+        }
+        _ => panic!("{error:?}"),
+    }
+    assert!(error.to_string().contains("L1"), "{error}");
+    assert_eq!(circuit.now(), 0.0);
+    assert!(circuit.try_tick(0.5e-6).unwrap());
+}
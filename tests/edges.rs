@@ -0,0 +1,97 @@
+//! Edges, duty cycle and frequency of synthesized logic-like channels.
+
+use esc_sim_test::sim::{
+    edges::{Edge, EdgeDirection},
+    probe::{Probe, Recording},
+    stats::StatsError,
+};
+
+/// A recording of one channel `x` holding `wave` every `dt` over `rows` rows from time 0.
+fn synthesized(dt: f64, rows: usize, wave: impl Fn(usize, f64) -> f64) -> Recording {
+    let mut recording = Recording::new(vec![("x".to_string(), Probe::Net(0))]);
+    for i in 0..rows {
+        let t = i as f64 * dt;
+        recording.time.push(t);
+        recording.channels[0].push(wave(i, t));
+    }
+    recording
+}
+
+const PERIOD: f64 = 10e-6;
+const DUTY: f64 = 0.3;
+const DELAY: f64 = 1e-6;
+/// Time the PWM takes to swing between 0 and 5 V.
+const RAMP: f64 = 100e-9;
+
+/// 0 to 5 V PWM at `PERIOD` and `DUTY`, each period starting `DELAY` in with a linear rise over
+/// `RAMP`, and falling as fast at `DUTY` of the period after.
+fn pwm(t: f64) -> f64 {
+    let phase = (t - DELAY).rem_euclid(PERIOD);
+    let on = DUTY * PERIOD;
+    5.0 * (phase / RAMP)
+        .min(1.0)
+        .min((on + RAMP - phase) / RAMP)
+        .max(0.0)
+}
+
+/// 10 periods sampled every 30 ns, so no row lands on an edge: the edges are interpolated to
+/// halfway up each ramp, where the wave crosses 2.5 V, from the first rising one (the first row
+/// only sets the level), and the duty cycle and frequency are those of the wave.
+#[test]
+fn pwm_timing() {
+    let recording = synthesized(30e-9, 3334, |_, t| pwm(t));
+    let edges = recording.edges("x", 2.5, 1.0).unwrap();
+    assert_eq!(edges.len(), 20);
+    for (i, edge) in edges.iter().enumerate() {
+        let k = (i / 2) as f64;
+        let (time, direction) = match i % 2 {
+            0 => (DELAY + k * PERIOD + RAMP / 2.0, EdgeDirection::Rising),
+            _ => (
+                DELAY + (k + DUTY) * PERIOD + RAMP / 2.0,
+                EdgeDirection::Falling,
+            ),
+        };
+        assert_eq!(edge.direction, direction, "edge {i}");
+        assert!(
+            (edge.time - time).abs() < 1e-15,
+            "edge {i} at {:e}",
+            edge.time
+        );
+    }
+    let duty = recording.duty_cycle("x", 2.5, 1.0).unwrap();
+    assert!((duty - DUTY).abs() < 1e-9, "duty cycle {duty}");
+    let frequency = recording.frequency("x", 2.5, 1.0).unwrap();
+    assert!((frequency * PERIOD - 1.0).abs() < 1e-9, "{frequency} Hz");
+}
+
+/// Less than a period has one rising edge, not enough for a duty cycle or frequency.
+#[test]
+fn too_few_edges() {
+    let recording = synthesized(30e-9, 300, |_, t| pwm(t));
+    let expected = Err(StatsError::TooFewEdges {
+        channel: "x".to_string(),
+        found: 1,
+    });
+    assert_eq!(recording.duty_cycle("x", 2.5, 1.0), expected);
+    assert_eq!(recording.frequency("x", 2.5, 1.0), expected);
+}
+
+/// A 1 us rise from 0 to 5 V with 0.2 V of noise alternating row by row, crossing 2.5 V back and
+/// forth for 40 ns either side of its middle: 1 V of hysteresis takes it as one edge, timed at
+/// the last crossing, within those 40 ns of the middle; without, every crossing is an edge.
+#[test]
+fn slow_noisy_edge() {
+    const START: f64 = 1e-6;
+    let recording = synthesized(10e-9, 300, |i, t| {
+        let noise = if i % 2 == 0 { 0.2 } else { -0.2 };
+        5.0 * ((t - START) / 1e-6).clamp(0.0, 1.0) + noise
+    });
+    let edges = recording.edges("x", 2.5, 1.0).unwrap();
+    let [Edge { time, direction }] = edges[..] else {
+        panic!("{edges:?}");
+    };
+    assert_eq!(direction, EdgeDirection::Rising);
+    assert!((time - (START + 0.5e-6)).abs() <= 40e-9, "edge at {time:e}");
+    let chattering = recording.edges("x", 2.5, 0.0).unwrap();
+    assert!(chattering.len() > 2, "{chattering:?}");
+}
@@ -0,0 +1,118 @@
+//! `EfficiencyMeter` over a bridge switching an inductive load, against the energy balance of
+//! a `LossAccumulator` over the same window.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{LinearComponentState, LinearComponentValue},
+    efficiency::{EfficiencyConfig, EfficiencyMeter},
+    power::LossAccumulator,
+    CircuitState, ComponentId, ComponentMut, ComponentValueEnum,
+};
+
+const DT: f64 = 500e-9;
+const PERIOD: f64 = 10e-6;
+const DUTY: f64 = 0.4;
+
+fn set_switch(circuit: &mut CircuitState, switch: ComponentId, on: bool) {
+    match circuit.component_mut(switch) {
+        ComponentMut::Linear(LinearComponentState {
+            value: LinearComponentValue::Switch { closed, .. },
+            ..
+        }) => *closed = on,
+        _ => unreachable!(),
+    }
+}
+
+/// A half bridge of ideal switches, each with 0.2 ohm in series, from 12 V into 100 uH and
+/// 2 ohm at 100 kHz and 40 % duty, from the mean load current and measured over 10 periods
+/// after 5 to settle: the stages of the report add up to what the accumulator has each
+/// component dissipate within 2 %, and input, output, losses and stored energy balance within
+/// 2 % of the input.
+#[test]
+fn switch_bridge() {
+    let open = LinearComponentValue::Switch {
+        closed: false,
+        off_resistance: None,
+    };
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "bus", 12.0)
+        .and_then(|b| b.component("SH", ComponentValueEnum::Linear(open), &["bus", "h"]))
+        .and_then(|b| b.resistor("RH", "h", "sw", 0.2))
+        .and_then(|b| b.component("SL", ComponentValueEnum::Linear(open), &["sw", "l"]))
+        .and_then(|b| b.resistor("RL", "l", "gnd", 0.2))
+        .and_then(|b| b.inductor("L1", "sw", "load", 100e-6))
+        .and_then(|b| b.resistor("RLOAD", "load", "gnd", 2.0))
+        .unwrap()
+        .build();
+    let id = |name: &str| names.component(name).unwrap();
+    let (sh, sl) = (id("SH"), id("SL"));
+    set_switch(&mut circuit, sl, true);
+    // the mean current, so the ripple settles within a few periods.
+    circuit
+        .set_initial_inductor_current(id("L1"), DUTY * 12.0 / 2.2)
+        .unwrap();
+    assert!(circuit.solve_state());
+    // half a tick either side, clear of the rounding of `now`.
+    let window = (5.0 * PERIOD - 0.5 * DT, 15.0 * PERIOD + 0.5 * DT);
+    let mut meter = EfficiencyMeter::new(EfficiencyConfig {
+        window,
+        inputs: vec![id("V1")],
+        outputs: vec![id("RLOAD")],
+        high_side: vec![sh, id("RH")],
+        low_side: vec![sl, id("RL")],
+        bus_capacitors: Vec::new(),
+    });
+    let mut losses = LossAccumulator::new();
+    let per_period = (PERIOD / DT).round() as usize;
+    let on_ticks = (DUTY * per_period as f64).round() as usize;
+    for k in 0..15 * per_period {
+        let high = k % per_period < on_ticks;
+        set_switch(&mut circuit, sh, high);
+        set_switch(&mut circuit, sl, !high);
+        assert!(circuit.tick(DT), "tick {k}");
+        meter.accumulate(&circuit, DT);
+        if circuit.now() > window.0 {
+            losses.accumulate(&circuit, DT);
+        }
+    }
+    let report = meter.report();
+    let duration = report.duration;
+    assert!(
+        (duration - (window.1 - window.0)).abs() < 1e-3 * DT,
+        "{report:?}"
+    );
+
+    let dissipated = |names: &[&str]| names.iter().map(|&n| losses.dissipated(id(n))).sum::<f64>();
+    for (stage, reported, accumulated) in [
+        (
+            "high side",
+            report.high_side.total(),
+            dissipated(&["SH", "RH"]),
+        ),
+        (
+            "low side",
+            report.low_side.total(),
+            dissipated(&["SL", "RL"]),
+        ),
+        ("output", report.output_power, dissipated(&["RLOAD"])),
+        ("input", report.input_power, -losses.energy(id("V1"))),
+    ] {
+        let reported = reported * duration;
+        assert!(
+            (reported - accumulated).abs() < 2e-2 * accumulated,
+            "{stage}: {reported:e} J reported, {accumulated:e} J accumulated"
+        );
+    }
+    let total = losses.report(&circuit).dissipated_energy() - dissipated(&["RLOAD"]);
+    assert!(
+        (report.losses() * duration - total).abs() < 2e-2 * total,
+        "{report}"
+    );
+    assert!(
+        report.unaccounted().abs() < 2e-2 * report.input_power,
+        "{report}"
+    );
+    // the high side conducts for 40 % of each period, and so dissipates 40 % of the switch loss.
+    let share = report.high_side.total() / (report.high_side.total() + report.low_side.total());
+    assert!((share - DUTY).abs() < 5e-2, "{report}");
+}
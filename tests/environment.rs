@@ -0,0 +1,75 @@
+//! Components following the ambient temperature of the circuit's `Environment`.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{MOSFETComponentValue, MOSFETDopingType},
+    environment::{Environment, Profile},
+    probe::Probe,
+    CircuitState,
+};
+
+/// `q / k`, K/V.
+const Q_OVER_K: f64 = 1.1604518121550082e4;
+
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.02,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+/// An idle MOSFET with its body diode held 0.58 V forward by a source while the ambient
+/// temperature ramps from -20 to 85 C over 100 ticks: the saturation current its diode current
+/// implies gives the forward voltage at 10 mA, which falls every tick, at the 1.5 to 2.5 mV/K
+/// of a silicon diode.
+///
+/// The relaxation doesn't settle with the diode forward biased through a resistor, so the
+/// forward voltage at a set current comes from the diode law rather than from the circuit.
+#[test]
+fn body_diode_forward_voltage() {
+    const DT: f64 = 1e-6;
+    const V_HELD: f64 = 0.58;
+    const I_F: f64 = 10e-3;
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "drain", -V_HELD)
+        .and_then(|b| b.source("VG", "gnd", "gate", 0.0))
+        .and_then(|b| b.mosfet("M1", MOSFET, "gnd", "gate", "drain"))
+        .unwrap()
+        .build();
+    let (cold, hot) = (253.15, 358.15);
+    circuit.set_environment(Environment {
+        ambient: Profile::Piecewise(vec![(0.0, cold), (100.0 * DT, hot)]),
+    });
+    assert!(circuit.dc_operating_point());
+    // the probe measures the channel current from source to drain, the forward current.
+    let current = Probe::parse("comp:M1.current", &names).unwrap();
+    let forward_voltage = |circuit: &CircuitState| {
+        let temperature = circuit.environment().ambient.at(circuit.now());
+        let n_vt = MOSFET.body_diode_ideality_facotor * temperature / Q_OVER_K;
+        let i_s = current.sample(circuit) / ((V_HELD / n_vt).exp() - 1.0);
+        let expected = MOSFET.body_diode_saturation_current_at(temperature);
+        assert!(
+            (i_s - expected).abs() < 1e-6 * expected,
+            "saturation current {i_s:e} A at {temperature} K, expected {expected:e} A"
+        );
+        n_vt * (I_F / i_s + 1.0).ln()
+    };
+
+    let v_cold = forward_voltage(&circuit);
+    let mut v_f = v_cold;
+    for _ in 0..100 {
+        assert!(circuit.tick(DT));
+        let now = forward_voltage(&circuit);
+        assert!(now < v_f, "forward voltage rose from {v_f} V to {now} V");
+        v_f = now;
+    }
+    let slope = (v_f - v_cold) / (hot - cold);
+    assert!(
+        (-2.5e-3..-1.5e-3).contains(&slope),
+        "{v_cold} V at -20 C, {v_f} V at 85 C: {slope:e} V/K"
+    );
+}
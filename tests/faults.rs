@@ -0,0 +1,116 @@
+//! Faults injected into running circuits: the response while the fault is in, and recovery to
+//! the nominal operating point once it is cleared.
+
+mod common;
+
+use common::build;
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{MOSFETComponentValue, MOSFETDopingType},
+    fault::Fault,
+    probe::Probe,
+    CircuitState,
+};
+
+const DT: f64 = 1e-6;
+
+/// `probe` after `n` ticks of `DT`, failing if one doesn't converge.
+fn settle(circuit: &mut CircuitState, probe: &Probe, n: usize) -> f64 {
+    for _ in 0..n {
+        assert!(
+            circuit.tick(DT),
+            "tick to {:e} s did not converge",
+            circuit.now()
+        );
+    }
+    probe.sample(circuit)
+}
+
+fn assert_close(name: &str, value: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (value - expected).abs() <= tolerance,
+        "{name}: {value:e}, expected {expected:e} (tolerance {tolerance:.1e})"
+    );
+}
+
+/// 10 V across two 1 kohm resistors: the lower one opened lifts the midpoint to the supply,
+/// shorted through 100 ohm pulls it to `10 / 11` V, and cleared either way it returns to 5 V.
+///
+/// The shorts here stay within a few tens of the resistance they bridge, which is as far apart
+/// as the relaxation solver converges on resistors meeting at a net.
+#[test]
+fn divider_open_and_short() {
+    let (mut circuit, names) = build(
+        CircuitBuilder::new()
+            .source("V1", "gnd", "in", 10.0)
+            .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+            .and_then(|b| b.resistor("R2", "out", "gnd", 1e3)),
+    );
+    let out = Probe::parse("net:out", &names).unwrap();
+    let r2 = names.component("R2").unwrap();
+    assert_close("nominal", settle(&mut circuit, &out, 10), 5.0, 1e-6);
+
+    circuit.inject_fault(r2, Fault::Open).unwrap();
+    assert_close("R2 open", settle(&mut circuit, &out, 10), 10.0, 1e-6);
+    assert_eq!(circuit.clear_fault(r2), Some(Fault::Open));
+    assert_close("open cleared", settle(&mut circuit, &out, 10), 5.0, 1e-6);
+
+    circuit
+        .inject_fault(r2, Fault::Short { resistance: 100.0 })
+        .unwrap();
+    assert_close(
+        "R2 shorted",
+        settle(&mut circuit, &out, 10),
+        10.0 / 11.0,
+        1e-6,
+    );
+    assert_eq!(
+        circuit.clear_fault(r2),
+        Some(Fault::Short { resistance: 100.0 })
+    );
+    assert_close("short cleared", settle(&mut circuit, &out, 10), 5.0, 1e-6);
+    assert!(circuit.faults().is_empty());
+}
+
+/// A MOSFET held off under a 12 V bus through 10 ohm, its drain and source shorted through
+/// 1 ohm mid-run: the bus current jumps from leakage to `12 / 11` A, then falls back to
+/// leakage when the short is cleared.
+#[test]
+fn mosfet_drain_source_short() {
+    let mosfet = MOSFETComponentValue {
+        beta: 0.02,
+        ty: MOSFETDopingType::NChannel,
+        body_diode_ideality_facotor: 1.0,
+        body_diode_saturation_current: 1e-12,
+        threshold_voltage: 2.0,
+        saturation_knee: 8.0,
+        multiplicity: 1.0,
+        avalanche: None,
+    };
+    let (mut circuit, names) = build(
+        CircuitBuilder::new()
+            .source("V1", "gnd", "bus", 12.0)
+            .and_then(|b| b.resistor("R1", "bus", "drain", 10.0))
+            .and_then(|b| b.mosfet("M1", mosfet, "gnd", "gnd", "drain")),
+    );
+    let bus_current = Probe::parse("comp:R1.current", &names).unwrap();
+    let m1 = names.component("M1").unwrap();
+    let nominal = settle(&mut circuit, &bus_current, 10);
+    assert!(nominal.abs() < 1e-9, "leakage {nominal:e} A with M1 off");
+
+    let since = circuit.now();
+    circuit
+        .inject_fault(m1, Fault::Short { resistance: 1.0 })
+        .unwrap();
+    assert_eq!(circuit.fault(m1).unwrap().since, since);
+    let shorted = settle(&mut circuit, &bus_current, 10);
+    assert_close("M1 shorted", shorted.abs(), 12.0 / 11.0, 1e-6);
+
+    circuit.clear_fault(m1).unwrap();
+    assert_close(
+        "short cleared",
+        settle(&mut circuit, &bus_current, 10),
+        nominal,
+        1e-9,
+    );
+}
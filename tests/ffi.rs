@@ -0,0 +1,46 @@
+//! `examples/ffi/rc_decay.c` compiled against `include/esc_sim.h` and the shared library, and run.
+
+use std::{path::Path, process::Command};
+
+/// The C example discharges 1 uF from 1 V through 1 kohm in ticks of 1 us over 3 RC, failing
+/// unless every status is `ESC_OK` and the curve stays within 10 mV of `exp(-t / RC)`.
+#[test]
+fn rc_decay() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // the shared library is built next to the test binary, in `target/<profile>/deps`.
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("rc_decay");
+    let compiler = cc::Build::new()
+        .target(env!("ESC_SIM_TARGET"))
+        .host(env!("ESC_SIM_TARGET"))
+        .opt_level(0)
+        .cargo_metadata(false)
+        .cargo_warnings(false)
+        .get_compiler();
+    let status = compiler
+        .to_command()
+        .arg(root.join("examples/ffi/rc_decay.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .args(["-lesc_sim_test", "-lm", "-o"])
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling rc_decay.c: {status}");
+    let output = Command::new(&out).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "rc_decay: {}\n{stdout}{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("largest deviation from exp(-t / RC)"),
+        "{stdout}"
+    );
+}
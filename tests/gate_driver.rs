@@ -0,0 +1,141 @@
+//! The gate driver model switching a MOSFET: edge times from its drive resistances and the gate
+//! capacitance, and its undervoltage lockout.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::{
+        ComponentParameter, GateDriverComponentValue, MOSFETComponentValue, MOSFETDopingType,
+    },
+    probe::Probe,
+    CircuitState, ComponentRef,
+};
+
+const DT: f64 = 1e-9;
+const C_GATE: f64 = 10e-9;
+const DRIVER: GateDriverComponentValue = GateDriverComponentValue {
+    input_threshold: 2.5,
+    propagation_delay: 50e-9,
+    source_resistance: 10.0,
+    sink_resistance: 5.0,
+    uvlo_threshold: 8.0,
+    uvlo_hysteresis: 1.0,
+};
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.5,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+/// `U1` from a 12 V `VCC` driving `M1`, with 10 nF at its gate, switching a 10 ohm load from
+/// 12 V, after the logic input `VIN`.
+fn circuit() -> (CircuitState, NameMap) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("VCC", "gnd", "vdd", 12.0)
+        .and_then(|b| b.source("VIN", "gnd", "in", 0.0))
+        .and_then(|b| b.gate_driver("U1", DRIVER, ["gnd", "vdd", "in", "gate"]))
+        .and_then(|b| b.capacitor("CG", "gate", "gnd", C_GATE))
+        .and_then(|b| b.source("VBUS", "gnd", "bus", 12.0))
+        .and_then(|b| b.resistor("RD", "bus", "drain", 10.0))
+        .and_then(|b| b.mosfet("M1", MOSFET, "gnd", "gate", "drain"))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    (circuit, names)
+}
+
+fn set(circuit: &mut CircuitState, names: &NameMap, source: &str, volts: f64) {
+    *circuit
+        .component_mut(names.component(source).unwrap())
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = volts;
+}
+
+/// Tick `n` times, returning the gate voltage after each.
+fn run(circuit: &mut CircuitState, names: &NameMap, n: usize) -> Vec<f64> {
+    let gate = Probe::parse("net:gate", names).unwrap();
+    (0..n)
+        .map(|_| {
+            assert!(circuit.tick(DT), "no convergence at {:e} s", circuit.now());
+            gate.sample(circuit)
+        })
+        .collect()
+}
+
+/// The time into `samples` (one per tick, the first at the end of the first) at which they
+/// first cross `level`, interpolated within the tick.
+fn crossing(samples: &[f64], level: f64) -> f64 {
+    let above = samples[0] > level;
+    let i = samples
+        .iter()
+        .position(|&v| (v > level) != above)
+        .unwrap_or_else(|| panic!("never crosses {level} V"));
+    let (a, b) = (samples[i - 1], samples[i]);
+    (i as f64 + (level - a) / (b - a)) * DT
+}
+
+/// Rising and falling edges at the gate: each starts 50 ns after the input, and goes from
+/// 10 % to 90 % of the 12 V swing in `ln(9) R C` of the source and sink resistance, 219.7 ns and
+/// 109.9 ns.
+#[test]
+fn edge_times() {
+    let (mut circuit, names) = circuit();
+    for (input, r) in [
+        (5.0, DRIVER.source_resistance),
+        (0.0, DRIVER.sink_resistance),
+    ] {
+        set(&mut circuit, &names, "VIN", input);
+        let samples = run(&mut circuit, &names, 1000);
+        let rising = input > 0.0;
+        // the gate leaves its rail at the end of the delay.
+        let delay = crossing(&samples, if rising { 1e-3 } else { 12.0 - 1e-3 });
+        let (from, to) = if rising { (1.2, 10.8) } else { (10.8, 1.2) };
+        let edge = crossing(&samples, to) - crossing(&samples, from);
+        assert!(
+            (delay - DRIVER.propagation_delay).abs() <= 2.0 * DT,
+            "edge starts {delay:e} s after the input"
+        );
+        let expected = 9f64.ln() * r * C_GATE;
+        assert!(
+            (edge - expected).abs() < 2e-2 * expected,
+            "10-90 % in {edge:e} s, expected {expected:e} s"
+        );
+    }
+}
+
+/// With `M1` on, `VCC` sagging to 6 V locks the driver out: the gate discharges and `M1` stops
+/// conducting. Back at 12 V, the lockout releases and the current returns.
+#[test]
+fn undervoltage_lockout() {
+    let (mut circuit, names) = circuit();
+    let current = Probe::parse("comp:RD.current", &names).unwrap();
+    let u1 = names.component("U1").unwrap();
+    let locked_out = |circuit: &CircuitState| match circuit.component(u1) {
+        ComponentRef::GateDriver(v) => v.locked_out,
+        _ => unreachable!(),
+    };
+    set(&mut circuit, &names, "VIN", 5.0);
+    run(&mut circuit, &names, 1000);
+    let on = current.sample(&circuit);
+    assert!(on > 1.0, "{on} A with the gate driven");
+
+    set(&mut circuit, &names, "VCC", 6.0);
+    let gate = run(&mut circuit, &names, 1000);
+    assert!(locked_out(&circuit));
+    assert!(
+        gate[gate.len() - 1] < 1e-3,
+        "gate at {} V",
+        gate[gate.len() - 1]
+    );
+    let off = current.sample(&circuit);
+    assert!(off.abs() < 1e-6, "{off} A locked out");
+
+    set(&mut circuit, &names, "VCC", 12.0);
+    run(&mut circuit, &names, 1000);
+    assert!(!locked_out(&circuit));
+    assert!((current.sample(&circuit) - on).abs() < 1e-6 * on);
+}
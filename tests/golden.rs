@@ -0,0 +1,272 @@
+//! Golden-waveform regression tests: named scenarios are recorded and compared against the CSVs
+//! under `tests/golden/` through `sim::golden`.
+//!
+//!     cargo test --release --test golden
+//!     UPDATE_GOLDEN=1 cargo test --release --test golden    # rewrite the goldens
+//!
+//! A scenario fails if its waveform leaves its tolerance band, reporting the channel and time of
+//! the worst deviation.
+
+use std::path::Path;
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::ComponentParameter,
+    components::{LinearComponentValue, MOSFETComponentValue, MOSFETDopingType},
+    golden::{check, GoldenOutcome},
+    mosfet_rl_test_circuit,
+    probe::{Probe, Recording},
+    rc_test_circuit,
+    waveform::Tolerance,
+    CircuitState, ComponentValueEnum,
+};
+
+struct Scenario {
+    name: &'static str,
+    circuit: CircuitState,
+    /// `(label, probe spec)` per channel.
+    probes: Vec<(String, Probe)>,
+    dt: f64,
+    n_steps: usize,
+    /// Ticks per recorded row.
+    every: usize,
+    tolerance: fn(&str) -> Tolerance,
+}
+
+#[test]
+fn rc_step() {
+    check_scenario(rc_step_scenario());
+}
+
+#[test]
+fn rlc_ring() {
+    check_scenario(rlc_ring_scenario());
+}
+
+#[test]
+fn mosfet_rl() {
+    check_scenario(mosfet_rl_scenario());
+}
+
+#[test]
+fn rc_test_prepool() {
+    check_scenario(rc_test_prepool_scenario());
+}
+
+#[test]
+fn mosfet_mixed_prepool() {
+    check_scenario(mosfet_mixed_prepool_scenario());
+}
+
+/// Run `scenario` and compare it against its golden file, or rewrite that under
+/// `UPDATE_GOLDEN=1`.
+fn check_scenario(scenario: Scenario) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let name = scenario.name;
+    let tolerance = scenario.tolerance;
+    match check(&dir, name, &run(scenario), tolerance) {
+        Ok(GoldenOutcome::Matched(comparisons)) => {
+            let worst = comparisons
+                .iter()
+                .max_by(|(_, a), (_, b)| a.max_deviation.total_cmp(&b.max_deviation));
+            if let Some((channel, c)) = worst {
+                println!(
+                    "{name}: worst {channel} {:.3e} at t = {:.3e} s",
+                    c.max_deviation, c.at
+                );
+            }
+        }
+        Ok(GoldenOutcome::Updated(path)) => println!("wrote {}", path.display()),
+        Err(err) => panic!("{name}: {err}"),
+    }
+}
+
+fn run(mut scenario: Scenario) -> Recording {
+    let mut recording = Recording::new(scenario.probes);
+    let circuit = &mut scenario.circuit;
+    circuit.solve_state();
+    recording.record(circuit, circuit.now());
+    for step in 1..=scenario.n_steps {
+        circuit.tick(scenario.dt);
+        if step % scenario.every == 0 {
+            recording.record(circuit, circuit.now());
+        }
+    }
+    recording
+}
+
+fn probes(names: &esc_sim_test::sim::builder::NameMap, specs: &[&str]) -> Vec<(String, Probe)> {
+    specs
+        .iter()
+        .map(|&spec| (spec.to_string(), Probe::parse(spec, names).unwrap()))
+        .collect()
+}
+
+/// 1 V into 1 kohm and 1 uF from rest, over 5 RC.
+fn rc_step_scenario() -> Scenario {
+    let (circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 1.0)
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.capacitor("C1", "gnd", "out", 1e-6))
+        .unwrap()
+        .build();
+    Scenario {
+        name: "rc_step",
+        probes: probes(&names, &["net:out", "comp:R1.current"]),
+        circuit,
+        dt: 1e-6,
+        n_steps: 5000,
+        every: 50,
+        tolerance: |_| Tolerance {
+            absolute: 1e-9,
+            relative: 1e-6,
+        },
+    }
+}
+
+/// Series 10 ohm, 1 mH, 1 uF with the capacitor at 1 V, over 10 periods of the ring.
+fn rlc_ring_scenario() -> Scenario {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .capacitor("C1", "gnd", "top", 1e-6)
+        .and_then(|b| b.resistor("R1", "top", "mid", 10.0))
+        .and_then(|b| b.inductor("L1", "mid", "gnd", 1e-3))
+        .unwrap()
+        .build();
+    circuit
+        .set_initial_capacitor_voltage(names.component("C1").unwrap(), 1.0)
+        .unwrap();
+    Scenario {
+        name: "rlc_ring",
+        probes: probes(&names, &["net:top", "comp:L1.current"]),
+        circuit,
+        dt: 1e-7,
+        n_steps: 20_000,
+        every: 100,
+        tolerance: |_| Tolerance {
+            absolute: 1e-9,
+            relative: 1e-6,
+        },
+    }
+}
+
+/// `mosfet_rl_test_circuit` with the gate driven to 10 V from the start; the current settles at
+/// 0.6 A.
+fn mosfet_rl_scenario() -> Scenario {
+    let (mut circuit, names) = mosfet_rl_test_circuit();
+    if let Some(v) = circuit
+        .component_mut(names.component("Vg").unwrap())
+        .parameter_mut(ComponentParameter::Value)
+    {
+        *v = 10.0;
+    }
+    Scenario {
+        name: "mosfet_rl",
+        probes: probes(&names, &["net:drain", "comp:L1.current"]),
+        circuit,
+        dt: 1e-6,
+        n_steps: 1000,
+        every: 10,
+        // the MOSFET is solved iteratively, so allow for the convergence threshold
+        tolerance: |_| Tolerance {
+            absolute: 1e-6,
+            relative: 1e-4,
+        },
+    }
+}
+
+/// `rc_test_circuit` over 20 ms in ticks of 10 us, against the waveform recorded before the
+/// components moved into per-kind pools (9cc6346, with its MOSFET tick indexing fixed so it
+/// builds). The pools reproduced it bit for bit; the solver changes since move it by 4e-13 V,
+/// within the convergence threshold of the 10 V tanks.
+fn rc_test_prepool_scenario() -> Scenario {
+    let (circuit, names) = rc_test_circuit();
+    Scenario {
+        name: "rc_test_prepool",
+        probes: probes(
+            &names,
+            &[
+                "comp:C1.voltage",
+                "comp:L1.current",
+                "comp:C2.voltage",
+                "comp:L3.current",
+            ],
+        ),
+        circuit,
+        dt: 1e-5,
+        n_steps: 2000,
+        every: 10,
+        tolerance: |_| Tolerance {
+            absolute: 1e-12,
+            relative: 0.0,
+        },
+    }
+}
+
+/// A MOSFET declared ahead of the linear components around it, its gate held on at 10 V by 1 nF
+/// charged through 1 kohm, drawing from 12 V through a 10 ohm / 1 uF filter into its 10 ohm load,
+/// over 100 us in ticks of 100 ns; against the waveform recorded before the pools, as
+/// `rc_test_prepool_scenario`.
+///
+/// The pools moved it by up to 3e-12 V, the order the components are stamped in changing where
+/// the iterations stop within the convergence threshold. Since then the gate, which only the
+/// threshold's leftover current moves, drifts by up to 2e-9 V over the run, and the rest by
+/// 1.3e-10 V and 3e-12 A. The gate stays on because a turn-on from 0 V recorded then isn't
+/// reproduced any more, now that a MOSFET in cutoff updates its `v_gs_positive`.
+fn mosfet_mixed_prepool_scenario() -> Scenario {
+    use LinearComponentValue::*;
+    let mosfet = MOSFETComponentValue {
+        beta: 0.1,
+        ty: MOSFETDopingType::NChannel,
+        body_diode_ideality_facotor: 1.0,
+        body_diode_saturation_current: 1e-12,
+        threshold_voltage: 2.0,
+        saturation_knee: f64::INFINITY,
+        multiplicity: 1.0,
+        avalanche: None,
+    };
+    let mut builder = CircuitBuilder::new();
+    builder
+        .mosfet("M1", mosfet, "gnd", "gate", "drain")
+        .unwrap();
+    for (name, value, a, b) in [
+        ("V1", Source(12.0), "gnd", "vdd"),
+        ("VG", Source(10.0), "gnd", "drive"),
+        ("RG", Resistive(1e3), "drive", "gate"),
+        ("CG", Capacitive(1e-9), "gate", "gnd"),
+        ("RF", Resistive(10.0), "vdd", "out"),
+        ("CF", Capacitive(1e-6), "out", "gnd"),
+        ("RL", Resistive(10.0), "out", "drain"),
+        ("RB", Resistive(100.0), "out", "gnd"),
+    ] {
+        builder
+            .component(name, ComponentValueEnum::Linear(value), &[a, b])
+            .unwrap();
+    }
+    let (mut circuit, names) = builder.build();
+    let cg = names.component("CG").unwrap();
+    circuit.set_initial_capacitor_voltage(cg, -10.0).unwrap();
+    Scenario {
+        name: "mosfet_mixed_prepool",
+        probes: probes(
+            &names,
+            &[
+                "comp:CG.voltage",
+                "comp:CF.voltage",
+                "comp:RL.voltage",
+                "comp:M1.current",
+            ],
+        ),
+        circuit,
+        dt: 100e-9,
+        n_steps: 1000,
+        every: 10,
+        tolerance: |label| Tolerance {
+            absolute: match label {
+                "comp:CG.voltage" => 1e-8,
+                "comp:M1.current" => 1e-11,
+                _ => 1e-9,
+            },
+            relative: 0.0,
+        },
+    }
+}
@@ -0,0 +1,66 @@
+//! `CircuitState::measure_impedance` and `measure_thevenin` against hand-computed values.
+
+mod common;
+
+use std::f64::consts::TAU;
+
+use common::build;
+use esc_sim_test::{linalg::Complex, sim::builder::CircuitBuilder};
+
+/// 10 V across 3 kohm over 1 kohm: 3 k || 1 k = 750 ohm, resistive at every frequency, between
+/// the tap and ground, and a Thevenin equivalent of 2.5 V behind it.
+#[test]
+fn divider() {
+    let (circuit, names) = build(
+        CircuitBuilder::new()
+            .source("V1", "gnd", "in", 10.0)
+            .and_then(|b| b.resistor("R1", "in", "out", 3e3))
+            .and_then(|b| b.resistor("R2", "out", "gnd", 1e3)),
+    );
+    let [gnd, out] = ["gnd", "out"].map(|net| names.net(net).unwrap());
+    for frequency in [0.0, 50.0, 1e3, 1e6] {
+        let z = circuit.measure_impedance(out, gnd, frequency).unwrap();
+        assert!(
+            (z - Complex::new(750.0, 0.0)).abs() < 1e-9,
+            "{frequency} Hz: {z:?} ohm"
+        );
+    }
+    let thevenin = circuit.measure_thevenin(out, gnd).unwrap();
+    assert!(
+        (thevenin.open_circuit_voltage - 2.5).abs() < 1e-9
+            && (thevenin.resistance - 750.0).abs() < 1e-6,
+        "{thevenin:?}"
+    );
+}
+
+/// 1 kohm into 1 uF: `R / (1 + j w R C)` across the capacitor, from well below the 159 Hz
+/// corner to well above it, and at DC a Thevenin equivalent of the source behind the resistor.
+/// Nets not joined by any component have no impedance between them.
+#[test]
+fn rc_network() {
+    const R: f64 = 1e3;
+    const C: f64 = 1e-6;
+    let (circuit, names) = build(
+        CircuitBuilder::new()
+            .source("V1", "gnd", "in", 5.0)
+            .and_then(|b| b.resistor("R1", "in", "out", R))
+            .and_then(|b| b.capacitor("C1", "out", "gnd", C))
+            .and_then(|b| b.resistor("R2", "x", "y", R)),
+    );
+    let [gnd, out, x] = ["gnd", "out", "x"].map(|net| names.net(net).unwrap());
+    for frequency in [10.0, 1.0 / (TAU * R * C), 1e3, 1e4] {
+        let z = circuit.measure_impedance(out, gnd, frequency).unwrap();
+        let expected = Complex::new(R, 0.0) / Complex::new(1.0, TAU * frequency * R * C);
+        assert!(
+            (z - expected).abs() < 1e-9 * R,
+            "{frequency} Hz: {z:?} ohm, expected {expected:?}"
+        );
+    }
+    assert!(circuit.measure_impedance(out, x, 1e3).is_none());
+    let thevenin = circuit.measure_thevenin(out, gnd).unwrap();
+    assert!(
+        (thevenin.open_circuit_voltage - 5.0).abs() < 1e-9
+            && (thevenin.resistance - R).abs() < 1e-6,
+        "{thevenin:?}"
+    );
+}
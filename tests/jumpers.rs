@@ -0,0 +1,76 @@
+//! `Jumper`s, solved as zero-volt branches or merged away by `CircuitBuilder::build_collapsed`.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    probe::Probe,
+    CircuitState,
+};
+
+const STAGES: usize = 50;
+
+/// A ladder of 50 stages from a 10 V `V1`, each a 100 ohm series resistor `RS<k>` from `a<k>`
+/// to `b<k>`, a jumper `J<k>` on to `a<k+1>` and a 1 kohm `RG<k>` from there to ground.
+fn ladder() -> CircuitBuilder {
+    let mut builder = CircuitBuilder::new();
+    builder.source("V1", "gnd", "a0", 10.0).unwrap();
+    for k in 0..STAGES {
+        let [from, tap, to] = [format!("a{k}"), format!("b{k}"), format!("a{}", k + 1)];
+        builder
+            .resistor(&format!("RS{k}"), &from, &tap, 100.0)
+            .and_then(|b| b.jumper(&format!("J{k}"), &tap, &to))
+            .and_then(|b| b.resistor(&format!("RG{k}"), &to, "gnd", 1e3))
+            .unwrap();
+    }
+    builder
+}
+
+fn solved((mut circuit, names): (CircuitState, NameMap)) -> (CircuitState, NameMap) {
+    assert!(circuit.solve_state());
+    (circuit, names)
+}
+
+/// The ladder solved as built and with every jumper but `J10` collapsed: each named net at the
+/// same voltage, `J10` carrying the same current, and the collapsed circuit 49 nets and 49
+/// components smaller.
+#[test]
+fn collapsed_ladder() {
+    let (full, full_names) = solved(ladder().build());
+    let (collapsed, collapsed_names) = solved(ladder().build_collapsed(&["J10"]));
+
+    let [stats, collapsed_stats] = [full.stats(), collapsed.stats()];
+    assert_eq!(stats.n_jumpers, STAGES);
+    assert_eq!(collapsed_stats.n_jumpers, 1);
+    assert_eq!(collapsed_stats.n_nets, stats.n_nets - (STAGES - 1));
+    assert_eq!(
+        collapsed_stats.n_components,
+        stats.n_components - (STAGES - 1)
+    );
+
+    for k in 0..=STAGES {
+        let names = [format!("a{k}")]
+            .into_iter()
+            .chain((k < STAGES).then(|| format!("b{k}")));
+        for net in names {
+            let spec = format!("net:{net}");
+            let v = Probe::parse(&spec, &full_names).unwrap().sample(&full);
+            let v_collapsed = Probe::parse(&spec, &collapsed_names)
+                .unwrap()
+                .sample(&collapsed);
+            assert!(
+                (v - v_collapsed).abs() < 1e-9,
+                "{net}: {v} V, collapsed {v_collapsed} V"
+            );
+        }
+    }
+    let i = Probe::parse("comp:J10.current", &full_names)
+        .unwrap()
+        .sample(&full);
+    let i_collapsed = Probe::parse("comp:J10.current", &collapsed_names)
+        .unwrap()
+        .sample(&collapsed);
+    assert!(i.abs() > 1e-6);
+    assert!(
+        (i - i_collapsed).abs() < 1e-12,
+        "{i} A, collapsed {i_collapsed} A"
+    );
+}
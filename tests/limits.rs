@@ -0,0 +1,60 @@
+//! `SolverConfig::limits` on a solve that starts far from its solution.
+
+use esc_sim_test::sim::{
+    components::ComponentParameter,
+    mosfet_test_circuit,
+    solver::{PerturbationLimits, SolveReport, SolverConfig},
+};
+
+/// `mosfet_test_circuit` settled at 5 V and re-solved with the supply at `supply`, with or
+/// without 1 V and 1 A limits.
+fn resolve(supply: f64, limited: bool) -> SolveReport {
+    let (mut circuit, names) = mosfet_test_circuit();
+    assert!(circuit.dc_operating_point());
+    if limited {
+        circuit.set_solver_config(SolverConfig {
+            limits: PerturbationLimits {
+                max_voltage_step: Some(1.0),
+                max_current: Some(1.0),
+            },
+            ..Default::default()
+        });
+    }
+    let v1 = names.component("V1").unwrap();
+    *circuit
+        .component_mut(v1)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = supply;
+    circuit.solve_state();
+    circuit.last_solve_report()
+}
+
+/// The supply stepped from 5 V to -500 V and 500 V, a volt-scale guess against the kilo-amp
+/// currents of the leaky body diode. Without limits the solve runs to its iteration cap, or stops
+/// at 1000 iterations with nets hundreds of megaamps out of balance; with them it settles, in
+/// fewer iterations, and the limiter engaged.
+///
+/// A 50 V step doesn't settle with these limits either.
+#[test]
+fn body_diode_step() {
+    for supply in [-500.0, 500.0] {
+        let free = resolve(supply, false);
+        let limited = resolve(supply, true);
+        assert_eq!(free.limited, 0);
+        assert!(
+            !free.converged || free.residual > 1.0,
+            "{free:?} at {supply} V without limits"
+        );
+        assert!(
+            limited.converged && limited.residual < 1e-12,
+            "{limited:?} at {supply} V with limits"
+        );
+        assert!(
+            limited.iterations < free.iterations,
+            "{} iterations at {supply} V with limits, {} without",
+            limited.iterations,
+            free.iterations
+        );
+        assert!(limited.limited > 0, "limits never engaged at {supply} V");
+    }
+}
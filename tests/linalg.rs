@@ -0,0 +1,377 @@
+//! Solves of `linalg` checked against products, closed forms and each other.
+
+use std::f64::consts::PI;
+
+use esc_sim_test::{
+    linalg::{
+        banded::BandedMat,
+        sparse::{CsrMat, IterativeConfig, Preconditioner},
+        Complex, Field, Mat,
+    },
+    sim::{
+        builder::CircuitBuilder,
+        components::{MOSFETComponentValue, MOSFETDopingType},
+        solver::{LinearSolver, SolverConfig},
+    },
+};
+
+type C = Complex<f64>;
+
+/// Largest `|a - b|` over the entries of two matrices of the same shape.
+fn max_difference<T: Field>(a: &Mat<T>, b: &Mat<T>) -> f64 {
+    assert_eq!([a.n_rows(), a.n_cols()], [b.n_rows(), b.n_cols()]);
+    (0..a.n_rows())
+        .flat_map(|i| (0..a.n_cols()).map(move |j| [i, j]))
+        .map(|index| (a[index] - b[index]).magnitude())
+        .fold(0.0, f64::max)
+}
+
+/// A 3x3 complex matrix and its inverse multiply to the identity, both ways round.
+#[test]
+fn complex_inverse() {
+    let a = Mat::new([
+        [C::new(2.0, 1.0), C::new(0.0, -1.0), C::new(1.0, 0.0)],
+        [C::new(1.0, 1.0), C::new(3.0, 0.0), C::new(0.0, 2.0)],
+        [C::new(0.0, 0.0), C::new(-1.0, 0.5), C::new(4.0, -1.0)],
+    ]);
+    let inverse = a.try_inverse().unwrap();
+    let identity = Mat::identity(3);
+    assert!(max_difference(&a.matmul(&inverse), &identity) < 1e-12);
+    assert!(max_difference(&inverse.matmul(&a), &identity) < 1e-12);
+}
+
+/// Singular matrices, real and complex, have no inverse.
+#[test]
+fn singular_inverse() {
+    // the second row is twice the first
+    let real = Mat::new([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 5.0]]);
+    assert!(real.try_inverse().is_none());
+    // the second row is `i` times the first
+    let complex = Mat::new([
+        [C::new(1.0, 0.0), C::new(0.0, 2.0)],
+        [C::new(0.0, 1.0), C::new(-2.0, 0.0)],
+    ]);
+    assert!(complex.try_inverse().is_none());
+}
+
+/// `lstsq` fits the line `a + b x` through `(0, 0)`, `(1, 1)` and `(2, 1)` with the `a = 1/6`,
+/// `b = 1/2` of the normal equations, recovers a line through points on it exactly, one column
+/// of the right-hand side at a time, and finds nothing for linearly dependent columns.
+#[test]
+fn least_squares() {
+    let line = Mat::new([[1.0, 0.0], [1.0, 1.0], [1.0, 2.0]]);
+    let x = line.lstsq(&Mat::new([[0.0], [1.0], [1.0]])).unwrap();
+    assert!(max_difference(&x, &Mat::new([[1.0 / 6.0], [0.5]])) < 1e-12);
+
+    let on_lines = Mat::new([[2.0, -1.0], [5.0, -1.5], [8.0, -2.0]]);
+    let x = line.lstsq(&on_lines).unwrap();
+    assert!(max_difference(&x, &Mat::new([[2.0, -1.0], [3.0, -0.5]])) < 1e-12);
+
+    let dependent = Mat::new([[1.0, 2.0], [1.0, 2.0], [1.0, 2.0]]);
+    assert!(dependent.lstsq(&Mat::new([[0.0], [1.0], [1.0]])).is_none());
+}
+
+/// A two-node RC ladder driven by 1 V: `R1` from the input to `a`, `C1` from `a` to ground,
+/// `R2` from `a` to `b` and `C2` from `b` to ground. Solving its nodal admittance system gives
+/// `V_b = 1 / (1 + s (R1 C1 + R1 C2 + R2 C2) + s^2 R1 R2 C1 C2)` at `s = j 2 pi f`.
+#[test]
+fn complex_rc_divider() {
+    let (r1, c1, r2, c2) = (1e3, 100e-9, 10e3, 10e-9);
+    for frequency in [10.0, 159.0, 1e3, 10e3, 1e6] {
+        let s = C::new(0.0, 2.0 * PI * frequency);
+        let [g1, g2] = [r1, r2].map(|r| C::new(1.0 / r, 0.0));
+        let [y1, y2] = [c1, c2].map(|c| s * C::new(c, 0.0));
+        let admittance = Mat::new([
+            [g1 + g2 + y1, C::new(0.0, 0.0) - g2],
+            [C::new(0.0, 0.0) - g2, g2 + y2],
+        ]);
+        // the input as a Norton source into `a`
+        let injected = Mat::new([[g1], [C::new(0.0, 0.0)]]);
+        let v_b = admittance.solve(&injected).unwrap()[[1, 0]];
+
+        let one = C::new(1.0, 0.0);
+        let expected = one
+            / (one
+                + s * C::new(r1 * c1 + r1 * c2 + r2 * c2, 0.0)
+                + s * s * C::new(r1 * r2 * c1 * c2, 0.0));
+        assert!(
+            (v_b - expected).abs() < 1e-12,
+            "{frequency} Hz: {v_b:?}, expected {expected:?}"
+        );
+        // the phase through `from_polar`, the magnitude through `abs`
+        let polar = C::from_polar(expected.abs(), expected.arg());
+        assert!((polar - expected).abs() < 1e-12, "{polar:?}");
+    }
+}
+
+/// Nodal matrix of an `n`-node RC ladder at one backward-Euler step (10 ohm sections, 1 uF to
+/// ground per node at dt = 100 ns, driven through 1 ohm at the first node), as the solver bench
+/// builds it, and a right-hand side.
+fn rc_ladder_system(n: usize) -> (Mat<f64>, Mat<f64>) {
+    let (g, c_dt) = (0.1, 10.0);
+    let mut a = Mat::zeros(n, n);
+    let mut b = Mat::zeros(n, 1);
+    for i in 0..n {
+        a[[i, i]] += c_dt;
+        b[[i, 0]] = (i as f64 * 0.01).sin();
+        if i + 1 < n {
+            a[[i, i]] += g;
+            a[[i + 1, i + 1]] += g;
+            a[[i, i + 1]] -= g;
+            a[[i + 1, i]] -= g;
+        }
+    }
+    a[[0, 0]] += 1.0;
+    b[[0, 0]] += 5.0;
+    (a, b)
+}
+
+/// The banded LU and the Thomas algorithm on RC ladders: at 200 nodes both match the dense
+/// solve within 1e-9; at 2000, where the dense one takes too long, they match each other and
+/// leave a residual within 1e-9.
+#[test]
+fn banded_rc_ladder() {
+    let (a, b) = rc_ladder_system(200);
+    let banded = BandedMat::from_mat(&a, 0.0).unwrap();
+    assert_eq!([banded.lower(), banded.upper()], [1, 1]);
+    let dense = a.solve(&b).unwrap();
+    for x in [
+        banded.solve(&b).unwrap(),
+        banded.solve_tridiagonal(&b).unwrap(),
+    ] {
+        assert!(max_difference(&x, &dense) < 1e-9);
+    }
+
+    let (a, b) = rc_ladder_system(2000);
+    let banded = BandedMat::from_mat(&a, 0.0).unwrap();
+    let lu = banded.solve(&b).unwrap();
+    let thomas = banded.solve_tridiagonal(&b).unwrap();
+    assert!(max_difference(&lu, &thomas) < 1e-9);
+    assert!(max_difference(&a.matmul(&lu), &b) < 1e-9);
+}
+
+/// Conductance matrix of a `side` by `side` grid of 1 ohm resistors with its corner grounded
+/// through 1 ohm, and, for `transconductance` nonzero, a voltage-controlled current source
+/// from every node into the next one along the grid of that gain, as triplets; and 1 A into the
+/// far corner.
+fn grid_system(side: usize, transconductance: f64) -> (usize, Vec<(usize, usize, f64)>, Vec<f64>) {
+    let n = side * side;
+    let mut triplets = vec![(0, 0, 1.0)];
+    let mut conductance = |a: usize, b: usize| {
+        triplets.extend([(a, a, 1.0), (b, b, 1.0), (a, b, -1.0), (b, a, -1.0)]);
+    };
+    for row in 0..side {
+        for col in 0..side {
+            let node = row * side + col;
+            if col + 1 < side {
+                conductance(node, node + 1);
+            }
+            if row + 1 < side {
+                conductance(node, node + side);
+            }
+        }
+    }
+    if transconductance != 0.0 {
+        // `g V(i)` out of node `i + 1`.
+        triplets.extend((0..n - 1).map(|i| (i + 1, i, transconductance)));
+    }
+    let mut b = vec![0.0; n];
+    b[n - 1] = 1.0;
+    (n, triplets, b)
+}
+
+/// `x` against `Mat::solve` of the same system, relative to the largest entry of the latter.
+fn difference_from_dense(n: usize, triplets: &[(usize, usize, f64)], b: &[f64], x: &[f64]) -> f64 {
+    let a = CsrMat::from_triplets(n, n, triplets.iter().copied()).to_mat();
+    let mut rhs = Mat::zeros(n, 1);
+    for (i, &b) in b.iter().enumerate() {
+        rhs[[i, 0]] = b;
+    }
+    let dense = a.solve(&rhs).unwrap();
+    let scale = (0..n).map(|i| dense[[i, 0]].abs()).fold(0.0, f64::max);
+    (0..n)
+        .map(|i| (x[i] - dense[[i, 0]]).abs())
+        .fold(0.0, f64::max)
+        / scale
+}
+
+/// Conjugate gradients with each preconditioner solve the 20x20 resistor grid to a relative
+/// residual of 1e-10, matching the dense solve.
+#[test]
+fn cg_resistor_grid() {
+    let (n, triplets, b) = grid_system(20, 0.0);
+    let a = CsrMat::from_triplets(n, n, triplets.iter().copied());
+    for preconditioner in [
+        Preconditioner::None,
+        Preconditioner::Jacobi,
+        Preconditioner::Ilu0,
+    ] {
+        let config = IterativeConfig {
+            tolerance: 1e-10,
+            max_iterations: 1000,
+            preconditioner,
+        };
+        let (x, report) = a.solve_cg(&b, &config);
+        assert!(report.converged(&config), "{preconditioner:?}: {report:?}");
+        let difference = difference_from_dense(n, &triplets, &b, &x);
+        assert!(difference < 1e-8, "{preconditioner:?}: {difference:e}");
+    }
+}
+
+/// BiCGSTAB with ILU(0) solves the grid with a controlled source along it, which makes it
+/// nonsymmetric, matching the dense solve.
+#[test]
+fn bicgstab_controlled_sources() {
+    let (n, triplets, b) = grid_system(20, 0.5);
+    let a = CsrMat::from_triplets(n, n, triplets.iter().copied());
+    assert_ne!(a.get(1, 0), a.get(0, 1));
+    let config = IterativeConfig::default();
+    let (x, report) = a.solve_bicgstab(&b, &config);
+    assert!(report.converged(&config), "{report:?}");
+    let difference = difference_from_dense(n, &triplets, &b, &x);
+    assert!(difference < 1e-8, "{difference:e}");
+}
+
+/// The AC analysis of a common-source stage, with `SolverConfig::linear_solver` iterative,
+/// matches the direct one from 10 Hz to 10 MHz: the MOSFET's transconductance makes the MNA
+/// system nonsymmetric, as well as complex.
+#[test]
+fn iterative_ac_analysis() {
+    const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+        beta: 0.02,
+        ty: MOSFETDopingType::NChannel,
+        body_diode_ideality_facotor: 1.0,
+        body_diode_saturation_current: 1e-12,
+        threshold_voltage: 2.0,
+        saturation_knee: 8.0,
+        multiplicity: 1.0,
+        avalanche: None,
+    };
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("VDD", "gnd", "vdd", 12.0)
+        .and_then(|b| b.source("VIN", "gnd", "gate", 3.0))
+        .and_then(|b| b.resistor("RD", "vdd", "drain", 1e3))
+        .and_then(|b| b.capacitor("CGD", "gate", "drain", 1e-9))
+        .and_then(|b| b.capacitor("CL", "drain", "gnd", 10e-9))
+        .and_then(|b| b.mosfet("M1", MOSFET, "gnd", "gate", "drain"))
+        .unwrap()
+        .build();
+    assert!(circuit.dc_operating_point());
+    let input = names.component("VIN").unwrap();
+    let output = [names.net("gnd").unwrap(), names.net("drain").unwrap()];
+    let frequencies = (0..=12)
+        .map(|k| 10f64.powf(1.0 + k as f64 / 2.0))
+        .collect::<Vec<_>>();
+
+    let direct = circuit.ac_analysis(input, output, &frequencies).unwrap();
+    circuit.set_solver_config(SolverConfig {
+        linear_solver: LinearSolver::Iterative(IterativeConfig::default()),
+        ..*circuit.solver_config()
+    });
+    let iterative = circuit.ac_analysis(input, output, &frequencies).unwrap();
+    assert!(direct[0].magnitude > 1.0, "gain {}", direct[0].magnitude);
+    for (direct, iterative) in direct.iter().zip(&iterative) {
+        assert!(
+            (direct.response - iterative.response).abs() < 1e-9 * direct.magnitude,
+            "{} Hz: {:?} direct, {:?} iterative",
+            direct.frequency,
+            direct.response,
+            iterative.response
+        );
+    }
+}
+
+/// The 5x5 matrix with `10 i + j` at `[i, j]`.
+fn numbered() -> Mat<f64> {
+    let mut a = Mat::zeros(5, 5);
+    for i in 0..5 {
+        for j in 0..5 {
+            a[[i, j]] = (10 * i + j) as f64;
+        }
+    }
+    a
+}
+
+/// A view of the interior of a 5x5 matrix reads the entries one in from each edge, and writing
+/// through a mutable one changes those and no others; the two halves of a split are written at
+/// once, and `copy_from` fills a block from another.
+#[test]
+fn interior_view() {
+    let mut a = numbered();
+    let interior = a.view(1..4, 1..4);
+    assert_eq!([interior.n_rows(), interior.n_cols()], [3, 3]);
+    for i in 0..3 {
+        for j in 0..3 {
+            assert_eq!(interior[[i, j]], (10 * (i + 1) + j + 1) as f64);
+        }
+    }
+    assert_eq!(interior.col(2), [13.0, 23.0, 33.0]);
+    let inner = interior.view(1..3, 0..2).to_mat();
+    assert_eq!(max_difference(&inner, &a.view(2..4, 1..3).to_mat()), 0.0);
+
+    let mut interior = a.view_mut(1..4, 1..4);
+    interior[[0, 0]] = -1.0;
+    interior.col_mut(2)[1] = -2.0;
+    interior.reborrow().into_view(2..3, 0..3).fill(-3.0);
+    for i in 0..5 {
+        for j in 0..5 {
+            let expected = match [i, j] {
+                [1, 1] => -1.0,
+                [2, 3] => -2.0,
+                [3, 1..=3] => -3.0,
+                _ => (10 * i + j) as f64,
+            };
+            assert_eq!(a[[i, j]], expected, "[{i}, {j}]");
+        }
+    }
+
+    let mut a = numbered();
+    let (mut left, mut right) = a.split_at_col_mut(2);
+    left.fill(0.0);
+    right.reborrow().into_view(0..5, 2..3).fill(1.0);
+    assert_eq!([left.n_cols(), right.n_cols()], [2, 3]);
+    let expected = Mat::new([
+        [0.0, 0.0, 2.0, 3.0, 1.0],
+        [0.0, 0.0, 12.0, 13.0, 1.0],
+        [0.0, 0.0, 22.0, 23.0, 1.0],
+        [0.0, 0.0, 32.0, 33.0, 1.0],
+        [0.0, 0.0, 42.0, 43.0, 1.0],
+    ]);
+    assert_eq!(max_difference(&a, &expected), 0.0);
+
+    let mut a = numbered();
+    let source = numbered();
+    a.view_mut(0..2, 3..5).copy_from(&source.view(3..5, 0..2));
+    let copied = a.view(0..2, 3..5).to_mat();
+    assert_eq!(
+        max_difference(&copied, &Mat::new([[30.0, 31.0], [40.0, 41.0]])),
+        0.0
+    );
+    assert_eq!(a[[2, 3]], 23.0);
+}
+
+/// `matmul` of views equals `matmul` of the blocks copied out, for square and rectangular
+/// blocks, and of a view of a whole matrix.
+#[test]
+fn view_matmul() {
+    let a = numbered();
+    let b = Mat::new([
+        [1.0, -2.0, 0.5, 3.0, 0.0],
+        [0.0, 1.0, 4.0, -1.0, 2.0],
+        [2.5, 0.0, -3.0, 1.0, 1.0],
+        [-1.0, 2.0, 0.0, 0.5, -2.0],
+        [3.0, 1.0, 1.0, 0.0, 4.0],
+    ]);
+    for (a_rows, a_cols, b_rows, b_cols) in [
+        (1..4, 1..4, 1..4, 1..4),
+        (0..2, 1..4, 2..5, 0..5),
+        (2..5, 0..1, 4..5, 1..3),
+    ] {
+        let [a_view, b_view] = [a.view(a_rows, a_cols), b.view(b_rows, b_cols)];
+        let product = a_view.matmul(&b_view);
+        let copied = a_view.to_mat().matmul(&b_view.to_mat());
+        assert_eq!(max_difference(&product, &copied), 0.0);
+    }
+    let whole = a.as_view().matmul(&b.as_view());
+    assert_eq!(max_difference(&whole, &a.matmul(&b)), 0.0);
+}
@@ -0,0 +1,81 @@
+//! A `RunManifest` written beside its results, read back, and verified against the circuit it
+//! came from and against one with a component changed.
+#![cfg(feature = "serde")]
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{ComponentParameter, NoiseDistribution, NoiseSourceComponentValue},
+    manifest::RunManifest,
+    run::RunConfig,
+    CircuitState, ComponentValueEnum,
+};
+
+const CONFIG: RunConfig = RunConfig {
+    dt: 1e-6,
+    n_steps: 500,
+    progress_every: 0,
+    start_step: 0,
+};
+
+/// A 5 V RC divider, with a seeded noise source on a load of its own.
+fn circuit() -> CircuitState {
+    let noise = NoiseSourceComponentValue {
+        distribution: NoiseDistribution::Gaussian,
+        sigma: 0.1,
+        offset: 1.0,
+        seed: 7,
+        bandwidth: Some(10e3),
+    };
+    let (mut circuit, _) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 5.0)
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.resistor("R2", "out", "gnd", 1e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-6))
+        .and_then(|b| b.component("Vn", ComponentValueEnum::NoiseSource(noise), &["gnd", "n"]))
+        .and_then(|b| b.resistor("RN", "n", "gnd", 1e3))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    circuit
+}
+
+/// The manifest round-trips through its file with the same hash and verifies bit for bit;
+/// with `R2` at 1100 ohm instead of 1000, verification fails, with `R2`'s value the one input
+/// named as changed.
+#[test]
+fn round_trip_and_change() {
+    let mut circuit = circuit();
+    let manifest = RunManifest::capture(&circuit, &CONFIG).unwrap();
+    let path = RunManifest::path_beside(
+        std::env::temp_dir().join(format!("esc_sim_results_{}.csv", std::process::id())),
+    );
+    assert!(path.to_str().unwrap().ends_with(".manifest.json"));
+    manifest.write(&path).unwrap();
+    let read = RunManifest::read(&path);
+    std::fs::remove_file(&path).unwrap();
+    let read = read.unwrap();
+    assert_eq!(read.hash, manifest.hash);
+
+    let verification = read.verify_against(&circuit, &CONFIG, 1e-9).unwrap();
+    assert!(
+        verification.passed() && verification.exact,
+        "{verification}"
+    );
+
+    let r2 = circuit.component_by_name("R2").unwrap();
+    *circuit
+        .component_mut(r2)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = 1100.0;
+    let verification = read.verify_against(&circuit, &CONFIG, 1e-9).unwrap();
+    assert!(!verification.passed() && !verification.exact);
+    let [change] = &verification.changes[..] else {
+        panic!("{verification}")
+    };
+    assert!(change.path.starts_with("component R2."), "{verification}");
+    assert_eq!(
+        (&change.expected[..], &change.found[..]),
+        ("1000.0", "1100.0")
+    );
+    assert!(!verification.mismatches.is_empty());
+}
@@ -0,0 +1,128 @@
+//! Transients of an RC filter and the MOSFET switch of `mosfet_rl_test_circuit` against ngspice,
+//! through the netlist exporter, `sim::ngspice` and `sim::waveform`.
+//!
+//!     NGSPICE=/path/to/ngspice cargo test --test ngspice
+//!
+//! Each test passes with a message on stderr, skipped, if ngspice isn't found.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::ComponentParameter,
+    mosfet_rl_test_circuit,
+    ngspice::Ngspice,
+    probe::{Probe, Recording},
+    waveform::{compare, Tolerance},
+    CircuitState,
+};
+
+struct Case {
+    name: &'static str,
+    circuit: CircuitState,
+    names: NameMap,
+    /// Nets to compare.
+    nets: &'static [&'static str],
+    dt: f64,
+    duration: f64,
+    tolerance: Tolerance,
+}
+
+/// 1 V into 1 kohm and 1 uF from rest, over 5 RC.
+#[test]
+fn rc_filter() {
+    let (circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 1.0)
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.capacitor("C1", "gnd", "out", 1e-6))
+        .unwrap()
+        .build();
+    crosscheck(Case {
+        name: "RC filter",
+        circuit,
+        names,
+        nets: &["out"],
+        dt: 1e-6,
+        duration: 5e-3,
+        tolerance: Tolerance {
+            absolute: 1e-3,
+            relative: 1e-3,
+        },
+    });
+}
+
+/// `mosfet_rl_test_circuit` with the gate driven to 10 V from the start. The exported LEVEL=1 model
+/// has a sharp saturation edge where ours has `saturation_knee`, hence the wider band.
+#[test]
+fn mosfet_switch() {
+    let (mut circuit, names) = mosfet_rl_test_circuit();
+    let gate_drive = names.component("Vg").unwrap();
+    if let Some(v) = circuit
+        .component_mut(gate_drive)
+        .parameter_mut(ComponentParameter::Value)
+    {
+        *v = 10.0;
+    }
+    crosscheck(Case {
+        name: "MOSFET switch",
+        circuit,
+        names,
+        nets: &["load", "drain"],
+        dt: 1e-6,
+        duration: 1e-3,
+        tolerance: Tolerance {
+            absolute: 0.1,
+            relative: 0.02,
+        },
+    });
+}
+
+/// Run `case` here and in ngspice, failing if a net leaves the tolerance band.
+fn crosscheck(mut case: Case) {
+    let Some(ngspice) = Ngspice::find() else {
+        eprintln!(
+            "ngspice not found (set NGSPICE to its path), skipping {}",
+            case.name
+        );
+        return;
+    };
+    // export the initial conditions before our run moves the state on.
+    let netlist = case.circuit.to_spice_netlist();
+    let spice = ngspice
+        .transient(&netlist, case.dt, case.duration)
+        .unwrap_or_else(|e| panic!("{}: {e}", case.name));
+
+    let probes = case
+        .nets
+        .iter()
+        .map(|&net| (net.to_string(), Probe::Net(case.names.net(net).unwrap())))
+        .collect();
+    let mut recording = Recording::new(probes);
+    case.circuit.solve_state();
+    recording.record(&case.circuit, 0.0);
+    for _ in 0..(case.duration / case.dt).round() as usize {
+        case.circuit.tick(case.dt);
+        recording.record(&case.circuit, case.circuit.now());
+    }
+
+    let mut failed = Vec::new();
+    for (&net, ours) in case.nets.iter().zip(&recording.channels) {
+        let reference = spice
+            .net_voltage(case.names.net(net).unwrap())
+            .unwrap_or_else(|| panic!("{}: no vector for net {net} in the rawfile", case.name));
+        let c = compare(
+            &recording.time,
+            ours,
+            spice.scale(),
+            &reference,
+            case.tolerance,
+        );
+        let report = format!(
+            "{}: V({net}) max deviation {:.3e} V at t = {:.3e} s",
+            case.name, c.max_deviation, c.at,
+        );
+        println!("{report}");
+        if !c.within {
+            failed.push(report);
+        }
+    }
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
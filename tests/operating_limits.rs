@@ -0,0 +1,96 @@
+//! Operating limits catching an undersized flyback's drain voltage at switch-off.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{ComponentParameter, MOSFETComponentValue, MOSFETDopingType},
+    operating_limits::LimitedQuantity,
+    probe::Probe,
+};
+
+const DT: f64 = 50e-9;
+const L: f64 = 100e-6;
+const C: f64 = 100e-9;
+const V_SUPPLY: f64 = 12.0;
+const V_MAX: f64 = 30.0;
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.2,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+/// 12 V into 100 uH, switched by `M1` for 10 us in 50 ns ticks (to 1.183 A) into nothing but
+/// 100 nF at the drain, rated for 30 V: at switch-off the drain rings up as the LC does from
+/// the current and voltage it had, `V_supply + (v_0 - V_supply) cos(w t) + I_0 Z sin(w t)`,
+/// past 30 V. With abort on, the run stops at the end of the step the crossing falls in, and
+/// the violation starts there.
+#[test]
+fn flyback_overvoltage() {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "supply", V_SUPPLY)
+        .and_then(|b| b.source("VG", "gnd", "gate", 20.0))
+        .and_then(|b| b.inductor("L1", "supply", "drain", L))
+        .and_then(|b| b.capacitor("C1", "drain", "gnd", C))
+        .and_then(|b| b.mosfet("M1", MOSFET, "gnd", "gate", "drain"))
+        .and_then(|b| b.voltage_limit("drain", "gnd", V_MAX))
+        .unwrap()
+        .build();
+    circuit.set_abort_on_violation(true);
+    assert!(circuit.solve_state());
+    let [i_l, drain] =
+        ["comp:L1.current", "net:drain"].map(|spec| Probe::parse(spec, &names).unwrap());
+    for _ in 0..200 {
+        assert!(circuit.tick(DT), "no convergence at {:e} s", circuit.now());
+    }
+    let (t_off, i_0, v_0) = (
+        circuit.now(),
+        i_l.sample(&circuit).abs(),
+        drain.sample(&circuit),
+    );
+    assert!((i_0 - 1.183).abs() < 1e-3, "{i_0} A at switch-off");
+    assert!(circuit.limit_violations().is_empty());
+
+    *circuit
+        .component_mut(names.component("VG").unwrap())
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = 0.0;
+    let summary = circuit.run_simple(DT, 1000, vec![("drain".to_string(), drain)]);
+
+    let omega = 1.0 / (L * C).sqrt();
+    let z = (L / C).sqrt();
+    let v = |t: f64| V_SUPPLY + (v_0 - V_SUPPLY) * (omega * t).cos() + i_0 * z * (omega * t).sin();
+    // bisect for the crossing on the rising quarter period.
+    let (mut a, mut b) = (0.0, std::f64::consts::FRAC_PI_2 / omega);
+    for _ in 0..60 {
+        let mid = 0.5 * (a + b);
+        if v(mid) < V_MAX {
+            a = mid;
+        } else {
+            b = mid;
+        }
+    }
+    let crossing = t_off + a;
+
+    assert!(summary.stopped_early && summary.converged());
+    let [violation] = &summary.limit_violations[..] else {
+        panic!("{:?}", summary.limit_violations)
+    };
+    assert_eq!(
+        violation.quantity,
+        LimitedQuantity::NetVoltage {
+            net: names.net("drain").unwrap(),
+            reference: names.net("gnd").unwrap(),
+        }
+    );
+    assert!(
+        (0.0..=DT).contains(&(violation.start - crossing)),
+        "violation from {:e} s, the drain crossing 30 V at {crossing:e} s",
+        violation.start
+    );
+    assert!(violation.ongoing && violation.peak > V_MAX);
+    assert!((circuit.now() - violation.start).abs() < 1e-12);
+}
@@ -0,0 +1,69 @@
+//! The parallel solver sweeps against the serial ones, which they have to match bit for bit.
+#![cfg(feature = "parallel")]
+
+use esc_sim_test::sim::{
+    components::ComponentParameter, make_half_bridge, make_resistor_grid, CircuitState,
+};
+
+fn voltage_bits(circuit: &CircuitState) -> Vec<u64> {
+    (0..circuit.n_nets())
+        .map(|net| circuit.net_voltage(net).to_bits())
+        .collect()
+}
+
+fn state_bits(circuit: &CircuitState) -> Vec<u64> {
+    circuit.state_vector().iter().map(|x| x.to_bits()).collect()
+}
+
+/// A 20x20 resistor grid, 760 resistors over three chunks of the parallel sweep: 200 sweeps
+/// leave the same voltages either way, after each.
+#[test]
+fn resistor_grid() {
+    let [mut serial, mut parallel] = [false, true].map(|parallel| {
+        let mut circuit = make_resistor_grid(20, 1.0);
+        circuit.set_parallel(parallel);
+        circuit
+    });
+    for sweep in 0..200 {
+        for circuit in [&mut serial, &mut parallel] {
+            circuit.relaxation_sweep(0.5);
+        }
+        assert_eq!(
+            voltage_bits(&serial),
+            voltage_bits(&parallel),
+            "sweep {sweep}"
+        );
+    }
+}
+
+/// `make_half_bridge` switching its low side on and off every 10 ticks of 1 us: every tick
+/// converges or not alike, and the net voltages and component states agree after it.
+#[test]
+fn half_bridge_ticks() {
+    let [mut serial, mut parallel] = [false, true].map(|parallel| {
+        let (mut circuit, names) = make_half_bridge();
+        circuit.set_parallel(parallel);
+        assert!(circuit.solve_state());
+        (circuit, names.component("Vg_low").unwrap())
+    });
+    for k in 0..60 {
+        for (circuit, gate) in [&mut serial, &mut parallel] {
+            if k % 10 == 0 {
+                let on = (k / 10) % 2 == 0;
+                *circuit
+                    .component_mut(*gate)
+                    .parameter_mut(ComponentParameter::Value)
+                    .unwrap() = if on { 10.0 } else { 0.0 };
+            }
+        }
+        let [serial, parallel] = [&mut serial.0, &mut parallel.0];
+        assert_eq!(serial.tick(1e-6), parallel.tick(1e-6), "tick {k}");
+        assert_eq!(
+            serial.last_iterations(),
+            parallel.last_iterations(),
+            "tick {k}"
+        );
+        assert_eq!(voltage_bits(serial), voltage_bits(parallel), "tick {k}");
+        assert_eq!(state_bits(serial), state_bits(parallel), "tick {k}");
+    }
+}
@@ -0,0 +1,130 @@
+//! One component standing for several in parallel (`MOSFETComponentValue::multiplicity`,
+//! `LinearComponentValue::parallel`) against the circuit with each of them explicit.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::{
+        ComponentParameter, LinearComponentValue, MOSFETComponentValue, MOSFETDopingType,
+    },
+    probe::Probe,
+    CircuitState, ComponentValueEnum,
+};
+
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.02,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+fn build(builder: &mut CircuitBuilder) -> (CircuitState, NameMap) {
+    let (mut circuit, names) = builder.build();
+    circuit.solve_state();
+    (circuit, names)
+}
+
+fn sample(circuit: &CircuitState, names: &NameMap, spec: &str) -> f64 {
+    Probe::parse(spec, names).unwrap().sample(circuit)
+}
+
+/// The drain voltage and the current of the 10 ohm load from 12 V, of `MOSFET` with multiplicity 2
+/// and of two explicit ones, at gate drives through cutoff, saturation and triode.
+#[test]
+fn mosfet_multiplicity() {
+    let circuit = |explicit: bool| {
+        let mut builder = CircuitBuilder::new();
+        builder
+            .source("V1", "gnd", "bus", 12.0)
+            .and_then(|b| b.source("VG", "gnd", "gate", 0.0))
+            .and_then(|b| b.resistor("R1", "bus", "drain", 10.0))
+            .unwrap();
+        if explicit {
+            builder
+                .mosfet("M1", MOSFET, "gnd", "gate", "drain")
+                .and_then(|b| b.mosfet("M2", MOSFET, "gnd", "gate", "drain"))
+                .unwrap();
+        } else {
+            let doubled = MOSFETComponentValue {
+                multiplicity: 2.0,
+                ..MOSFET
+            };
+            builder
+                .mosfet("M1", doubled, "gnd", "gate", "drain")
+                .unwrap();
+        }
+        build(&mut builder)
+    };
+    let (mut single, single_names) = circuit(false);
+    let (mut pair, pair_names) = circuit(true);
+    for v_gate in [0.0, 3.0, 4.0, 6.0, 10.0] {
+        for (circuit, names) in [(&mut single, &single_names), (&mut pair, &pair_names)] {
+            let vg = names.component("VG").unwrap();
+            *circuit
+                .component_mut(vg)
+                .parameter_mut(ComponentParameter::Value)
+                .unwrap() = v_gate;
+            assert!(circuit.solve_state());
+        }
+        for spec in ["net:drain", "comp:R1.current"] {
+            let (a, b) = (
+                sample(&single, &single_names, spec),
+                sample(&pair, &pair_names, spec),
+            );
+            assert!(
+                (a - b).abs() <= 1e-9 * b.abs().max(1.0),
+                "{spec} at a {v_gate} V gate: {a} with multiplicity 2, {b} with two devices"
+            );
+        }
+    }
+}
+
+/// 1 V into 1 kohm and 2 uF, each as two components in parallel and as one from
+/// `LinearComponentValue::parallel`: the same charging curve over 5 RC.
+#[test]
+fn linear_parallel() {
+    let circuit = |explicit: bool| {
+        let mut builder = CircuitBuilder::new();
+        builder.source("V1", "gnd", "in", 1.0).unwrap();
+        let (r, c) = (
+            LinearComponentValue::Resistive(2e3),
+            LinearComponentValue::Capacitive(1e-6),
+        );
+        let components = if explicit {
+            vec![
+                ("R1", r, "in"),
+                ("R2", r, "in"),
+                ("C1", c, "gnd"),
+                ("C2", c, "gnd"),
+            ]
+        } else {
+            vec![
+                ("R1", r.parallel(2.0), "in"),
+                ("C1", c.parallel(2.0), "gnd"),
+            ]
+        };
+        for (name, value, net) in components {
+            builder
+                .component(name, ComponentValueEnum::Linear(value), &[net, "out"])
+                .unwrap();
+        }
+        build(&mut builder)
+    };
+    let (mut single, single_names) = circuit(false);
+    let (mut pair, pair_names) = circuit(true);
+    let mut deviation = 0.0_f64;
+    for _ in 0..500 {
+        assert!(single.tick(20e-6) && pair.tick(20e-6));
+        deviation = deviation.max(
+            (sample(&single, &single_names, "net:out") - sample(&pair, &pair_names, "net:out"))
+                .abs(),
+        );
+    }
+    assert!(
+        deviation < 1e-9,
+        "charging curves differ by {deviation:e} V"
+    );
+}
@@ -0,0 +1,81 @@
+//! Series parasitics from `CircuitState::connect_with_parasitics` ringing with the capacitance
+//! they drive.
+
+use std::f64::consts::PI;
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{ComponentParameter, MOSFETComponentValue, MOSFETDopingType},
+    probe::Probe,
+};
+
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.02,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+/// A 1 V step into the gate of `MOSFET` through 1 ohm and 10 nH of trace, against 1 nF of gate
+/// capacitance: the gate net overshoots and rings about 1 V at the damped frequency of the loop,
+/// 50 MHz at a Q of 3.2.
+///
+/// The step stays below threshold and the drain is left unpowered: the loop current changes
+/// at 1e8 A/s, and a larger step, or another loop sharing its nets, leaves rounding errors in
+/// the node currents above the solver's absolute tolerance.
+#[test]
+fn gate_loop_ringing() {
+    const DT: f64 = 0.05e-9;
+    let (r, l, c): (f64, f64, f64) = (1.0, 10e-9, 1e-9);
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("VG", "gnd", "drive", 0.0)
+        .and_then(|b| b.capacitor("Cgs", "gate", "gnd", c))
+        .and_then(|b| b.mosfet("M1", MOSFET, "gnd", "gate", "drain"))
+        .unwrap()
+        .build();
+    let (drive, gate) = (names.net("drive").unwrap(), names.net("gate").unwrap());
+    circuit.connect_with_parasitics(drive, gate, r, l);
+    assert!(circuit.dc_operating_point());
+    let vg = names.component("VG").unwrap();
+    *circuit
+        .component_mut(vg)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = 1.0;
+
+    let v = Probe::parse("net:gate", &names).unwrap();
+    let mut prev = v.sample(&circuit) - 1.0;
+    let mut peak: f64 = 0.0;
+    let mut crossings = Vec::new();
+    for step in 1..=(60e-9 / DT) as usize {
+        assert!(circuit.tick(DT), "no convergence at {step}");
+        let now = v.sample(&circuit) - 1.0;
+        peak = peak.max(now);
+        if (prev < 0.0) != (now < 0.0) {
+            // interpolate within the step
+            crossings.push((step as f64 - now / (now - prev)) * DT);
+        }
+        prev = now;
+    }
+
+    let alpha = r / (2.0 * l);
+    let expected = (1.0 / (l * c) - alpha * alpha).sqrt() / (2.0 * PI);
+    assert!(crossings.len() >= 4, "{} crossings of 1 V", crossings.len());
+    // a half period between crossings of the final value
+    let period =
+        2.0 * (crossings[crossings.len() - 1] - crossings[0]) / (crossings.len() - 1) as f64;
+    assert!(
+        (1.0 / period - expected).abs() < 2e-2 * expected,
+        "rings at {:e} Hz, expected {expected:e} Hz",
+        1.0 / period
+    );
+    // the first overshoot of an underdamped step, `exp(-alpha pi / w_d)`
+    let overshoot = (-alpha * PI / (2.0 * PI * expected)).exp();
+    assert!(
+        (peak - overshoot).abs() < 2e-2,
+        "overshoots by {peak} V, expected {overshoot} V"
+    );
+}
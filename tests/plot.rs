@@ -0,0 +1,138 @@
+//! The batches `run::run_with_plot` hands a `PlotSink`.
+
+use std::sync::atomic::AtomicBool;
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    plot::{PlotBatch, PlotSink},
+    probe::{Policy, Probe, Recording},
+    run::{run_with_plot, PlotConfig, RunConfig, RunOutcome},
+};
+
+/// Keeps everything it is handed.
+#[derive(Debug, Default)]
+struct MockSink {
+    labels: Vec<String>,
+    /// Time and channels of each batch.
+    batches: Vec<(Vec<f64>, Vec<Vec<f64>>)>,
+    ended: bool,
+}
+impl PlotSink for MockSink {
+    fn begin(&mut self, labels: &[String]) {
+        assert!(
+            self.labels.is_empty() && self.batches.is_empty(),
+            "begun twice"
+        );
+        self.labels = labels.to_vec();
+    }
+    fn batch(&mut self, batch: PlotBatch<'_>) {
+        assert!(!self.ended, "batch after the end");
+        assert_eq!(batch.channels.len(), self.labels.len());
+        for channel in batch.channels {
+            assert_eq!(channel.len(), batch.time.len());
+        }
+        self.batches.push((
+            batch.time.to_vec(),
+            batch.channels.iter().map(|c| c.to_vec()).collect(),
+        ));
+    }
+    fn end(&mut self) {
+        assert!(!self.ended, "ended twice");
+        self.ended = true;
+    }
+}
+
+/// 100 steps of 10 us of 1 V into 1 kohm and 1 uF, recorded in a row per `every` steps (a plain
+/// recording for 1) and plotted every 250 us.
+fn plotted_run(every: usize) -> (MockSink, RunOutcome) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 1.0)
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-6))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let probe = |spec| Probe::parse(spec, &names).unwrap();
+    let recording = match every {
+        1 => Recording::new(vec![
+            ("out".to_string(), probe("net:out")),
+            ("i".to_string(), probe("comp:R1.current")),
+        ]),
+        _ => Recording::decimated(
+            vec![
+                ("out".to_string(), probe("net:out"), Policy::Mean),
+                ("i".to_string(), probe("comp:R1.current"), Policy::Envelope),
+            ],
+            every,
+        ),
+    };
+    let mut sink = MockSink::default();
+    let config = RunConfig {
+        dt: 10e-6,
+        n_steps: 100,
+        progress_every: 0,
+        start_step: 0,
+    };
+    let plot = PlotConfig {
+        sink: &mut sink,
+        every: 250e-6,
+    };
+    let outcome = run_with_plot(
+        &mut circuit,
+        recording,
+        config,
+        &AtomicBool::new(false),
+        |_| {},
+        plot,
+    )
+    .unwrap();
+    assert!(sink.ended);
+    (sink, outcome)
+}
+
+/// The batches, laid end to end, are the recording the run returns.
+fn assert_covers(sink: &MockSink, outcome: &RunOutcome) {
+    let recording = &outcome.recording;
+    assert_eq!(sink.labels, recording.labels().collect::<Vec<_>>());
+    let time = sink.batches.iter().flat_map(|(t, _)| t).copied();
+    assert!(time.eq(recording.time.iter().copied()));
+    for (i, channel) in recording.channels.iter().enumerate() {
+        let plotted = sink.batches.iter().flat_map(|(_, c)| &c[i]).copied();
+        assert!(plotted.eq(channel.iter().copied()), "channel {i}");
+    }
+}
+
+/// A row per step and the initial one: a batch at each 250 us, the first with the initial row,
+/// and none left for the end.
+#[test]
+fn every_step() {
+    let (sink, outcome) = plotted_run(1);
+    let sizes = sink
+        .batches
+        .iter()
+        .map(|(t, _)| t.len())
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, [26, 25, 25, 25]);
+    for ((time, _), end) in sink.batches.iter().zip([250e-6, 500e-6, 750e-6, 1e-3]) {
+        assert!((time.last().unwrap() - end).abs() < 1e-12, "{time:?}");
+    }
+    assert_covers(&sink, &outcome);
+}
+
+/// A row per 10 samples, the initial one among them, so rows land at 90, 190, ... 990 us: the
+/// batches at each 250 us hand over the rows completed by then, and the end the row of the last
+/// step alone.
+#[test]
+fn decimated() {
+    let (sink, outcome) = plotted_run(10);
+    assert_eq!(sink.labels, ["out", "i.min", "i.max"]);
+    let sizes = sink
+        .batches
+        .iter()
+        .map(|(t, _)| t.len())
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, [2, 3, 2, 3, 1]);
+    let (last, _) = sink.batches.last().unwrap();
+    assert!((last[0] - 1e-3).abs() < 1e-12, "{last:?}");
+    assert_covers(&sink, &outcome);
+}
@@ -0,0 +1,72 @@
+//! Circuits simulated in `f32` against the same ones in `f64`.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::{MOSFETComponentValue, MOSFETDopingType},
+    CircuitState, Scalar,
+};
+
+/// 1 uF discharging from 1 V through 1 kohm.
+fn rc<S: Scalar>() -> (CircuitState<S>, NameMap) {
+    let (mut circuit, names) = CircuitBuilder::<S>::new()
+        .resistor("R1", "out", "gnd", S::from_f64(1e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", S::from_f64(1e-6)))
+        .unwrap()
+        .build();
+    let c1 = names.component("C1").unwrap();
+    circuit
+        .set_initial_capacitor_voltage(c1, S::from_f64(1.0))
+        .unwrap();
+    assert!(circuit.solve_state());
+    (circuit, names)
+}
+
+/// The RC discharge over 5 time constants in ticks of 10 us: the `f32` run tracks the `f64` one
+/// to within 1e-3 V at every tick.
+#[test]
+fn rc_discharge() {
+    let ((mut single, names), (mut double, _)) = (rc::<f32>(), rc::<f64>());
+    let c1 = names.component("C1").unwrap();
+    for k in 0..500 {
+        assert!(single.tick(10e-6), "f32 tick {k}");
+        assert!(double.tick(10e-6), "f64 tick {k}");
+        let (a, b) = (single.branch_voltage(c1) as f64, double.branch_voltage(c1));
+        assert!((a - b).abs() < 1e-3, "tick {k}: {a} V in f32, {b} V in f64");
+    }
+    let v = double.branch_voltage(c1);
+    assert!(v.abs() < 1e-2, "{v} V after 5 RC");
+}
+
+/// A MOSFET with its gate at 4 V sinking from a 12 V supply through 100 ohm.
+fn common_source<S: Scalar>() -> (CircuitState<S>, NameMap) {
+    let mosfet = MOSFETComponentValue {
+        beta: S::from_f64(0.02),
+        ty: MOSFETDopingType::NChannel,
+        body_diode_ideality_facotor: S::from_f64(1.0),
+        body_diode_saturation_current: S::from_f64(1e-12),
+        threshold_voltage: S::from_f64(2.0),
+        saturation_knee: S::from_f64(8.0),
+        multiplicity: S::from_f64(1.0),
+        avalanche: None,
+    };
+    let (mut circuit, names) = CircuitBuilder::<S>::new()
+        .source("VDD", "gnd", "vdd", S::from_f64(12.0))
+        .and_then(|b| b.source("VG", "gnd", "gate", S::from_f64(4.0)))
+        .and_then(|b| b.resistor("RD", "vdd", "drain", S::from_f64(100.0)))
+        .and_then(|b| b.mosfet("M1", mosfet, "gnd", "gate", "drain"))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    (circuit, names)
+}
+
+/// The operating point of the common-source stage: the drain settles in `f32` to within 1e-3 V
+/// of where it does in `f64`, pulled well below the supply.
+#[test]
+fn mosfet_operating_point() {
+    let ((single, names), (double, _)) = (common_source::<f32>(), common_source::<f64>());
+    let drain = names.net("drain").unwrap();
+    let (a, b) = (single.net_voltage(drain) as f64, double.net_voltage(drain));
+    assert!((a - b).abs() < 1e-3, "{a} V in f32, {b} V in f64");
+    assert!(b < 11.0, "drain at {b} V");
+}
@@ -0,0 +1,46 @@
+//! `SolverConfig::predictor` warm-starting the ticks of `rc_test_circuit`.
+
+use esc_sim_test::sim::{
+    rc_test_circuit,
+    solver::{Predictor, SolverConfig},
+};
+
+/// 1000 ticks of 10 us of the two LC tanks under `predictor`, returning the outer iterations of
+/// all ticks together and the voltage of C1 at the end.
+fn run(predictor: Predictor) -> (usize, f64) {
+    let (mut circuit, names) = rc_test_circuit();
+    circuit.set_solver_config(SolverConfig {
+        predictor,
+        ..*circuit.solver_config()
+    });
+    assert!(circuit.solve_state());
+    let mut iterations = 0;
+    for k in 0..1000 {
+        assert!(circuit.tick(10e-6), "tick {k} under {predictor:?}");
+        iterations += circuit.last_iterations();
+    }
+    let c1 = names.component("C1").unwrap();
+    (iterations, circuit.branch_voltage(c1))
+}
+
+/// Starting from the previous tick takes 63921 iterations; extrapolating the line through the
+/// last two cuts that by over a quarter and the parabola through the last three by over 95 %,
+/// to the same voltages.
+#[test]
+fn iteration_budget() {
+    let (off, v_off) = run(Predictor::Off);
+    let (linear, v_linear) = run(Predictor::Linear);
+    let (quadratic, v_quadratic) = run(Predictor::Quadratic);
+    assert!(off <= 64_000, "{off} iterations without a predictor");
+    assert!(
+        linear <= 46_000,
+        "{linear} iterations with a linear predictor"
+    );
+    assert!(
+        quadratic <= 2_500,
+        "{quadratic} iterations with a quadratic predictor"
+    );
+    for v in [v_linear, v_quadratic] {
+        assert!((v - v_off).abs() < 1e-9, "{v} V against {v_off} V");
+    }
+}
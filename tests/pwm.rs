@@ -0,0 +1,110 @@
+//! `PwmWave`s ticked through a circuit: ideal timing against the closed form, and the duty
+//! steps of a quantized compare register.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::{ComponentParameter, PwmTiming, PwmWave},
+    probe::Probe,
+    CircuitState,
+};
+
+const PERIOD: f64 = 10e-6;
+/// 256 ticks a period, so the edges of an 8-bit duty fall on ticks.
+const DT: f64 = PERIOD / 256.0;
+
+/// `wave` from `V1` into 2 kohm and 1 nF, a time constant of a fifth of a period, the capacitor
+/// starting at `v_out`.
+fn filtered(wave: PwmWave, v_out: f64) -> (CircuitState, NameMap) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .pwm_source("V1", "gnd", "in", wave)
+        .and_then(|b| b.resistor("R1", "in", "out", 2e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-9))
+        .unwrap()
+        .build();
+    let c1 = names.component("C1").unwrap();
+    circuit.set_initial_capacitor_voltage(c1, v_out).unwrap();
+    assert!(circuit.solve_state());
+    (circuit, names)
+}
+
+/// Net voltages of `circuit` by bits.
+fn voltage_bits(circuit: &CircuitState) -> Vec<u64> {
+    (0..circuit.n_nets())
+        .map(|net| circuit.net_voltage(net).to_bits())
+        .collect()
+}
+
+/// An ideal wave, delayed by a fifth of a period, against a plain source set to
+/// `PwmWave::voltage` at the end of each tick: the filter sees the same voltages at every tick,
+/// bit for bit, over 3 periods. So does a wave latching its duty, which keeps a schedule of its
+/// periods instead of the closed form.
+#[test]
+fn ideal_timing() {
+    let wave = PwmWave::new(0.0, 5.0, PERIOD, 0.3, 0.2 * PERIOD);
+    let mut latched = wave;
+    latched.timing.latch_duty = true;
+    let (mut closed_form, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", wave.voltage(0.0))
+        .and_then(|b| b.resistor("R1", "in", "out", 2e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-9))
+        .unwrap()
+        .build();
+    assert!(closed_form.solve_state());
+    let v1 = names.component("V1").unwrap();
+    let mut pwm = [filtered(wave, 0.0).0, filtered(latched, 0.0).0];
+    let mut edges = 0;
+    for k in 0..3 * 256 {
+        let v = wave.voltage(closed_form.now() + DT);
+        let source = closed_form
+            .component_mut(v1)
+            .parameter_mut(ComponentParameter::Value)
+            .unwrap();
+        edges += usize::from(*source != v);
+        *source = v;
+        assert!(closed_form.tick(DT), "tick {k}");
+        for circuit in &mut pwm {
+            assert!(circuit.tick(DT), "tick {k}");
+            assert_eq!(
+                voltage_bits(circuit),
+                voltage_bits(&closed_form),
+                "tick {k}"
+            );
+        }
+    }
+    assert_eq!(edges, 6);
+}
+
+/// 5 V at duties from 0.5 to 0.507 through an 8-bit compare register: the mean of the filtered
+/// output over a period, after 3 (15 time constants) to settle, takes the duty rounded to a
+/// 256th, and so steps by 5 V / 256 (19.5 mV): 0.5015 still reads 128 / 256, 0.5035 reads 129
+/// and 0.507 reads 130.
+#[test]
+fn duty_quantization() {
+    for (duty, steps) in [
+        (0.5, 128.0),
+        (0.5015, 128.0),
+        (0.5035, 129.0),
+        (0.507, 130.0),
+    ] {
+        let mut wave = PwmWave::new(0.0, 5.0, PERIOD, duty, 0.0);
+        wave.timing = PwmTiming {
+            duty_bits: Some(8),
+            ..PwmTiming::default()
+        };
+        let (mut circuit, names) = filtered(wave, 5.0 * duty);
+        let out = Probe::parse("net:out", &names).unwrap();
+        let mut sum = 0.0;
+        for k in 0..4 * 256 {
+            assert!(circuit.tick(DT), "duty {duty}, tick {k}");
+            if k >= 3 * 256 {
+                sum += out.sample(&circuit);
+            }
+        }
+        let mean = sum / (256.0);
+        let expected = 5.0 * steps / 256.0;
+        assert!(
+            (mean - expected).abs() < 1e-3,
+            "duty {duty}: mean {mean} V, expected {expected} V"
+        );
+    }
+}
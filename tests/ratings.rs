@@ -0,0 +1,85 @@
+//! `ResistorRating`s: power ratings checked by `LossAccumulator` and temperature coefficients
+//! moving the resistance with the ambient temperature.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{LinearComponentValue, ResistorRating, NOMINAL_TEMPERATURE},
+    environment::Environment,
+    power::LossAccumulator,
+    probe::Probe,
+    ComponentMut, ComponentRef,
+};
+
+/// 10 V across two 100 ohm resistors for 5 ms, one rated for 0.25 W and one for 2 W, with a
+/// 1 ms rating window: both dissipate 1 W, and the report holds one violation, of the first,
+/// over the whole run.
+#[test]
+fn power_rating() {
+    const DT: f64 = 100e-6;
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 10.0)
+        .and_then(|b| b.resistor("R1", "in", "gnd", 100.0))
+        .and_then(|b| b.resistor("R2", "in", "gnd", 100.0))
+        .unwrap()
+        .build();
+    let [r1, r2] = ["R1", "R2"].map(|name| names.component(name).unwrap());
+    for (r, power) in [(r1, 0.25), (r2, 2.0)] {
+        let ComponentMut::Linear(v) = circuit.component_mut(r) else {
+            unreachable!()
+        };
+        v.set_rating(Some(ResistorRating {
+            power: Some(power),
+            tempco: 0.0,
+        }));
+    }
+    assert!(circuit.solve_state());
+    let mut losses = LossAccumulator::new().with_rating_window(1e-3);
+    for _ in 0..50 {
+        assert!(circuit.tick(DT));
+        losses.accumulate(&circuit, DT);
+    }
+    let report = losses.report(&circuit);
+    let [violation] = &report.violations[..] else {
+        panic!("{:?}", report.violations)
+    };
+    assert_eq!(violation.component, r1);
+    assert_eq!(violation.rating, 0.25);
+    assert!(violation.start.abs() < 1e-12 && (violation.end - 5e-3).abs() < 1e-9);
+    for watts in [violation.average, violation.peak] {
+        assert!((watts - 1.0).abs() < 1e-9, "{violation:?}");
+    }
+}
+
+/// A 1 kohm resistor of 100 ppm/K at an ambient of 85 C: `1 + 100e-6 (358.15 K - 295 K)`
+/// times its resistance, and 10 V across it draw the current of that.
+#[test]
+fn tempco() {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 10.0)
+        .and_then(|b| b.resistor("R1", "in", "gnd", 1e3))
+        .unwrap()
+        .build();
+    let r1 = names.component("R1").unwrap();
+    let ComponentMut::Linear(v) = circuit.component_mut(r1) else {
+        unreachable!()
+    };
+    v.set_rating(Some(ResistorRating {
+        power: None,
+        tempco: 100e-6,
+    }));
+    circuit.set_environment(Environment::ambient_celsius(85.0));
+    assert!(circuit.solve_state());
+
+    let expected = 1e3 * (1.0 + 100e-6 * (358.15 - NOMINAL_TEMPERATURE));
+    let ComponentRef::Linear(v) = circuit.component(r1) else {
+        unreachable!()
+    };
+    let LinearComponentValue::Resistive(r) = v.value else {
+        unreachable!()
+    };
+    assert!((r - expected).abs() < 1e-9, "{r} ohm, expected {expected}");
+    let i = Probe::parse("comp:R1.current", &names)
+        .unwrap()
+        .sample(&circuit);
+    assert!((i - 10.0 / expected).abs() < 1e-12, "{i} A");
+}
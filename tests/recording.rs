@@ -0,0 +1,174 @@
+//! `Probe::Derived` math channels of a `Recording`, against the loss accounting and the step
+//! response of a first-order filter.
+
+use std::f64::consts::TAU;
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::ComponentParameter,
+    power::LossAccumulator,
+    probe::{DerivedExpr, Policy, Probe, Recording, StreamingRecording},
+};
+
+/// 1 V into 1 kohm and 1 uF for 5 RC: the voltage across the resistor as the difference of its
+/// net channels times its current channel, summed over the ticks, is the energy
+/// `LossAccumulator` has it absorb.
+#[test]
+fn power_channel() {
+    const DT: f64 = 10e-6;
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 1.0)
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-6))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let probe = |spec| Probe::parse(spec, &names).unwrap();
+    let power =
+        (DerivedExpr::Channel(0).minus(DerivedExpr::Channel(1))).times(DerivedExpr::Channel(2));
+    let mut recording = Recording::new(vec![
+        ("in".to_string(), probe("net:in")),
+        ("out".to_string(), probe("net:out")),
+        ("i".to_string(), probe("comp:R1.current")),
+        ("p".to_string(), Probe::Derived(power)),
+    ]);
+    let mut losses = LossAccumulator::new();
+    for _ in 0..500 {
+        assert!(circuit.tick(DT));
+        losses.accumulate(&circuit, DT);
+        recording.record(&circuit, circuit.now());
+    }
+    let energy = recording.channel("p").unwrap().iter().sum::<f64>() * DT;
+    let expected = losses.energy(names.component("R1").unwrap());
+    assert!(
+        (energy - expected).abs() < 1e-12 * expected,
+        "{energy:e} J from the channel, {expected:e} J accounted"
+    );
+}
+
+/// A 1 V step of a source, through a 1 kHz low-pass channel: `1 - exp(-2 pi f t)`, sampled every
+/// 10 us over 5 time constants.
+#[test]
+fn low_pass_step() {
+    const DT: f64 = 10e-6;
+    const CUTOFF: f64 = 1e3;
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 0.0)
+        .and_then(|b| b.resistor("R1", "in", "gnd", 1e3))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let mut recording = Recording::new(vec![
+        ("in".to_string(), Probe::parse("net:in", &names).unwrap()),
+        (
+            "filtered".to_string(),
+            Probe::Derived(DerivedExpr::Channel(0).low_pass(CUTOFF)),
+        ),
+    ]);
+    recording.record(&circuit, circuit.now());
+    let v1 = names.component("V1").unwrap();
+    *circuit
+        .component_mut(v1)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = 1.0;
+    let n = (5.0 / (TAU * CUTOFF) / DT) as usize;
+    for _ in 0..n {
+        assert!(circuit.tick(DT));
+        recording.record(&circuit, circuit.now());
+    }
+    let deviation = recording
+        .time
+        .iter()
+        .zip(recording.channel("filtered").unwrap())
+        .map(|(t, y)| (y - (1.0 - (-TAU * CUTOFF * t).exp())).abs())
+        .fold(0.0, f64::max);
+    assert!(deviation < 1e-9, "off the step response by {deviation:e}");
+}
+
+/// A 1 V pulse of one tick out of 100 ticks otherwise at 0 V, decimated to a row per 10 ticks:
+/// taking every 10th sample misses it, the mean of its bucket is a tenth of it and the envelope
+/// of its bucket reaches it.
+#[test]
+fn decimated_spike() {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 0.0)
+        .and_then(|b| b.resistor("R1", "in", "gnd", 1e3))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let probe = || Probe::parse("comp:V1.voltage", &names).unwrap();
+    let mut recording = Recording::decimated(
+        vec![
+            ("sample".to_string(), probe(), Policy::Sample),
+            ("mean".to_string(), probe(), Policy::Mean),
+            ("envelope".to_string(), probe(), Policy::Envelope),
+        ],
+        10,
+    );
+    let v1 = names.component("V1").unwrap();
+    for k in 0..100 {
+        *circuit
+            .component_mut(v1)
+            .parameter_mut(ComponentParameter::Value)
+            .unwrap() = if k == 43 { 1.0 } else { 0.0 };
+        assert!(circuit.tick(1e-6));
+        recording.record(&circuit, circuit.now());
+    }
+    recording.flush();
+    assert_eq!(recording.time.len(), 10);
+    let channel = |label| recording.channel(label).unwrap();
+    assert!(channel("sample").iter().all(|&v| v.abs() < 1e-9));
+    for (row, (&mean, &max)) in channel("mean")
+        .iter()
+        .zip(channel("envelope.max"))
+        .enumerate()
+    {
+        let expected = if row == 4 { 1.0 } else { 0.0 };
+        assert!((max - expected).abs() < 1e-9, "row {row}: max {max}");
+        assert!(
+            (mean - expected / 10.0).abs() < 1e-9,
+            "row {row}: mean {mean}"
+        );
+    }
+    assert!(channel("envelope.min").iter().all(|&v| v.abs() < 1e-9));
+}
+
+/// 100 ticks of a 1 kohm, 1 uF charge recorded in a row per 7 ticks (the last of 2), streamed
+/// in chunks of 3 rows: the streamed CSV is byte for byte the one `write_csv` writes of the same
+/// recording kept in memory.
+#[test]
+fn streamed_csv() {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 1.0)
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-6))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let recording = || {
+        let probe = |spec| Probe::parse(spec, &names).unwrap();
+        Recording::decimated(
+            vec![
+                ("out".to_string(), probe("net:out"), Policy::Mean),
+                ("i".to_string(), probe("comp:R1.current"), Policy::Envelope),
+            ],
+            7,
+        )
+    };
+    let mut in_memory = recording();
+    let mut streamed = StreamingRecording::new(recording(), Vec::new(), 3);
+    for _ in 0..100 {
+        assert!(circuit.tick(10e-6));
+        in_memory.record(&circuit, circuit.now());
+        streamed.record(&circuit, circuit.now()).unwrap();
+    }
+    in_memory.flush();
+    assert_eq!(in_memory.time.len(), 15);
+    let mut expected = Vec::new();
+    in_memory.write_csv(&mut expected).unwrap();
+    let streamed = streamed.finish().unwrap();
+    assert_eq!(
+        String::from_utf8(streamed).unwrap(),
+        String::from_utf8(expected).unwrap()
+    );
+}
@@ -0,0 +1,28 @@
+//! The relaxation schedules of `SolverConfig`: outer iterations `solve_state` takes under each.
+
+use esc_sim_test::sim::{
+    make_resistor_grid,
+    solver::{Relaxation, SolverConfig},
+    CircuitState,
+};
+
+/// Solve `circuit` under `relaxation`, returning the outer iterations it took to converge.
+fn iterations(mut circuit: CircuitState, relaxation: Relaxation) -> usize {
+    circuit.set_solver_config(SolverConfig {
+        relaxation,
+        ..*circuit.solver_config()
+    });
+    assert!(circuit.solve_state(), "no convergence under {relaxation:?}");
+    circuit.last_iterations()
+}
+
+/// A 5x5 grid of 1 ohm resistors: the default adaptive schedule converges in 769 outer
+/// iterations against 852 for a fixed factor of 1, and solving the same circuit again takes
+/// exactly as many.
+#[test]
+fn resistor_grid() {
+    let adaptive = SolverConfig::default().relaxation;
+    let counts = [adaptive, adaptive, Relaxation::Fixed { omega: 1.0 }]
+        .map(|relaxation| iterations(make_resistor_grid(5, 1.0), relaxation));
+    assert_eq!(counts, [769, 769, 852]);
+}
@@ -0,0 +1,91 @@
+//! `CircuitState::set_component_value` between solves: the charge policies of a capacitor and
+//! the values it turns down.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    components::{LinearComponentValue, MOSFETComponentValue, MOSFETDopingType},
+    error::SimError,
+    retune::ChargePolicy,
+    CircuitState, ComponentValueEnum,
+};
+
+/// 1 uF charged to 2 V, with 1 Gohm across it so its nets are tied together.
+fn charged() -> (CircuitState, NameMap) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .capacitor("C1", "a", "gnd", 1e-6)
+        .and_then(|b| b.resistor("R1", "a", "gnd", 1e9))
+        .unwrap()
+        .build();
+    let c1 = names.component("C1").unwrap();
+    circuit.set_initial_capacitor_voltage(c1, 2.0).unwrap();
+    assert!(circuit.solve_state());
+    (circuit, names)
+}
+
+/// Doubling the capacitance keeps the 2 V across it when preserving the voltage, doubling its
+/// charge, and halves the voltage when preserving the charge; either way the capacitor holds
+/// what it is left with over the next tick.
+#[test]
+fn capacitor_policies() {
+    for (policy, voltage) in [
+        (ChargePolicy::PreserveVoltage, 2.0),
+        (ChargePolicy::PreserveCharge, 1.0),
+    ] {
+        let (mut circuit, names) = charged();
+        let c1 = names.component("C1").unwrap();
+        let doubled = ComponentValueEnum::Linear(LinearComponentValue::Capacitive(2e-6));
+        circuit.set_component_value(c1, doubled, policy).unwrap();
+        assert!(circuit.solve_state());
+        let v = circuit.branch_voltage(c1);
+        assert!((v - voltage).abs() < 1e-9, "{v} V under {policy:?}");
+        assert!(circuit.tick(1e-6));
+        let v = circuit.branch_voltage(c1);
+        assert!(
+            (v - voltage).abs() < 1e-6,
+            "{v} V a tick later under {policy:?}"
+        );
+    }
+}
+
+/// A resistor doesn't take a capacitance, nor the value of a three-terminal MOSFET, and an id
+/// past the last component is unknown; the values turned down leave the circuit as it was.
+#[test]
+fn rejected_values() {
+    let (mut circuit, names) = charged();
+    let r1 = names.component("R1").unwrap();
+    let before = circuit.state_vector();
+    let capacitance = ComponentValueEnum::Linear(LinearComponentValue::Capacitive(1e-6));
+    assert_eq!(
+        circuit.set_component_value(r1, capacitance, ChargePolicy::default()),
+        Err(SimError::WrongComponentKind {
+            component: r1,
+            expected: "a capacitor",
+        })
+    );
+    let mosfet = ComponentValueEnum::MOSFET(MOSFETComponentValue {
+        beta: 0.02,
+        ty: MOSFETDopingType::NChannel,
+        body_diode_ideality_facotor: 1.0,
+        body_diode_saturation_current: 1e-12,
+        threshold_voltage: 2.0,
+        saturation_knee: 8.0,
+        multiplicity: 1.0,
+        avalanche: None,
+    });
+    assert_eq!(
+        circuit.set_component_value(r1, mosfet, ChargePolicy::default()),
+        Err(SimError::WrongComponentKind {
+            component: r1,
+            expected: "a MOSFET",
+        })
+    );
+    assert_eq!(
+        circuit.set_component_value(2, capacitance, ChargePolicy::default()),
+        Err(SimError::UnknownComponent(2))
+    );
+    assert_eq!(circuit.state_vector(), before);
+    let resistance = ComponentValueEnum::Linear(LinearComponentValue::Resistive(2e9));
+    circuit
+        .set_component_value(r1, resistance, ChargePolicy::default())
+        .unwrap();
+}
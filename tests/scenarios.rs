@@ -0,0 +1,31 @@
+//! The validation scenarios under `tests/scenarios/`, through `sim::scenario`. Each fails with
+//! the first of its assertions it violates, and when.
+#![cfg(feature = "serde")]
+
+use std::{fs, path::Path};
+
+use esc_sim_test::sim::scenario::Scenario;
+
+#[test]
+fn throttle_ramp() {
+    run_scenario("throttle_ramp");
+}
+
+#[test]
+fn phase_short() {
+    run_scenario("phase_short");
+}
+
+/// Run `tests/scenarios/<name>.json` from its solved initial state.
+fn run_scenario(name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/scenarios")
+        .join(name)
+        .with_extension("json");
+    let scenario = Scenario::from_json(&fs::read_to_string(path).unwrap()).unwrap();
+    let (mut circuit, names) = scenario.build().unwrap();
+    circuit.solve_state();
+    let report = scenario.run(&mut circuit, &names).unwrap();
+    assert!(report.passed(), "{report}");
+    assert_eq!(report.unconverged, 0, "{report}");
+}
@@ -0,0 +1,68 @@
+//! `SolverConfig::settle_after` against sweeping every component on each iteration.
+
+use esc_sim_test::sim::{
+    components::{ComponentParameter, LinearComponentValue},
+    solver::SolverConfig,
+    CircuitState, ComponentValueEnum,
+};
+
+/// A ladder of `sections` RC sections of 10 ohm and 1 uF, solved with its source at 0 V and then
+/// stepped to 1 V.
+fn rc_ladder(sections: usize, settle_after: Option<usize>) -> CircuitState {
+    let mut circuit = CircuitState::new_empty();
+    let gnd = circuit.create_net();
+    let mut prev = circuit.create_net();
+    let source = circuit.create_component(
+        ComponentValueEnum::Linear(LinearComponentValue::Source(0.0)),
+        &[gnd, prev],
+    );
+    for _ in 0..sections {
+        let next = circuit.create_net();
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Resistive(10.0)),
+            &[prev, next],
+        );
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Capacitive(1e-6)),
+            &[next, gnd],
+        );
+        prev = next;
+    }
+    #[cfg(feature = "parallel")]
+    circuit.set_parallel(false);
+    circuit.set_solver_config(SolverConfig {
+        settle_after,
+        ..*circuit.solver_config()
+    });
+    assert!(circuit.solve_state());
+    *circuit
+        .component_mut(source)
+        .parameter_mut(ComponentParameter::Value)
+        .unwrap() = 1.0;
+    circuit
+}
+
+/// 20 ticks of 1 us of a 50 section ladder after the step, masked after 5 settled iterations:
+/// the masked run skips sweeps of the far end of the ladder, and every net stays within 1e-10 V
+/// of the unmasked run at every tick.
+#[test]
+fn rc_ladder_step() {
+    let mut unmasked = rc_ladder(50, None);
+    let mut masked = rc_ladder(50, Some(5));
+    let mut skipped = 0;
+    for k in 0..20 {
+        assert!(unmasked.tick(1e-6), "unmasked tick {k}");
+        assert!(masked.tick(1e-6), "masked tick {k}");
+        skipped += masked.last_solve_report().skipped;
+        assert_eq!(unmasked.last_solve_report().skipped, 0);
+        for net in 1..unmasked.n_nets() {
+            let relative = |c: &CircuitState| c.net_voltage(net) - c.net_voltage(0);
+            let (a, b) = (relative(&unmasked), relative(&masked));
+            assert!(
+                (a - b).abs() < 1e-10,
+                "tick {k}, net {net}: {a} V against {b} V"
+            );
+        }
+    }
+    assert!(skipped > 0, "nothing masked");
+}
@@ -0,0 +1,59 @@
+//! Sources bound to a `SignalBus` signal following it together.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{LinearComponentState, LinearComponentValue},
+    probe::Probe,
+    CircuitState, ComponentRef,
+};
+
+/// One signal driving the high and low gates of a half-bridge, the low one inverted
+/// (`12 - signal`), each into 100 ohm and 1 nF, from the operating point at a signal of 0.
+/// Through a run of the signal stepping and ramping between 0 and 12 V, the two sources always
+/// add up to 12 V, and so do their gate nets as they charge.
+#[test]
+fn complementary_gates() {
+    const DT: f64 = 10e-9;
+    let (mut circuit, names) = CircuitBuilder::new()
+        .signal_source("VH", "gnd", "drive_h", "pwm_a", 1.0, 0.0)
+        .and_then(|b| b.signal_source("VL", "gnd", "drive_l", "pwm_a", -1.0, 12.0))
+        .and_then(|b| b.resistor("RH", "drive_h", "gate_h", 100.0))
+        .and_then(|b| b.resistor("RL", "drive_l", "gate_l", 100.0))
+        .and_then(|b| b.capacitor("CH", "gate_h", "gnd", 1e-9))
+        .and_then(|b| b.capacitor("CL", "gate_l", "gnd", 1e-9))
+        .unwrap()
+        .build();
+    assert!(circuit.dc_operating_point());
+    let source =
+        |circuit: &CircuitState, name| match circuit.component(names.component(name).unwrap()) {
+            ComponentRef::Linear(LinearComponentState {
+                value: LinearComponentValue::Source(v),
+                ..
+            }) => *v,
+            _ => unreachable!(),
+        };
+    let [gate_h, gate_l] =
+        ["net:gate_h", "net:gate_l"].map(|spec| Probe::parse(spec, &names).unwrap());
+
+    let mut deviation: f64 = 0.0;
+    for step in 0..400 {
+        // 12 V pulses, then a ramp down
+        let signal = match step {
+            0..=199 if step / 50 % 2 == 0 => 12.0,
+            0..=199 => 0.0,
+            _ => 12.0 * (400 - step) as f64 / 200.0,
+        };
+        circuit.set_signal("pwm_a", signal).unwrap();
+        assert_eq!(circuit.signal("pwm_a"), Some(signal));
+        assert_eq!(
+            (source(&circuit, "VH"), source(&circuit, "VL")),
+            (signal, 12.0 - signal)
+        );
+        assert!(circuit.tick(DT));
+        deviation = deviation.max((gate_h.sample(&circuit) + gate_l.sample(&circuit) - 12.0).abs());
+    }
+    assert!(
+        deviation < 1e-9,
+        "gates off complementary by {deviation:e} V"
+    );
+}
@@ -0,0 +1,74 @@
+//! Circuits of a `SimulationSet` ticked together and recorded into one `Recording`.
+
+use esc_sim_test::sim::{
+    builder::{CircuitBuilder, NameMap},
+    error::SimError,
+    probe::Probe,
+    simulation_set::SimulationSet,
+    CircuitState,
+};
+
+const DT: f64 = 10e-6;
+
+/// 1 V into `r` and 1 uF.
+fn rc(r: f64) -> (CircuitState, NameMap) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "in", 1.0)
+        .and_then(|b| b.resistor("R1", "in", "out", r))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-6))
+        .unwrap()
+        .build();
+    circuit.solve_state();
+    (circuit, names)
+}
+
+/// The RC circuit with 1 kohm as `fast` and with 2 kohm as `slow`, over 5 ms: the recording has
+/// a `fast.out` and a `slow.out` channel, each the charging curve of its own circuit ticked
+/// alone, sample for sample.
+#[test]
+fn two_rc_circuits() {
+    let mut set = SimulationSet::new();
+    let mut probes = Vec::new();
+    let mut alone = Vec::new();
+    for (name, r) in [("fast", 1e3), ("slow", 2e3)] {
+        let (circuit, names) = rc(r);
+        set.add(name, circuit).unwrap();
+        probes.push((
+            name,
+            "out".to_string(),
+            Probe::parse("net:out", &names).unwrap(),
+        ));
+        alone.push(rc(r).0);
+    }
+    assert!(matches!(
+        set.add("fast", rc(1e3).0),
+        Err(SimError::DuplicateCircuit(_))
+    ));
+    assert!(set
+        .recording(vec![("other", "out".to_string(), Probe::Net(1))])
+        .is_err());
+    // `out` is the same net of both circuits.
+    let out = probes[0].2.clone();
+    let mut recording = set.recording(probes).unwrap();
+    assert_eq!(
+        recording.recording.labels().collect::<Vec<_>>(),
+        ["fast.out", "slow.out"]
+    );
+
+    let mut expected = [Vec::new(), Vec::new()];
+    for _ in 0..500 {
+        assert!(set.tick_all(DT));
+        set.record(&mut recording);
+        for (circuit, expected) in alone.iter_mut().zip(&mut expected) {
+            assert!(circuit.tick(DT));
+            expected.push(out.sample(circuit));
+        }
+    }
+    for (label, expected) in ["fast.out", "slow.out"].iter().zip(&expected) {
+        assert_eq!(recording.recording.channel(label).unwrap(), &expected[..]);
+    }
+    // at 5 ms, 5 RC and 2.5 RC
+    let last = expected.map(|samples| samples[499]);
+    assert!((last[0] - (1.0 - (-5.0f64).exp())).abs() < 5e-3, "{last:?}");
+    assert!((last[1] - (1.0 - (-2.5f64).exp())).abs() < 5e-3, "{last:?}");
+}
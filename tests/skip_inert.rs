@@ -0,0 +1,96 @@
+//! `SolverConfig::skip_inert` against sweeping every component, over runs that open and close
+//! switches.
+
+use esc_sim_test::sim::{
+    components::{LinearComponentState, LinearComponentValue},
+    solver::SolverConfig,
+    CircuitState, ComponentId, ComponentMut, ComponentValueEnum,
+};
+
+/// A 1 V source behind 1 ohm charging 10 uF on a bank net, which 4 loads of 10 ohm each connect
+/// to ground through 3 switches, all open. Returns the circuit and the switches, load by load.
+fn switch_bank(skip_inert: bool) -> (CircuitState, Vec<ComponentId>) {
+    let mut circuit = CircuitState::new_empty();
+    let gnd = circuit.create_net();
+    let supply = circuit.create_net();
+    let bank = circuit.create_net();
+    circuit.create_component(
+        ComponentValueEnum::Linear(LinearComponentValue::Source(1.0)),
+        &[gnd, supply],
+    );
+    circuit.create_component(
+        ComponentValueEnum::Linear(LinearComponentValue::Resistive(1.0)),
+        &[supply, bank],
+    );
+    circuit.create_component(
+        ComponentValueEnum::Linear(LinearComponentValue::Capacitive(10e-6)),
+        &[bank, gnd],
+    );
+    let mut switches = Vec::new();
+    for _ in 0..4 {
+        let load = circuit.create_net();
+        circuit.create_component(
+            ComponentValueEnum::Linear(LinearComponentValue::Resistive(10.0)),
+            &[bank, load],
+        );
+        for _ in 0..3 {
+            switches.push(circuit.create_component(
+                ComponentValueEnum::Linear(LinearComponentValue::Switch {
+                    closed: false,
+                    off_resistance: None,
+                }),
+                &[load, gnd],
+            ));
+        }
+    }
+    #[cfg(feature = "parallel")]
+    circuit.set_parallel(false);
+    circuit.set_solver_config(SolverConfig {
+        skip_inert,
+        ..*circuit.solver_config()
+    });
+    assert!(circuit.solve_state());
+    (circuit, switches)
+}
+
+fn set_switch(circuit: &mut CircuitState, switch: ComponentId, on: bool) {
+    match circuit.component_mut(switch) {
+        ComponentMut::Linear(LinearComponentState {
+            value: LinearComponentValue::Switch { closed, .. },
+            ..
+        }) => *closed = on,
+        _ => unreachable!(),
+    }
+}
+
+/// 200 ticks of 1 us, every 10 ticks closing the next switch in a pattern that walks over the
+/// loads and opening the one closed 30 ticks before: skipping the open switches takes the same
+/// iterations to the same net voltages and component states, bit for bit, at every tick.
+#[test]
+fn toggled_switches() {
+    let (mut full, switches) = switch_bank(false);
+    let (mut skipping, _) = switch_bank(true);
+    assert_eq!(full.state_vector(), skipping.state_vector());
+    for k in 0..200 {
+        if k % 10 == 0 {
+            let step = k / 10;
+            for circuit in [&mut full, &mut skipping] {
+                set_switch(circuit, switches[step * 5 % switches.len()], true);
+                if step >= 3 {
+                    set_switch(circuit, switches[(step - 3) * 5 % switches.len()], false);
+                }
+            }
+        }
+        assert!(full.tick(1e-6), "tick {k} iterating over every component");
+        assert!(skipping.tick(1e-6), "tick {k} skipping inert components");
+        assert_eq!(
+            full.last_iterations(),
+            skipping.last_iterations(),
+            "tick {k}"
+        );
+        let voltages = |c: &CircuitState| (0..c.n_nets()).map(|n| c.net_voltage(n)).collect();
+        let (a, b): (Vec<_>, Vec<_>) = (voltages(&full), voltages(&skipping));
+        assert_eq!(a, b, "tick {k}");
+        assert_eq!(full.state_vector(), skipping.state_vector(), "tick {k}");
+    }
+}
@@ -0,0 +1,37 @@
+//! `CircuitState::solve_state_traced` recording the iterations of a solve.
+
+use esc_sim_test::sim::{mosfet_rl_test_circuit, solve_trace::SolveTrace};
+
+/// The first solve of `mosfet_rl_test_circuit`, which converges: past the first two iterations
+/// (from zero currents) the residual shrinks every iteration, no net oscillates, and the CSV
+/// has a row per iteration.
+#[test]
+fn converging_mosfet_solve() {
+    let (mut circuit, _) = mosfet_rl_test_circuit();
+    let mut trace = SolveTrace::new(10_000);
+    assert!(circuit.solve_state_traced(&mut trace));
+    assert_eq!(trace.dropped(), 0);
+    let residuals = trace.residuals();
+    assert_eq!(residuals.len(), circuit.last_solve_report().iterations);
+    for (i, pair) in residuals.windows(2).enumerate().skip(2) {
+        assert!(
+            pair[1] <= pair[0],
+            "residual rose from {:e} to {:e} after iteration {}",
+            pair[0],
+            pair[1],
+            i + 1
+        );
+    }
+    assert!(residuals[residuals.len() - 1] < 1e-12);
+    assert_eq!(trace.oscillating_nets(3), Vec::<usize>::new());
+
+    let mut csv = Vec::new();
+    trace.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("iteration,omega,residual,v0,"));
+    assert_eq!(lines.count(), residuals.len());
+}
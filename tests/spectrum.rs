@@ -0,0 +1,97 @@
+//! Spectra of recorded channels: amplitudes of synthesized tones, and the switching frequency in
+//! the output of a PWM'd filter.
+
+use std::f64::consts::TAU;
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::PwmWave,
+    probe::{Probe, Recording},
+    spectrum::Window,
+};
+
+/// A recording of one channel `x` holding `samples`, 1 us apart.
+fn synthesized(samples: impl Iterator<Item = f64>) -> Recording {
+    let mut recording = Recording::new(vec![("x".to_string(), Probe::Net(0))]);
+    for (i, sample) in samples.enumerate() {
+        recording.time.push(i as f64 * 1e-6);
+        recording.channels[0].push(sample);
+    }
+    recording
+}
+
+/// 0.5 V DC, 1 V on bin 50 and 0.25 V on bin 150, its third harmonic, over 1024 samples (an FFT)
+/// and over 1000 (Bluestein's algorithm): each reads its own amplitude, the bins between them
+/// nothing, and the THD is 25 %.
+#[test]
+fn two_tones() {
+    for n in [1024, 1000] {
+        let fundamental = 50.0 / (n as f64 * 1e-6);
+        let recording = synthesized((0..n).map(|i| {
+            let t = i as f64 * 1e-6;
+            0.5 + (TAU * fundamental * t).sin() + 0.25 * (TAU * 3.0 * fundamental * t).cos()
+        }));
+        let spectrum = recording.fft("x", Window::Rect).unwrap();
+        assert!((spectrum.resolution() - fundamental / 50.0).abs() < 1e-6);
+        for (bin, &amplitude) in spectrum.amplitudes.iter().enumerate() {
+            let expected = match bin {
+                0 => 0.5,
+                50 => 1.0,
+                150 => 0.25,
+                _ => 0.0,
+            };
+            assert!(
+                (amplitude.abs() - expected).abs() < 1e-9,
+                "{n} samples, bin {bin}: {} V, expected {expected} V",
+                amplitude.abs()
+            );
+        }
+        assert_eq!(spectrum.peak(), Some(50));
+        let thd = recording.thd("x", fundamental, Window::Rect).unwrap();
+        assert!((thd - 0.25).abs() < 1e-9, "{n} samples: THD {thd}");
+    }
+}
+
+/// A 10 kHz PWM at 30 % into 1 kohm and 100 nF, recorded every 1 us over 10 periods once it has
+/// settled: the ripple's largest bin above DC is the switching frequency's.
+#[test]
+fn pwm_ripple() {
+    const DT: f64 = 1e-6;
+    const PERIOD: f64 = 100e-6;
+    let wave = PwmWave {
+        low: 0.0,
+        high: 5.0,
+        period: PERIOD,
+        duty: 0.3,
+        delay: 0.0,
+        timing: Default::default(),
+    };
+    let (mut circuit, names) = CircuitBuilder::new()
+        .pwm_source("V1", "gnd", "in", wave)
+        .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+        .and_then(|b| b.capacitor("C1", "out", "gnd", 100e-9))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let mut recording = Recording::new(vec![(
+        "out".to_string(),
+        Probe::parse("net:out", &names).unwrap(),
+    )]);
+    let per_period = (PERIOD / DT).round() as usize;
+    for k in 0..20 * per_period {
+        assert!(circuit.tick(DT));
+        if k >= 10 * per_period {
+            recording.record(&circuit, circuit.now());
+        }
+    }
+    let spectrum = recording.fft("out", Window::Rect).unwrap();
+    let peak = spectrum.peak().unwrap();
+    assert!(
+        (spectrum.frequencies[peak] - 1.0 / PERIOD).abs() < 1e-6 / PERIOD,
+        "peak at {} Hz",
+        spectrum.frequencies[peak]
+    );
+    // the mean is the duty's share of the 5 V.
+    let dc = spectrum.amplitudes[0].abs();
+    assert!((dc - 1.5).abs() < 2e-2, "{dc} V DC");
+}
@@ -0,0 +1,114 @@
+//! Range and cycle statistics of recorded channels, on synthesized waves with known values.
+
+use std::f64::consts::{SQRT_2, TAU};
+
+use esc_sim_test::sim::{
+    probe::{Probe, Recording},
+    stats::StatsError,
+};
+
+/// Samples per 1 ms period of the synthesized waves.
+const N: usize = 1000;
+const PERIOD: f64 = 1e-3;
+
+/// A recording of one channel `x` with rows at `times`, holding `wave` of each.
+fn synthesized(times: impl Iterator<Item = f64>, wave: impl Fn(f64) -> f64) -> Recording {
+    let mut recording = Recording::new(vec![("x".to_string(), Probe::Net(0))]);
+    for time in times {
+        recording.time.push(time);
+        recording.channels[0].push(wave(time));
+    }
+    recording
+}
+
+/// `periods` periods of `wave` at `N` rows per period, from time 0.
+fn periods(periods: f64, wave: impl Fn(f64) -> f64) -> Recording {
+    let rows = (periods * N as f64).round() as usize;
+    synthesized((0..rows).map(|i| i as f64 * PERIOD / N as f64), wave)
+}
+
+/// 3 V for the first quarter of each period, 0 V for the rest.
+fn square(t: f64) -> f64 {
+    match (t / PERIOD * N as f64).round() as usize % N < N / 4 {
+        true => 3.0,
+        false => 0.0,
+    }
+}
+
+/// A 2 V sine over 0.5 V over 5 whole periods: RMS `sqrt(0.5^2 + 2^2 / 2)`, mean 0.5 and 4 V
+/// peak to peak; the same over any whole period, and an unknown channel or an empty range is an
+/// error.
+#[test]
+fn sine() {
+    let recording = periods(5.0, |t| 0.5 + 2.0 * (TAU * t / PERIOD).sin());
+    let rms = (0.25f64 + 2.0).sqrt();
+    for range in [0.0..1.0, 2e-3..3e-3] {
+        let (a, b) = (range.start, range.end);
+        assert!((recording.rms("x", a..b).unwrap() - rms).abs() < 1e-12);
+        assert!((recording.mean("x", a..b).unwrap() - 0.5).abs() < 1e-12);
+        assert!((recording.peak_to_peak("x", a..b).unwrap() - 4.0).abs() < 1e-12);
+    }
+    let zero_mean = periods(5.0, |t| 2.0 * (TAU * t / PERIOD).sin());
+    assert!((zero_mean.rms("x", ..).unwrap() - SQRT_2).abs() < 1e-12);
+    assert_eq!(
+        recording.rms("y", ..),
+        Err(StatsError::UnknownChannel("y".to_string()))
+    );
+    assert_eq!(recording.mean("x", 1.0..2.0), Err(StatsError::Empty));
+}
+
+/// The 25 % square wave of 3 V: RMS `3 sqrt(0.25)`, mean 0.75 V, 3 V peak to peak, over the whole
+/// recording and cycle by cycle.
+#[test]
+fn square_wave() {
+    let recording = periods(4.0, square);
+    assert!((recording.rms("x", ..).unwrap() - 1.5).abs() < 1e-12);
+    assert!((recording.mean("x", ..).unwrap() - 0.75).abs() < 1e-12);
+    assert_eq!(recording.peak_to_peak("x", ..).unwrap(), 3.0);
+    let cycles = recording.cycle_stats("x", PERIOD).unwrap();
+    assert_eq!(cycles.len(), 4);
+    for (k, cycle) in cycles.iter().enumerate() {
+        assert!((cycle.start - k as f64 * PERIOD).abs() < 1e-15, "{cycle:?}");
+        assert!((cycle.rms - 1.5).abs() < 1e-12, "{cycle:?}");
+        assert!((cycle.mean - 0.75).abs() < 1e-12, "{cycle:?}");
+        assert_eq!((cycle.min, cycle.max), (0.0, 3.0));
+    }
+    assert_eq!(
+        recording.cycle_stats("x", 0.0),
+        Err(StatsError::InvalidPeriod(0.0))
+    );
+}
+
+/// A final cycle is only kept if the recording covers it: its last row stands for the interval
+/// up to where the next would be. 4 periods to the row before 4 ms give 4 cycles, one row fewer
+/// or 3.5 periods 3; one more row, at 4 ms, only starts a fifth.
+#[test]
+fn partial_final_cycle() {
+    let rows = |n: usize| synthesized((0..n).map(|i| i as f64 * PERIOD / N as f64), square);
+    let count = |recording: Recording| recording.cycle_stats("x", PERIOD).unwrap().len();
+    assert_eq!(count(rows(4 * N)), 4);
+    assert_eq!(count(rows(4 * N + 1)), 4);
+    assert_eq!(count(rows(4 * N - 1)), 3);
+    assert_eq!(count(periods(3.5, square)), 3);
+}
+
+/// Row times summed tick by tick, as `CircuitState::now` gives them, fall a rounding error short
+/// of the cycle boundaries (ten ticks of 10 ms add up to 0.09999999999999999 s): each row still
+/// lands in the cycle it starts, which a channel holding the cycle number shows.
+#[test]
+fn accumulated_times() {
+    let times = (0..100).scan(0.0, |t: &mut f64, _| {
+        let time = *t;
+        *t += 0.01;
+        Some(time)
+    });
+    let times = times.collect::<Vec<_>>();
+    assert!(times[10] < 0.1, "{}", times[10]);
+    let mut recording = synthesized(times.iter().copied(), |_| 0.0);
+    recording.channels[0] = (0..100).map(|i| (i / 10) as f64).collect();
+    let cycles = recording.cycle_stats("x", 0.1).unwrap();
+    assert_eq!(cycles.len(), 10);
+    for (k, cycle) in cycles.iter().enumerate() {
+        assert_eq!((cycle.min, cycle.max), (k as f64, k as f64), "{cycle:?}");
+    }
+}
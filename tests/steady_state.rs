@@ -0,0 +1,86 @@
+//! `CircuitState::run_until_steady` on a circuit that settles to DC and on one that settles to
+//! a periodic ripple.
+
+mod common;
+
+use common::build;
+use esc_sim_test::sim::{builder::CircuitBuilder, components::PwmWave, probe::Probe};
+
+const DT: f64 = 10e-6;
+/// A tenth of the time constant of both circuits.
+const PERIOD: f64 = 100e-6;
+const TAU: f64 = 1e-3;
+
+/// 1 V into 1 kohm and 1 uF from rest. The state changes fastest in the current of its three
+/// components, `exp(-t / RC)` mA: by `(exp(1 / 10) - 1) exp(-t / RC)` mA over the period of
+/// `RC / 10` up to `t`, so a tolerance of that at 5 RC stops the run there.
+#[test]
+fn rc_charge() {
+    let (mut circuit, names) = build(
+        CircuitBuilder::new()
+            .source("V1", "gnd", "in", 1.0)
+            .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+            .and_then(|b| b.capacitor("C1", "out", "gnd", 1e-6)),
+    );
+    let tolerance = 3f64.sqrt() * ((PERIOD / TAU).exp() - 1.0) * (-5.0f64).exp() * 1e-3;
+    let periods = circuit
+        .run_until_steady(DT, PERIOD, tolerance, 1000)
+        .unwrap();
+    let t = periods as f64 * PERIOD;
+    assert!(
+        (t / TAU - 5.0).abs() <= 0.2,
+        "stopped after {t:e} s, {} RC",
+        t / TAU
+    );
+    let v_out = Probe::parse("net:out", &names).unwrap().sample(&circuit);
+    assert!((v_out - (1.0 - (-t / TAU).exp())).abs() < 1e-3);
+}
+
+/// A 50 % PWM between 0 and 2 V into 1 kohm, 2 uF and a 1 kohm load from rest (an averaging
+/// buck output stage, 1 ms time constant): the output ripples about 0.5 V once settled, and the
+/// run stops when one period repeats the last to 1 nA, though each passes 50 nC through the load.
+#[test]
+fn pwm_ripple() {
+    let wave = PwmWave {
+        low: 0.0,
+        high: 2.0,
+        period: PERIOD,
+        duty: 0.5,
+        delay: 0.0,
+        timing: Default::default(),
+    };
+    let (mut circuit, names) = build(
+        CircuitBuilder::new()
+            .pwm_source("V1", "gnd", "in", wave)
+            .and_then(|b| b.resistor("R1", "in", "out", 1e3))
+            .and_then(|b| b.capacitor("C1", "out", "gnd", 2e-6))
+            .and_then(|b| b.resistor("R2", "out", "gnd", 1e3)),
+    );
+    let periods = circuit.run_until_steady(DT, PERIOD, 1e-9, 1000).unwrap();
+    // the change over a period decays from about 1e-4 A to 1e-9 A, over ln(1e5) = 11.5 RC.
+    let t = periods as f64 * PERIOD;
+    assert!(
+        t > 10.0 * TAU && t < 14.0 * TAU,
+        "stopped after {t:e} s, {} RC",
+        t / TAU
+    );
+
+    // the next period repeats the last one.
+    let out = Probe::parse("net:out", &names).unwrap();
+    let steps = (PERIOD / DT).round() as usize;
+    let mut ripple = [Vec::new(), Vec::new()];
+    for ripple in &mut ripple {
+        for _ in 0..steps {
+            assert!(circuit.tick(DT));
+            ripple.push(out.sample(&circuit));
+        }
+    }
+    let deviation = ripple[0]
+        .iter()
+        .zip(&ripple[1])
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, f64::max);
+    assert!(deviation < 1e-6, "periods differ by {deviation:e} V");
+    let mean = ripple[1].iter().sum::<f64>() / steps as f64;
+    assert!((mean - 0.5).abs() < 1e-2, "mean output {mean} V");
+}
@@ -0,0 +1,80 @@
+//! `SolveStrategy`s: the default relaxation and the interleaved one solve circuits to the same
+//! answers, and `SolveReport` records which ran.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{MOSFETComponentValue, MOSFETDopingType},
+    rc_test_circuit,
+    strategy::{InterleavedStrategy, RelaxationStrategy},
+    CircuitState,
+};
+
+/// Tick `circuit` and a copy of it, one under each strategy, `n` times by `dt`, failing unless
+/// every tick converges, each records its strategy, and after each the net voltages (relative to
+/// net 0) and the component states agree within `tolerance`.
+fn compare_strategies(circuit: CircuitState, dt: f64, n: usize, tolerance: f64) {
+    let mut relaxation = circuit.clone();
+    relaxation.set_strategy(Box::new(RelaxationStrategy::default()));
+    let mut interleaved = circuit;
+    interleaved.set_strategy(Box::new(InterleavedStrategy::default()));
+    for k in 0..n {
+        for (circuit, name) in [
+            (&mut relaxation, "relaxation"),
+            (&mut interleaved, "interleaved"),
+        ] {
+            assert!(circuit.tick(dt), "{name}, tick {k}");
+            assert_eq!(circuit.last_solve_report().strategy, name);
+        }
+        // relative to net 0, as nothing holds the circuit's common mode.
+        let relative =
+            |circuit: &CircuitState, net| circuit.net_voltage(net) - circuit.net_voltage(0);
+        for net in 1..relaxation.n_nets() {
+            let (a, b) = (relative(&relaxation, net), relative(&interleaved, net));
+            assert!(
+                (a - b).abs() < tolerance,
+                "tick {k}, net {net}: {a} V against {b} V"
+            );
+        }
+        let states = relaxation
+            .state_vector()
+            .into_iter()
+            .zip(interleaved.state_vector());
+        for (i, (a, b)) in states.enumerate() {
+            assert!(
+                (a - b).abs() < tolerance,
+                "tick {k}, state {i}: {a} against {b}"
+            );
+        }
+    }
+}
+
+/// The two LC tanks of `rc_test_circuit`, over 100 ticks of 10 us.
+#[test]
+fn lc_tanks() {
+    compare_strategies(rc_test_circuit().0, 10e-6, 100, 1e-10);
+}
+
+/// An N-channel MOSFET, its gate at 5 V, sinking from 12 V through 100 ohm with 100 nF across the
+/// channel, over 200 ticks of 1 us.
+#[test]
+fn mosfet_drain_rc() {
+    let mosfet = MOSFETComponentValue {
+        beta: 0.02,
+        ty: MOSFETDopingType::NChannel,
+        body_diode_ideality_facotor: 1.0,
+        body_diode_saturation_current: 1e-12,
+        threshold_voltage: 2.0,
+        saturation_knee: 8.0,
+        multiplicity: 1.0,
+        avalanche: None,
+    };
+    let (circuit, _) = CircuitBuilder::new()
+        .source("VDD", "gnd", "vdd", 12.0)
+        .and_then(|b| b.source("VG", "gnd", "gate", 5.0))
+        .and_then(|b| b.resistor("RD", "vdd", "drain", 100.0))
+        .and_then(|b| b.capacitor("CD", "drain", "gnd", 100e-9))
+        .and_then(|b| b.mosfet("M1", mosfet, "gnd", "gate", "drain"))
+        .unwrap()
+        .build();
+    compare_strategies(circuit, 1e-6, 200, 1e-10);
+}
@@ -0,0 +1,70 @@
+//! `SolverConfig::sub_steps` integrating a MOSFET's switching energy within coarse ticks.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{MOSFETComponentValue, MOSFETDopingType},
+    power::LossAccumulator,
+    solver::SolverConfig,
+};
+
+const T_END: f64 = 6e-6;
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.1,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+/// The energy `M1` dissipates turning on over 6 us in ticks of `dt`, with `sub_steps`, and the
+/// solver iterations that took: the gate charged from 0 V by 12 V through 100 ohm into 100 nF
+/// (`tau` 10 us), switching 12 V through 100 ohm.
+fn turn_on(dt: f64, sub_steps: Option<usize>) -> (f64, usize) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("VG", "gnd", "drive", 12.0)
+        .and_then(|b| b.resistor("RG", "drive", "gate", 100.0))
+        .and_then(|b| b.capacitor("CG", "gate", "gnd", 100e-9))
+        .and_then(|b| b.source("VD", "gnd", "supply", 12.0))
+        .and_then(|b| b.resistor("RD", "supply", "drain", 100.0))
+        .and_then(|b| b.mosfet("M1", MOSFET, "gnd", "gate", "drain"))
+        .unwrap()
+        .build();
+    circuit.set_solver_config(SolverConfig {
+        sub_steps,
+        ..*circuit.solver_config()
+    });
+    assert!(circuit.solve_state());
+
+    let m1 = names.component("M1").unwrap();
+    let mut losses = LossAccumulator::new();
+    let mut iterations = 0;
+    for _ in 0..(T_END / dt).round() as usize {
+        assert!(circuit.tick(dt), "no convergence at {:e} s", circuit.now());
+        iterations += circuit.last_solve_report().iterations;
+        losses.accumulate(&circuit, dt);
+    }
+    (losses.dissipated(m1), iterations)
+}
+
+/// With 0.25 us ticks and 10 sub-steps, the switching energy is within 2 % of that of 10 ns
+/// ticks, for under a tenth of the iterations.
+///
+/// Most of the error left is the gate's: backward Euler lags the RC charging curve by about
+/// `dt / 2`, and sub-stepping only interpolates between the solutions it is given. The plain
+/// end-of-tick power happens to land closer here, its own error partly cancelling that lag.
+#[test]
+fn gate_turn_on() {
+    let (reference, reference_iterations) = turn_on(10e-9, None);
+    let (sub_stepped, iterations) = turn_on(0.25e-6, Some(10));
+    assert!(
+        (sub_stepped - reference).abs() < 2e-2 * reference,
+        "{sub_stepped:e} J sub-stepped, {reference:e} J fine-stepped"
+    );
+    assert!(
+        10 * iterations < reference_iterations,
+        "{iterations} iterations sub-stepped, {reference_iterations} fine-stepped"
+    );
+}
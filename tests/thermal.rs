@@ -0,0 +1,181 @@
+//! `ThermalNetwork` heated by the MOSFETs bound to it, against the steady-state temperature rise
+//! of its thermal resistances.
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder,
+    components::{MOSFETComponentValue, MOSFETDopingType},
+    thermal::ThermalNetwork,
+    ComponentRef,
+};
+
+const MOSFET: MOSFETComponentValue = MOSFETComponentValue {
+    beta: 0.02,
+    ty: MOSFETDopingType::NChannel,
+    body_diode_ideality_facotor: 1.0,
+    body_diode_saturation_current: 1e-12,
+    threshold_voltage: 2.0,
+    saturation_knee: 8.0,
+    multiplicity: 1.0,
+    avalanche: None,
+};
+
+const AMBIENT: f64 = 300.0;
+/// Junction to heatsink and heatsink to ambient, K/W.
+const R_JS: f64 = 2.0;
+const R_SA: f64 = 5.0;
+
+/// Two MOSFETs each on 12 V through 10 ohm, M1 on and M2 off, with their junctions on
+/// one heatsink. The heat of M1 warms the junction of M2 with the heatsink, and once settled
+/// each junction is `P R` above ambient along its path: M1 by its power through both
+/// resistances, M2 by that of M1 through the heatsink's.
+#[test]
+fn shared_heatsink() {
+    const DT: f64 = 1e-4;
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("V1", "gnd", "bus", 12.0)
+        .and_then(|b| b.source("VG1", "gnd", "g1", 10.0))
+        .and_then(|b| b.source("VG2", "gnd", "g2", 0.0))
+        .and_then(|b| b.resistor("R1", "bus", "d1", 10.0))
+        .and_then(|b| b.resistor("R2", "bus", "d2", 10.0))
+        .and_then(|b| b.mosfet("M1", MOSFET, "gnd", "g1", "d1"))
+        .and_then(|b| b.mosfet("M2", MOSFET, "gnd", "g2", "d2"))
+        .unwrap()
+        .build();
+    assert!(circuit.solve_state());
+    let (m1, m2) = (
+        names.component("M1").unwrap(),
+        names.component("M2").unwrap(),
+    );
+
+    // time constants of 0.2 ms for the junctions and 5 ms for the heatsink
+    let mut thermal = ThermalNetwork::new();
+    let ambient = thermal.add_fixed_node(AMBIENT);
+    let sink = thermal.add_node(1e-3, AMBIENT);
+    let j1 = thermal.add_node(1e-4, AMBIENT);
+    let j2 = thermal.add_node(1e-4, AMBIENT);
+    thermal.add_resistance(sink, ambient, R_SA);
+    thermal.add_resistance(j1, sink, R_JS);
+    thermal.add_resistance(j2, sink, R_JS);
+    thermal.bind(m1, j1);
+    thermal.bind(m2, j2);
+
+    let mut rise = (0.0, 0.0);
+    for _ in 0..1000 {
+        assert!(circuit.tick(DT));
+        thermal.tick(&mut circuit, DT);
+        let now = (
+            thermal.temperature(j1) - AMBIENT,
+            thermal.temperature(j2) - AMBIENT,
+        );
+        assert!(
+            now.0 >= rise.0 && now.1 >= rise.1,
+            "junctions from {rise:?} K to {now:?} K above ambient"
+        );
+        rise = now;
+    }
+
+    // the junctions pass their temperature on to the MOSFETs.
+    for (id, node) in [(m1, j1), (m2, j2)] {
+        let ComponentRef::MOSFET(mosfet) = circuit.component(id) else {
+            unreachable!()
+        };
+        assert_eq!(mosfet.temperature, thermal.temperature(node));
+    }
+
+    let (p1, p2) = (circuit.dissipated_power(m1), circuit.dissipated_power(m2));
+    assert!(p1 > 1.0 && p2 < 1e-6, "M1 dissipates {p1} W, M2 {p2} W");
+    let sink_rise = (p1 + p2) * R_SA;
+    for (name, rise, expected) in [
+        ("M1", rise.0, sink_rise + p1 * R_JS),
+        ("M2", rise.1, sink_rise + p2 * R_JS),
+    ] {
+        assert!(
+            (rise - expected).abs() < 1e-6,
+            "{name} junction {rise} K above ambient, expected {expected} K"
+        );
+    }
+}
+
+/// A MOSFET with multiplicity 2 bound to a junction node, against two explicit ones from the same
+/// drain each bound to their own: its junction is heated by the power of one device, so it
+/// follows theirs tick by tick and settles at half the total power through the junction to
+/// ambient.
+#[test]
+fn multiplicity() {
+    const DT: f64 = 1e-4;
+    const R_JA: f64 = R_JS + R_SA;
+    let run = |explicit: bool| {
+        let mut builder = CircuitBuilder::new();
+        builder
+            .source("V1", "gnd", "bus", 12.0)
+            .and_then(|b| b.source("VG", "gnd", "gate", 10.0))
+            .and_then(|b| b.resistor("R1", "bus", "drain", 10.0))
+            .unwrap();
+        let mosfets: &[&str] = if explicit {
+            builder
+                .mosfet("M1", MOSFET, "gnd", "gate", "drain")
+                .and_then(|b| b.mosfet("M2", MOSFET, "gnd", "gate", "drain"))
+                .unwrap();
+            &["M1", "M2"]
+        } else {
+            let doubled = MOSFETComponentValue {
+                multiplicity: 2.0,
+                ..MOSFET
+            };
+            builder
+                .mosfet("M1", doubled, "gnd", "gate", "drain")
+                .unwrap();
+            &["M1"]
+        };
+        let (mut circuit, names) = builder.build();
+        assert!(circuit.solve_state());
+        let mut thermal = ThermalNetwork::new();
+        let ambient = thermal.add_fixed_node(AMBIENT);
+        let junctions = mosfets
+            .iter()
+            .map(|name| {
+                let junction = thermal.add_node(1e-4, AMBIENT);
+                thermal.add_resistance(junction, ambient, R_JA);
+                thermal.bind(names.component(name).unwrap(), junction);
+                junction
+            })
+            .collect::<Vec<_>>();
+        let mut temperatures = Vec::new();
+        for _ in 0..500 {
+            assert!(circuit.tick(DT));
+            thermal.tick(&mut circuit, DT);
+            temperatures.push(
+                junctions
+                    .iter()
+                    .map(|&junction| thermal.temperature(junction))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let power = mosfets
+            .iter()
+            .map(|name| circuit.dissipated_power(names.component(name).unwrap()))
+            .sum::<f64>();
+        (temperatures, power)
+    };
+    let (single, power) = run(false);
+    let (pair, pair_power) = run(true);
+    assert!(
+        (power - pair_power).abs() < 1e-9 * pair_power,
+        "{power} W with multiplicity 2, {pair_power} W with two devices"
+    );
+    for (k, (single, pair)) in single.iter().zip(&pair).enumerate() {
+        for &t in pair {
+            assert!(
+                (single[0] - t).abs() < 1e-9,
+                "tick {k}: junction at {} K with multiplicity 2, {t} K with two devices",
+                single[0]
+            );
+        }
+    }
+    let rise = single.last().unwrap()[0] - AMBIENT;
+    let expected = power / 2.0 * R_JA;
+    assert!(
+        power > 1.0 && (rise - expected).abs() < 1e-6,
+        "junction {rise} K above ambient, expected {expected} K"
+    );
+}
@@ -0,0 +1,95 @@
+//! `ThreePhaseProbe` channels of a balanced three-phase source driving a star of resistors.
+
+use std::f64::consts::{PI, TAU};
+
+use esc_sim_test::sim::{
+    builder::CircuitBuilder, components::ComponentParameter, probe::Recording,
+    three_phase::ThreePhaseProbe, CircuitState,
+};
+
+const AMPLITUDE: f64 = 10.0;
+const FREQUENCY: f64 = 1e3;
+const R: f64 = 10.0;
+const DT: f64 = 10e-6;
+
+/// Phase voltages of `AMPLITUDE` at `FREQUENCY`, 120 degrees apart, from a neutral into 10 ohm
+/// per phase in star, recorded every 10 us over 2 periods with the star point of the load as
+/// the neutral when `star_neutral`, the mean of the phases otherwise.
+fn balanced(star_neutral: bool) -> (ThreePhaseProbe, Recording) {
+    let (mut circuit, names) = CircuitBuilder::new()
+        .source("VA", "n", "a", 0.0)
+        .and_then(|b| b.source("VB", "n", "b", 0.0))
+        .and_then(|b| b.source("VC", "n", "c", 0.0))
+        .and_then(|b| b.resistor("RA", "a", "star", R))
+        .and_then(|b| b.resistor("RB", "b", "star", R))
+        .and_then(|b| b.resistor("RC", "c", "star", R))
+        .unwrap()
+        .build();
+    let net = |name| names.net(name).unwrap();
+    let component = |name| names.component(name).unwrap();
+    let mut probe = ThreePhaseProbe::new("out", [net("a"), net("b"), net("c")]).with_currents([
+        component("RA"),
+        component("RB"),
+        component("RC"),
+    ]);
+    if star_neutral {
+        probe = probe.with_neutral(net("star"));
+    }
+    let mut probes = Vec::new();
+    probe.append_to(&mut probes);
+    let mut recording = Recording::new(probes);
+    let sources = [component("VA"), component("VB"), component("VC")];
+    let set_phases = |circuit: &mut CircuitState, t: f64| {
+        for (k, &source) in sources.iter().enumerate() {
+            *circuit
+                .component_mut(source)
+                .parameter_mut(ComponentParameter::Value)
+                .unwrap() = AMPLITUDE * (TAU * FREQUENCY * t - k as f64 * 2.0 * PI / 3.0).sin();
+        }
+    };
+    set_phases(&mut circuit, 0.0);
+    assert!(circuit.solve_state());
+    recording.record(&circuit, circuit.now());
+    for k in 1..=200 {
+        set_phases(&mut circuit, k as f64 * DT);
+        assert!(circuit.tick(DT), "tick {k}");
+        recording.record(&circuit, circuit.now());
+    }
+    (probe, recording)
+}
+
+/// Over the whole periods, each phase voltage has the RMS of the sine and each line voltage
+/// sqrt(3) times it, with the star point of the load as the neutral and with the mean of the
+/// phases. The total power is `3 V^2 / 2 R` at every sample, although each phase's pulsates
+/// between 0 and twice its mean.
+#[test]
+fn balanced_load() {
+    let v_phase = AMPLITUDE / 2f64.sqrt();
+    let whole_periods = ..2.0 / FREQUENCY - DT / 2.0;
+    for star_neutral in [false, true] {
+        let (probe, recording) = balanced(star_neutral);
+        let phases = probe.phase_rms(&recording, whole_periods).unwrap();
+        let lines = probe.line_rms(&recording, whole_periods).unwrap();
+        for (phase, line) in phases.into_iter().zip(lines) {
+            assert!((phase - v_phase).abs() < 1e-6 * v_phase, "{phase} V phase");
+            assert!(
+                (line - 3f64.sqrt() * v_phase).abs() < 1e-6 * v_phase,
+                "{line} V line"
+            );
+        }
+        let total = 3.0 * v_phase * v_phase / R;
+        for &p in recording.channel("out.p").unwrap() {
+            assert!((p - total).abs() < 1e-6 * total, "{p} W total");
+        }
+        let pa = recording.channel("out.pa").unwrap();
+        let (min, max) = pa
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &p| {
+                (lo.min(p), hi.max(p))
+            });
+        assert!(
+            min < 1e-3 * total && max > 0.66 * total,
+            "pa in [{min}, {max}] W"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! The JavaScript bindings, run in a wasm runtime by `wasm-pack test --node -- --features wasm`.
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use esc_sim_test::wasm::Circuit;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// 1 V through 1 kohm into 1 uF from 0 V: each tick of 10 us charges the capacitor further,
+/// along `1 - exp(-t / RC)` over half a time constant.
+#[wasm_bindgen_test]
+fn tick_charges_capacitor() {
+    let mut circuit = Circuit::new();
+    let [gnd, supply, out] = [(); 3].map(|_| circuit.add_net());
+    circuit.add_source(gnd, supply, 1.0).unwrap();
+    circuit.add_resistor(supply, out, 1e3).unwrap();
+    circuit.add_capacitor(out, gnd, 1e-6).unwrap();
+    assert!(circuit.solve_state().unwrap());
+    let v_out =
+        |circuit: &Circuit| circuit.net_voltage(out).unwrap() - circuit.net_voltage(gnd).unwrap();
+    let mut prev = v_out(&circuit);
+    assert!(prev.abs() < 1e-9, "{prev} V before the first tick");
+    for k in 1..=50 {
+        assert!(circuit.tick(10e-6).unwrap(), "tick {k}");
+        let v = v_out(&circuit);
+        assert!(v > prev, "tick {k}: {v} V after {prev} V");
+        let expected = 1.0 - (-(k as f64) * 0.01).exp();
+        assert!(
+            (v - expected).abs() < 5e-3,
+            "tick {k}: {v} V, expected {expected} V"
+        );
+        prev = v;
+    }
+}